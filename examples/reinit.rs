@@ -0,0 +1,26 @@
+use std::{thread, time};
+use webview_official::{SizeHint, WebviewBuilder};
+
+// Creates, runs and destroys webviews in a loop, every round has to come up and shut down
+// cleanly in the same process.
+fn main() {
+    for round in 0..50 {
+        let title = format!("Round {}", round);
+        let mut webview = WebviewBuilder::new()
+            .title(&title)
+            .width(400)
+            .height(300)
+            .resize(SizeHint::NONE)
+            .url("data:text/html,<h1>Hello again</h1>")
+            .build();
+
+        let mut webview_ref = webview.handle();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(200));
+            webview_ref.terminate().unwrap();
+        });
+
+        webview.run();
+        println!("{} done", title);
+    }
+}
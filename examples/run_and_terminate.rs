@@ -11,7 +11,7 @@ fn main() {
         .url("https://google.com")
         .build();
 
-    let mut webview_ref = webview.as_mut();
+    let mut webview_ref = webview.handle();
 
     thread::spawn(move || {
         thread::sleep(time::Duration::from_secs(5));
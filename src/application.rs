@@ -0,0 +1,98 @@
+use std::io;
+#[cfg(feature = "health")]
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use crate::deep_link;
+#[cfg(feature = "health")]
+use crate::health;
+use crate::instance::{self, InstanceLock};
+#[cfg(target_os = "macos")]
+use crate::platform;
+use crate::schedule::{self, Schedule, ScheduledTask};
+use crate::{Webview, WebviewHandle};
+
+/// Process wide operations that don't belong to a single webview.
+pub enum Application {}
+
+/// How the application shows up in the Dock and the menu bar.
+#[cfg(target_os = "macos")]
+#[repr(isize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationPolicy {
+    /// An ordinary application with a Dock icon and a menu bar.
+    Regular = 0,
+    /// No Dock icon or menu bar, but windows can still be shown and activated.
+    Accessory = 1,
+    /// No Dock icon and no windows, for background only processes.
+    Prohibited = 2,
+}
+
+impl Application {
+    /// Serves a health report for `webview` over HTTP on `addr`, returns the address listened
+    /// on.
+    ///
+    /// Any request is answered with JSON holding the `backend`, `null` once the webview is gone,
+    /// whether the webview is `alive`, whether its page is `responsive` and the Unix time of the
+    /// `last_load` of a page. The status is 503 when the webview is gone or its page doesn't
+    /// answer within five seconds, so supervisors can restart the application. A few requests
+    /// are answered at a time, and clients that take over two seconds or 8 KiB for their
+    /// headers are disconnected.
+    ///
+    /// Loads are reported by the engine, see `Webview::on_load_finished`, so `last_load` is
+    /// `null` until a page loads after this call, and stays so on Windows.
+    #[cfg(feature = "health")]
+    pub fn serve_health<A: ToSocketAddrs>(
+        addr: A,
+        webview: WebviewHandle,
+    ) -> io::Result<SocketAddr> {
+        health::serve(addr, webview)
+    }
+
+    /// Runs `task` on the UI thread of `webview` according to `schedule`, e.g. to reload a kiosk's
+    /// content every night.
+    ///
+    /// Runs missed while the system was asleep are caught up once on wake-up, not once per missed
+    /// run. A run that panics is logged and the task keeps its schedule.
+    pub fn schedule<F>(webview: WebviewHandle, schedule: Schedule, task: F) -> ScheduledTask
+    where
+        F: FnMut(&mut Webview) + Send + 'static,
+    {
+        schedule::schedule(webview, schedule, task)
+    }
+
+    /// Makes sure only one instance of the application identified by `id` runs for the user.
+    ///
+    /// Returns the lock to keep for as long as the application runs, or `None` when another
+    /// instance is running, in which case this invocation's arguments were forwarded to it and
+    /// the process should exit. The running instance focuses its window and calls `f` with them
+    /// on the UI thread once given a webview with `InstanceLock::forward_to`.
+    ///
+    /// Fails if the lock file another instance left isn't the user's own or others can access
+    /// it, rather than handing it the arguments.
+    pub fn ensure_single_instance<F>(id: &str, f: F) -> io::Result<Option<InstanceLock>>
+    where
+        F: FnMut(&mut Webview, Vec<String>) + Send + 'static,
+    {
+        instance::ensure(id, f)
+    }
+
+    /// Registers the application as the current user's handler of links to `scheme`, e.g.
+    /// `"myapp"` for `myapp://` links, which then reach `Webview::on_deep_link`.
+    ///
+    /// Call it on every start, only links to the schemes registered by the running process are
+    /// treated as such. On Linux and Windows links start a new instance of the application, use
+    /// `ensure_single_instance` to hand them to the running one. On macOS the scheme also has to
+    /// be declared in the bundle's `Info.plist`.
+    pub fn register_url_scheme(scheme: &str) -> io::Result<()> {
+        deep_link::register(scheme)
+    }
+
+    /// Changes the activation policy, returns `false` if it couldn't be applied.
+    ///
+    /// This can be called at any time from the main thread, e.g. to switch a tray only app to
+    /// `Regular` while one of its windows is open and back to `Accessory` when it closes.
+    #[cfg(target_os = "macos")]
+    pub fn set_activation_policy(policy: ActivationPolicy) -> bool {
+        platform::set_activation_policy(policy as isize)
+    }
+}
@@ -0,0 +1,12 @@
+/// How insistently to ask for the user's attention, see `Webview::request_user_attention`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum UserAttention {
+    /// Something finished or arrived: the Dock icon bounces once, the taskbar entry flashes a
+    /// few times and stays highlighted.
+    #[default]
+    Informational,
+    /// Something needs the user: the Dock icon and the taskbar entry keep at it until the
+    /// application is activated.
+    Critical,
+}
@@ -0,0 +1,182 @@
+//! Driving the page like a user would, for end-to-end tests, see `Webview::click`.
+//!
+//! The steps are scripts evaluated in the page, the events they fire are `isTrusted: false`.
+
+use std::time::Duration;
+
+use crate::{json, Error, Rect};
+
+/// An element found by `Webview::query_selector`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Element {
+    /// Lower case, e.g. `button`.
+    pub tag_name: String,
+    /// The rendered text, `innerText`.
+    pub text: String,
+    /// The `value` of form fields, `None` for other elements.
+    pub value: Option<String>,
+    /// Where the element is in the viewport, in CSS pixels.
+    pub bounds: Rect,
+}
+
+/// Runs `body` with the first element matching `selector` as `element`, throws if there is
+/// none.
+fn with_element(selector: &str, body: &str) -> String {
+    format!(
+        r#"(function (selector) {{
+  var element = document.querySelector(selector);
+  if (!element) {{
+    throw new Error("No element matches " + selector);
+  }}
+  {}
+}})({})"#,
+        body,
+        json::string(selector)
+    )
+}
+
+pub(crate) fn query_script(selector: &str) -> String {
+    format!(
+        r#"(function (selector) {{
+  var element = document.querySelector(selector);
+  if (!element) {{
+    return null;
+  }}
+  var rect = element.getBoundingClientRect();
+  return {{
+    tag_name: element.tagName.toLowerCase(),
+    text: element.innerText || "",
+    value: "value" in element ? String(element.value) : null,
+    x: Math.round(rect.x),
+    y: Math.round(rect.y),
+    width: Math.round(rect.width),
+    height: Math.round(rect.height),
+  }};
+}})({})"#,
+        json::string(selector)
+    )
+}
+
+pub(crate) fn parse_element(reply: String) -> Result<Option<Element>, Error> {
+    let value = match json::parse(&reply) {
+        Some(json::Value::Null) => return Ok(None),
+        Some(value) => value,
+        None => return Err(Error::Ipc(reply)),
+    };
+    let string = |key| match value.get(key) {
+        Some(json::Value::String(s)) => Some(s.clone()),
+        _ => None,
+    };
+    let number = |key| match value.get(key) {
+        Some(json::Value::Number(n)) => *n as i32,
+        _ => 0,
+    };
+    Ok(Some(Element {
+        tag_name: string("tag_name").unwrap_or_default(),
+        text: string("text").unwrap_or_default(),
+        value: string("value"),
+        bounds: Rect {
+            x: number("x"),
+            y: number("y"),
+            width: number("width"),
+            height: number("height"),
+        },
+    }))
+}
+
+/// Scrolls the element into view and clicks its center, with the mouse events a click fires.
+pub(crate) fn click_script(selector: &str) -> String {
+    with_element(
+        selector,
+        r#"element.scrollIntoView({ block: "center", inline: "center" });
+  var rect = element.getBoundingClientRect();
+  var at = {
+    bubbles: true,
+    cancelable: true,
+    view: window,
+    button: 0,
+    clientX: rect.x + rect.width / 2,
+    clientY: rect.y + rect.height / 2,
+  };
+  element.dispatchEvent(new MouseEvent("mousedown", at));
+  if (element.focus) {
+    element.focus();
+  }
+  element.dispatchEvent(new MouseEvent("mouseup", at));
+  element.click();
+  return null;"#,
+    )
+}
+
+/// Focuses the element and types `text` into it a character at a time, with key and input
+/// events, so frameworks tracking the field see every change.
+pub(crate) fn type_script(selector: &str, text: &str) -> String {
+    with_element(
+        selector,
+        &format!(
+            r#"element.focus();
+  var text = {};
+  var editable = element.isContentEditable;
+  var proto = Object.getPrototypeOf(element);
+  var setter = (Object.getOwnPropertyDescriptor(proto, "value") || {{}}).set;
+  for (var i = 0; i < text.length; i++) {{
+    var key = {{ key: text[i], bubbles: true, cancelable: true }};
+    if (!element.dispatchEvent(new KeyboardEvent("keydown", key))) {{
+      continue;
+    }}
+    element.dispatchEvent(new KeyboardEvent("keypress", key));
+    if (editable) {{
+      document.execCommand("insertText", false, text[i]);
+    }} else if (setter) {{
+      setter.call(element, element.value + text[i]);
+      element.dispatchEvent(new InputEvent("input", {{ bubbles: true, data: text[i], inputType: "insertText" }}));
+    }}
+    element.dispatchEvent(new KeyboardEvent("keyup", key));
+  }}
+  if (!editable) {{
+    element.dispatchEvent(new Event("change", {{ bubbles: true }}));
+  }}
+  return null;"#,
+            json::string(text)
+        ),
+    )
+}
+
+/// Resolves with `true` once an element matches `selector`, or `false` after `timeout`.
+pub(crate) fn wait_script(selector: &str, timeout: Duration) -> String {
+    format!(
+        r#"(function (selector, timeout) {{
+  return new Promise(function (resolve) {{
+    if (document.querySelector(selector)) {{
+      return resolve(true);
+    }}
+    var observer = new MutationObserver(function () {{
+      if (document.querySelector(selector)) {{
+        observer.disconnect();
+        clearTimeout(timer);
+        resolve(true);
+      }}
+    }});
+    observer.observe(document, {{ childList: true, subtree: true, attributes: true }});
+    var timer = setTimeout(function () {{
+      observer.disconnect();
+      resolve(false);
+    }}, timeout);
+  }});
+}})({}, {})"#,
+        json::string(selector),
+        timeout.as_millis()
+    )
+}
+
+pub(crate) fn parse_done(_reply: String) -> Result<(), Error> {
+    Ok(())
+}
+
+pub(crate) fn parse_found(reply: String) -> Result<(), Error> {
+    match reply.trim() {
+        "true" => Ok(()),
+        _ => Err(Error::Timeout),
+    }
+}
@@ -0,0 +1,42 @@
+/// The native engine behind a `Webview`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backend {
+    /// WebKitGTK, on Linux and the BSDs.
+    WebKitGtk,
+    /// `WKWebView`, on macOS.
+    WebKit,
+    /// Microsoft Edge WebView2, on Windows.
+    WebView2,
+    /// The in-memory model of the `mock` feature.
+    Mock,
+}
+
+/// What the active backend supports, so applications can leave out what isn't available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Calling into Rust from the page with `bind` and `return`.
+    pub bind: bool,
+    /// Injecting scripts with `init` and `eval`.
+    pub scripts: bool,
+    /// A native window handle from `get_window`.
+    pub window_handle: bool,
+    /// Embedding the webview into a host window with `reparent`.
+    pub reparent: bool,
+    /// Driving the event loop from the host, see `WebviewBuilder::external_loop`.
+    pub external_loop: bool,
+    /// A transparent page background showing the window behind it.
+    pub transparency: bool,
+    /// Rendering the page to pixels instead of a window, see `WebviewBuilder::offscreen`.
+    pub offscreen: bool,
+}
+
+/// A call the active backend couldn't honor, see `Webview::unsupported_calls`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UnsupportedCall {
+    /// The method that was called, e.g. `Webview::reparent`.
+    pub call: &'static str,
+    pub backend: Backend,
+}
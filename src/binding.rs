@@ -0,0 +1,136 @@
+//! The handlers of the bindings, which can be replaced and removed while the webview runs, see
+//! `Webview::unbind`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::json;
+
+/// Gets the `seq` and the JSON arguments of a call.
+type HandlerFn = dyn FnMut(&str, &str);
+
+/// A binding's handler.
+pub(crate) struct Handler(Box<HandlerFn>);
+
+impl Handler {
+    pub(crate) fn new<F>(f: F) -> Handler
+    where
+        F: FnMut(&str, &str) + 'static,
+    {
+        Handler(Box::new(f))
+    }
+
+    pub(crate) fn call(&mut self, seq: &str, req: &str) {
+        (self.0)(seq, req)
+    }
+}
+
+struct Slot {
+    /// Tells a handler apart from the ones bound under the same name before and after it.
+    id: u64,
+    /// `None` while the handler runs.
+    handler: Option<Handler>,
+}
+
+#[derive(Default)]
+pub(crate) struct Bindings {
+    slots: HashMap<String, Slot>,
+    /// The names the page has a function for.
+    exposed: HashSet<String>,
+    next_id: u64,
+}
+
+impl Bindings {
+    /// Binds `handler` to `name`, returning the one it replaces and whether the page needs a
+    /// function for it.
+    pub(crate) fn set(&mut self, name: &str, handler: Handler) -> (Option<Handler>, bool) {
+        self.next_id += 1;
+        let slot = Slot {
+            id: self.next_id,
+            handler: Some(handler),
+        };
+        let replaced = self
+            .slots
+            .insert(name.to_string(), slot)
+            .and_then(|slot| slot.handler);
+        (replaced, self.exposed.insert(name.to_string()))
+    }
+
+    /// Removes the binding `name`, returning its handler unless it is running.
+    pub(crate) fn remove(&mut self, name: &str) -> Option<Option<Handler>> {
+        self.exposed.remove(name);
+        self.slots.remove(name).map(|slot| slot.handler)
+    }
+
+    /// Takes the handler of `name` out to call it.
+    pub(crate) fn take(&mut self, name: &str) -> Option<(u64, Handler)> {
+        let slot = self.slots.get_mut(name)?;
+        Some((slot.id, slot.handler.take()?))
+    }
+
+    /// Puts a handler taken with `take` back, or returns it if it was replaced or removed in
+    /// the meantime.
+    pub(crate) fn restore(&mut self, name: &str, id: u64, handler: Handler) -> Option<Handler> {
+        match self.slots.get_mut(name) {
+            Some(slot) if slot.id == id => {
+                slot.handler = Some(handler);
+                None
+            }
+            _ => Some(handler),
+        }
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.slots.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// The names the crate binds for itself start with this, they stay global for its scripts.
+const INTERNAL: &str = "__webview_";
+
+pub(crate) fn is_internal(name: &str) -> bool {
+    name.starts_with(INTERNAL)
+}
+
+/// Where the page finds the binding `name` in `namespace`, see
+/// `WebviewBuilder::binding_namespace`.
+pub(crate) fn namespaced<'a>(namespace: Option<&'a str>, name: &str) -> Option<&'a str> {
+    namespace.filter(|_| !is_internal(name))
+}
+
+/// A JS expression for the object at `namespace`, e.g. `myapp.commands`, creating what is
+/// missing of it.
+fn object(namespace: &str) -> String {
+    let keys: Vec<String> = namespace
+        .split('.')
+        .filter(|key| !key.is_empty())
+        .map(json::string)
+        .collect();
+    format!(
+        "[{}].reduce(function (o, k) {{ return o[k] = o[k] || {{}}; }}, window)",
+        keys.join(",")
+    )
+}
+
+/// Creates the object at `namespace`, so the page can rely on it before anything is bound.
+pub(crate) fn namespace_script(namespace: &str) -> String {
+    format!("{};", object(namespace))
+}
+
+/// Moves the function the C library put on `window` for the binding `name` to `namespace`.
+pub(crate) fn move_script(namespace: &str, name: &str) -> String {
+    format!(
+        "(function (name) {{ if (name in window) {{ {}[name] = window[name]; delete window[name]; }} }})({});",
+        object(namespace),
+        json::string(name)
+    )
+}
+
+/// Takes the function for the binding `name` out of the page.
+pub(crate) fn removal_script(namespace: Option<&str>, name: &str) -> String {
+    match namespace {
+        Some(namespace) => format!("delete {}[{}];", object(namespace), json::string(name)),
+        None => format!("delete window[{}];", json::string(name)),
+    }
+}
@@ -1,4 +1,23 @@
-use crate::{SizeHint, Webview, Window};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::csp;
+use crate::platform::PaintCallback;
+use crate::{
+    Backend, BackgroundThrottling, Color, Error, Frame, NativeWindow, NavigationPolicy, Plugin,
+    SizeHint, Splash, Theme, Webview, WindowEffect,
+};
+
+/// The HTTP cache settings of a webview.
+#[derive(Default)]
+pub(crate) struct Cache {
+    pub(crate) dir: Option<PathBuf>,
+    pub(crate) max_size: Option<u64>,
+    pub(crate) disabled: bool,
+}
+
+/// Adds a plugin to the webview once it's created, see `WebviewBuilder::plugin`.
+type AddPlugin = Box<dyn FnOnce(&mut Webview<'_>)>;
 
 #[derive(Default)]
 pub struct WebviewBuilder<'a> {
@@ -8,8 +27,47 @@ pub struct WebviewBuilder<'a> {
     eval: Option<&'a str>,
     size: (usize, usize, SizeHint),
     debug: bool,
+    external_loop: bool,
+    sync_window_title: bool,
+    favicon_as_window_icon: bool,
+    drag_regions: bool,
+    theme: Theme,
+    background_color: Option<Color>,
+    window_effect: Option<WindowEffect>,
+    disable_hardware_acceleration: bool,
+    disable_service_workers: bool,
+    owner: Option<Webview<'static>>,
+    modal: bool,
+    splash: Option<Splash>,
+    window_state: Option<PathBuf>,
     dispatch: Option<Box<dyn FnOnce(&mut Webview) + Send + 'static>>,
-    window: Option<&'a mut Window>,
+    window: Option<&'a mut NativeWindow>,
+    cache: Cache,
+    navigation_policy: Option<NavigationPolicy>,
+    content_security_policy: Option<String>,
+    relax_csp_in_debug: bool,
+    secure_ipc: Option<Vec<String>>,
+    worker_threads: Option<usize>,
+    binding_namespace: Option<String>,
+    binding_timeout: Option<Duration>,
+    plugins: Vec<AddPlugin>,
+    offscreen: Option<PaintCallback>,
+    background_throttling: Option<BackgroundThrottling>,
+    hosts: Vec<(String, PathBuf)>,
+    #[cfg(feature = "mock")]
+    mock: bool,
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub(crate) gtk_options: Option<crate::gtk::Options>,
+    #[cfg(target_os = "macos")]
+    pub(crate) macos_options: Option<crate::macos::Options>,
+    #[cfg(target_os = "windows")]
+    pub(crate) windows_options: Option<crate::windows::Options>,
 }
 
 impl<'a> WebviewBuilder<'a> {
@@ -22,7 +80,272 @@ impl<'a> WebviewBuilder<'a> {
         self
     }
 
-    pub fn window(mut self, window: &'a mut Window) -> Self {
+    /// Leaves the native event loop to the host application instead of `Webview::run`.
+    ///
+    /// Use this when embedding into an application that already runs one, such as a
+    /// `GtkApplication` or `gtk_main` on Linux, or a Win32 message loop: pass its window with
+    /// `window`, build the webview and keep running the host loop. On Windows the loop has to
+    /// pass thread messages to `Webview::handle_message`. Urls are loaded as soon as they are set
+    /// and `terminate` only runs the quit hooks.
+    pub fn external_loop(mut self, external_loop: bool) -> Self {
+        self.external_loop = external_loop;
+        self
+    }
+
+    /// Backs the webview with the in-memory model of the `mock` module instead of a native
+    /// window, for testing the application without a display server. `window` is ignored.
+    #[cfg(feature = "mock")]
+    pub fn mock(mut self) -> Self {
+        self.mock = true;
+        self
+    }
+
+    /// Keeps the window title in sync with the page's `document.title`, see
+    /// `Webview::on_title_changed`.
+    pub fn sync_window_title(mut self, sync: bool) -> Self {
+        self.sync_window_title = sync;
+        self
+    }
+
+    /// Uses the page's icon as the window icon, see `Webview::on_favicon_changed`.
+    pub fn favicon_as_window_icon(mut self, enabled: bool) -> Self {
+        self.favicon_as_window_icon = enabled;
+        self
+    }
+
+    /// Lets the page move the window: pressing the primary button on an element with the
+    /// `data-webview-drag-region` attribute, or styled `app-region: drag`, starts a window drag.
+    /// Children marked `no-drag` stay clickable. See `Webview::start_window_drag`.
+    pub fn drag_regions(mut self, enabled: bool) -> Self {
+        self.drag_regions = enabled;
+        self
+    }
+
+    /// Opens the window owned by `owner`'s, it stays above it, e.g. for a settings or login
+    /// dialog. See `modal`.
+    ///
+    /// The window runs on its owner's event loop, as with `external_loop`, so `Webview::run`
+    /// isn't called for it and closing it doesn't quit. Dropping the webview closes it. The owner
+    /// is kept alive as long as it owns a window. Ignored along with `window`.
+    pub fn owner(mut self, owner: &Webview<'_>) -> Self {
+        self.owner = Some(owner.share());
+        self
+    }
+
+    /// Disables the owner's window while this one is open. On macOS the window is shown as a
+    /// sheet of its owner. Needs `owner`.
+    pub fn modal(mut self, modal: bool) -> Self {
+        self.modal = modal;
+        self
+    }
+
+    /// Shows `splash` in a window of its own right away, while the webview is created and its
+    /// page loads. The webview's window stays hidden until the page calls
+    /// `window.__webview_ready()`, or until `Webview::close_splash`. Ignored along with `window`.
+    pub fn splash(mut self, splash: Splash) -> Self {
+        self.splash = Some(splash);
+        self
+    }
+
+    /// Restores the window's size, position and maximized state from `path` and saves them
+    /// there as they change, the file is created as needed. Ignored along with `window`.
+    ///
+    /// A window saved on a monitor that is no longer connected moves to the one it overlaps
+    /// most, or the primary one, and is shrunk to fit.
+    pub fn persist_window_state(mut self, path: &Path) -> Self {
+        self.window_state = Some(path.to_path_buf());
+        self
+    }
+
+    /// Makes the page light or dark, by default it follows the system. See `Webview::set_theme`.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Whether the page slows down while the window is hidden, minimized or covered, by default
+    /// the engine throttles it.
+    ///
+    /// WebKitGTK only throttles, other choices are recorded in `unsupported_calls`, and so is
+    /// `Suspend` on Windows. On macOS before 14 the engine decides.
+    pub fn background_throttling(mut self, throttling: BackgroundThrottling) -> Self {
+        self.background_throttling = Some(throttling);
+        self
+    }
+
+    /// Shows `color` where the page doesn't paint, from before the first paint on. See
+    /// `Webview::set_background_color`.
+    ///
+    /// On Windows a color that isn't fully transparent is made opaque.
+    pub fn background_color(mut self, color: Color) -> Self {
+        self.background_color = Some(color);
+        self
+    }
+
+    /// Shows `effect` through the page wherever it doesn't paint, the background is transparent
+    /// unless `background_color` is set.
+    ///
+    /// Needs macOS, or Windows 11 22H2 or later. Elsewhere `WebviewBuilder::window_effect` is
+    /// recorded in `Webview::unsupported_calls`.
+    pub fn window_effect(mut self, effect: WindowEffect) -> Self {
+        self.window_effect = Some(effect);
+        self
+    }
+
+    /// Turns GPU compositing off with `false`, for virtual machines and remote desktops where
+    /// accelerated pages render as a black window. On by default.
+    ///
+    /// WebKitGTK then never composites on the GPU and WebView2 is started with `--disable-gpu`.
+    /// On Windows webviews sharing a user data folder share a browser process, the first one
+    /// created decides. `WKWebView` can't be kept off the GPU, there the call is recorded in
+    /// `Webview::unsupported_calls`.
+    pub fn hardware_acceleration(mut self, enabled: bool) -> Self {
+        self.disable_hardware_acceleration = !enabled;
+        self
+    }
+
+    /// Turns service workers off with `false`, so no worker goes on serving an old version of
+    /// the application after an update. On by default.
+    ///
+    /// An `init` script unregisters the workers registered before and removes
+    /// `navigator.serviceWorker` from every page, which pages check for before registering one.
+    /// The first page load can still be answered by a worker registered in an earlier run, see
+    /// `Webview::clear_service_workers`.
+    pub fn service_workers(mut self, enabled: bool) -> Self {
+        self.disable_service_workers = !enabled;
+        self
+    }
+
+    /// Keeps the HTTP cache in `dir`. Only supported on Windows.
+    ///
+    /// On Windows webviews sharing a user data folder share a browser process, the first one
+    /// created decides its cache settings.
+    pub fn cache_dir(mut self, dir: &Path) -> Self {
+        self.cache.dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Caps the HTTP cache at `bytes`. Only supported on Windows.
+    pub fn max_cache_size(mut self, bytes: u64) -> Self {
+        self.cache.max_size = Some(bytes);
+        self
+    }
+
+    /// Turns off the HTTP cache, or on macOS keeps it in memory only.
+    ///
+    /// On Linux and the BSDs this applies to every webview in the process.
+    pub fn disable_cache(mut self, disabled: bool) -> Self {
+        self.cache.disabled = disabled;
+        self
+    }
+
+    /// Restricts where the webview may navigate, see `NavigationPolicy`.
+    ///
+    /// `Webview::navigate` checks the policy before loading a url. The engine asks it before
+    /// following links, forms, redirects and `window.open`, so blocked pages never load. Frames
+    /// are not restricted, except on Linux where WebKitGTK doesn't tell them apart.
+    ///
+    /// On Windows, where the engine can't be asked, and on macOS when the view already has a
+    /// navigation delegate, an `init` script is all there is. It cancels links, forms and
+    /// `window.open` and leaves pages that were loaded anyway, on a best effort basis: pages
+    /// can get around it, and a blocked page may run before it leaves.
+    pub fn navigation_policy(mut self, policy: NavigationPolicy) -> Self {
+        self.navigation_policy = Some(policy);
+        self
+    }
+
+    /// Puts `policy`, e.g. `default-src 'self'`, on every page in a
+    /// `<meta http-equiv="Content-Security-Policy">` element, so packaged applications get a
+    /// secure default.
+    ///
+    /// The element is inserted by an `init` script as soon as the page's `<head>` is parsed and
+    /// applies to what loads from then on. It adds to the policy a page brings along, which
+    /// can only restrict it further. Style sheets from `Webview::add_css` need `style-src` to
+    /// allow inline styles.
+    pub fn content_security_policy(mut self, policy: &str) -> Self {
+        self.content_security_policy = Some(policy.to_string());
+        self
+    }
+
+    /// With `debug`, relaxes the `content_security_policy` for a dev server: inline scripts and
+    /// styles and `eval` are allowed, as are connections to `localhost` for hot reloading.
+    pub fn relax_csp_in_debug(mut self, relax: bool) -> Self {
+        self.relax_csp_in_debug = relax;
+        self
+    }
+
+    /// Only lets pages whose url matches one of `patterns` call bindings, with the same
+    /// patterns as `NavigationPolicy`, e.g. `https://app.example.com/*`.
+    ///
+    /// The bridge sends a random token made for this webview and the url the top frame was
+    /// loaded from along with every call, which are checked before the handler runs. Only pages
+    /// matching `patterns` are given the token. Calls from other pages, including remote content
+    /// the window navigated to, and calls that go around the bridge are rejected. `Webview::restrict_binding` narrows this down per binding.
+    pub fn secure_ipc(mut self, patterns: &[&str]) -> Self {
+        self.secure_ipc = Some(patterns.iter().map(|p| p.to_string()).collect());
+        self
+    }
+
+    /// How many threads `Webview::bind_pooled` handlers run on, by default as many as there
+    /// are CPUs, but at most 4.
+    pub fn worker_threads(mut self, threads: usize) -> Self {
+        self.worker_threads = Some(threads);
+        self
+    }
+
+    /// Puts bindings on the object at `namespace`, e.g. `myapp.commands` has the page call
+    /// `window.myapp.commands.save()`, instead of on `window`.
+    ///
+    /// The object is created by an init script, so it's there before anything is bound. The
+    /// bindings this crate makes for itself, starting with `__webview_`, stay on `window`.
+    pub fn binding_namespace(mut self, namespace: &str) -> Self {
+        self.binding_namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Rejects binding calls whose handler doesn't `return` within `timeout`, unless
+    /// `Webview::set_binding_timeout` gives the binding a timeout of its own.
+    pub fn binding_timeout(mut self, timeout: Duration) -> Self {
+        self.binding_timeout = Some(timeout);
+        self
+    }
+
+    /// Adds `plugin` once the webview is created, before `init` and `url`, see `Plugin`.
+    /// Plugins are added in the order they are given.
+    pub fn plugin<P: Plugin>(mut self, plugin: P) -> Self {
+        self.plugins.push(Box::new(move |w| w.add_plugin(plugin)));
+        self
+    }
+
+    /// Renders the page to pixels handed to `on_frame` instead of showing it in a window, e.g.
+    /// for an HTML UI composited by a game engine. The frames are `width` by `height` large,
+    /// and the page gets no input from the window. See `Webview::on_frame` for more handlers.
+    ///
+    /// None of the native backends can render offscreen yet, see `Capabilities::offscreen`:
+    /// `try_build` fails with `Error::BackendUnavailable` there and `build` panics.
+    pub fn offscreen<F>(mut self, on_frame: F) -> Self
+    where
+        F: FnMut(&Frame<'_>) + 'static,
+    {
+        self.offscreen = Some(Box::new(on_frame));
+        self
+    }
+
+    /// Serves the files in `folder` at `webview://{host}/`, so a packaged frontend keeps one
+    /// origin wherever it is installed, e.g. `map_host("app.local", path)` and
+    /// `url("webview://app.local/index.html")`. A directory is answered with its `index.html`,
+    /// paths leading out of `folder`, symbolic links included, aren't served.
+    ///
+    /// Hosts are shared by the webviews of the process. WebKitGTK treats the scheme as secure,
+    /// so pages get a secure context as on `https`. WebView2's virtual host mapping is out of
+    /// the native backend's reach, on Windows the call is recorded in
+    /// `Webview::unsupported_calls`.
+    pub fn map_host(mut self, host: &str, folder: &Path) -> Self {
+        self.hosts.push((host.to_string(), folder.to_path_buf()));
+        self
+    }
+
+    pub fn window(mut self, window: &'a mut NativeWindow) -> Self {
         self.window = Some(window);
         self
     }
@@ -70,12 +393,161 @@ impl<'a> WebviewBuilder<'a> {
         self
     }
 
+    /// Like `build`, but fails instead of panicking or creating a broken webview: with
+    /// `Error::RuntimeMissing` when Windows lacks the WebView2 runtime,
+    /// `Error::BackendUnavailable` when GTK has no display to connect to and
+    /// `Error::CreationFailed` when the native webview can't be created.
+    pub fn try_build(self) -> Result<Webview<'a>, Error> {
+        #[cfg(feature = "mock")]
+        if self.mock {
+            return self.create();
+        }
+        #[cfg(target_os = "windows")]
+        crate::windows::runtime_version()?;
+        crate::platform::check_backend()?;
+        self.create()
+    }
+
+    /// Panics if the native webview can't be created, see `try_build`.
     pub fn build(self) -> Webview<'a> {
-        let mut w = Webview::create(self.debug, self.window);
+        self.create().expect("Creating the webview failed")
+    }
+
+    fn create(self) -> Result<Webview<'a>, Error> {
+        let _span = span!(INFO, "build", title = ?self.title);
+        let (debug, window) = (self.debug, self.window);
+        let splash = match (&self.splash, &window) {
+            (Some(splash), None) => splash.open(self.title),
+            _ => None,
+        };
+        let owner = self.owner.filter(|_| window.is_none());
+        let window_state = self.window_state.filter(|_| window.is_none());
+        let external_loop = self.external_loop || owner.is_some();
+        #[cfg(feature = "mock")]
+        let mock = self.mock;
+        let create = move || {
+            #[cfg(feature = "mock")]
+            if mock {
+                return Ok(Webview::new_mock(external_loop));
+            }
+            Webview::new(debug, window, external_loop)
+        };
+
+        #[cfg(target_os = "windows")]
+        let environment = self
+            .windows_options
+            .clone()
+            .unwrap_or_default()
+            .with_cache(&self.cache)
+            .with_hardware_acceleration(!self.disable_hardware_acceleration)
+            .with_background_throttling(self.background_throttling)
+            .with_background_color(
+                // WebView2 only reads the color on creation, see `window_effect`.
+                self.background_color
+                    .or_else(|| self.window_effect.map(|_| Color::rgba(0, 0, 0, 0))),
+            )
+            .apply();
+        #[cfg(target_os = "macos")]
+        let mut w = match crate::macos::Options::with_hosts(
+            crate::macos::Options::with_cache(self.macos_options.clone(), &self.cache),
+            !self.hosts.is_empty(),
+        ) {
+            Some(options) => crate::platform::with_webkit_options(&options, create),
+            None => create(),
+        }?;
+        #[cfg(not(target_os = "macos"))]
+        let mut w = create()?;
+        #[cfg(target_os = "windows")]
+        drop(environment);
+        if self.disable_hardware_acceleration {
+            w.disable_hardware_acceleration();
+        }
+        if let Some(throttling) = self.background_throttling {
+            w.apply_background_throttling(throttling);
+        }
+        for (host, folder) in &self.hosts {
+            w.map_host(host, folder);
+        }
+        if let Some(mut on_frame) = self.offscreen {
+            w.render_offscreen()?;
+            w.on_frame(move |_, frame| on_frame(frame));
+        }
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        if let Some(options) = &self.gtk_options {
+            w.apply_gtk_options(options);
+        }
+        if let Some(owner) = owner {
+            w.set_owner(owner, self.modal);
+        }
+        if let Some(splash) = splash {
+            w.show_splash(splash);
+        }
+        w.apply_cache(&self.cache);
+        w.set_theme(self.theme);
+        // WebView2 took the color on creation already.
+        let set_color = cfg!(not(target_os = "windows")) || w.backend() == Backend::Mock;
+        if let Some(color) = self.background_color.filter(|_| set_color) {
+            w.set_background_color(color);
+        }
+        if let Some(effect) = self.window_effect {
+            // The effect shows where the page doesn't paint.
+            if w.apply_window_effect(effect) && set_color && self.background_color.is_none() {
+                w.set_background_color(Color::rgba(0, 0, 0, 0));
+            }
+        }
+        if let Some(policy) = self.navigation_policy {
+            w.apply_navigation_policy(policy);
+        }
+        if self.disable_service_workers {
+            w.disable_service_workers();
+        }
+        if let Some(mut policy) = self.content_security_policy {
+            if self.debug && self.relax_csp_in_debug {
+                policy = csp::relax(&policy);
+            }
+            w.apply_content_security_policy(&policy);
+        }
+        if let Some(patterns) = self.secure_ipc {
+            w.apply_secure_ipc(patterns);
+        }
+        if let Some(threads) = self.worker_threads {
+            w.set_worker_threads(threads);
+        }
+        if let Some(namespace) = self.binding_namespace {
+            w.apply_binding_namespace(namespace);
+        }
+        if let Some(timeout) = self.binding_timeout {
+            w.apply_binding_timeout(timeout);
+        }
+
         if let Some(title) = self.title {
             w.set_title(title);
         }
 
+        if self.sync_window_title {
+            w.on_title_changed(|w, title| w.set_title(title));
+        }
+
+        if self.favicon_as_window_icon {
+            w.on_favicon_changed(|w, icon| {
+                w.set_window_icon(&icon.bytes);
+            });
+        }
+
+        if self.drag_regions {
+            w.enable_drag_regions();
+        }
+
+        for add in self.plugins {
+            add(&mut w);
+        }
+
         if let Some(init) = self.init {
             w.init(init);
         }
@@ -90,10 +562,14 @@ impl<'a> WebviewBuilder<'a> {
 
         w.set_size(self.size.0 as i32, self.size.1 as i32, self.size.2);
 
+        if let Some(path) = window_state {
+            w.persist_window_state(path);
+        }
+
         if let Some(f) = self.dispatch {
             w.dispatch(f);
         }
 
-        w
+        Ok(w)
     }
 }
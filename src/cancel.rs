@@ -0,0 +1,148 @@
+//! Cancelling binding calls the page no longer waits for, see `Webview::bind_cancellable`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::json;
+
+/// The name of the binding the page cancels calls through.
+pub(crate) const BINDING: &str = "__webview_cancel";
+
+/// What the page's promise is rejected with when it cancels a call.
+pub(crate) const REJECTION: &str = r#"{"name":"AbortError","message":"The call was cancelled"}"#;
+
+/// Remembers the id of the latest binding call for `window.__webview_last_call` and cancels
+/// all calls when the page goes away.
+pub(crate) const SCRIPT: &str = r#"(function () {
+  var external = window.external, invoke = external && external.invoke;
+  if (window !== window.top || !invoke || window.__webview_last_call) {
+    return;
+  }
+  var last = null;
+  external.invoke = function (message) {
+    try {
+      last = JSON.parse(message).id;
+    } catch (e) {}
+    return invoke.apply(external, arguments);
+  };
+  // The bridge of `secure_ipc` goes around this wrapper when it comes after, it records the id.
+  external.invoke.__webview_record = function (id) { last = id; };
+  window.__webview_last_call = function () { return last; };
+  window.addEventListener("pagehide", function () { window.__webview_cancel("*"); });
+})();"#;
+
+/// The name of the binding a new page announces itself through, so the calls of the previous
+/// one are cancelled.
+pub(crate) const PAGE_BINDING: &str = "__webview_page";
+
+/// Announces the page as it starts, with `nonce`. Init scripts run before the page's own and
+/// their source isn't visible to it, so a page can't announce itself again to drop the calls
+/// and limits it has pending.
+pub(crate) fn page_script(nonce: &str) -> String {
+    format!(
+        "if (window === window.top) {{ window.{}({}); }}",
+        PAGE_BINDING,
+        json::string(nonce)
+    )
+}
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// Tells a binding handler that the page no longer waits for the call, see
+/// `Webview::bind_cancellable`.
+///
+/// Clones share the state, it can be handed to other threads.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<State>);
+
+#[derive(Default)]
+struct State {
+    cancelled: AtomicBool,
+    callbacks: Mutex<Vec<Callback>>,
+}
+
+impl CancellationToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Calls `f` once the call is cancelled, right away if it already is, e.g. to interrupt a
+    /// database query.
+    pub fn on_cancel<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut callbacks = self.0.callbacks.lock().unwrap();
+        if self.is_cancelled() {
+            drop(callbacks);
+            f();
+        } else {
+            callbacks.push(Box::new(f));
+        }
+    }
+
+    pub(crate) fn cancel(&self) {
+        let callbacks = {
+            let mut callbacks = self.0.callbacks.lock().unwrap();
+            self.0.cancelled.store(true, Ordering::SeqCst);
+            mem::take(&mut *callbacks)
+        };
+        for callback in callbacks {
+            callback();
+        }
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// The cancellable calls that haven't been returned yet, by their `seq`.
+#[derive(Default)]
+pub(crate) struct Calls {
+    pending: HashMap<String, CancellationToken>,
+    /// Calls the page cancelled, whose results are no longer handed to it.
+    cancelled: HashSet<String>,
+}
+
+impl Calls {
+    pub(crate) fn start(&mut self, seq: &str) -> CancellationToken {
+        let token = CancellationToken::default();
+        self.pending.insert(seq.to_string(), token.clone());
+        token
+    }
+
+    /// Takes the token of the pending call `seq` to cancel it.
+    pub(crate) fn cancel(&mut self, seq: &str) -> Option<CancellationToken> {
+        let token = self.pending.remove(seq)?;
+        self.cancelled.insert(seq.to_string());
+        Some(token)
+    }
+
+    /// Gives up on the call `seq` whether it is cancellable or not, its result is no longer
+    /// handed to the page. Returns its token to cancel it.
+    pub(crate) fn abandon(&mut self, seq: &str) -> Option<CancellationToken> {
+        self.cancelled.insert(seq.to_string());
+        self.pending.remove(seq)
+    }
+
+    /// Takes the tokens of all pending calls to cancel them, when the page is gone.
+    pub(crate) fn cancel_all(&mut self) -> Vec<CancellationToken> {
+        // The next page counts its calls from 1 again, its own calls mustn't be held back.
+        self.cancelled.clear();
+        self.pending.drain().map(|(_, token)| token).collect()
+    }
+
+    /// Notes that the call `seq` was returned, `false` if the page cancelled it.
+    pub(crate) fn finished(&mut self, seq: &str) -> bool {
+        self.pending.remove(seq);
+        !self.cancelled.remove(seq)
+    }
+}
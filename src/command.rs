@@ -0,0 +1,63 @@
+//! Errors binding handlers reject the page's promise with, see `Webview::reject`.
+
+use std::fmt;
+
+use crate::json;
+
+/// What went wrong in a binding call, the page's promise is rejected with an `Error` carrying
+/// the `code`, the `message` and the `data`, see `Webview::reject`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandError {
+    code: String,
+    message: String,
+    /// JSON.
+    data: Option<String>,
+}
+
+impl CommandError {
+    /// `code` is meant for the page to tell errors apart, e.g. `NOT_FOUND`, `message` for
+    /// people.
+    pub fn new(code: &str, message: &str) -> Self {
+        CommandError {
+            code: code.to_string(),
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    /// Hands the page more about the error as the `data` of the `Error`, e.g. the id that
+    /// wasn't found. `json` that isn't valid JSON is handed over as a string.
+    pub fn data(mut self, json: &str) -> Self {
+        self.data = Some(match json::parse(json) {
+            Some(_) => json.to_string(),
+            None => json::string(json),
+        });
+        self
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Creates the `Error` for the page, `webview_return` hands it to the promise as it is.
+    pub(crate) fn script(&self) -> String {
+        format!(
+            "Object.assign(new Error({}), {{\"name\":\"CommandError\",\"code\":{},\"data\":{}}})",
+            json::string(&self.message),
+            json::string(&self.code),
+            self.data.as_deref().unwrap_or("null")
+        )
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
@@ -0,0 +1,41 @@
+use crate::json;
+
+/// The name of the binding the page reports compositions through.
+pub(crate) const BINDING: &str = "__webview_composition";
+
+/// Reports the composition events of every element, before the page's own listeners.
+pub(crate) const INIT_SCRIPT: &str = r#"(function () {
+  ["compositionstart", "compositionupdate", "compositionend"].forEach(function (type) {
+    window.addEventListener(type, function (event) {
+      window.__webview_composition(type.slice(11), event.data || "");
+    }, true);
+  });
+})();"#;
+
+/// A step of composing text with an input method, e.g. for Chinese, Japanese or Korean, see
+/// `Webview::on_composition`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompositionEvent {
+    /// The input method started composing.
+    Start,
+    /// The text being composed changed.
+    Update(String),
+    /// The input method committed the text, or cancelled with an empty one.
+    End(String),
+}
+
+impl CompositionEvent {
+    /// Parses the arguments the init script passed to the binding.
+    pub(crate) fn from_req(req: &str) -> Option<CompositionEvent> {
+        let mut args = json::strings(req).into_iter();
+        let kind = args.next()?;
+        let data = args.next().unwrap_or_default();
+        match kind.as_str() {
+            "start" => Some(CompositionEvent::Start),
+            "update" => Some(CompositionEvent::Update(data)),
+            "end" => Some(CompositionEvent::End(data)),
+            _ => None,
+        }
+    }
+}
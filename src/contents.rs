@@ -0,0 +1,113 @@
+//! The page half of a webview, see `WebContents`.
+
+use std::time::Duration;
+
+use crate::{CssHandle, Reply, Webview};
+
+/// What a webview shows: navigating, running scripts and binding functions for the page.
+///
+/// Got from `Webview::contents`, it keeps the webview alive like a clone and leaves the window
+/// alone, see `Window` for that.
+#[derive(Clone)]
+pub struct WebContents<'a> {
+    webview: Webview<'a>,
+}
+
+impl<'a> WebContents<'a> {
+    pub(crate) fn new(webview: Webview<'a>) -> WebContents<'a> {
+        WebContents { webview }
+    }
+
+    /// The webview showing the contents.
+    pub fn webview(&self) -> &Webview<'a> {
+        &self.webview
+    }
+
+    /// See `Webview::navigate`.
+    pub fn navigate(&mut self, url: &'a str) {
+        self.webview.navigate(url);
+    }
+
+    /// See `Webview::navigate_with_headers`.
+    pub fn navigate_with_headers(&mut self, url: &str, headers: &[(&str, &str)]) {
+        self.webview.navigate_with_headers(url, headers);
+    }
+
+    /// See `Webview::navigate_post`.
+    pub fn navigate_post(&mut self, url: &str, body: &[u8], content_type: &str) {
+        self.webview.navigate_post(url, body, content_type);
+    }
+
+    pub fn go_back(&mut self) {
+        self.webview.go_back();
+    }
+
+    pub fn go_forward(&mut self) {
+        self.webview.go_forward();
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.webview.can_go_back()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.webview.can_go_forward()
+    }
+
+    pub fn reload(&mut self) {
+        self.webview.reload();
+    }
+
+    pub fn stop(&mut self) {
+        self.webview.stop();
+    }
+
+    pub fn init(&mut self, js: &str) {
+        self.webview.init(js);
+    }
+
+    pub fn eval(&mut self, js: &str) {
+        self.webview.eval(js);
+    }
+
+    /// See `Webview::eval_with_result`.
+    pub fn eval_with_result(&mut self, js: &str) -> Reply {
+        self.webview.eval_with_result(js)
+    }
+
+    /// See `Webview::call`.
+    pub fn call(&mut self, function: &str, args: &str) -> Reply {
+        self.webview.call(function, args)
+    }
+
+    /// See `Webview::wait_for_selector`.
+    pub fn wait_for_selector(&mut self, selector: &str, timeout: Duration) -> Reply<()> {
+        self.webview.wait_for_selector(selector, timeout)
+    }
+
+    /// See `Webview::add_css`.
+    pub fn add_css(&mut self, css: &str) -> CssHandle {
+        self.webview.add_css(css)
+    }
+
+    pub fn remove_css(&mut self, handle: CssHandle) {
+        self.webview.remove_css(handle);
+    }
+
+    pub fn bind<F>(&mut self, name: &str, f: F)
+    where
+        F: FnMut(&str, &str) + 'static,
+    {
+        self.webview.bind(name, f);
+    }
+
+    /// See `Webview::unbind`.
+    pub fn unbind(&mut self, name: &str) {
+        self.webview.unbind(name);
+    }
+
+    /// Answers the call `seq` of a binding, see `Webview::r#return`.
+    pub fn r#return(&self, seq: &str, status: i32, result: &str) {
+        self.webview.r#return(seq, status, result);
+    }
+}
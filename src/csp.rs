@@ -0,0 +1,84 @@
+//! The Content Security Policy put on every page, see `WebviewBuilder::content_security_policy`.
+
+use crate::json;
+
+/// What a relaxed policy allows on top, for dev servers with hot reloading.
+const RELAXED: [(&str, &str); 3] = [
+    ("script-src", "'unsafe-inline' 'unsafe-eval'"),
+    ("style-src", "'unsafe-inline'"),
+    (
+        "connect-src",
+        "ws://localhost:* ws://127.0.0.1:* http://localhost:* http://127.0.0.1:*",
+    ),
+];
+
+/// `policy` with the sources of `RELAXED` added. Directives the policy doesn't have start out
+/// with the sources of its `default-src`, if it has one.
+pub(crate) fn relax(policy: &str) -> String {
+    let mut directives: Vec<(String, String)> = policy
+        .split(';')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(
+            |directive| match directive.split_once(char::is_whitespace) {
+                Some((name, sources)) => (name.to_ascii_lowercase(), sources.trim().to_string()),
+                None => (directive.to_ascii_lowercase(), String::new()),
+            },
+        )
+        .collect();
+    let default = directives
+        .iter()
+        .find(|(name, _)| name == "default-src")
+        .map(|(_, sources)| sources.clone());
+    for (name, extra) in RELAXED.iter() {
+        match directives.iter_mut().find(|(n, _)| n == name) {
+            Some((_, sources)) if sources.trim() == "'none'" => *sources = extra.to_string(),
+            Some((_, sources)) => *sources = format!("{} {}", sources, extra).trim().to_string(),
+            // Without `default-src` anything goes already.
+            None => {
+                if let Some(default) = default.as_deref() {
+                    let sources = match default.trim() {
+                        "'none'" => extra.to_string(),
+                        default => format!("{} {}", default, extra),
+                    };
+                    directives.push((name.to_string(), sources));
+                }
+            }
+        }
+    }
+    directives
+        .iter()
+        .map(|(name, sources)| format!("{} {}", name, sources).trim().to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Puts `policy` in a `<meta http-equiv="Content-Security-Policy">` element as soon as the
+/// document has its `<head>`, where the policy takes effect, in the top frame only.
+pub(crate) fn script(policy: &str) -> String {
+    format!(
+        r#"(function () {{
+  if (window !== window.top) {{
+    return;
+  }}
+  var meta = document.createElement("meta");
+  meta.httpEquiv = "Content-Security-Policy";
+  meta.content = {};
+  var place = function () {{
+    if (document.head && !meta.isConnected) {{
+      document.head.insertBefore(meta, document.head.firstChild);
+    }}
+    return meta.isConnected;
+  }};
+  if (!place()) {{
+    var observer = new MutationObserver(function () {{
+      if (place()) {{
+        observer.disconnect();
+      }}
+    }});
+    observer.observe(document, {{ childList: true, subtree: true }});
+  }}
+}})();"#,
+        json::string(policy)
+    )
+}
@@ -0,0 +1,122 @@
+//! Style sheets applied to every page, see `Webview::add_css`.
+
+use crate::json;
+
+pub(crate) const BINDING: &str = "__webview_css_sheets";
+
+/// Keeps the sheets of the page, inserting each as a `<style>` element as soon as the document
+/// has an element to put it in, and again if the page takes it out.
+///
+/// Init scripts can't be removed, so this one is added once and asks `BINDING` for the sheets
+/// on every page. Sheets added or removed while a page is shown are passed to `add` and
+/// `remove`.
+pub(crate) const INIT_SCRIPT: &str = r#"(function () {
+  if (window.__webview_css) {
+    return;
+  }
+  var sheets = {};
+  var place = function () {
+    var root = document.head || document.documentElement;
+    if (!root) {
+      return;
+    }
+    for (var id in sheets) {
+      if (!sheets[id].isConnected) {
+        root.appendChild(sheets[id]);
+      }
+    }
+  };
+  new MutationObserver(place).observe(document, { childList: true, subtree: true });
+  window.__webview_css = {
+    add: function (id, css) {
+      if (sheets[id]) {
+        return;
+      }
+      var style = document.createElement("style");
+      style.setAttribute("data-webview-css", id);
+      style.textContent = css;
+      sheets[id] = style;
+      place();
+    },
+    remove: function (id) {
+      var style = sheets[id];
+      delete sheets[id];
+      if (style && style.parentNode) {
+        style.parentNode.removeChild(style);
+      }
+    },
+  };
+  window.__webview_css_sheets().then(function (list) {
+    list.forEach(function (sheet) { window.__webview_css.add(sheet[0], sheet[1]); });
+  }, function () {});
+})();"#;
+
+/// A style sheet added with `Webview::add_css`, to remove it with `Webview::remove_css`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CssHandle(pub(crate) u64);
+
+/// The sheets added and not removed yet, in the order they were added.
+#[derive(Default)]
+pub(crate) struct Sheets {
+    last: u64,
+    active: Vec<(u64, String)>,
+}
+
+impl Sheets {
+    /// Adds `css`, returns its id and whether it is the first sheet ever added.
+    pub(crate) fn add(&mut self, css: &str) -> (u64, bool) {
+        self.last += 1;
+        self.active.push((self.last, css.to_string()));
+        (self.last, self.last == 1)
+    }
+
+    pub(crate) fn remove(&mut self, id: u64) {
+        self.active.retain(|(sheet, _)| *sheet != id);
+    }
+
+    /// The sheets as `BINDING` answers with them, `[id, css]` pairs.
+    pub(crate) fn json(&self) -> String {
+        let sheets: Vec<String> = self
+            .active
+            .iter()
+            .map(|(id, css)| format!("[{},{}]", id, json::string(css)))
+            .collect();
+        format!("[{}]", sheets.join(","))
+    }
+}
+
+/// Adds the sheet `id`.
+pub(crate) fn add_script(id: u64, css: &str) -> String {
+    format!("window.__webview_css.add({}, {});", id, json::string(css))
+}
+
+/// Removes the sheet `id`.
+pub(crate) fn remove_script(id: u64) -> String {
+    format!("window.__webview_css.remove({});", id)
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::WebviewBuilder;
+
+    #[test]
+    fn pages_ask_for_the_active_sheets() {
+        let mut webview = WebviewBuilder::new().mock().build();
+        let first = webview.add_css("body { color: red; }");
+        webview.add_css("p { margin: 0; }");
+        webview.remove_css(first);
+        let mock = webview.mock();
+        let scripts = mock.init_scripts();
+        assert_eq!(scripts.iter().filter(|s| *s == INIT_SCRIPT).count(), 1);
+        assert!(!scripts.iter().any(|s| s.contains("margin")));
+
+        let seq = mock.call(BINDING, "[]").unwrap();
+        mock.run_pending();
+        let answer = mock.returns().into_iter().find(|ret| ret.seq == seq);
+        assert_eq!(
+            answer.map(|ret| ret.result),
+            Some(r#"[[2,"p { margin: 0; }"]]"#.to_string())
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! The C strings handed to the native webview, which end at the first null byte, so the null
+//! bytes of titles and scripts coming from the page or the user are dealt with instead.
+
+use std::ffi::CString;
+
+fn replacing_nul(s: &str, replacement: &str) -> CString {
+    CString::new(s)
+        .unwrap_or_else(|_| CString::new(s.replace('\0', replacement)).unwrap_or_default())
+}
+
+/// `text` without its null bytes, for titles, names and the like.
+pub(crate) fn text(text: &str) -> CString {
+    replacing_nul(text, "")
+}
+
+/// `js` with its null bytes escaped. They can only be in its literals and comments, where the
+/// escape means the same.
+pub(crate) fn script(js: &str) -> CString {
+    replacing_nul(js, "\\u0000")
+}
+
+/// `url` with its null bytes percent-encoded.
+pub(crate) fn url(url: &str) -> CString {
+    replacing_nul(url, "%00")
+}
@@ -0,0 +1,53 @@
+//! Links to the application's own url schemes, see `Application::register_url_scheme`.
+
+use std::env;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::platform;
+
+/// The schemes registered by this process, lowercase.
+static SCHEMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static LAUNCH_TAKEN: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn register(scheme: &str) -> io::Result<()> {
+    // RFC 3986: a letter followed by letters, digits, "+", "-" or ".".
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a valid url scheme",
+        ));
+    }
+    platform::register_url_scheme(scheme)?;
+    let scheme = scheme.to_ascii_lowercase();
+    let mut schemes = SCHEMES.lock().unwrap();
+    if !schemes.contains(&scheme) {
+        schemes.push(scheme);
+    }
+    Ok(())
+}
+
+/// The arguments that are links to one of the registered schemes.
+pub(crate) fn links(args: &[String]) -> Vec<String> {
+    let schemes = SCHEMES.lock().unwrap();
+    args.iter()
+        .filter(|arg| match arg.find(':') {
+            Some(end) => schemes.contains(&arg[..end].to_ascii_lowercase()),
+            None => false,
+        })
+        .cloned()
+        .collect()
+}
+
+/// The links the process was started with, only the first call gets them.
+pub(crate) fn take_launch_links() -> Vec<String> {
+    if LAUNCH_TAKEN.swap(true, Ordering::SeqCst) {
+        return Vec::new();
+    }
+    links(&env::args().skip(1).collect::<Vec<_>>())
+}
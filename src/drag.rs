@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+/// The name of the binding the page starts window drags through.
+pub(crate) const BINDING: &str = "__webview_drag";
+
+/// Starts a window drag when the primary button goes down on a drag region: an element with a
+/// `data-webview-drag-region` attribute, or with `app-region: drag` where the engine knows the
+/// property. `no-drag` as either value excludes an element from its ancestor's region.
+pub(crate) const INIT_SCRIPT: &str = r#"(function () {
+  var region = function (element) {
+    for (; element && element.nodeType === 1; element = element.parentElement) {
+      var value = element.getAttribute("data-webview-drag-region");
+      if (value === "") {
+        value = "drag";
+      } else if (value === null) {
+        var style = getComputedStyle(element);
+        value = style.getPropertyValue("app-region") ||
+          style.getPropertyValue("-webkit-app-region");
+      }
+      if (value === "no-drag") {
+        return false;
+      }
+      if (value === "drag") {
+        return true;
+      }
+    }
+    return false;
+  };
+  document.addEventListener("mousedown", function (event) {
+    if (event.button === 0 && region(event.target)) {
+      event.preventDefault();
+      window.__webview_drag();
+    }
+  });
+})();"#;
+
+/// What `Webview::start_drag` drags out of the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DragItem {
+    /// Files, which file managers copy and other applications open.
+    Files(Vec<PathBuf>),
+    /// Plain text.
+    Text(String),
+}
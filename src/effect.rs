@@ -0,0 +1,14 @@
+/// A translucent material behind the page, see `WebviewBuilder::window_effect`.
+///
+/// Each backend shows the closest material it has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WindowEffect {
+    /// The blurred window background of macOS. Acrylic on Windows.
+    Vibrancy,
+    /// The blurred, lightly tinted material of Windows 11 popups. The HUD material on macOS.
+    Acrylic,
+    /// The wallpaper-tinted material of Windows 11 main windows. The window background material
+    /// on macOS.
+    Mica,
+}
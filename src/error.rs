@@ -1,16 +1,74 @@
+use std::ffi::NulError;
 use std::fmt;
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     WebviewNull,
+    /// The native webview couldn't be created, e.g. the window system refused the window.
+    CreationFailed,
+    /// A string handed to the native webview held a null byte, which it can't take.
+    InvalidCString(NulError),
+    /// The backend can't run here, e.g. there is no display to connect to, with the reason.
+    BackendUnavailable(String),
+    /// The page's answer to the webview couldn't be understood, with the answer.
+    Ipc(String),
+    /// The page's script threw, with the exception's message.
+    Script(String),
+    /// The page didn't answer in time.
+    Timeout,
+    /// The page navigated away before it answered.
+    Navigated,
+    /// No WebView2 runtime is installed, see the `windows` module.
+    RuntimeMissing,
+    /// Installing the WebView2 runtime failed.
+    RuntimeInstall(String),
+    /// Checking for, downloading or installing an update failed, see the `updater` module.
+    Update(String),
+    /// The OAuth provider refused or the redirect was malformed, see the `oauth` module.
+    Authorization(String),
+    /// A `Schema` couldn't be parsed or uses keywords that aren't supported.
+    Schema(String),
+    /// The system's credential store failed, see the `secure_storage` module.
+    SecureStorage(String),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::WebviewNull => "Webview instance is null. Probably already dropped.".fmt(f),
+            Error::CreationFailed => "Creating the webview failed.".fmt(f),
+            Error::InvalidCString(_) => "The string holds a null byte.".fmt(f),
+            Error::BackendUnavailable(reason) => {
+                write!(f, "The webview can't run here: {}", reason)
+            }
+            Error::Ipc(answer) => write!(f, "The page's answer is malformed: {}", answer),
+            Error::Script(message) => write!(f, "The script threw: {}", message),
+            Error::Timeout => "The page didn't answer in time.".fmt(f),
+            Error::Navigated => "The page navigated away before it answered.".fmt(f),
+            Error::RuntimeMissing => "The WebView2 runtime is not installed.".fmt(f),
+            Error::RuntimeInstall(reason) => {
+                write!(f, "Installing the WebView2 runtime failed: {}", reason)
+            }
+            Error::Update(reason) => write!(f, "Updating failed: {}", reason),
+            Error::Authorization(reason) => write!(f, "Authorization failed: {}", reason),
+            Error::Schema(reason) => write!(f, "The schema is invalid: {}", reason),
+            Error::SecureStorage(reason) => write!(f, "The secure storage failed: {}", reason),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidCString(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<NulError> for Error {
+    fn from(e: NulError) -> Self {
+        Error::InvalidCString(e)
+    }
+}
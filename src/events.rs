@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::json;
+use crate::SidecarEvent;
+
+/// The name of the binding the page reports its navigations through.
+pub(crate) const BINDING: &str = "__webview_event";
+
+/// Reports navigations from every page, `init` scripts run before the page's own scripts.
+pub(crate) const INIT_SCRIPT: &str = r#"
+window.__webview_event("started", window.location.href);
+window.addEventListener("load", function () {
+  window.__webview_event("loaded", window.location.href);
+});
+"#;
+
+/// Something that happened to a webview, see `Webview::events_stream`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A page started loading, before any of its scripts ran.
+    NavigationStarted(String),
+    /// A page and its resources finished loading.
+    Loaded(String),
+    /// The webview started quitting, either through `terminate` or because its window was closed.
+    Quit,
+    /// A thread other than the UI thread panicked, see `Webview::install_panic_overlay`.
+    Panic {
+        message: String,
+        location: Option<String>,
+    },
+    /// Something happened to the sidecar `name`, see `Webview::spawn_sidecar`.
+    Sidecar { name: String, event: SidecarEvent },
+}
+
+impl Event {
+    /// Parses the arguments the init script passed to the binding.
+    pub(crate) fn from_req(req: &str) -> Option<Event> {
+        let mut args = json::strings(req).into_iter();
+        let kind = args.next()?;
+        let url = args.next()?;
+        match kind.as_str() {
+            "started" => Some(Event::NavigationStarted(url)),
+            "loaded" => Some(Event::Loaded(url)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Channel {
+    queue: VecDeque<Event>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// The streams handed out for a webview.
+#[derive(Default)]
+pub(crate) struct Events {
+    channels: Mutex<Vec<Arc<Mutex<Channel>>>>,
+    bound: AtomicBool,
+}
+
+impl Events {
+    /// Returns `true` the first time it is called, when the binding still has to be set up.
+    pub(crate) fn bind_once(&self) -> bool {
+        !self.bound.swap(true, Ordering::SeqCst)
+    }
+
+    pub(crate) fn subscribe(&self) -> EventStream {
+        let channel = Arc::new(Mutex::new(Channel::default()));
+        self.channels.lock().unwrap().push(channel.clone());
+        EventStream { channel }
+    }
+
+    pub(crate) fn emit(&self, event: Event) {
+        let mut channels = self.channels.lock().unwrap();
+        // Streams that were dropped don't need the event.
+        channels.retain(|channel| Arc::strong_count(channel) > 1);
+        for channel in channels.iter() {
+            let mut channel = channel.lock().unwrap();
+            channel.queue.push_back(event.clone());
+            if let Some(waker) = channel.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Ends every stream once the queued events are consumed.
+    pub(crate) fn close(&self) {
+        for channel in self.channels.lock().unwrap().drain(..) {
+            let mut channel = channel.lock().unwrap();
+            channel.closed = true;
+            if let Some(waker) = channel.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The events of a webview, ending once the webview is destroyed.
+pub struct EventStream {
+    channel: Arc<Mutex<Channel>>,
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        let mut channel = self.channel.lock().unwrap();
+        if let Some(event) = channel.queue.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if channel.closed {
+            return Poll::Ready(None);
+        }
+        channel.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
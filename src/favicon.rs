@@ -0,0 +1,76 @@
+/// The name of the binding the page reports its icon through.
+pub(crate) const BINDING: &str = "__webview_favicon";
+
+/// Fetches the icon the page links to, or `/favicon.ico` of http(s) pages, whenever it changes
+/// and reports it base64 encoded. Icons on other origins are only reported if they allow CORS.
+pub(crate) const INIT_SCRIPT: &str = r#"(function () {
+  if (window !== window.top) {
+    return;
+  }
+  var last;
+  var check = function () {
+    var link = document.querySelector('link[rel~="icon"][href]');
+    if (!link && !/^https?:$/.test(location.protocol)) {
+      return;
+    }
+    var url = new URL(link ? link.getAttribute("href") : "/favicon.ico", document.baseURI).href;
+    if (url === last) {
+      return;
+    }
+    last = url;
+    fetch(url).then(function (response) {
+      if (!response.ok) {
+        throw new Error(response.statusText);
+      }
+      return response.arrayBuffer();
+    }).then(function (buffer) {
+      var bytes = new Uint8Array(buffer), binary = "";
+      for (var i = 0; i < bytes.length; i++) {
+        binary += String.fromCharCode(bytes[i]);
+      }
+      window.__webview_favicon(url, btoa(binary));
+    }).catch(function () {});
+  };
+  document.addEventListener("DOMContentLoaded", function () {
+    check();
+    new MutationObserver(check).observe(document.head || document.documentElement, {
+      subtree: true,
+      childList: true,
+      attributes: true,
+      attributeFilter: ["href", "rel"],
+    });
+  });
+})();"#;
+
+/// The icon of the loaded page, see `Webview::on_favicon_changed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Favicon {
+    pub url: String,
+    /// The image as served, usually PNG, ICO or SVG.
+    pub bytes: Vec<u8>,
+}
+
+/// Decodes standard base64, as produced by `btoa`.
+pub(crate) fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let s = s.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for &c in s {
+        bits = bits << 6 | value(c)? as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Some(bytes)
+}
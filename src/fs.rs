@@ -0,0 +1,502 @@
+//! Letting the page read and write files in the directories the application allows, with the
+//! `Fs` plugin.
+//!
+//! The page calls these, which return promises:
+//!
+//! - `window.__webview_fs_read(path)` resolves with the file's text.
+//! - `window.__webview_fs_write(path, text)` replaces the file's text, creating the file.
+//! - `window.__webview_fs_list(path)` resolves with the entries of a directory, sorted by name,
+//!   as objects with their `name`, `path`, `kind` (`"file"` or `"dir"`) and `size` in bytes.
+//! - `window.__webview_fs_watch(path)` resolves with an id, changes to the file or to the
+//!   entries of the directory are then reported as `webviewfs` events, whose `detail` has the
+//!   `id`, the `path` and a `kind` of `"created"`, `"modified"` or `"removed"`.
+//! - `window.__webview_fs_unwatch(id)` stops reporting them.
+//!
+//! Only pages allowed with `Fs::allow_from` can call them, the calls of other pages are
+//! rejected before they reach the plugin.
+//!
+//! Relative paths are taken from the first allowed directory. Paths are resolved, following
+//! `..` and symbolic links, before they are checked, calls reaching outside the allowed
+//! directories are rejected with a `CommandError` whose `code` is `"FORBIDDEN"`, writes to a
+//! directory allowed with `Fs::allow_read` with `"READ_ONLY"` and failing I/O with `"IO"`. A
+//! file that doesn't exist yet can't be a symbolic link, and files are opened without following
+//! one put in place after the check. Watches end when the page navigates away.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(windows)]
+use std::os::windows::fs::OpenOptionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::{json, CommandError, Plugin, Webview, WebviewHandle};
+
+const READ_BINDING: &str = "__webview_fs_read";
+const WRITE_BINDING: &str = "__webview_fs_write";
+const LIST_BINDING: &str = "__webview_fs_list";
+const WATCH_BINDING: &str = "__webview_fs_watch";
+const UNWATCH_BINDING: &str = "__webview_fs_unwatch";
+
+/// How often watched paths are looked at unless `Fs::poll_interval` says otherwise.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    ReadWrite,
+}
+
+/// The directories the page may reach.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    dirs: Vec<(PathBuf, Access)>,
+}
+
+impl Scope {
+    /// Resolves `path` and checks that it is in an allowed directory, with `access`.
+    fn resolve(&self, path: &str, access: Access) -> Result<PathBuf, CommandError> {
+        let forbidden =
+            || CommandError::new("FORBIDDEN", "The path is outside the allowed directories");
+        let path = Path::new(path);
+        let path = match self.dirs.first() {
+            Some((base, _)) if path.is_relative() => base.join(path),
+            Some(_) => path.to_path_buf(),
+            None => return Err(forbidden()),
+        };
+        let resolved = canonicalize(&path).ok_or_else(forbidden)?;
+        let allowed = self
+            .dirs
+            .iter()
+            .filter_map(|(dir, dir_access)| Some((fs::canonicalize(dir).ok()?, *dir_access)))
+            .filter(|(dir, _)| resolved.starts_with(dir))
+            .map(|(_, dir_access)| dir_access)
+            .max_by_key(|dir_access| *dir_access == Access::ReadWrite);
+        match allowed {
+            None => Err(forbidden()),
+            Some(Access::Read) if access == Access::ReadWrite => Err(CommandError::new(
+                "READ_ONLY",
+                "The path is in a read-only directory",
+            )),
+            Some(_) => Ok(resolved),
+        }
+    }
+}
+
+/// Resolves `path` like `fs::canonicalize`, but also when its last component doesn't exist yet,
+/// as for a file about to be written. A symbolic link pointing nowhere isn't resolved, writing
+/// to it would create its target wherever that is.
+fn canonicalize(path: &Path) -> Option<PathBuf> {
+    if let Ok(path) = fs::canonicalize(path) {
+        return Some(path);
+    }
+    let name = match path.components().next_back()? {
+        Component::Normal(name) => name,
+        _ => return None,
+    };
+    let path = fs::canonicalize(path.parent()?).ok()?.join(name);
+    match fs::symlink_metadata(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Some(path),
+        _ => None,
+    }
+}
+
+/// Opens the file at `path`, which `Scope::resolve` checked, failing if it has become a
+/// symbolic link since.
+fn open(path: &Path, write: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    if write {
+        options.write(true).create(true).truncate(true);
+    } else {
+        options.read(true);
+    }
+    let link = || {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the path is a symbolic link",
+        )
+    };
+    #[cfg(unix)]
+    {
+        match no_follow() {
+            Some(flag) => {
+                options.custom_flags(flag);
+            }
+            // Not as tight as the flag, but the best this system allows for.
+            None if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) => {
+                return Err(link())
+            }
+            None => {}
+        }
+        options.open(path)
+    }
+    #[cfg(windows)]
+    {
+        // Opens a link itself rather than what it points to, so it can be told apart.
+        const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+        let file = options
+            .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT)
+            .open(path)?;
+        if file.metadata()?.file_type().is_symlink() {
+            return Err(link());
+        }
+        Ok(file)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = link;
+        options.open(path)
+    }
+}
+
+/// `O_NOFOLLOW`, whose value depends on the system and, on Linux, the architecture.
+#[cfg(unix)]
+fn no_follow() -> Option<i32> {
+    if cfg!(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd"
+    )) {
+        Some(0x100)
+    } else if !cfg!(any(target_os = "linux", target_os = "android")) {
+        None
+    } else if cfg!(any(
+        target_arch = "arm",
+        target_arch = "aarch64",
+        target_arch = "powerpc",
+        target_arch = "powerpc64"
+    )) {
+        Some(0o100000)
+    } else {
+        Some(0o400000)
+    }
+}
+
+fn io_error(e: io::Error) -> CommandError {
+    CommandError::new("IO", &e.to_string())
+}
+
+/// The plugin giving the page access to files, see the module documentation.
+///
+/// Nothing is allowed until pages and directories are, e.g.
+/// `Fs::new().allow_from(&["app://*"]).allow(&data_dir).allow_read(&assets_dir)`.
+pub struct Fs {
+    scope: Arc<Scope>,
+    origins: Vec<String>,
+    poll_interval: Duration,
+    watches: Arc<Mutex<Watches>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for Fs {
+    fn default() -> Self {
+        Fs::new()
+    }
+}
+
+impl Fs {
+    pub fn new() -> Self {
+        Fs {
+            scope: Arc::default(),
+            origins: Vec::new(),
+            poll_interval: POLL_INTERVAL,
+            watches: Arc::default(),
+            stop: Arc::default(),
+        }
+    }
+
+    /// Lets the page read and write files in `dir` and below.
+    pub fn allow(self, dir: &Path) -> Self {
+        self.allow_with(dir, Access::ReadWrite)
+    }
+
+    /// Lets the page read files in `dir` and below, but not write them.
+    pub fn allow_read(self, dir: &Path) -> Self {
+        self.allow_with(dir, Access::Read)
+    }
+
+    fn allow_with(mut self, dir: &Path, access: Access) -> Self {
+        Arc::make_mut(&mut self.scope)
+            .dirs
+            .push((dir.to_path_buf(), access));
+        self
+    }
+
+    /// Lets pages whose url matches one of `patterns` call the plugin's functions, with the
+    /// same patterns as `NavigationPolicy`, e.g. `app://*`. No page can by default.
+    pub fn allow_from(mut self, patterns: &[&str]) -> Self {
+        self.origins = patterns.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /// How often watched paths are looked at for changes, every second by default.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    fn bind<F>(&self, webview: &mut Webview<'_>, name: &str, f: F)
+    where
+        F: Fn(&Scope, &[json::Value]) -> Result<String, CommandError> + Send + Sync + 'static,
+    {
+        let origins: Vec<&str> = self.origins.iter().map(|p| p.as_str()).collect();
+        webview.restrict_binding(name, &origins);
+        let scope = self.scope.clone();
+        webview.bind_pooled(name, move |req, _token| {
+            let args = match json::parse(req) {
+                Some(json::Value::Array(args)) => args,
+                _ => Vec::new(),
+            };
+            f(&scope, &args).map_err(|e| e.script())
+        });
+    }
+}
+
+/// The string argument at `index`.
+fn string_arg(args: &[json::Value], index: usize) -> Result<&str, CommandError> {
+    match args.get(index) {
+        Some(json::Value::String(s)) => Ok(s),
+        _ => Err(CommandError::new(
+            "INVALID_ARGUMENT",
+            &format!("Argument {} must be a string", index + 1),
+        )),
+    }
+}
+
+fn read(scope: &Scope, args: &[json::Value]) -> Result<String, CommandError> {
+    let path = scope.resolve(string_arg(args, 0)?, Access::Read)?;
+    let mut text = String::new();
+    open(&path, false)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(io_error)?;
+    Ok(json::string(&text))
+}
+
+fn write(scope: &Scope, args: &[json::Value]) -> Result<String, CommandError> {
+    let path = scope.resolve(string_arg(args, 0)?, Access::ReadWrite)?;
+    let text = string_arg(args, 1)?;
+    open(&path, true)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(io_error)?;
+    Ok("null".to_string())
+}
+
+fn list(scope: &Scope, args: &[json::Value]) -> Result<String, CommandError> {
+    let path = scope.resolve(string_arg(args, 0)?, Access::Read)?;
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(path).map_err(io_error)? {
+        let entry = entry.map_err(io_error)?;
+        let metadata = entry.metadata().map_err(io_error)?;
+        entries.push((
+            entry.file_name().to_string_lossy().into_owned(),
+            entry.path(),
+            metadata,
+        ));
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|(name, path, metadata)| {
+            format!(
+                r#"{{"name":{},"path":{},"kind":"{}","size":{}}}"#,
+                json::string(name),
+                json::string(&path.to_string_lossy()),
+                if metadata.is_dir() { "dir" } else { "file" },
+                metadata.len()
+            )
+        })
+        .collect();
+    Ok(format!("[{}]", entries.join(",")))
+}
+
+/// The modification time and size of each entry of a directory, or of a file itself.
+///
+/// Links are described themselves rather than what they point to, so a watch can't be
+/// used to follow one out of the scope.
+type Snapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
+
+fn snapshot(path: &Path) -> Snapshot {
+    let stat = |metadata: fs::Metadata| (metadata.modified().ok(), metadata.len());
+    let mut snapshot = Snapshot::new();
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {
+            for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+                if let Ok(metadata) = entry.metadata() {
+                    snapshot.insert(entry.path(), stat(metadata));
+                }
+            }
+        }
+        Ok(metadata) => {
+            snapshot.insert(path.to_path_buf(), stat(metadata));
+        }
+        Err(_) => {}
+    }
+    snapshot
+}
+
+/// The paths the page watches, by id.
+#[derive(Default)]
+struct Watches {
+    paths: HashMap<u64, (PathBuf, Snapshot)>,
+    next_id: u64,
+}
+
+impl Watches {
+    /// The changes since the last poll, as the watch's id, the path and what happened to it.
+    fn poll(&mut self) -> Vec<(u64, PathBuf, &'static str)> {
+        let mut changes = Vec::new();
+        for (id, (path, old)) in self.paths.iter_mut() {
+            let new = snapshot(path);
+            for (entry, stat) in &new {
+                match old.get(entry) {
+                    None => changes.push((*id, entry.clone(), "created")),
+                    Some(old_stat) if old_stat != stat => {
+                        changes.push((*id, entry.clone(), "modified"))
+                    }
+                    Some(_) => {}
+                }
+            }
+            for entry in old.keys().filter(|entry| !new.contains_key(*entry)) {
+                changes.push((*id, entry.clone(), "removed"));
+            }
+            *old = new;
+        }
+        changes
+    }
+}
+
+fn watch_in_background(
+    watches: Arc<Mutex<Watches>>,
+    stop: Arc<AtomicBool>,
+    interval: Duration,
+    webview: WebviewHandle,
+) {
+    thread::Builder::new()
+        .name("webview-fs-watch".to_string())
+        .spawn(move || loop {
+            thread::sleep(interval);
+            if stop.load(Ordering::SeqCst) || !webview.is_alive() {
+                return;
+            }
+            for (id, path, kind) in watches.lock().unwrap().poll() {
+                let script = format!(
+                    r#"window.dispatchEvent(new CustomEvent("webviewfs", {{ detail: {{"id":{},"path":{},"kind":"{}"}} }}));"#,
+                    id,
+                    json::string(&path.to_string_lossy()),
+                    kind
+                );
+                let _ = webview.clone().dispatch(move |w| w.eval(&script));
+            }
+        })
+        .expect("Spawning the fs watch thread failed");
+}
+
+impl Plugin for Fs {
+    fn name(&self) -> &str {
+        "fs"
+    }
+
+    fn setup(&mut self, webview: &mut Webview<'_>) {
+        self.bind(webview, READ_BINDING, read);
+        self.bind(webview, WRITE_BINDING, write);
+        self.bind(webview, LIST_BINDING, list);
+
+        let watches = self.watches.clone();
+        self.bind(webview, WATCH_BINDING, move |scope, args| {
+            let path = scope.resolve(string_arg(args, 0)?, Access::Read)?;
+            let snapshot = snapshot(&path);
+            let mut watches = watches.lock().unwrap();
+            watches.next_id += 1;
+            let id = watches.next_id;
+            watches.paths.insert(id, (path, snapshot));
+            Ok(id.to_string())
+        });
+
+        let watches = self.watches.clone();
+        self.bind(webview, UNWATCH_BINDING, move |_scope, args| {
+            if let Some(json::Value::Number(id)) = args.first() {
+                watches.lock().unwrap().paths.remove(&(*id as u64));
+            }
+            Ok("null".to_string())
+        });
+
+        watch_in_background(
+            self.watches.clone(),
+            self.stop.clone(),
+            self.poll_interval,
+            webview.handle(),
+        );
+    }
+
+    fn on_page_load(&mut self, _webview: &mut Webview<'_>, _url: &str) {
+        self.watches.lock().unwrap().paths.clear();
+    }
+
+    fn on_quit(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("webview-fs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn dangling_links_are_not_resolved() {
+        let dir = temp_dir("dangling");
+        let outside = temp_dir("dangling-outside");
+        symlink(outside.join("created"), dir.join("link")).unwrap();
+        let scope = Scope {
+            dirs: vec![(dir.clone(), Access::ReadWrite)],
+        };
+        assert!(scope.resolve("link", Access::ReadWrite).is_err());
+        assert!(scope.resolve("new", Access::ReadWrite).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+
+    #[test]
+    fn links_put_in_place_after_the_check_are_not_followed() {
+        let dir = temp_dir("swapped");
+        let target = dir.join("target");
+        fs::write(&target, "secret").unwrap();
+        symlink(&target, dir.join("link")).unwrap();
+        assert!(open(&dir.join("link"), false).is_err());
+        assert!(open(&dir.join("link"), true).is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "secret");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn snapshots_do_not_follow_links() {
+        let dir = temp_dir("snapshot");
+        let outside = temp_dir("snapshot-outside");
+        fs::write(outside.join("target"), "secret").unwrap();
+        symlink(outside.join("target"), dir.join("link")).unwrap();
+        symlink(&outside, dir.join("dir")).unwrap();
+        let before = snapshot(&dir.join("link"));
+        assert_eq!(before.len(), 1);
+        assert_ne!(before[&dir.join("link")].1, "secret".len() as u64);
+        assert!(snapshot(&dir.join("dir")).contains_key(&dir.join("dir")));
+        fs::write(outside.join("target"), "a longer secret").unwrap();
+        assert_eq!(snapshot(&dir.join("link")), before);
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use crate::json;
+
+/// The name of the binding the page asks to enter and leave fullscreen through.
+pub(crate) const BINDING: &str = "__webview_fullscreen";
+
+/// Replaces the Fullscreen API, in its standard and prefixed forms, with one asking the
+/// application. An allowed element is laid over the viewport, which fills the window the
+/// application made fullscreen, and Escape leaves it again.
+pub(crate) const INIT_SCRIPT: &str = r#"(function () {
+  if (window.__webview_fullscreen_page) {
+    return;
+  }
+  window.__webview_fullscreen_page = true;
+  var current = null;
+  var style = null;
+  var define = function (target, name, get) {
+    Object.defineProperty(target, name, { configurable: true, get: get });
+  };
+  var element = function () {
+    return current;
+  };
+  define(Document.prototype, "fullscreenElement", element);
+  define(Document.prototype, "webkitFullscreenElement", element);
+  define(Document.prototype, "webkitCurrentFullScreenElement", element);
+  define(Document.prototype, "fullscreenEnabled", function () {
+    return true;
+  });
+  define(Document.prototype, "webkitIsFullScreen", function () {
+    return current !== null;
+  });
+  var changed = function (element) {
+    element.dispatchEvent(new Event("fullscreenchange", { bubbles: true }));
+    element.dispatchEvent(new Event("webkitfullscreenchange", { bubbles: true }));
+  };
+  var enter = function (element) {
+    current = element;
+    style = element.getAttribute("style");
+    element.style.cssText += ";position:fixed !important;left:0 !important;top:0 !important;" +
+      "width:100vw !important;height:100vh !important;max-width:none !important;" +
+      "max-height:none !important;margin:0 !important;box-sizing:border-box !important;" +
+      "z-index:2147483647 !important;background:black !important;object-fit:contain !important;";
+    changed(element);
+  };
+  var leave = function () {
+    var element = current;
+    current = null;
+    if (style === null) {
+      element.removeAttribute("style");
+    } else {
+      element.setAttribute("style", style);
+    }
+    changed(element);
+  };
+  var request = function () {
+    var element = this;
+    var info = {
+      tag_name: element.tagName.toLowerCase(),
+      id: element.id || "",
+      src: element.currentSrc || element.src || null,
+    };
+    return window.__webview_fullscreen("request", info).then(function (allowed) {
+      if (!allowed) {
+        element.dispatchEvent(new Event("fullscreenerror", { bubbles: true }));
+        throw new TypeError("The fullscreen request was denied");
+      }
+      if (current) {
+        leave();
+      }
+      enter(element);
+    });
+  };
+  var exit = function () {
+    if (!current) {
+      return Promise.reject(new TypeError("Not in fullscreen"));
+    }
+    leave();
+    return window.__webview_fullscreen("exit").then(function () {});
+  };
+  Element.prototype.requestFullscreen = request;
+  Element.prototype.webkitRequestFullscreen = request;
+  Element.prototype.webkitRequestFullScreen = request;
+  Document.prototype.exitFullscreen = exit;
+  Document.prototype.webkitExitFullscreen = exit;
+  Document.prototype.webkitCancelFullScreen = exit;
+  document.addEventListener("keydown", function (event) {
+    if (event.key === "Escape" && current) {
+      exit();
+    }
+  }, true);
+})();"#;
+
+/// The element a page asks to show fullscreen, see `Webview::on_fullscreen_request`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FullscreenElement {
+    /// Lower case, e.g. `video`.
+    pub tag_name: String,
+    /// The element's `id`, empty if it has none.
+    pub id: String,
+    /// The url of the media playing, for `video` and similar elements.
+    pub src: Option<String>,
+}
+
+/// What the page asked for.
+pub(crate) enum Request {
+    Enter(FullscreenElement),
+    Exit,
+}
+
+/// Parses the arguments the init script passed to the binding.
+pub(crate) fn parse(req: &str) -> Option<Request> {
+    let args = match json::parse(req)? {
+        json::Value::Array(args) => args,
+        _ => return None,
+    };
+    let string = |value: Option<&json::Value>| match value {
+        Some(json::Value::String(s)) => Some(s.clone()),
+        _ => None,
+    };
+    match string(args.first())?.as_str() {
+        "request" => {
+            let info = args.get(1)?;
+            Some(Request::Enter(FullscreenElement {
+                tag_name: string(info.get("tag_name")).unwrap_or_default(),
+                id: string(info.get("id")).unwrap_or_default(),
+                src: string(info.get("src")),
+            }))
+        }
+        "exit" => Some(Request::Exit),
+        _ => None,
+    }
+}
@@ -0,0 +1,106 @@
+/// The name of the binding the page asks for its position through.
+pub(crate) const BINDING: &str = "__webview_geolocation";
+
+/// Replaces `navigator.geolocation` with one asking the application. Watches ask again every
+/// five seconds.
+pub(crate) const INIT_SCRIPT: &str = r#"(function () {
+  if (window.__webview_geolocation_page) {
+    return;
+  }
+  window.__webview_geolocation_page = true;
+  var error = function (code, message) {
+    return { code: code, message: message, PERMISSION_DENIED: 1, POSITION_UNAVAILABLE: 2, TIMEOUT: 3 };
+  };
+  var locate = function (success, failure) {
+    window.__webview_geolocation().then(function (coords) {
+      if (coords === null) {
+        if (failure) {
+          failure(error(1, "User denied Geolocation"));
+        }
+        return;
+      }
+      success({ coords: Object.freeze(coords), timestamp: Date.now() });
+    }, function (message) {
+      if (failure) {
+        failure(error(2, String(message)));
+      }
+    });
+  };
+  var watches = {};
+  var next = 1;
+  var geolocation = {
+    getCurrentPosition: function (success, failure) {
+      locate(success, failure);
+    },
+    watchPosition: function (success, failure) {
+      var id = next++;
+      locate(success, failure);
+      watches[id] = setInterval(function () {
+        locate(success, failure);
+      }, 5000);
+      return id;
+    },
+    clearWatch: function (id) {
+      clearInterval(watches[id]);
+      delete watches[id];
+    },
+  };
+  Object.defineProperty(Navigator.prototype, "geolocation", {
+    configurable: true,
+    get: function () {
+      return geolocation;
+    },
+  });
+})();"#;
+
+/// A position handed to the page, see `Webview::set_geolocation_provider`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Coordinates {
+    /// In decimal degrees.
+    pub latitude: f64,
+    /// In decimal degrees.
+    pub longitude: f64,
+    /// How far off the position may be, in meters.
+    pub accuracy: f64,
+    /// In meters above the WGS 84 ellipsoid.
+    pub altitude: Option<f64>,
+    /// In meters.
+    pub altitude_accuracy: Option<f64>,
+    /// In degrees clockwise from true north.
+    pub heading: Option<f64>,
+    /// In meters per second.
+    pub speed: Option<f64>,
+}
+
+impl Coordinates {
+    pub fn new(latitude: f64, longitude: f64, accuracy: f64) -> Self {
+        Coordinates {
+            latitude,
+            longitude,
+            accuracy,
+            altitude: None,
+            altitude_accuracy: None,
+            heading: None,
+            speed: None,
+        }
+    }
+
+    /// The `GeolocationCoordinates` the page gets, as JSON.
+    pub(crate) fn to_json(self) -> String {
+        let number = |n: Option<f64>| match n.filter(|n| n.is_finite()) {
+            Some(n) => n.to_string(),
+            None => "null".to_string(),
+        };
+        format!(
+            r#"{{"latitude":{},"longitude":{},"accuracy":{},"altitude":{},"altitudeAccuracy":{},"heading":{},"speed":{}}}"#,
+            number(Some(self.latitude)),
+            number(Some(self.longitude)),
+            number(Some(self.accuracy)),
+            number(self.altitude),
+            number(self.altitude_accuracy),
+            number(self.heading),
+            number(self.speed),
+        )
+    }
+}
@@ -0,0 +1,70 @@
+//! Settings of the WebKitGTK view the Linux and BSD backend runs on.
+
+use crate::WebviewBuilder;
+
+/// When WebKitGTK composites the page on the GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HardwareAcceleration {
+    /// Only for pages that need it, e.g. for 3D transforms or WebGL.
+    OnDemand,
+    Always,
+    /// Never, which avoids rendering problems with some drivers.
+    Never,
+}
+
+/// `WebKitSettings` for a webview, applied before the first page loads.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub(crate) hardware_acceleration: Option<HardwareAcceleration>,
+    pub(crate) developer_extras: Option<bool>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) smooth_scrolling: Option<bool>,
+    pub(crate) input_method: Option<bool>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    pub fn hardware_acceleration(mut self, policy: HardwareAcceleration) -> Self {
+        self.hardware_acceleration = Some(policy);
+        self
+    }
+
+    /// Enables the Web Inspector, overriding `WebviewBuilder::debug`.
+    pub fn developer_extras(mut self, enabled: bool) -> Self {
+        self.developer_extras = Some(enabled);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    pub fn smooth_scrolling(mut self, enabled: bool) -> Self {
+        self.smooth_scrolling = Some(enabled);
+        self
+    }
+
+    /// Turns the input method off with `false`, keys then reach the page as typed instead of
+    /// through the IBus or GTK input method, e.g. for games and terminals. Needs WebKitGTK 2.28.
+    pub fn input_method(mut self, enabled: bool) -> Self {
+        self.input_method = Some(enabled);
+        self
+    }
+}
+
+/// Linux and BSD specific options of `WebviewBuilder`.
+pub trait WebviewBuilderExt {
+    /// Applies `options` to the webview's `WebKitSettings`.
+    fn gtk_options(self, options: Options) -> Self;
+}
+
+impl<'a> WebviewBuilderExt for WebviewBuilder<'a> {
+    fn gtk_options(mut self, options: Options) -> Self {
+        self.gtk_options = Some(options);
+        self
+    }
+}
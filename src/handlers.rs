@@ -0,0 +1,88 @@
+use std::mem;
+use std::sync::Mutex;
+
+/// The handlers added for one of the webview's events, called in the order they were added.
+pub(crate) struct Handlers<H>(Mutex<Vec<H>>);
+
+impl<H> Default for Handlers<H> {
+    fn default() -> Self {
+        Handlers(Mutex::new(Vec::new()))
+    }
+}
+
+impl<H> Handlers<H> {
+    /// Adds `handler`.
+    ///
+    /// Whether it is the first one can't be told from here, the others may be running.
+    pub(crate) fn push(&self, handler: H) {
+        self.0.lock().unwrap().push(handler);
+    }
+
+    /// Drops the handlers, outside the lock since they may hold the webview.
+    pub(crate) fn clear(&self) {
+        let handlers = mem::take(&mut *self.0.lock().unwrap());
+        drop(handlers);
+    }
+
+    /// Calls `call` with each handler.
+    pub(crate) fn emit(&self, mut call: impl FnMut(&mut H)) {
+        self.emit_until(|handler| {
+            call(handler);
+            false
+        });
+    }
+
+    /// Calls `call` with each handler until it returns `true`, returns whether it did.
+    ///
+    /// Handlers may add more handlers, so they are called outside the lock. The ones added are
+    /// called from the next event on.
+    pub(crate) fn emit_until(&self, call: impl FnMut(&mut H) -> bool) -> bool {
+        let mut handlers = mem::take(&mut *self.0.lock().unwrap());
+        let handled = handlers.iter_mut().any(call);
+        let mut current = self.0.lock().unwrap();
+        handlers.append(&mut current);
+        *current = handlers;
+        handled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn handlers_added_while_emitting_run_from_the_next_event() {
+        let handlers: Arc<Handlers<Box<dyn FnMut() -> u32 + Send>>> = Arc::default();
+        let added = handlers.clone();
+        handlers.push(Box::new(move || {
+            added.push(Box::new(|| 2));
+            1
+        }));
+
+        let mut calls = Vec::new();
+        handlers.emit(|handler| calls.push(handler()));
+        assert_eq!(calls, [1]);
+
+        calls.clear();
+        handlers.emit(|handler| calls.push(handler()));
+        assert_eq!(calls, [1, 2]);
+    }
+
+    #[test]
+    fn emit_until_stops_at_the_first_handled() {
+        let handlers = Handlers::default();
+        for handler in 0..3 {
+            handlers.push(handler);
+        }
+
+        let mut seen = Vec::new();
+        assert!(handlers.emit_until(|handler| {
+            seen.push(*handler);
+            *handler == 1
+        }));
+        assert_eq!(seen, [0, 1]);
+        assert!(!handlers.emit_until(|handler| *handler == 3));
+    }
+}
@@ -0,0 +1,175 @@
+//! A health endpoint answering HTTP requests with the state of a webview, see
+//! `Application::serve_health`.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::WebviewHandle;
+
+/// How long the probe waits for the page to answer.
+const RESPONSIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client has to send all of its request headers, and to take the report.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the request headers may be, clients sending more are disconnected.
+const MAX_HEADERS: u64 = 8 * 1024;
+
+/// How many requests are answered at a time, other clients are disconnected right away.
+const MAX_CONNECTIONS: usize = 4;
+
+type LastLoad = Arc<Mutex<Option<SystemTime>>>;
+
+pub(crate) fn serve<A: ToSocketAddrs>(
+    addr: A,
+    mut webview: WebviewHandle,
+) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+
+    let last_load = LastLoad::default();
+    let loaded = last_load.clone();
+    let _ = webview.dispatch(move |w| {
+        w.on_load_finished(move |_| *loaded.lock().unwrap() = Some(SystemTime::now()));
+    });
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let slot = match Slot::take(&connections) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            let webview = webview.clone();
+            let last_load = last_load.clone();
+            thread::spawn(move || {
+                let _slot = slot;
+                let _ = respond(stream, webview, &last_load);
+            });
+        }
+    });
+    Ok(local_addr)
+}
+
+fn respond(stream: TcpStream, mut webview: WebviewHandle, last_load: &LastLoad) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    writer.set_write_timeout(Some(READ_TIMEOUT))?;
+    let stream = Deadline {
+        stream,
+        deadline: Instant::now() + READ_TIMEOUT,
+    };
+    // Any request gets the report, only the headers need to be read.
+    let mut complete = false;
+    for line in BufReader::new(stream.take(MAX_HEADERS)).lines() {
+        if line?.is_empty() {
+            complete = true;
+            break;
+        }
+    }
+    if !complete {
+        return Ok(());
+    }
+
+    let backend = webview
+        .backend()
+        .map_or("null".to_string(), |backend| format!("\"{:?}\"", backend));
+    let reply = webview.eval_with_result("true");
+    let alive = reply.is_ok();
+    let responsive = reply.is_ok_and(|reply| reply.wait_timeout(RESPONSIVE_TIMEOUT).is_ok());
+    let last_load = last_load
+        .lock()
+        .unwrap()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or("null".to_string(), |time| time.as_secs().to_string());
+    let body = format!(
+        r#"{{"backend":{},"alive":{},"responsive":{},"last_load":{}}}"#,
+        backend, alive, responsive, last_load
+    );
+    let status = if alive && responsive {
+        "200 OK"
+    } else {
+        "503 Service Unavailable"
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// One of the `MAX_CONNECTIONS` places, given back when dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    /// Takes one of the places counted by `count`, `None` if they are all taken.
+    fn take(count: &Arc<AtomicUsize>) -> Option<Slot> {
+        if count.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            count.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Slot(count.clone()))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Reads from `stream` until `deadline`, however slowly the client sends.
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+
+    use crate::WebviewBuilder;
+
+    #[test]
+    fn reports_a_webview_that_is_gone() {
+        let webview = WebviewBuilder::new().mock().build();
+        let addr = serve("127.0.0.1:0", webview.handle()).unwrap();
+        drop(webview);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response
+            .ends_with(r#"{"backend":null,"alive":false,"responsive":false,"last_load":null}"#));
+    }
+
+    #[test]
+    fn slots_are_given_back() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<Slot> = (0..MAX_CONNECTIONS)
+            .map(|_| Slot::take(&count).unwrap())
+            .collect();
+        assert!(Slot::take(&count).is_none());
+        drop(slots);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert!(Slot::take(&count).is_some());
+    }
+}
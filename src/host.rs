@@ -0,0 +1,91 @@
+//! Serving folders under host names, see `WebviewBuilder::map_host`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// The scheme mapped hosts are served under, engines only let custom schemes be handled.
+pub(crate) const SCHEME: &str = "webview";
+
+/// The folders of the mapped hosts, shared by the webviews of the process like the scheme
+/// handlers serving them.
+fn hosts() -> &'static Mutex<HashMap<String, PathBuf>> {
+    static HOSTS: OnceLock<Mutex<HashMap<String, PathBuf>>> = OnceLock::new();
+    HOSTS.get_or_init(Default::default)
+}
+
+pub(crate) fn map(host: &str, folder: &Path) {
+    hosts()
+        .lock()
+        .unwrap()
+        .insert(host.to_ascii_lowercase(), folder.to_path_buf());
+}
+
+/// Reads the file `url` stands for, with its MIME type. `None` if the host isn't mapped, or the
+/// file doesn't exist or is outside the host's folder.
+pub(crate) fn load(url: &str) -> Option<(Vec<u8>, &'static str)> {
+    let rest = url.strip_prefix(SCHEME)?.strip_prefix("://")?;
+    let rest = &rest[..rest.find(['?', '#']).unwrap_or(rest.len())];
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let folder = hosts()
+        .lock()
+        .unwrap()
+        .get(&host.to_ascii_lowercase())?
+        .clone();
+    let folder = fs::canonicalize(folder).ok()?;
+    let mut file = fs::canonicalize(folder.join(decode(path)?)).ok()?;
+    if !file.starts_with(&folder) {
+        return None;
+    }
+    if file.is_dir() {
+        file.push("index.html");
+    }
+    Some((fs::read(&file).ok()?, mime_type(&file)))
+}
+
+/// Decodes a percent-encoded path, `None` if it isn't valid UTF-8.
+fn decode(path: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut input = path.bytes();
+    while let Some(byte) = input.next() {
+        bytes.push(match byte {
+            b'%' => {
+                let hex = [input.next()?, input.next()?];
+                u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?
+            }
+            byte => byte,
+        });
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The MIME type of a file going by its extension, for what frontends are usually made of.
+fn mime_type(file: &Path) -> &'static str {
+    let extension = file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "js" | "mjs" => "text/javascript",
+        "css" => "text/css",
+        "json" | "map" => "application/json",
+        "wasm" => "application/wasm",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "txt" => "text/plain",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,240 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::mem;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{deep_link, json, random, user_dir, Webview, WebviewHandle};
+
+/// How long a starting instance waits on the running one.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Set for an instance started to replace the running one, e.g. after an update. It waits for
+/// the running one to exit instead of forwarding its arguments to it.
+///
+/// It's only read, changing the environment isn't safe while other threads may read it.
+pub(crate) const RESTART_VAR: &str = "WEBVIEW_RESTART";
+
+/// How long a replacing instance waits for the one it replaces to exit.
+const RESTART_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Handler = Box<dyn FnMut(&mut Webview, Vec<String>) + Send>;
+
+struct Shared {
+    handler: Arc<Mutex<Handler>>,
+    webview: Option<WebviewHandle>,
+    /// Arguments that arrived before there was a webview to hand them to.
+    pending: Vec<Vec<String>>,
+    closed: bool,
+}
+
+/// Keeps other instances of the application from starting, see
+/// `Application::ensure_single_instance`. Dropping it lets the next one start.
+pub struct InstanceLock {
+    path: PathBuf,
+    token: String,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl InstanceLock {
+    /// Hands the arguments of instances started from now on to the handler on `webview`'s UI
+    /// thread, after focusing its window. Arguments that arrived before are handed over right
+    /// away.
+    pub fn forward_to(&self, webview: WebviewHandle) {
+        let mut shared = self.shared.lock().unwrap();
+        for args in mem::take(&mut shared.pending) {
+            deliver(&shared.handler, webview.clone(), args);
+        }
+        shared.webview = Some(webview);
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        self.shared.lock().unwrap().closed = true;
+        // Leave a lock that a later instance took over alone.
+        if matches!(read_lock(&self.path), Ok(Some((_, token))) if token == self.token) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn deliver(handler: &Arc<Mutex<Handler>>, mut webview: WebviewHandle, args: Vec<String>) {
+    let handler = handler.clone();
+    let _ = webview.dispatch(move |w| {
+        w.focus();
+        w.open_deep_links(&deep_link::links(&args));
+        (handler.lock().unwrap())(w, args);
+    });
+}
+
+pub(crate) fn ensure<F>(id: &str, f: F) -> io::Result<Option<InstanceLock>>
+where
+    F: FnMut(&mut Webview, Vec<String>) + Send + 'static,
+{
+    let path = lock_path(id)?;
+    let args: Vec<String> = env::args().skip(1).collect();
+    if env::var_os(RESTART_VAR).is_some() {
+        let start = Instant::now();
+        while let Some((port, _)) = read_lock(&path)? {
+            let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+            if start.elapsed() > RESTART_TIMEOUT
+                || TcpStream::connect_timeout(&addr, TIMEOUT).is_err()
+            {
+                break;
+            }
+            thread::sleep(TIMEOUT / 10);
+        }
+    }
+    // A lock left behind by an instance that didn't exit cleanly is taken over once.
+    for _ in 0..2 {
+        if let Some((port, token)) = read_lock(&path)? {
+            if forward(port, &token, &args).is_ok() {
+                return Ok(None);
+            }
+            fs::remove_file(&path)?;
+        }
+        match lock(&path) {
+            Ok(lock) => {
+                let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+                let token = random::token();
+                writeln!(&lock, "{} {}", listener.local_addr()?.port(), token)?;
+                let shared = Arc::new(Mutex::new(Shared {
+                    handler: Arc::new(Mutex::new(Box::new(f))),
+                    webview: None,
+                    pending: Vec::new(),
+                    closed: false,
+                }));
+                listen(listener, token.clone(), shared.clone());
+                return Ok(Some(InstanceLock {
+                    path,
+                    token,
+                    shared,
+                }));
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                // Another instance is starting, give it the time to write its port.
+                thread::sleep(TIMEOUT / 10);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::WouldBlock,
+        "another instance holds the lock but doesn't answer",
+    ))
+}
+
+/// The lock file, in a directory only the user can use.
+fn lock_path(id: &str) -> io::Result<PathBuf> {
+    let name: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let dir = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => user_dir::temp("webview-instances")?,
+    };
+    Ok(dir.join(format!("{}.instance", name)))
+}
+
+fn lock(path: &PathBuf) -> io::Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+/// The port and token in the lock file, `None` if there is none or it isn't complete yet.
+///
+/// Fails if the file isn't the user's own, or others can access it, since its port gets the
+/// arguments of the instances started from now on.
+fn read_lock(path: &PathBuf) -> io::Result<Option<(u16, String)>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    user_dir::check_private(&file.metadata()?)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let mut parts = content.split_whitespace();
+    let port = match parts.next().and_then(|port| port.parse().ok()) {
+        Some(port) => port,
+        None => return Ok(None),
+    };
+    Ok(parts.next().map(|token| (port, token.to_string())))
+}
+
+/// Sends the token and `args` as a JSON array of strings, one line each, and waits for the
+/// running instance to acknowledge them.
+fn forward(port: u16, token: &str, args: &[String]) -> io::Result<()> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let args: Vec<String> = args.iter().map(|arg| json::string(arg)).collect();
+    write!(stream, "{}\n[{}]\n", token, args.join(","))?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim_end() == "ok" {
+        Ok(())
+    } else {
+        Err(io::ErrorKind::InvalidData.into())
+    }
+}
+
+/// Serves each client on a thread of its own, so a slow one doesn't hold up the others.
+fn listen(listener: TcpListener, token: String, shared: Arc<Mutex<Shared>>) {
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if shared.lock().unwrap().closed {
+                break;
+            }
+            let token = token.clone();
+            let shared = shared.clone();
+            thread::spawn(move || {
+                if let Some(args) = receive(stream, &token) {
+                    let mut shared = shared.lock().unwrap();
+                    if shared.closed {
+                        return;
+                    }
+                    match shared.webview.clone() {
+                        Some(webview) => deliver(&shared.handler, webview, args),
+                        None => shared.pending.push(args),
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn receive(stream: TcpStream, token: &str) -> Option<Vec<String>> {
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    let mut writer = stream.try_clone().ok()?;
+    let mut reader = BufReader::new(stream);
+    // Clients that don't know the token only get to send as much.
+    let mut line = String::new();
+    (&mut reader)
+        .take(token.len() as u64 + 1)
+        .read_line(&mut line)
+        .ok()?;
+    if line.trim_end() != token {
+        return None;
+    }
+    line.clear();
+    reader.read_line(&mut line).ok()?;
+    let args = json::strings(&line);
+    writer.write_all(b"ok\n").ok()?;
+    Some(args)
+}
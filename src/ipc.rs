@@ -0,0 +1,80 @@
+//! Binding calls carrying a per-session token and the url of the page making them, see
+//! `WebviewBuilder::secure_ipc` and `Webview::restrict_binding`.
+
+use crate::{json, policy};
+
+/// Makes the bridge send the token and the url the page was loaded from as the first two
+/// arguments of every binding call, in the top frame of pages matching one of `trusted`, or of
+/// all pages if there are none.
+///
+/// What it relies on is captured when it's injected, before the page's scripts run, so they
+/// can't reach the token by replacing `JSON`, `Array` or the native message channel. The
+/// recorder of the cancellation script, if it's already there, is only handed the call's id.
+pub(crate) fn script(token: &str, trusted: &[String]) -> String {
+    let trusted: Vec<String> = trusted
+        .iter()
+        .map(|pattern| format!("new RegExp({})", json::string(&policy::regex(pattern))))
+        .collect();
+    format!(
+        r#"(function () {{
+  var token = {token}, url = location.href, trusted = [{trusted}];
+  var external = window.external, invoke = external && external.invoke;
+  if (window !== window.top || !invoke || invoke.__webview_ipc) {{
+    return;
+  }}
+  if (trusted.length && !trusted.some(function (pattern) {{ return pattern.test(url); }})) {{
+    return;
+  }}
+  var parse = JSON.parse, stringify = JSON.stringify, isArray = Array.isArray;
+  var apply = Reflect.apply, slice = String.prototype.slice, freeze = Object.freeze;
+  var record = invoke.__webview_record;
+  var channel = window.chrome && window.chrome.webview
+    || window.webkit && window.webkit.messageHandlers && window.webkit.messageHandlers.external;
+  var post = channel && channel.postMessage;
+  var send = post
+    ? function (message) {{ return apply(post, channel, [message]); }}
+    : function (message) {{ return apply(invoke, external, [message]); }};
+  var wrapper = function (message) {{
+    var call = parse(message);
+    if (record) {{
+      record(call.id);
+    }}
+    if (!isArray(call.params)) {{
+      return send(message);
+    }}
+    var params = stringify(call.params);
+    var rest = params === "[]" ? "" : "," + apply(slice, params, [1, -1]);
+    return send("{{\"id\":" + stringify(call.id) + ",\"method\":" + stringify(call.method)
+      + ",\"params\":[" + stringify(token) + "," + stringify(url) + rest + "]}}");
+  }};
+  wrapper.__webview_ipc = true;
+  external.invoke = freeze(wrapper);
+}})();"#,
+        token = json::string(token),
+        trusted = trusted.join(", "),
+    )
+}
+
+/// The url a call was made from and its arguments without the leading `token` and url, `None`
+/// if the call didn't carry the token.
+pub(crate) fn split_call(req: &str, token: &str) -> Option<(String, String)> {
+    let rest = req
+        .trim_start()
+        .strip_prefix('[')?
+        .trim_start()
+        .strip_prefix(json::string(token).as_str())?
+        .trim_start()
+        .strip_prefix(',')?
+        .trim_start()
+        .strip_prefix('"')?;
+    let mut chars = rest.chars();
+    let url = json::read_string(&mut chars);
+    let rest = chars.as_str().trim_start();
+    if let Some(rest) = rest.strip_prefix(',') {
+        Some((url, format!("[{}", rest)))
+    } else if rest.starts_with(']') {
+        Some((url, "[]".to_string()))
+    } else {
+        None
+    }
+}
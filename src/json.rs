@@ -0,0 +1,340 @@
+//! The little JSON handling needed to talk to the page.
+
+/// The strings in a JSON array, as produced by `JSON.stringify`. Other values are skipped.
+pub(crate) fn strings(json: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut chars = json.chars();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            strings.push(read_string(&mut chars));
+        }
+    }
+    strings
+}
+
+/// The members of a JSON object that have string values, others are skipped. `None` if `json`
+/// isn't an object.
+#[cfg(feature = "updater")]
+pub(crate) fn string_members(json: &str) -> Option<Vec<(String, String)>> {
+    let mut chars = json.trim().strip_prefix('{')?.chars();
+    let mut members = Vec::new();
+    loop {
+        match chars.find(|c| !c.is_whitespace())? {
+            '}' => return Some(members),
+            ',' => continue,
+            '"' => {}
+            _ => return None,
+        }
+        let key = read_string(&mut chars);
+        if chars.find(|c| !c.is_whitespace())? != ':' {
+            return None;
+        }
+        match chars.find(|c| !c.is_whitespace())? {
+            '"' => members.push((key, read_string(&mut chars))),
+            c => {
+                // Skip the value, up to the comma or brace that ends it.
+                let mut depth = matches!(c, '{' | '[') as usize;
+                let mut rest = chars.clone();
+                loop {
+                    match rest.next()? {
+                        '"' => {
+                            read_string(&mut rest);
+                        }
+                        '{' | '[' => depth += 1,
+                        '}' | ']' if depth > 0 => depth -= 1,
+                        ',' | '}' if depth == 0 => break,
+                        _ => {}
+                    }
+                    chars = rest.clone();
+                }
+            }
+        }
+    }
+}
+
+/// A parsed JSON value, object members in the order they appear.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// The value of the member `key`, if this is an object that has it.
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Parses `json`, `None` if it isn't a single valid JSON value.
+pub(crate) fn parse(json: &str) -> Option<Value> {
+    let mut chars = json.chars();
+    let value = parse_value(&mut chars, 0)?;
+    if chars.any(|c| !c.is_whitespace()) {
+        return None;
+    }
+    Some(value)
+}
+
+/// How deeply arrays and objects may nest, to keep the stack in bounds.
+const MAX_DEPTH: usize = 128;
+
+fn parse_value(chars: &mut std::str::Chars<'_>, depth: usize) -> Option<Value> {
+    let c = chars.find(|c| !c.is_whitespace())?;
+    match c {
+        '"' => parse_string(chars).map(Value::String),
+        '[' | '{' if depth < MAX_DEPTH => {
+            let close = if c == '[' { ']' } else { '}' };
+            let mut array = Vec::new();
+            let mut object = Vec::new();
+            let mut rest = chars.clone();
+            if rest.find(|c| !c.is_whitespace())? == close {
+                *chars = rest;
+            } else {
+                loop {
+                    if c == '[' {
+                        array.push(parse_value(chars, depth + 1)?);
+                    } else {
+                        if chars.find(|c| !c.is_whitespace())? != '"' {
+                            return None;
+                        }
+                        let key = parse_string(chars)?;
+                        if chars.find(|c| !c.is_whitespace())? != ':' {
+                            return None;
+                        }
+                        object.push((key, parse_value(chars, depth + 1)?));
+                    }
+                    match chars.find(|c| !c.is_whitespace())? {
+                        ',' => continue,
+                        c if c == close => break,
+                        _ => return None,
+                    }
+                }
+            }
+            Some(if c == '[' {
+                Value::Array(array)
+            } else {
+                Value::Object(object)
+            })
+        }
+        '-' | '0'..='9' => {
+            let mut number = c.to_string();
+            let mut rest = chars.clone();
+            while let Some(c) = rest.next() {
+                if !matches!(c, '0'..='9' | '.' | 'e' | 'E' | '+' | '-') {
+                    break;
+                }
+                number.push(c);
+                *chars = rest.clone();
+            }
+            let digits = number.trim_start_matches('-');
+            let leading_zero = digits.len() > 1
+                && digits.starts_with('0')
+                && !digits[1..].starts_with(['.', 'e', 'E']);
+            // Rust takes `1.`, `.5` and `1.e5`, JSON wants digits on both sides of the point.
+            let bare_point = number
+                .split('.')
+                .skip(1)
+                .any(|after| !after.starts_with(|c: char| c.is_ascii_digit()));
+            if leading_zero || digits.starts_with('.') || bare_point {
+                return None;
+            }
+            number
+                .parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+                .map(Value::Number)
+        }
+        _ => {
+            let (word, value) = match c {
+                't' => ("rue", Value::Bool(true)),
+                'f' => ("alse", Value::Bool(false)),
+                'n' => ("ull", Value::Null),
+                _ => return None,
+            };
+            if chars.as_str().starts_with(word) {
+                chars.nth(word.len() - 1);
+                Some(value)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Like `read_string`, but `None` if the string isn't closed or has raw control characters.
+fn parse_string(chars: &mut std::str::Chars<'_>) -> Option<String> {
+    let rest = chars.as_str();
+    let mut scan = rest.chars();
+    loop {
+        match scan.next()? {
+            '"' => break,
+            '\\' => {
+                scan.next()?;
+            }
+            c if (c as u32) < 0x20 => return None,
+            _ => {}
+        }
+    }
+    Some(read_string(chars))
+}
+
+/// Reads a string literal up to its closing quote, the opening one already read.
+pub(crate) fn read_string(chars: &mut std::str::Chars<'_>) -> String {
+    let mut s = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('u') => {
+                    let mut code = read_hex(chars);
+                    // Characters outside the BMP come as a pair of surrogates.
+                    if let Some(high @ 0xd800..=0xdbff) = code {
+                        let mut rest = chars.clone();
+                        if rest.next() == Some('\\') && rest.next() == Some('u') {
+                            if let Some(low @ 0xdc00..=0xdfff) = read_hex(&mut rest) {
+                                code = Some(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00));
+                                *chars = rest;
+                            }
+                        }
+                    }
+                    let c = code.and_then(std::char::from_u32);
+                    s.push(c.unwrap_or(std::char::REPLACEMENT_CHARACTER));
+                }
+                Some(c) => s.push(c),
+                None => break,
+            },
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+/// The four hex digits of a `\u` escape.
+fn read_hex(chars: &mut std::str::Chars<'_>) -> Option<u32> {
+    let hex: String = chars.by_ref().take(4).collect();
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+/// `s` as a JSON string literal, which is a valid JavaScript string literal as well.
+pub(crate) fn string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            // Valid in JSON but line terminators in older JavaScript engines.
+            '\u{2028}' => json.push_str("\\u2028"),
+            '\u{2029}' => json.push_str("\\u2029"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_kind_of_value() {
+        let value = parse(r#" {"a": [null, true, false, -1.5e2, "x"], "b": {}} "#).unwrap();
+        assert_eq!(
+            value,
+            Value::Object(vec![
+                (
+                    "a".to_string(),
+                    Value::Array(vec![
+                        Value::Null,
+                        Value::Bool(true),
+                        Value::Bool(false),
+                        Value::Number(-150.0),
+                        Value::String("x".to_string()),
+                    ])
+                ),
+                ("b".to_string(), Value::Object(Vec::new())),
+            ])
+        );
+        assert_eq!(value.get("b"), Some(&Value::Object(Vec::new())));
+        assert_eq!(value.get("c"), None);
+        assert_eq!(parse("[]"), Some(Value::Array(Vec::new())));
+    }
+
+    #[test]
+    fn refuses_malformed_json() {
+        for json in [
+            "",
+            "[",
+            "]",
+            "[1,]",
+            "[1 2]",
+            "{\"a\"}",
+            "{\"a\":}",
+            "{a:1}",
+            "{\"a\":1,}",
+            "\"open",
+            "\"a\u{1}b\"",
+            "tru",
+            "nul",
+            "True",
+            "[1] [2]",
+            "'a'",
+        ] {
+            assert_eq!(parse(json), None, "{}", json);
+        }
+    }
+
+    #[test]
+    fn numbers_follow_the_json_grammar() {
+        for (json, n) in [
+            ("0", 0.0),
+            ("-0", 0.0),
+            ("12", 12.0),
+            ("0.5", 0.5),
+            ("1E+2", 100.0),
+        ] {
+            assert_eq!(parse(json), Some(Value::Number(n)), "{}", json);
+        }
+        for json in [
+            "01", "-01", ".5", "-.5", "1.", "1.e5", "1e", "-", "+1", "1e400", "NaN",
+        ] {
+            assert_eq!(parse(json), None, "{}", json);
+        }
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_some());
+        assert_eq!(parse(&nested(MAX_DEPTH + 1)), None);
+    }
+
+    #[test]
+    fn strings_round_trip_through_escapes() {
+        let text = "quote \" backslash \\ line\nbreak\ttab \u{1} \u{2028} \u{1f600}";
+        assert_eq!(parse(&string(text)), Some(Value::String(text.to_string())));
+        assert_eq!(
+            parse(r#""\ud83d\ude00 \u00e9 \ud800""#),
+            Some(Value::String("\u{1f600} \u{e9} \u{fffd}".to_string()))
+        );
+        assert_eq!(strings(r#"["a", 1, "b\"c"]"#), ["a", "b\"c"]);
+    }
+}
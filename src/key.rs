@@ -0,0 +1,26 @@
+/// A key pressed while the webview has the keyboard focus, see `Webview::on_key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct KeyEvent {
+    /// The key as the DOM's `KeyboardEvent.key` names it: the character typed, e.g. `w` or `W`
+    /// with Shift, or the name of the key, e.g. `F11`, `Escape`, `Enter` or `ArrowLeft`.
+    pub key: String,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// The Command key on macOS, the Windows or Super key elsewhere.
+    pub meta: bool,
+}
+
+impl KeyEvent {
+    /// `key` without modifiers.
+    pub fn new(key: &str) -> Self {
+        KeyEvent {
+            key: key.to_string(),
+            ctrl: false,
+            shift: false,
+            alt: false,
+            meta: false,
+        }
+    }
+}
@@ -1,7 +1,137 @@
+// Declared first, so the modules below can use its macros.
+#[macro_use]
+mod trace;
+
+mod application;
+mod attention;
+mod automation;
+mod backend;
+mod binding;
 mod builder;
+mod cancel;
+mod command;
+mod composition;
+mod contents;
+mod csp;
+mod css;
+mod cstring;
+mod deep_link;
+mod drag;
+mod effect;
 mod error;
+#[cfg(feature = "futures")]
+mod events;
+mod favicon;
+#[cfg(feature = "fs")]
+pub mod fs;
+mod fullscreen;
+mod geolocation;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub mod gtk;
+mod handlers;
+#[cfg(feature = "health")]
+mod health;
+mod host;
+mod instance;
+mod ipc;
+mod json;
+mod key;
+#[cfg(target_os = "macos")]
+pub mod macos;
+mod metrics;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod network;
+#[cfg(feature = "oauth")]
+pub mod oauth;
+mod offscreen;
+mod panic_hook;
+mod platform;
+mod plugin;
+mod policy;
+mod pool;
+mod post;
+mod progress;
+mod quit;
+mod random;
+mod rate_limit;
+#[cfg(feature = "remote-admin")]
+pub mod remote;
+mod reply;
+mod schedule;
+mod schema;
+#[cfg(feature = "secure-storage")]
+pub mod secure_storage;
+mod service_worker;
+mod session;
+#[cfg(feature = "shell")]
+pub mod shell;
+mod sidecar;
+mod splash;
+mod storage;
+mod system_info;
+mod theme;
+mod throttling;
+mod timeout;
+mod title;
+#[cfg(feature = "updater")]
+pub mod updater;
+mod user_dir;
 mod webview;
+mod window;
+mod window_state;
+#[cfg(target_os = "windows")]
+pub mod windows;
 
+#[cfg(target_os = "macos")]
+pub use application::ActivationPolicy;
+pub use application::Application;
+pub use attention::UserAttention;
+pub use automation::Element;
+pub use backend::{Backend, Capabilities, UnsupportedCall};
 pub use builder::WebviewBuilder;
+pub use cancel::CancellationToken;
+pub use command::CommandError;
+pub use composition::CompositionEvent;
+pub use contents::WebContents;
+pub use css::CssHandle;
+pub use drag::DragItem;
+pub use effect::WindowEffect;
 pub use error::Error;
-pub use webview::{SizeHint, Webview, WebviewMut, Window};
+#[cfg(feature = "futures")]
+pub use events::{Event, EventStream};
+pub use favicon::Favicon;
+pub use fullscreen::FullscreenElement;
+pub use geolocation::Coordinates;
+pub use instance::InstanceLock;
+pub use key::KeyEvent;
+pub use metrics::Metrics;
+pub use network::NetworkConditions;
+pub use offscreen::Frame;
+pub use plugin::Plugin;
+pub use policy::NavigationPolicy;
+pub use progress::ProgressState;
+pub use quit::QuitGuard;
+pub use rate_limit::RateLimit;
+pub use reply::Reply;
+pub use schedule::{Schedule, ScheduledTask};
+pub use schema::Schema;
+pub use session::SessionEvent;
+pub use sidecar::{Sidecar, SidecarEvent};
+pub use splash::Splash;
+pub use storage::StorageReport;
+pub use system_info::SystemInfo;
+pub use theme::Theme;
+pub use throttling::BackgroundThrottling;
+#[allow(deprecated)]
+pub use webview::WebviewMut;
+pub use webview::{Color, NativeWindow, Rect, SizeHint, Webview, WebviewHandle};
+/// The native bindings, for what `Webview::as_sys` and `Webview::into_raw` give out.
+pub use webview_official_sys as sys;
+pub use window::Window;
@@ -0,0 +1,93 @@
+//! Settings of the `WKWebView` the macOS backend runs on.
+
+use crate::builder::Cache;
+use crate::WebviewBuilder;
+
+/// Which media needs a user gesture before it starts playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaPlayback {
+    /// Audio and video may play on their own.
+    Autoplay,
+    /// Media with sound waits for the user, muted video may play on its own.
+    AudioRequiresUserAction,
+    /// Video waits for the user.
+    VideoRequiresUserAction,
+    /// All media waits for the user.
+    RequiresUserAction,
+}
+
+/// Where the webview keeps cookies, caches and local storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataStore {
+    /// The application's persistent store.
+    Default,
+    /// An in-memory store discarded along with the webview, like a private browsing window.
+    NonPersistent,
+}
+
+/// Options for the `WKWebView` a webview is created with, these can't be changed afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub(crate) developer_extras: Option<bool>,
+    pub(crate) media_playback: Option<MediaPlayback>,
+    pub(crate) data_store: Option<DataStore>,
+    pub(crate) scheme_handler: bool,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Enables the Web Inspector, overriding `WebviewBuilder::debug`.
+    pub fn developer_extras(mut self, enabled: bool) -> Self {
+        self.developer_extras = Some(enabled);
+        self
+    }
+
+    pub fn media_playback(mut self, playback: MediaPlayback) -> Self {
+        self.media_playback = Some(playback);
+        self
+    }
+
+    pub fn data_store(mut self, store: DataStore) -> Self {
+        self.data_store = Some(store);
+        self
+    }
+
+    /// Keeps the cache in memory when the builder disables it, unless a data store was chosen.
+    pub(crate) fn with_cache(options: Option<Options>, cache: &Cache) -> Option<Options> {
+        if !cache.disabled {
+            return options;
+        }
+        let mut options = options.unwrap_or_default();
+        if options.data_store.is_none() {
+            options.data_store = Some(DataStore::NonPersistent);
+        }
+        Some(options)
+    }
+
+    /// Installs the scheme handler serving mapped hosts when the builder has any, see
+    /// `WebviewBuilder::map_host`.
+    pub(crate) fn with_hosts(options: Option<Options>, hosts: bool) -> Option<Options> {
+        if !hosts {
+            return options;
+        }
+        let mut options = options.unwrap_or_default();
+        options.scheme_handler = true;
+        Some(options)
+    }
+}
+
+/// macOS specific options of `WebviewBuilder`.
+pub trait WebviewBuilderExt {
+    /// Creates the `WKWebView` with `options`.
+    fn macos_options(self, options: Options) -> Self;
+}
+
+impl<'a> WebviewBuilderExt for WebviewBuilder<'a> {
+    fn macos_options(mut self, options: Options) -> Self {
+        self.macos_options = Some(options);
+        self
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{json, platform, Error};
+
+/// How much memory the webview uses, see `Webview::metrics`. Sizes are in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// The resident memory of the application's own process, `None` where it isn't known.
+    pub app_memory: Option<u64>,
+    /// The resident memory of the engine's processes started by the application: web content,
+    /// network and GPU processes. `None` where they aren't the application's children
+    /// (`WKWebView`'s are started by the system).
+    ///
+    /// WebKitGTK shares its web process between the webviews of a context, so it isn't this
+    /// webview's alone.
+    pub engine_memory: Option<u64>,
+    /// How many processes `engine_memory` adds up.
+    pub engine_processes: usize,
+    /// The page's JavaScript heap in use, `None` where the engine doesn't report it (WebKit).
+    pub js_heap_used: Option<u64>,
+    /// The page's JavaScript heap reserved, `None` where the engine doesn't report it (WebKit).
+    pub js_heap_total: Option<u64>,
+    /// The elements in the page's document.
+    pub dom_elements: u64,
+}
+
+/// Reports the page's numbers as an array of strings, empty where the engine has no number.
+pub(crate) const SCRIPT: &str = r#"(function () {
+  var memory = performance.memory || {};
+  var part = function (n) { return n === undefined ? "" : String(n); };
+  return [part(memory.usedJSHeapSize), part(memory.totalJSHeapSize),
+    String(document.getElementsByTagName("*").length)];
+})()"#;
+
+/// Adds the processes' memory, sampled now, to the page's numbers.
+pub(crate) fn parse(reply: String) -> Result<Metrics, Error> {
+    let fields = json::strings(&reply);
+    let number = |i: usize| fields.get(i).and_then(|field| field.parse().ok());
+    let dom_elements = match number(2) {
+        Some(dom_elements) => dom_elements,
+        None => return Err(Error::Ipc(reply)),
+    };
+    let memory = platform::process_memory();
+    Ok(Metrics {
+        app_memory: memory.app,
+        engine_memory: memory.engine.map(|(bytes, _)| bytes),
+        engine_processes: memory.engine.map_or(0, |(_, count)| count),
+        js_heap_used: number(0),
+        js_heap_total: number(1),
+        dom_elements,
+    })
+}
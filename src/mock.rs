@@ -0,0 +1,1120 @@
+//! An in-memory backend for testing applications without a display server.
+//!
+//! A `Webview` built with `WebviewBuilder::mock` is backed by a model that records what the
+//! application did to it (navigations, scripts, title, size, `return`ed results) instead of a
+//! native window. `Webview::mock` gives access to that model, which can also play the page's
+//! part by calling bindings. Webviews built without it stay native, so enabling the feature
+//! changes nothing for them.
+//!
+//! `run` behaves like the native loop: it runs dispatched closures until `terminate` is called.
+
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::os::raw::*;
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+
+use webview_official_sys::{BindFn, DispatchFn};
+
+use crate::cstring;
+use crate::platform::{
+    self, DeepLinkCallback, FileDropCallback, FocusCallback, FrameCallback, HistoryAction,
+    KeyCallback, LoadCallback, NavigationCallback, PaintCallback, SessionCallback, ThemeCallback,
+};
+use crate::{
+    BackgroundThrottling, Color, DragItem, Frame, KeyEvent, ProgressState, Rect, SessionEvent,
+    Theme, UserAttention, WindowEffect,
+};
+
+/// A result handed back to the page with `Webview::r#return`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Return {
+    pub seq: String,
+    pub status: i32,
+    pub result: String,
+}
+
+/// A page loaded with `Webview::navigate_with_headers` or `Webview::navigate_post`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// The body posted and its content type.
+    pub body: Option<(Vec<u8>, String)>,
+}
+
+struct Ptr(*mut c_void);
+
+// The model never dereferences these, they are handed back to the callbacks they came with.
+unsafe impl Send for Ptr {}
+
+#[derive(Default)]
+struct State {
+    title: String,
+    size: (i32, i32),
+    origin: (i32, i32),
+    maximized: bool,
+    fullscreen: bool,
+    /// `None` for the default single monitor.
+    monitors: Option<Vec<Rect>>,
+    navigations: Vec<String>,
+    requests: Vec<Request>,
+    history: Vec<String>,
+    /// How many entries of `history` are at or behind the current page.
+    position: usize,
+    init_scripts: Vec<String>,
+    evaluated: Vec<String>,
+    bindings: HashMap<String, (BindFn, Ptr)>,
+    returns: Vec<Return>,
+    queue: VecDeque<(DispatchFn, Ptr)>,
+    next_seq: u64,
+    terminated: bool,
+    opened_externally: Vec<String>,
+    window_icon: Option<Vec<u8>>,
+    window_drags: usize,
+    drags: Vec<DragItem>,
+    window_effect: Option<WindowEffect>,
+    aspect_ratio: Option<(u32, u32)>,
+    sleep_prevented: Option<String>,
+    focused: bool,
+    attention_requests: Vec<UserAttention>,
+    progress: (ProgressState, f64),
+    badge_count: Option<u32>,
+    owned: bool,
+    hidden: bool,
+    modals: isize,
+    focus_callback: Option<FocusCallback>,
+    file_drop_callback: Option<FileDropCallback>,
+    key_callback: Option<KeyCallback>,
+    load_callback: Option<LoadCallback>,
+    navigation_callback: Option<NavigationCallback>,
+    deep_link_callback: Option<DeepLinkCallback>,
+    frame_callback: Option<FrameCallback>,
+    session: Option<SessionCallback>,
+    paint_callback: Option<PaintCallback>,
+    background_throttling: Option<BackgroundThrottling>,
+    theme: Theme,
+    background_color: Option<Color>,
+    system_theme: Option<Theme>,
+    theme_callback: Option<ThemeCallback>,
+}
+
+impl State {
+    fn visit(&mut self, url: String) {
+        self.history.truncate(self.position);
+        self.history.push(url.clone());
+        self.position += 1;
+        self.navigations.push(url);
+    }
+}
+
+/// The addresses of the models in use, webviews not among them are native.
+static MODELS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Whether `w` is backed by a model rather than a native webview.
+pub(crate) fn is_model(w: sys::webview_t) -> bool {
+    MODELS.lock().unwrap().contains(&(w as usize))
+}
+
+/// Creates a model standing in for a native webview, `webview_destroy` drops it.
+pub(crate) fn create() -> sys::webview_t {
+    let w = Box::into_raw(Box::<Model>::default()) as sys::webview_t;
+    MODELS.lock().unwrap().push(w as usize);
+    w
+}
+
+#[derive(Default)]
+struct Model {
+    state: Mutex<State>,
+    wake: Condvar,
+}
+
+impl Model {
+    unsafe fn from_raw<'a>(w: sys::webview_t) -> &'a Model {
+        &*(w as *const Model)
+    }
+
+    fn run_pending(&self, w: sys::webview_t) -> usize {
+        let mut count = 0;
+        loop {
+            let next = self.state.lock().unwrap().queue.pop_front();
+            match next {
+                Some((f, arg)) => f(w, arg.0),
+                None => return count,
+            }
+            count += 1;
+        }
+    }
+}
+
+/// The model behind a `Webview` built with `WebviewBuilder::mock`.
+pub struct Mock<'a> {
+    webview: sys::webview_t,
+    _webview: PhantomData<&'a ()>,
+}
+
+impl<'a> Mock<'a> {
+    pub(crate) fn new(webview: sys::webview_t) -> Mock<'a> {
+        Mock {
+            webview,
+            _webview: PhantomData,
+        }
+    }
+
+    fn model(&self) -> &Model {
+        unsafe { Model::from_raw(self.webview) }
+    }
+
+    /// The urls navigated to so far, urls passed to `navigate` are loaded by `run`.
+    ///
+    /// Going back, forward or reloading counts as navigating to the url loaded.
+    pub fn navigations(&self) -> Vec<String> {
+        self.model().state.lock().unwrap().navigations.clone()
+    }
+
+    /// The pages loaded with `navigate_with_headers` or `navigate_post`, they also show up in
+    /// `navigations`.
+    pub fn requests(&self) -> Vec<Request> {
+        self.model().state.lock().unwrap().requests.clone()
+    }
+
+    /// The scripts registered with `init`.
+    pub fn init_scripts(&self) -> Vec<String> {
+        self.model().state.lock().unwrap().init_scripts.clone()
+    }
+
+    /// The scripts passed to `eval`.
+    pub fn evaluated(&self) -> Vec<String> {
+        self.model().state.lock().unwrap().evaluated.clone()
+    }
+
+    pub fn title(&self) -> String {
+        self.model().state.lock().unwrap().title.clone()
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        self.model().state.lock().unwrap().size
+    }
+
+    /// The names of the bound functions.
+    pub fn bindings(&self) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .model()
+            .state
+            .lock()
+            .unwrap()
+            .bindings
+            .keys()
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Calls a binding the way the page would, `req` being the JSON array of arguments.
+    ///
+    /// The handler runs on the calling thread, returns the call's `seq` or `None` if nothing is
+    /// bound under `name`.
+    pub fn call(&self, name: &str, req: &str) -> Option<String> {
+        let (f, arg, seq) = {
+            let mut state = self.model().state.lock().unwrap();
+            state.next_seq += 1;
+            let seq = state.next_seq.to_string();
+            let (f, arg) = state.bindings.get(name)?;
+            (*f, arg.0, seq)
+        };
+        let c_seq = CString::new(seq.as_str()).unwrap();
+        let c_req = cstring::script(req);
+        f(c_seq.as_ptr(), c_req.as_ptr(), arg);
+        Some(seq)
+    }
+
+    /// The results handed back to the page so far.
+    pub fn returns(&self) -> Vec<Return> {
+        self.model().state.lock().unwrap().returns.clone()
+    }
+
+    /// Runs the closures dispatched so far without entering `run`, returns how many ran.
+    pub fn run_pending(&self) -> usize {
+        self.model().run_pending(self.webview)
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.model().state.lock().unwrap().terminated
+    }
+
+    /// The choice of `WebviewBuilder::background_throttling`, `None` if it wasn't made.
+    pub fn background_throttling(&self) -> Option<BackgroundThrottling> {
+        self.model().state.lock().unwrap().background_throttling
+    }
+
+    /// The color set with `Webview::set_background_color`.
+    pub fn background_color(&self) -> Option<Color> {
+        self.model().state.lock().unwrap().background_color
+    }
+
+    /// The theme set with `Webview::set_theme`.
+    pub fn theme(&self) -> Theme {
+        self.model().state.lock().unwrap().theme
+    }
+
+    /// Switches the system between light and dark, reporting it the way the operating system
+    /// would, on the calling thread. The system starts out light.
+    pub fn system_theme_changed(&self, theme: Theme) {
+        let callback = {
+            let mut state = self.model().state.lock().unwrap();
+            state.system_theme = Some(theme);
+            state.theme_callback.take()
+        };
+        if let Some(mut callback) = callback {
+            callback(theme);
+            let mut state = self.model().state.lock().unwrap();
+            if state.theme_callback.is_none() {
+                state.theme_callback = Some(callback);
+            }
+        }
+    }
+
+    /// Moves the keyboard focus to or away from the window, reporting it the way the operating
+    /// system would, on the calling thread. The window starts out without the focus.
+    pub fn focus_changed(&self, focused: bool) {
+        focus_changed(self.webview, focused);
+    }
+
+    /// Drops `paths` on the view at `position`, in its coordinates, reporting them the way the
+    /// operating system would, on the calling thread.
+    pub fn drop_files(&self, paths: &[PathBuf], position: (i32, i32)) {
+        let callback = self.model().state.lock().unwrap().file_drop_callback.take();
+        if let Some(mut callback) = callback {
+            callback(paths.to_vec(), position);
+            let mut state = self.model().state.lock().unwrap();
+            if state.file_drop_callback.is_none() {
+                state.file_drop_callback = Some(callback);
+            }
+        }
+    }
+
+    /// Presses `event` in the view, reporting it the way the operating system would, on the
+    /// calling thread. Returns whether a `Webview::on_key` handler kept it from the page.
+    pub fn press_key(&self, event: KeyEvent) -> bool {
+        let callback = self.model().state.lock().unwrap().key_callback.take();
+        match callback {
+            Some(mut callback) => {
+                let handled = callback(event);
+                let mut state = self.model().state.lock().unwrap();
+                if state.key_callback.is_none() {
+                    state.key_callback = Some(callback);
+                }
+                handled
+            }
+            None => false,
+        }
+    }
+
+    /// Follows a link of the page to `url`, asking the way the engine would whether to load it,
+    /// on the calling thread. Returns whether it was loaded.
+    pub fn follow_link(&self, url: &str) -> bool {
+        let callback = self
+            .model()
+            .state
+            .lock()
+            .unwrap()
+            .navigation_callback
+            .take();
+        let allowed = match callback {
+            Some(mut callback) => {
+                let allowed = callback(url);
+                let mut state = self.model().state.lock().unwrap();
+                if state.navigation_callback.is_none() {
+                    state.navigation_callback = Some(callback);
+                }
+                allowed
+            }
+            None => true,
+        };
+        if allowed {
+            self.model().state.lock().unwrap().visit(url.to_string());
+        }
+        allowed
+    }
+
+    /// Finishes loading the current page, reporting it the way the engine would, on the calling
+    /// thread.
+    pub fn finish_load(&self) {
+        let callback = self.model().state.lock().unwrap().load_callback.take();
+        if let Some(mut callback) = callback {
+            callback();
+            let mut state = self.model().state.lock().unwrap();
+            if state.load_callback.is_none() {
+                state.load_callback = Some(callback);
+            }
+        }
+    }
+
+    /// The window's position on the screen and its size.
+    pub fn window_frame(&self) -> Rect {
+        window_frame(self.webview)
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        is_maximized(self.webview)
+    }
+
+    /// Replaces the work areas of the monitors, the primary one first. There is a single
+    /// 1920x1080 one by default.
+    pub fn set_monitors(&self, monitors: Vec<Rect>) {
+        self.model().state.lock().unwrap().monitors = Some(monitors);
+    }
+
+    /// Moves and resizes the window the way the user would, reporting it on the calling thread.
+    pub fn move_window(&self, frame: Rect) {
+        set_window_frame(self.webview, frame);
+        frame_changed(self.webview);
+    }
+
+    /// Maximizes or restores the window the way the user would, reporting it on the calling
+    /// thread.
+    pub fn set_maximized(&self, maximized: bool) {
+        set_maximized(self.webview, maximized);
+        frame_changed(self.webview);
+    }
+
+    /// Opens `url` the way the system would when a link to one of the application's schemes is
+    /// followed while it runs, on the calling thread.
+    pub fn open_deep_link(&self, url: &str) {
+        let callback = self.model().state.lock().unwrap().deep_link_callback.take();
+        if let Some(mut callback) = callback {
+            callback(url.to_string());
+            let mut state = self.model().state.lock().unwrap();
+            if state.deep_link_callback.is_none() {
+                state.deep_link_callback = Some(callback);
+            }
+        }
+    }
+
+    /// The state and fraction last set with `Webview::set_progress`.
+    pub fn progress(&self) -> (ProgressState, f64) {
+        self.model().state.lock().unwrap().progress
+    }
+
+    /// Whether the window is shown, see `Webview::set_visible`.
+    pub fn is_visible(&self) -> bool {
+        !self.model().state.lock().unwrap().hidden
+    }
+
+    /// Whether the window was built with `WebviewBuilder::owner`.
+    pub fn is_owned(&self) -> bool {
+        self.model().state.lock().unwrap().owned
+    }
+
+    /// Whether the window takes input, it doesn't while a modal window it owns is open.
+    pub fn is_enabled(&self) -> bool {
+        self.model().state.lock().unwrap().modals == 0
+    }
+
+    /// The count last set with `Webview::set_badge_count`.
+    pub fn badge_count(&self) -> Option<u32> {
+        self.model().state.lock().unwrap().badge_count
+    }
+
+    /// The levels passed to `Webview::request_user_attention` so far.
+    pub fn attention_requests(&self) -> Vec<UserAttention> {
+        self.model()
+            .state
+            .lock()
+            .unwrap()
+            .attention_requests
+            .clone()
+    }
+
+    /// The image last set with `Webview::set_window_icon`.
+    pub fn window_icon(&self) -> Option<Vec<u8>> {
+        self.model().state.lock().unwrap().window_icon.clone()
+    }
+
+    /// The width to height ratio set with `Webview::set_aspect_ratio`.
+    pub fn aspect_ratio(&self) -> Option<(u32, u32)> {
+        self.model().state.lock().unwrap().aspect_ratio
+    }
+
+    /// The reason given to `Webview::prevent_sleep`, `None` if sleep is allowed.
+    pub fn sleep_prevented(&self) -> Option<String> {
+        self.model().state.lock().unwrap().sleep_prevented.clone()
+    }
+
+    /// The effect set with `WebviewBuilder::window_effect`.
+    pub fn window_effect(&self) -> Option<WindowEffect> {
+        self.model().state.lock().unwrap().window_effect
+    }
+
+    /// How many window drags the page started, see `WebviewBuilder::drag_regions`.
+    pub fn window_drags(&self) -> usize {
+        self.model().state.lock().unwrap().window_drags
+    }
+
+    /// What `Webview::start_drag` dragged out of the window, in order.
+    pub fn drags(&self) -> Vec<DragItem> {
+        self.model().state.lock().unwrap().drags.clone()
+    }
+
+    /// The urls handed to the default browser, see `NavigationPolicy::open_externally`.
+    pub fn opened_externally(&self) -> Vec<String> {
+        self.model().state.lock().unwrap().opened_externally.clone()
+    }
+
+    /// Reports a session change the way the operating system would, on the calling thread.
+    ///
+    /// Does nothing unless `Webview::on_session_event` was called.
+    pub fn session_event(&self, event: SessionEvent) {
+        let callback = self.model().state.lock().unwrap().session.take();
+        if let Some(mut callback) = callback {
+            callback(event);
+            let mut state = self.model().state.lock().unwrap();
+            if state.session.is_none() {
+                state.session = Some(callback);
+            }
+        }
+    }
+
+    /// Renders the page as `pixels`, `width` by `height` premultiplied BGRA of which `dirty`
+    /// changed, handing them to the `Webview::on_frame` handlers the way the engine would, on
+    /// the calling thread.
+    ///
+    /// Does nothing unless the webview renders offscreen.
+    pub fn paint(&self, width: u32, height: u32, pixels: &[u8], dirty: &[Rect]) {
+        let callback = self.model().state.lock().unwrap().paint_callback.take();
+        if let Some(mut callback) = callback {
+            callback(&Frame {
+                width,
+                height,
+                dirty,
+                pixels,
+            });
+            let mut state = self.model().state.lock().unwrap();
+            if state.paint_callback.is_none() {
+                state.paint_callback = Some(callback);
+            }
+        }
+    }
+
+    /// Loads `url` the way the engine would from a host mapped with `WebviewBuilder::map_host`,
+    /// the file's contents and MIME type. `None` where the page would get an error.
+    pub fn load_mapped(&self, url: &str) -> Option<(Vec<u8>, &'static str)> {
+        crate::host::load(url)
+    }
+}
+
+pub(crate) fn set_background_color(w: sys::webview_t, color: Color) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .background_color = Some(color);
+}
+
+pub(crate) fn set_theme(w: sys::webview_t, theme: Theme) {
+    unsafe { Model::from_raw(w) }.state.lock().unwrap().theme = theme;
+}
+
+pub(crate) fn system_theme(w: sys::webview_t) -> Theme {
+    let state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    state.system_theme.unwrap_or(Theme::Light)
+}
+
+/// Installs the callback `Mock::system_theme_changed` reports to, `None` removes it.
+pub(crate) fn set_theme_callback(w: sys::webview_t, callback: Option<ThemeCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .theme_callback = callback;
+}
+
+pub(crate) fn set_window_icon(w: sys::webview_t, image: &[u8]) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .window_icon = Some(image.to_vec());
+}
+
+pub(crate) fn focus_changed(w: sys::webview_t, focused: bool) {
+    let callback = {
+        let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+        if state.focused == focused {
+            return;
+        }
+        state.focused = focused;
+        state.focus_callback.take()
+    };
+    if let Some(mut callback) = callback {
+        callback(focused);
+        let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+        if state.focus_callback.is_none() {
+            state.focus_callback = Some(callback);
+        }
+    }
+}
+
+pub(crate) fn set_progress(w: sys::webview_t, state: ProgressState, fraction: f64) {
+    unsafe { Model::from_raw(w) }.state.lock().unwrap().progress = (state, fraction);
+}
+
+pub(crate) fn set_visible(w: sys::webview_t, visible: bool) {
+    unsafe { Model::from_raw(w) }.state.lock().unwrap().hidden = !visible;
+}
+
+pub(crate) fn set_owned(w: sys::webview_t) {
+    unsafe { Model::from_raw(w) }.state.lock().unwrap().owned = true;
+}
+
+pub(crate) fn add_modal(w: sys::webview_t, count: isize) {
+    unsafe { Model::from_raw(w) }.state.lock().unwrap().modals += count;
+}
+
+pub(crate) fn set_badge_count(w: sys::webview_t, count: Option<u32>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .badge_count = count;
+}
+
+pub(crate) fn request_user_attention(w: sys::webview_t, level: UserAttention) {
+    let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    state.attention_requests.push(level);
+}
+
+pub(crate) fn is_focused(w: sys::webview_t) -> bool {
+    unsafe { Model::from_raw(w) }.state.lock().unwrap().focused
+}
+
+/// Installs the callback `Mock::focus_changed` reports to, `None` removes it.
+pub(crate) fn set_focus_callback(w: sys::webview_t, callback: Option<FocusCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .focus_callback = callback;
+}
+
+/// Installs the callback `Mock::drop_files` reports to, `None` removes it.
+pub(crate) fn set_file_drop_callback(w: sys::webview_t, callback: Option<FileDropCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .file_drop_callback = callback;
+}
+
+/// Installs the callback `Mock::press_key` reports to, `None` removes it.
+pub(crate) fn set_key_callback(w: sys::webview_t, callback: Option<KeyCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .key_callback = callback;
+}
+
+/// Installs the callback `Mock::follow_link` asks, `None` removes it.
+pub(crate) fn set_navigation_callback(w: sys::webview_t, callback: Option<NavigationCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .navigation_callback = callback;
+}
+
+/// Installs the callback `Mock::finish_load` reports to, `None` removes it.
+pub(crate) fn set_load_callback(w: sys::webview_t, callback: Option<LoadCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .load_callback = callback;
+}
+
+pub(crate) fn window_frame(w: sys::webview_t) -> Rect {
+    let state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    Rect {
+        x: state.origin.0,
+        y: state.origin.1,
+        width: state.size.0,
+        height: state.size.1,
+    }
+}
+
+pub(crate) fn set_window_frame(w: sys::webview_t, frame: Rect) {
+    let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    state.origin = (frame.x, frame.y);
+    state.size = (frame.width, frame.height);
+}
+
+pub(crate) fn is_maximized(w: sys::webview_t) -> bool {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .maximized
+}
+
+pub(crate) fn is_fullscreen(w: sys::webview_t) -> bool {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .fullscreen
+}
+
+pub(crate) fn set_fullscreen(w: sys::webview_t, fullscreen: bool) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .fullscreen = fullscreen;
+}
+
+pub(crate) fn set_maximized(w: sys::webview_t, maximized: bool) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .maximized = maximized;
+}
+
+pub(crate) fn monitors(w: sys::webview_t) -> Vec<Rect> {
+    let state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    state.monitors.clone().unwrap_or_else(|| {
+        vec![Rect {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        }]
+    })
+}
+
+fn frame_changed(w: sys::webview_t) {
+    let callback = unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .frame_callback
+        .take();
+    if let Some(mut callback) = callback {
+        callback();
+        let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+        if state.frame_callback.is_none() {
+            state.frame_callback = Some(callback);
+        }
+    }
+}
+
+/// Installs the callback `Mock::move_window` and `Mock::set_maximized` report to, `None`
+/// removes it.
+pub(crate) fn set_frame_callback(w: sys::webview_t, callback: Option<FrameCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .frame_callback = callback;
+}
+
+/// Installs the callback `Mock::open_deep_link` reports to, `None` removes it.
+pub(crate) fn set_deep_link_callback(w: sys::webview_t, callback: Option<DeepLinkCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .deep_link_callback = callback;
+}
+
+pub(crate) fn set_aspect_ratio(w: sys::webview_t, ratio: Option<(u32, u32)>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .aspect_ratio = ratio;
+}
+
+pub(crate) fn set_sleep_prevented(w: sys::webview_t, reason: Option<String>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .sleep_prevented = reason;
+}
+
+pub(crate) fn set_window_effect(w: sys::webview_t, effect: WindowEffect) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .window_effect = Some(effect);
+}
+
+pub(crate) fn start_window_drag(w: sys::webview_t) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .window_drags += 1;
+}
+
+pub(crate) fn start_drag(w: sys::webview_t, item: &DragItem) {
+    let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    state.drags.push(item.clone());
+}
+
+pub(crate) fn open_external(w: sys::webview_t, url: &str) {
+    let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    state.opened_externally.push(url.to_string());
+}
+
+pub(crate) fn load(w: sys::webview_t, request: &platform::Request) {
+    let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    state.visit(request.url.clone());
+    state.requests.push(Request {
+        url: request.url.clone(),
+        headers: request.headers.clone(),
+        body: request.body.clone(),
+    });
+}
+
+/// Moves through the history `webview_navigate` records.
+pub(crate) fn go(w: sys::webview_t, action: HistoryAction) {
+    let mut state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    match action {
+        HistoryAction::Back if state.position > 1 => state.position -= 1,
+        HistoryAction::Forward if state.position < state.history.len() => state.position += 1,
+        HistoryAction::Reload | HistoryAction::ReloadIgnoringCache if state.position > 0 => {}
+        _ => return,
+    }
+    let url = state.history[state.position - 1].clone();
+    state.navigations.push(url);
+}
+
+/// The url of the current page, `None` before the first navigation.
+pub(crate) fn url(w: sys::webview_t) -> Option<String> {
+    let state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    state
+        .position
+        .checked_sub(1)
+        .map(|i| state.history[i].clone())
+}
+
+/// Whether there are entries behind and ahead of the current page.
+pub(crate) fn can_go(w: sys::webview_t) -> (bool, bool) {
+    let state = unsafe { Model::from_raw(w) }.state.lock().unwrap();
+    (state.position > 1, state.position < state.history.len())
+}
+
+pub(crate) fn set_background_throttling(w: sys::webview_t, throttling: BackgroundThrottling) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .background_throttling = Some(throttling);
+}
+
+pub(crate) fn set_paint_callback(w: sys::webview_t, callback: Option<PaintCallback>) {
+    unsafe { Model::from_raw(w) }
+        .state
+        .lock()
+        .unwrap()
+        .paint_callback = callback;
+}
+
+/// Installs the callback `Mock::session_event` reports to, `None` removes it.
+pub(crate) fn set_session_callback(w: sys::webview_t, callback: Option<SessionCallback>) {
+    unsafe { Model::from_raw(w) }.state.lock().unwrap().session = callback;
+}
+
+/// The `webview_official_sys` functions, backed by the model for the webviews it stands in for.
+pub(crate) mod sys {
+    use super::*;
+
+    use webview_official_sys as native;
+    pub use webview_official_sys::{webview_t, BindFn, DispatchFn};
+
+    unsafe fn string(s: *const c_char) -> String {
+        CStr::from_ptr(s).to_string_lossy().into_owned()
+    }
+
+    /// Models are created with `mock::create` instead.
+    pub unsafe fn webview_create(debug: c_int, window: *mut c_void) -> webview_t {
+        native::webview_create(debug, window)
+    }
+
+    pub unsafe fn webview_destroy(w: webview_t) {
+        if !is_model(w) {
+            return native::webview_destroy(w);
+        }
+        MODELS.lock().unwrap().retain(|&model| model != w as usize);
+        drop(Box::from_raw(w as *mut Model));
+    }
+
+    pub unsafe fn webview_run(w: webview_t) {
+        if !is_model(w) {
+            return native::webview_run(w);
+        }
+        let model = Model::from_raw(w);
+        loop {
+            model.run_pending(w);
+            let state = model.state.lock().unwrap();
+            if state.terminated {
+                return;
+            }
+            if state.queue.is_empty() {
+                drop(model.wake.wait(state).unwrap());
+            }
+        }
+    }
+
+    pub unsafe fn webview_terminate(w: webview_t) {
+        if !is_model(w) {
+            return native::webview_terminate(w);
+        }
+        let model = Model::from_raw(w);
+        model.state.lock().unwrap().terminated = true;
+        model.wake.notify_all();
+    }
+
+    pub unsafe fn webview_dispatch(w: webview_t, fn_: Option<DispatchFn>, arg: *mut c_void) {
+        if !is_model(w) {
+            return native::webview_dispatch(w, fn_, arg);
+        }
+        if let Some(f) = fn_ {
+            let model = Model::from_raw(w);
+            model.state.lock().unwrap().queue.push_back((f, Ptr(arg)));
+            model.wake.notify_all();
+        }
+    }
+
+    pub unsafe fn webview_get_window(w: webview_t) -> *mut c_void {
+        if !is_model(w) {
+            return native::webview_get_window(w);
+        }
+        std::ptr::null_mut()
+    }
+
+    pub unsafe fn webview_set_title(w: webview_t, title: *const c_char) {
+        if !is_model(w) {
+            return native::webview_set_title(w, title);
+        }
+        Model::from_raw(w).state.lock().unwrap().title = string(title);
+    }
+
+    pub unsafe fn webview_set_size(w: webview_t, width: c_int, height: c_int, hints: c_int) {
+        if !is_model(w) {
+            return native::webview_set_size(w, width, height, hints);
+        }
+        Model::from_raw(w).state.lock().unwrap().size = (width, height);
+    }
+
+    pub unsafe fn webview_navigate(w: webview_t, url: *const c_char) {
+        if !is_model(w) {
+            return native::webview_navigate(w, url);
+        }
+        Model::from_raw(w).state.lock().unwrap().visit(string(url));
+    }
+
+    pub unsafe fn webview_init(w: webview_t, js: *const c_char) {
+        if !is_model(w) {
+            return native::webview_init(w, js);
+        }
+        let js = string(js);
+        Model::from_raw(w)
+            .state
+            .lock()
+            .unwrap()
+            .init_scripts
+            .push(js);
+    }
+
+    pub unsafe fn webview_eval(w: webview_t, js: *const c_char) {
+        if !is_model(w) {
+            return native::webview_eval(w, js);
+        }
+        let js = string(js);
+        Model::from_raw(w).state.lock().unwrap().evaluated.push(js);
+    }
+
+    pub unsafe fn webview_bind(
+        w: webview_t,
+        name: *const c_char,
+        fn_: Option<BindFn>,
+        arg: *mut c_void,
+    ) {
+        if !is_model(w) {
+            return native::webview_bind(w, name, fn_, arg);
+        }
+        if let Some(f) = fn_ {
+            let name = string(name);
+            let mut state = Model::from_raw(w).state.lock().unwrap();
+            state.bindings.insert(name, (f, Ptr(arg)));
+        }
+    }
+
+    pub unsafe fn webview_return(
+        w: webview_t,
+        seq: *const c_char,
+        status: c_int,
+        result: *const c_char,
+    ) {
+        if !is_model(w) {
+            return native::webview_return(w, seq, status, result);
+        }
+        let ret = Return {
+            seq: string(seq),
+            status,
+            result: string(result),
+        };
+        Model::from_raw(w).state.lock().unwrap().returns.push(ret);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::thread;
+
+    use crate::{Backend, Error, NavigationPolicy, WebviewBuilder};
+
+    #[test]
+    fn builds_on_the_model() {
+        let webview = WebviewBuilder::new()
+            .mock()
+            .title("Tested")
+            .width(320)
+            .height(240)
+            .build();
+        assert_eq!(webview.backend(), Backend::Mock);
+        assert!(!webview.capabilities().window_handle);
+        assert_eq!(webview.mock().title(), "Tested");
+        assert_eq!(webview.mock().size(), (320, 240));
+    }
+
+    #[test]
+    fn calls_bindings_like_the_page() {
+        let mut webview = WebviewBuilder::new().mock().build();
+        let handle = webview.handle();
+        webview.bind("echo", move |seq, req| {
+            let _ = handle.r#return(seq, 0, req);
+        });
+        let mock = webview.mock();
+        assert!(mock.bindings().contains(&"echo".to_string()));
+        let seq = mock.call("echo", "[1,2]").unwrap();
+        mock.run_pending();
+        let returned = mock.returns().into_iter().find(|ret| ret.seq == seq);
+        assert_eq!(returned.map(|ret| ret.result), Some("[1,2]".to_string()));
+        assert_eq!(mock.call("missing", "[]"), None);
+    }
+
+    #[test]
+    fn the_engine_asks_the_navigation_policy_before_loading() {
+        let policy = NavigationPolicy::new()
+            .allow("https://example.com/*")
+            .open_externally(true);
+        let webview = WebviewBuilder::new()
+            .mock()
+            .navigation_policy(policy)
+            .build();
+        let mock = webview.mock();
+        assert!(!mock.follow_link("https://evil.net/"));
+        assert!(mock.follow_link("https://example.com/next"));
+        assert_eq!(mock.navigations(), ["https://example.com/next"]);
+        assert_eq!(mock.opened_externally(), ["https://evil.net/"]);
+    }
+
+    #[test]
+    fn loads_are_reported_by_the_engine_not_the_page() {
+        let mut webview = WebviewBuilder::new().mock().build();
+        let bindings = webview.mock().bindings();
+        let loads = Rc::new(Cell::new(0));
+        let counted = loads.clone();
+        webview.on_load_finished(move |_| counted.set(counted.get() + 1));
+        assert_eq!(webview.mock().bindings(), bindings);
+        webview.mock().finish_load();
+        webview.mock().finish_load();
+        assert_eq!(loads.get(), 2);
+    }
+
+    #[test]
+    fn title_handlers_added_by_handlers_share_the_bridge() {
+        let mut webview = WebviewBuilder::new().mock().build();
+        let calls = Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        webview.on_title_changed(move |w, _| {
+            counted.set(counted.get() + 1);
+            let counted = counted.clone();
+            w.on_title_changed(move |_, _| counted.set(counted.get() + 1));
+        });
+        let mock = webview.mock();
+        mock.call("__webview_title", r#"["First"]"#).unwrap();
+        assert_eq!(calls.get(), 1);
+        calls.set(0);
+        mock.call("__webview_title", r#"["Second"]"#).unwrap();
+        assert_eq!(calls.get(), 2);
+        let scripts = mock.init_scripts();
+        assert_eq!(
+            scripts
+                .iter()
+                .filter(|s| s.contains("document.title"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn runs_until_terminated() {
+        let mut webview = WebviewBuilder::new()
+            .mock()
+            .url("https://example.com/")
+            .build();
+        let mut handle = webview.handle();
+        let terminating = thread::spawn(move || handle.terminate().unwrap());
+        webview.run();
+        terminating.join().unwrap();
+        assert!(webview.mock().is_terminated());
+        assert_eq!(webview.mock().navigations(), ["https://example.com/"]);
+    }
+
+    #[test]
+    fn creates_and_destroys_webviews_repeatedly() {
+        let quits = Rc::new(Cell::new(0));
+        for round in 0..20 {
+            let title = format!("Round {}", round);
+            let mut webview = WebviewBuilder::new().mock().title(&title).build();
+            let counted = quits.clone();
+            webview.on_before_quit(move || {
+                counted.set(counted.get() + 1);
+            });
+            let mut handle = webview.handle();
+            let mut left = webview.handle();
+            let terminating = thread::spawn(move || handle.terminate().unwrap());
+            webview.run();
+            terminating.join().unwrap();
+            assert_eq!(webview.mock().title(), title);
+            assert_eq!(quits.get(), round + 1);
+            // The model goes with the last clone, handles outliving it fail.
+            drop(webview);
+            assert!(matches!(left.terminate(), Err(Error::WebviewNull)));
+        }
+    }
+
+    #[test]
+    fn terminating_again_stops_without_waiting_for_hooks() {
+        let mut webview = WebviewBuilder::new().mock().build();
+        let held = Rc::new(Cell::new(None));
+        let holding = held.clone();
+        webview.on_before_quit_async(move |guard| holding.set(Some(guard)));
+        webview.terminate();
+        webview.mock().run_pending();
+        assert!(!webview.mock().is_terminated());
+        webview.terminate();
+        assert!(webview.mock().is_terminated());
+    }
+}
@@ -0,0 +1,163 @@
+//! Emulated network conditions, see `Webview::emulate_network`.
+
+use std::time::Duration;
+
+/// The network conditions a page is put under, see `Webview::emulate_network`.
+///
+/// The default is the actual network, without emulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NetworkConditions {
+    /// Requests fail as without a connection and `navigator.onLine` is `false`.
+    pub offline: bool,
+    /// Added to every request.
+    pub latency: Duration,
+    /// Bytes per second responses arrive at, `None` for no limit.
+    pub download: Option<u64>,
+    /// Bytes per second request bodies are sent at, `None` for no limit.
+    pub upload: Option<u64>,
+}
+
+impl NetworkConditions {
+    /// No connection.
+    pub fn offline() -> Self {
+        NetworkConditions {
+            offline: true,
+            ..NetworkConditions::default()
+        }
+    }
+
+    /// A poor mobile connection, as Chromium's developer tools have it.
+    pub fn slow_3g() -> Self {
+        NetworkConditions {
+            offline: false,
+            latency: Duration::from_millis(2000),
+            download: Some(50_000),
+            upload: Some(50_000),
+        }
+    }
+
+    /// A good mobile connection, as Chromium's developer tools have it.
+    pub fn fast_3g() -> Self {
+        NetworkConditions {
+            offline: false,
+            latency: Duration::from_millis(563),
+            download: Some(180_000),
+            upload: Some(84_375),
+        }
+    }
+}
+
+pub(crate) const BINDING: &str = "__webview_network_conditions";
+
+/// Wraps `fetch` and `XMLHttpRequest` to hold requests back or fail them, and `navigator.onLine`.
+///
+/// Init scripts can't be changed once added, so this one is added once and asks `BINDING` for
+/// the conditions on every page, requests wait for the answer. Conditions changed while a page
+/// is shown are set with `window.__webview_network`, which fires `online` and `offline`.
+pub(crate) const INIT_SCRIPT: &str = r#"(function () {
+  if (window.__webview_network) {
+    return;
+  }
+  var conditions = { offline: false, latency: 0, download: 0, upload: 0 };
+  var delay = function (bytes, rate) {
+    return rate ? bytes / rate * 1000 : 0;
+  };
+  var wait = function (ms) {
+    return new Promise(function (resolve) { setTimeout(resolve, ms); });
+  };
+  var length = function (body) {
+    return typeof body === "string" ? body.length : body && body.byteLength || body && body.size || 0;
+  };
+  var onLine = Object.getOwnPropertyDescriptor(Navigator.prototype, "onLine");
+  Object.defineProperty(Navigator.prototype, "onLine", {
+    configurable: true,
+    enumerable: true,
+    get: function () { return !conditions.offline && onLine.get.call(this); },
+  });
+  var fetch = window.fetch;
+  window.fetch = function (input, init) {
+    var self = this, args = arguments;
+    return ready.then(function () {
+      if (conditions.offline) {
+        throw new TypeError("Failed to fetch");
+      }
+      return wait(conditions.latency + delay(length(init && init.body), conditions.upload));
+    }).then(function () {
+      return fetch.apply(self, args);
+    }).then(function (response) {
+      var received = Number(response.headers.get("content-length")) || 0;
+      return wait(delay(received, conditions.download)).then(function () { return response; });
+    });
+  };
+  var open = XMLHttpRequest.prototype.open;
+  XMLHttpRequest.prototype.open = function (method, url, async) {
+    this.__webview_sync = async === false;
+    return open.apply(this, arguments);
+  };
+  var send = XMLHttpRequest.prototype.send;
+  XMLHttpRequest.prototype.send = function (body) {
+    var xhr = this, args = arguments;
+    if (xhr.__webview_sync) {
+      return send.apply(xhr, args);
+    }
+    ready.then(function () {
+      if (conditions.offline) {
+        xhr.dispatchEvent(new ProgressEvent("error"));
+        xhr.dispatchEvent(new ProgressEvent("loadend"));
+        return;
+      }
+      var sent = conditions.latency + delay(length(body), conditions.upload);
+      setTimeout(function () { send.apply(xhr, args); }, sent);
+    });
+  };
+  window.__webview_network = function (next) {
+    var offline = conditions.offline;
+    conditions = next;
+    if (offline !== next.offline) {
+      window.dispatchEvent(new Event(next.offline ? "offline" : "online"));
+    }
+  };
+  var ready = window.__webview_network_conditions().then(window.__webview_network, function () {});
+})();"#;
+
+/// `conditions` as the page's script has them.
+pub(crate) fn json(conditions: &NetworkConditions) -> String {
+    format!(
+        r#"{{"offline":{},"latency":{},"download":{},"upload":{}}}"#,
+        conditions.offline,
+        conditions.latency.as_millis(),
+        conditions.download.unwrap_or(0),
+        conditions.upload.unwrap_or(0)
+    )
+}
+
+/// Puts the page shown under `conditions`.
+pub(crate) fn set_script(conditions: &NetworkConditions) -> String {
+    format!("window.__webview_network({});", json(conditions))
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::WebviewBuilder;
+
+    #[test]
+    fn pages_ask_for_the_conditions_of_the_moment() {
+        let mut webview = WebviewBuilder::new().mock().build();
+        webview.emulate_network(NetworkConditions::slow_3g());
+        webview.set_offline(true);
+        webview.emulate_network(NetworkConditions::fast_3g());
+        let mock = webview.mock();
+        let scripts = mock.init_scripts();
+        assert_eq!(scripts.iter().filter(|s| *s == INIT_SCRIPT).count(), 1);
+        assert!(!scripts.iter().any(|s| s.contains("__webview_network(")));
+
+        let seq = mock.call(BINDING, "[]").unwrap();
+        mock.run_pending();
+        let answer = mock.returns().into_iter().find(|ret| ret.seq == seq);
+        assert_eq!(
+            answer.map(|ret| ret.result),
+            Some(json(&NetworkConditions::fast_3g()))
+        );
+    }
+}
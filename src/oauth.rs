@@ -0,0 +1,539 @@
+//! Signing in with OAuth 2.0 providers from the application, following RFC 8252.
+//!
+//! An `Authorization` opens the provider's authorization page, in the default browser or in the
+//! window, and captures the redirect that comes back with the authorization code. The code is
+//! bound to the flow with a `state` and a PKCE `code_challenge`, so the application can exchange
+//! it for tokens without a client secret, by posting the `code`, `code_verifier` and
+//! `redirect_uri` of the `AuthorizationCode` to the provider's token endpoint.
+//!
+//! The redirect is captured by a listener on a free loopback port, which providers allow for
+//! native applications, or as a deep link to one of the application's url schemes, see
+//! `Application::register_url_scheme`.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use crate::{json, random, Error, Webview, WebviewHandle};
+
+/// How long a flow waits for the redirect unless `Authorization::timeout` says otherwise.
+const TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The path the loopback listener answers on.
+const CALLBACK_PATH: &str = "/callback";
+
+const DONE_PAGE: &str = "<!DOCTYPE html><title>Signed in</title>\
+<p>You can close this window and return to the application.</p>";
+
+type Finish = Box<dyn FnOnce(&mut Webview, Result<AuthorizationCode, Error>) + Send>;
+
+/// A flow's `f` until whoever finishes the flow first takes it.
+struct Finishing {
+    f: Mutex<Option<Finish>>,
+    taken: Condvar,
+}
+
+impl Finishing {
+    fn new(f: Finish) -> Arc<Finishing> {
+        Arc::new(Finishing {
+            f: Mutex::new(Some(f)),
+            taken: Condvar::new(),
+        })
+    }
+
+    /// Takes `f` unless it was taken already, waking `wait_taken`.
+    fn take(&self) -> Option<Finish> {
+        let f = self.f.lock().unwrap().take();
+        self.taken.notify_all();
+        f
+    }
+
+    /// Waits until `f` was taken or `deadline` passed, returns whether it was taken.
+    fn wait_taken(&self, deadline: Instant) -> bool {
+        let f = self.f.lock().unwrap();
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let (f, _) = self
+            .taken
+            .wait_timeout_while(f, timeout, |f| f.is_some())
+            .unwrap();
+        f.is_none()
+    }
+}
+
+fn failed(reason: impl Into<String>) -> Error {
+    Error::Authorization(reason.into())
+}
+
+enum Redirect {
+    Loopback(TcpListener),
+    Scheme,
+}
+
+/// An authorization code flow, see the module documentation.
+pub struct Authorization {
+    auth_url: String,
+    redirect: Redirect,
+    redirect_uri: String,
+    state: String,
+    code_verifier: String,
+    timeout: Duration,
+}
+
+impl Authorization {
+    /// Captures the redirect with a listener on a free port of `127.0.0.1`.
+    ///
+    /// `auth_url` is the provider's authorization endpoint with the client's parameters, e.g.
+    /// `client_id` and `scope`. `response_type`, `redirect_uri`, `state` and the PKCE
+    /// `code_challenge` are added to it.
+    pub fn loopback(auth_url: &str) -> io::Result<Authorization> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let redirect_uri = format!(
+            "http://127.0.0.1:{}{}",
+            listener.local_addr()?.port(),
+            CALLBACK_PATH
+        );
+        Ok(Authorization::new(
+            auth_url,
+            Redirect::Loopback(listener),
+            redirect_uri,
+        ))
+    }
+
+    /// Captures the redirect to `redirect_uri` as a deep link, e.g. `com.example.app:/oauth`.
+    ///
+    /// Its scheme has to be registered with `Application::register_url_scheme`. Outside macOS
+    /// the browser starts another instance of the application with the link, so the running
+    /// one needs an `InstanceLock` forwarding to the webview the flow is opened on.
+    pub fn custom_scheme(auth_url: &str, redirect_uri: &str) -> Authorization {
+        Authorization::new(auth_url, Redirect::Scheme, redirect_uri.to_string())
+    }
+
+    fn new(auth_url: &str, redirect: Redirect, redirect_uri: String) -> Authorization {
+        Authorization {
+            auth_url: auth_url.to_string(),
+            redirect,
+            redirect_uri,
+            state: random::token(),
+            // 64 characters, RFC 7636 asks for 43 to 128.
+            code_verifier: random::token() + &random::token(),
+            timeout: TIMEOUT,
+        }
+    }
+
+    /// How long to wait for the redirect before giving up with `Error::Timeout`, five minutes
+    /// by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The url the provider redirects to, which has to be allowed for the client.
+    pub fn redirect_uri(&self) -> &str {
+        &self.redirect_uri
+    }
+
+    /// The authorization page with the parameters of this flow.
+    pub fn url(&self) -> String {
+        let challenge = code_challenge(&self.code_verifier);
+        let params = [
+            ("response_type", "code"),
+            ("redirect_uri", &self.redirect_uri),
+            ("state", &self.state),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256"),
+        ];
+        let query: Vec<String> = params
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, encode_component(value)))
+            .collect();
+        let separator = if self.auth_url.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        format!("{}{}{}", self.auth_url, separator, query.join("&"))
+    }
+
+    /// Opens the authorization page in the default browser and calls `f` on `webview`'s UI
+    /// thread with the code once the provider redirects back, or with the reason it didn't.
+    pub fn open_externally<F>(self, webview: &mut Webview, f: F)
+    where
+        F: FnOnce(&mut Webview, Result<AuthorizationCode, Error>) + Send + 'static,
+    {
+        let url = self.url();
+        if !webview.open_external(&url) {
+            let error = failed("the browser couldn't be opened");
+            webview.dispatch(move |w| f(w, Err(error)));
+            return;
+        }
+        self.capture(webview, Box::new(f));
+    }
+
+    /// Like `open_externally`, but loads the authorization page in the window.
+    ///
+    /// Only loopback redirects can be captured this way, and the navigation policy has to allow
+    /// both the provider's pages and `http://127.0.0.1`. The window stays on a page saying the
+    /// sign in finished, `f` usually navigates back to the application.
+    pub fn open_in_window<F>(self, webview: &mut Webview, f: F)
+    where
+        F: FnOnce(&mut Webview, Result<AuthorizationCode, Error>) + Send + 'static,
+    {
+        if let Redirect::Scheme = self.redirect {
+            let error = failed("custom scheme redirects can't be captured in the window");
+            webview.dispatch(move |w| f(w, Err(error)));
+            return;
+        }
+        let script = format!("window.location.assign({})", json::string(&self.url()));
+        webview.dispatch(move |w| w.eval(&script));
+        self.capture(webview, Box::new(f));
+    }
+
+    fn capture(self, webview: &mut Webview, f: Finish) {
+        let finish = Finishing::new(f);
+        let deadline = Instant::now() + self.timeout;
+        let flow = Flow {
+            redirect_uri: self.redirect_uri,
+            state: self.state,
+            code_verifier: self.code_verifier,
+        };
+        match self.redirect {
+            Redirect::Loopback(listener) => {
+                let handle = webview.handle();
+                thread::spawn(move || {
+                    let result = listen(&listener, &flow, deadline);
+                    dispatch_finish(handle, finish, result);
+                });
+            }
+            Redirect::Scheme => {
+                let flows = webview.oauth_flows();
+                let id = flows.add(flow, finish.clone());
+                let handle = webview.handle();
+                thread::spawn(move || {
+                    let finished = finish.wait_taken(deadline);
+                    flows.remove(id);
+                    if !finished {
+                        dispatch_finish(handle, finish, Err(Error::Timeout));
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Calls the flow's `f` with `result` on the UI thread, unless it was called already.
+fn dispatch_finish(
+    mut webview: WebviewHandle,
+    finish: Arc<Finishing>,
+    result: Result<AuthorizationCode, Error>,
+) {
+    let _ = webview.dispatch(move |w| {
+        if let Some(f) = finish.take() {
+            f(w, result);
+        }
+    });
+}
+
+/// The custom scheme flows of a webview waiting for their redirect, which share one deep link
+/// handler, see `Webview::oauth_flows`.
+#[derive(Clone, Default)]
+pub(crate) struct PendingFlows(Arc<Mutex<Pending>>);
+
+#[derive(Default)]
+struct Pending {
+    flows: Vec<PendingFlow>,
+    next_id: u64,
+}
+
+struct PendingFlow {
+    id: u64,
+    flow: Flow,
+    finish: Arc<Finishing>,
+}
+
+impl PendingFlows {
+    fn add(&self, flow: Flow, finish: Arc<Finishing>) -> u64 {
+        let mut pending = self.0.lock().unwrap();
+        pending.next_id += 1;
+        let id = pending.next_id;
+        pending.flows.push(PendingFlow { id, flow, finish });
+        id
+    }
+
+    /// Forgets the flow `id`, its `f` is shared with whoever finishes it.
+    fn remove(&self, id: u64) {
+        self.0
+            .lock()
+            .unwrap()
+            .flows
+            .retain(|pending| pending.id != id);
+    }
+
+    /// Finishes the flow `link` is the redirect of, if there is one.
+    pub(crate) fn redirected(&self, webview: &mut Webview, link: &str) {
+        let found = {
+            let mut pending = self.0.lock().unwrap();
+            let found =
+                pending.flows.iter().enumerate().find_map(|(i, pending)| {
+                    pending.flow.redirected(link).map(|result| (i, result))
+                });
+            found.map(|(i, result)| (pending.flows.remove(i).finish, result))
+        };
+        if let Some((finish, result)) = found {
+            if let Some(f) = finish.take() {
+                f(webview, result);
+            }
+        }
+    }
+
+    #[cfg(all(test, feature = "mock"))]
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().flows.len()
+    }
+}
+
+/// What the redirect of a flow is checked against.
+struct Flow {
+    redirect_uri: String,
+    state: String,
+    code_verifier: String,
+}
+
+impl Flow {
+    /// The outcome `url` carries if it is the redirect of this flow, `None` if it isn't.
+    fn redirected(&self, url: &str) -> Option<Result<AuthorizationCode, Error>> {
+        let (target, query) = url.split_once('?').unwrap_or((url, ""));
+        let query = query.split('#').next().unwrap_or(query);
+        let expected = self.redirect_uri.split('?').next().unwrap_or("");
+        if !target.eq_ignore_ascii_case(expected) {
+            return None;
+        }
+        self.outcome(query)
+    }
+
+    /// The outcome of the redirect's `query`, `None` if its `state` isn't the one of this flow.
+    fn outcome(&self, query: &str) -> Option<Result<AuthorizationCode, Error>> {
+        let params: Vec<(String, String)> = query
+            .split('&')
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                Some((decode_component(name)?, decode_component(value)?))
+            })
+            .collect();
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.as_str())
+        };
+        // A redirect without the state may be forged by another page, it isn't ours.
+        if param("state") != Some(self.state.as_str()) {
+            return None;
+        }
+        Some(match (param("code"), param("error")) {
+            (_, Some(error)) => Err(failed(match param("error_description") {
+                Some(description) => format!("{}, {}", error, description),
+                None => error.to_string(),
+            })),
+            (Some(code), None) => Ok(AuthorizationCode {
+                code: code.to_string(),
+                code_verifier: self.code_verifier.clone(),
+                redirect_uri: self.redirect_uri.clone(),
+            }),
+            (None, None) => Err(failed("the redirect has no code")),
+        })
+    }
+}
+
+/// Answers requests to the listener until the redirect of `flow` arrives or `deadline` passes.
+fn listen(
+    listener: &TcpListener,
+    flow: &Flow,
+    deadline: Instant,
+) -> Result<AuthorizationCode, Error> {
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| failed(e.to_string()))?;
+    while Instant::now() < deadline {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Some(result) = answer(stream, flow) {
+                    return result;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(50))
+            }
+            Err(e) => return Err(failed(e.to_string())),
+        }
+    }
+    Err(Error::Timeout)
+}
+
+/// Answers one request, with the outcome if it was the redirect of `flow`.
+fn answer(stream: TcpStream, flow: &Flow) -> Option<Result<AuthorizationCode, Error>> {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    // Skip the headers.
+    let mut line = String::new();
+    while reader.read_line(&mut line).ok()? > 2 {
+        line.clear();
+    }
+    let mut stream = reader.into_inner();
+
+    let mut parts = request_line.split_whitespace();
+    let outcome = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => {
+            let (path, query) = target.split_once('?').unwrap_or((target, ""));
+            if path == CALLBACK_PATH {
+                flow.outcome(query)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    let (status, body) = match &outcome {
+        Some(_) => ("200 OK", DONE_PAGE),
+        None => ("404 Not Found", ""),
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    outcome
+}
+
+/// The code the provider redirected back with, see `Authorization`.
+///
+/// All three values go along when exchanging it at the provider's token endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationCode {
+    code: String,
+    code_verifier: String,
+    redirect_uri: String,
+}
+
+impl AuthorizationCode {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The PKCE verifier the code is bound to.
+    pub fn code_verifier(&self) -> &str {
+        &self.code_verifier
+    }
+
+    pub fn redirect_uri(&self) -> &str {
+        &self.redirect_uri
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+fn encode_component(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Decodes a query component, `None` if it isn't valid percent-encoded UTF-8.
+fn decode_component(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut input = s.bytes();
+    while let Some(byte) = input.next() {
+        bytes.push(match byte {
+            b'+' => b' ',
+            b'%' => {
+                let hex = [input.next()?, input.next()?];
+                u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?
+            }
+            byte => byte,
+        });
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The PKCE `code_challenge` of `code_verifier`, the base64url encoded SHA-256 digest.
+fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finishing_wakes_the_timeout() {
+        let finish = Finishing::new(Box::new(|_, _| {}));
+        let waiting = finish.clone();
+        let started = Instant::now();
+        let waiter =
+            thread::spawn(move || waiting.wait_taken(Instant::now() + Duration::from_secs(60)));
+        assert!(finish.take().is_some());
+        assert!(waiter.join().unwrap());
+        assert!(started.elapsed() < Duration::from_secs(60));
+        assert!(finish.take().is_none());
+    }
+
+    #[test]
+    fn challenges_like_rfc_7636() {
+        // Appendix B.
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn custom_scheme_flows_share_one_handler_and_leave_it() {
+        use crate::WebviewBuilder;
+
+        let mut webview = WebviewBuilder::new().mock().build();
+        let codes = Arc::new(Mutex::new(Vec::new()));
+        for _ in 0..2 {
+            let codes = codes.clone();
+            Authorization::custom_scheme("https://example.com/auth", "com.example.app:/oauth")
+                .open_externally(&mut webview, move |_, result| {
+                    codes
+                        .lock()
+                        .unwrap()
+                        .push(result.unwrap().code().to_string());
+                });
+        }
+        assert_eq!(webview.oauth_flows().len(), 2);
+
+        let opened = webview.mock().opened_externally();
+        let state = |url: &str| {
+            let (_, rest) = url.split_once("state=").unwrap();
+            rest.split('&').next().unwrap().to_string()
+        };
+        let link = format!(
+            "com.example.app:/oauth?state={}&code=second",
+            state(&opened[1])
+        );
+        webview.mock().open_deep_link(&link);
+        webview.mock().open_deep_link(&link);
+        assert_eq!(*codes.lock().unwrap(), ["second"]);
+        assert_eq!(webview.oauth_flows().len(), 1);
+    }
+}
@@ -0,0 +1,35 @@
+//! Rendering the page to pixels instead of a window, see `WebviewBuilder::offscreen`.
+
+use crate::Rect;
+
+/// A page rendered offscreen, see `WebviewBuilder::offscreen` and `Webview::on_frame`.
+///
+/// The pixels can be uploaded to a texture as they are. That's a wgpu texture of the
+/// `Bgra8Unorm` format, or an OpenGL one read as `GL_BGRA` and `GL_UNSIGNED_BYTE`, blended as
+/// premultiplied alpha. Uploading only the `dirty` parts saves copying the rest.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct Frame<'a> {
+    /// The width in physical pixels.
+    pub width: u32,
+    /// The height in physical pixels.
+    pub height: u32,
+    /// The parts that changed since the previous frame, in physical pixels, all of it for the
+    /// first one.
+    pub dirty: &'a [Rect],
+    /// Premultiplied BGRA, row after row from the top, each `bytes_per_row` long.
+    pub pixels: &'a [u8],
+}
+
+impl Frame<'_> {
+    /// The length of a row of `pixels`, e.g. the `bytes_per_row` of a wgpu `ImageDataLayout`.
+    pub fn bytes_per_row(&self) -> u32 {
+        self.width * 4
+    }
+
+    /// Where the top left pixel of `rect` is in `pixels`, e.g. the `offset` of a wgpu
+    /// `ImageDataLayout` uploading only that part.
+    pub fn offset(&self, rect: Rect) -> usize {
+        rect.y as usize * self.bytes_per_row() as usize + rect.x as usize * 4
+    }
+}
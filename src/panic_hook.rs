@@ -0,0 +1,139 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::panic::{self, PanicHookInfo};
+use std::sync::{Mutex, Once};
+use std::thread::{self, ThreadId};
+
+#[cfg(feature = "futures")]
+use crate::Event;
+use crate::{json, platform, WebviewHandle};
+
+const TITLE: &str = "Unexpected error";
+
+/// How a panic is shown to the user.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Display {
+    /// A native dialog, after which the webview terminates.
+    Dialog,
+    /// An overlay in the page, the webview keeps running.
+    Overlay,
+}
+
+static TARGET: Mutex<Option<(ThreadId, WebviewHandle, Display)>> = Mutex::new(None);
+static INSTALL: Once = Once::new();
+
+struct Report {
+    message: String,
+    location: Option<String>,
+    backtrace: Option<String>,
+}
+
+impl Report {
+    fn new(info: &PanicHookInfo<'_>) -> Report {
+        let payload = info.payload();
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "Box<dyn Any>".to_string()
+        };
+        let backtrace = Backtrace::capture();
+        Report {
+            message,
+            location: info.location().map(|location| location.to_string()),
+            backtrace: if backtrace.status() == BacktraceStatus::Captured {
+                Some(backtrace.to_string())
+            } else {
+                None
+            },
+        }
+    }
+
+    fn text(&self, with_backtrace: bool) -> String {
+        let mut text = self.message.clone();
+        if let Some(location) = &self.location {
+            text.push_str(&format!("\n\nat {}", location));
+        }
+        if let (true, Some(backtrace)) = (with_backtrace, &self.backtrace) {
+            text.push_str(&format!("\n\n{}", backtrace));
+        }
+        text
+    }
+
+    /// Shows the report on top of the page and fires a `webviewpanic` event at `window`.
+    fn overlay_script(&self) -> String {
+        // Backtraces are for developers, release builds only show the message.
+        let text = self.text(cfg!(debug_assertions));
+        format!(
+            r#"(function () {{
+  var overlay = document.createElement("div");
+  overlay.style.cssText = "position:fixed;inset:0;z-index:2147483647;overflow:auto;padding:16px;background:rgba(32,0,0,0.92);color:#fff;font:13px monospace";
+  var text = document.createElement("pre");
+  text.style.whiteSpace = "pre-wrap";
+  text.textContent = {title} + "\n\n" + {text};
+  var close = document.createElement("button");
+  close.textContent = "Dismiss";
+  close.onclick = function () {{ overlay.remove(); }};
+  overlay.appendChild(close);
+  overlay.appendChild(text);
+  (document.body || document.documentElement).appendChild(overlay);
+  window.dispatchEvent(new CustomEvent("webviewpanic", {{
+    detail: {{ message: {message}, location: {location} }}
+  }}));
+}})();"#,
+            title = json::string(TITLE),
+            text = json::string(&text),
+            message = json::string(&self.message),
+            location = self
+                .location
+                .as_ref()
+                .map_or("null".to_string(), |location| json::string(location)),
+        )
+    }
+}
+
+pub(crate) fn install(handle: WebviewHandle, display: Display) {
+    *TARGET.lock().unwrap() = Some((thread::current().id(), handle, display));
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous(info);
+
+            let (ui_thread, mut handle, display) = match TARGET.lock().ok().and_then(|t| t.clone())
+            {
+                Some(target) => target,
+                None => return,
+            };
+            let report = Report::new(info);
+            let on_ui_thread = thread::current().id() == ui_thread;
+
+            match display {
+                Display::Dialog if on_ui_thread => {
+                    // A panic on the UI thread ends the event loop, the window goes away with it.
+                    if let Ok(window) = handle.get_window() {
+                        platform::show_error_dialog(window as *mut _, TITLE, &report.text(true));
+                    }
+                }
+                Display::Dialog => {
+                    let _ = handle.dispatch(move |w| {
+                        let window = w.get_window() as *mut _;
+                        platform::show_error_dialog(window, TITLE, &report.text(true));
+                        w.terminate();
+                    });
+                }
+                // The page can't be reached anymore once the UI thread unwinds.
+                Display::Overlay if on_ui_thread => {}
+                Display::Overlay => {
+                    let _ = handle.dispatch(move |w| {
+                        w.eval(&report.overlay_script());
+                        #[cfg(feature = "futures")]
+                        w.emit(Event::Panic {
+                            message: report.message,
+                            location: report.location,
+                        });
+                    });
+                }
+            }
+        }));
+    });
+}
@@ -0,0 +1,2553 @@
+use std::cell::{Cell, RefCell};
+use std::ffi::{CStr, CString};
+use std::io;
+use std::mem;
+use std::os::raw::*;
+use std::path::{Path, PathBuf};
+use std::ptr::{null, null_mut};
+use std::sync::OnceLock;
+
+#[cfg(feature = "raw-window-handle")]
+use raw_window_handle::RawWindowHandle;
+use webview_official_sys as sys;
+
+use super::{
+    DeepLinkCallback, FileDropCallback, FocusCallback, FrameCallback, HistoryAction, KeyCallback,
+    Layout, LoadCallback, NavigationCallback, PaintCallback, ProcessMemory, Request,
+    SessionCallback, ThemeCallback,
+};
+use crate::builder::Cache;
+use crate::cstring;
+use crate::host;
+use crate::macos::{DataStore, MediaPlayback, Options};
+use crate::{
+    Backend, BackgroundThrottling, Capabilities, Color, DragItem, Error, KeyEvent, ProgressState,
+    Rect, SessionEvent, Theme, UserAttention, WindowEffect,
+};
+
+/// `WKWebView` ships with the system.
+pub(crate) fn check_backend() -> Result<(), Error> {
+    Ok(())
+}
+
+/// `NSApp` is shared with the host, there is nothing to detach.
+pub(crate) fn detach_main_loop(_webview: sys::webview_t) {}
+
+/// The native dispatch goes through the main dispatch queue, which the host loop drains as well.
+pub(crate) fn dispatch_external(webview: sys::webview_t, f: sys::DispatchFn, arg: *mut c_void) {
+    unsafe { sys::webview_dispatch(webview, Some(f), arg) }
+}
+
+type CFStringRef = *const c_void;
+
+const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+const K_CF_USER_NOTIFICATION_STOP_ALERT_LEVEL: c_ulong = 0;
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFStringCreateWithBytes(
+        alloc: *const c_void,
+        bytes: *const u8,
+        num_bytes: isize,
+        encoding: u32,
+        is_external_representation: u8,
+    ) -> CFStringRef;
+    fn CFRelease(cf: *const c_void);
+    fn CFUserNotificationDisplayNotice(
+        timeout: f64,
+        flags: c_ulong,
+        icon_url: *const c_void,
+        sound_url: *const c_void,
+        localization_url: *const c_void,
+        alert_header: CFStringRef,
+        alert_message: CFStringRef,
+        default_button_title: CFStringRef,
+    ) -> i32;
+}
+
+fn cf_string(s: &str) -> CFStringRef {
+    unsafe {
+        CFStringCreateWithBytes(
+            null(),
+            s.as_ptr(),
+            s.len() as isize,
+            K_CF_STRING_ENCODING_UTF8,
+            0,
+        )
+    }
+}
+
+/// Shows a blocking error alert, it is not attached to `_window`.
+pub(crate) fn show_error_dialog(_window: *mut c_void, title: &str, message: &str) {
+    let title = cf_string(title);
+    let message = cf_string(message);
+    unsafe {
+        CFUserNotificationDisplayNotice(
+            0.0,
+            K_CF_USER_NOTIFICATION_STOP_ALERT_LEVEL,
+            null(),
+            null(),
+            null(),
+            title,
+            message,
+            null(),
+        );
+        CFRelease(title);
+        CFRelease(message);
+    }
+}
+
+#[link(name = "objc")]
+extern "C" {
+    fn objc_getClass(name: *const c_char) -> *mut c_void;
+    fn sel_registerName(name: *const c_char) -> *mut c_void;
+    fn objc_msgSend();
+}
+
+unsafe fn shared_application() -> *mut c_void {
+    let send: extern "C" fn(*mut c_void, *mut c_void) -> *mut c_void =
+        mem::transmute(objc_msgSend as *const ());
+    send(
+        objc_getClass(b"NSApplication\0".as_ptr() as *const c_char),
+        selector(b"sharedApplication\0"),
+    )
+}
+
+unsafe fn selector(name: &[u8]) -> *mut c_void {
+    sel_registerName(name.as_ptr() as *const c_char)
+}
+
+const NS_EVENT_TYPE_APPLICATION_DEFINED: usize = 15;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct NSPoint {
+    x: f64,
+    y: f64,
+}
+
+/// Stops `NSApp`'s run loop.
+///
+/// The native backend sends `terminate:`, which exits the process and rules out creating
+/// another webview afterwards.
+pub(crate) fn terminate(_webview: sys::webview_t) {
+    unsafe {
+        let app = shared_application();
+        let stop: extern "C" fn(*mut c_void, *mut c_void, *mut c_void) =
+            mem::transmute(objc_msgSend as *const ());
+        stop(app, selector(b"stop:\0"), null_mut());
+
+        // `stop:` only takes effect once the loop handles another event.
+        let event: extern "C" fn(
+            *mut c_void,
+            *mut c_void,
+            usize,
+            NSPoint,
+            usize,
+            f64,
+            isize,
+            *mut c_void,
+            i16,
+            isize,
+            isize,
+        ) -> *mut c_void = mem::transmute(objc_msgSend as *const ());
+        let event = event(
+            objc_getClass(b"NSEvent\0".as_ptr() as *const c_char),
+            selector(
+                b"otherEventWithType:location:modifierFlags:timestamp:windowNumber:context:subtype:data1:data2:\0",
+            ),
+            NS_EVENT_TYPE_APPLICATION_DEFINED,
+            NSPoint { x: 0.0, y: 0.0 },
+            0,
+            0.0,
+            0,
+            null_mut(),
+            0,
+            0,
+            0,
+        );
+        let post: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, i8) =
+            mem::transmute(objc_msgSend as *const ());
+        post(app, selector(b"postEvent:atStart:\0"), event, 1);
+    }
+}
+
+/// Closes the window if the native backend created it.
+pub(crate) fn destroy(webview: sys::webview_t, owns_window: bool) {
+    if owns_window {
+        unsafe {
+            let close: extern "C" fn(*mut c_void, *mut c_void) =
+                mem::transmute(objc_msgSend as *const ());
+            close(sys::webview_get_window(webview), selector(b"close\0"));
+        }
+    }
+}
+
+/// Sets `NSApp`'s activation policy, must be called from the main thread.
+pub(crate) fn set_activation_policy(policy: isize) -> bool {
+    unsafe {
+        let send: extern "C" fn(*mut c_void, *mut c_void, isize) -> i8 =
+            mem::transmute(objc_msgSend as *const ());
+        send(
+            shared_application(),
+            selector(b"setActivationPolicy:\0"),
+            policy,
+        ) != 0
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct NSSize {
+    width: f64,
+    height: f64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct NSRect {
+    origin: NSPoint,
+    size: NSSize,
+}
+
+#[link(name = "objc")]
+extern "C" {
+    fn objc_allocateClassPair(
+        superclass: *mut c_void,
+        name: *const c_char,
+        extra_bytes: usize,
+    ) -> *mut c_void;
+    fn objc_registerClassPair(class: *mut c_void);
+    fn class_addIvar(
+        class: *mut c_void,
+        name: *const c_char,
+        size: usize,
+        alignment: u8,
+        types: *const c_char,
+    ) -> i8;
+    fn class_addMethod(
+        class: *mut c_void,
+        name: *mut c_void,
+        imp: *const c_void,
+        types: *const c_char,
+    ) -> i8;
+    fn class_getInstanceVariable(class: *mut c_void, name: *const c_char) -> *mut c_void;
+    fn object_getIvar(object: *mut c_void, ivar: *mut c_void) -> *mut c_void;
+    fn object_setIvar(object: *mut c_void, ivar: *mut c_void, value: *mut c_void);
+    fn object_getClass(object: *mut c_void) -> *mut c_void;
+    #[cfg(target_arch = "x86_64")]
+    fn objc_msgSend_stret();
+}
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    static NSViewFrameDidChangeNotification: *mut c_void;
+    static NSWorkspaceSessionDidBecomeActiveNotification: *mut c_void;
+    static NSWorkspaceSessionDidResignActiveNotification: *mut c_void;
+    static NSWindowDidBecomeKeyNotification: *mut c_void;
+    static NSWindowDidResignKeyNotification: *mut c_void;
+    static NSWindowDidMoveNotification: *mut c_void;
+    static NSWindowDidResizeNotification: *mut c_void;
+    static NSWindowDidMiniaturizeNotification: *mut c_void;
+    static NSWindowDidDeminiaturizeNotification: *mut c_void;
+}
+
+unsafe fn class(name: &[u8]) -> *mut c_void {
+    objc_getClass(name.as_ptr() as *const c_char)
+}
+
+unsafe fn send(object: *mut c_void, sel: &[u8]) -> *mut c_void {
+    let send: extern "C" fn(*mut c_void, *mut c_void) -> *mut c_void =
+        mem::transmute(objc_msgSend as *const ());
+    send(object, selector(sel))
+}
+
+unsafe fn send_object(object: *mut c_void, sel: &[u8], arg: *mut c_void) -> *mut c_void {
+    let send: extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> *mut c_void =
+        mem::transmute(objc_msgSend as *const ());
+    send(object, selector(sel), arg)
+}
+
+unsafe fn send_rect(object: *mut c_void, sel: &[u8]) -> NSRect {
+    // Structs this large come back through the `_stret` variant on x86_64.
+    #[cfg(target_arch = "x86_64")]
+    let send: extern "C" fn(*mut c_void, *mut c_void) -> NSRect =
+        mem::transmute(objc_msgSend_stret as *const ());
+    #[cfg(not(target_arch = "x86_64"))]
+    let send: extern "C" fn(*mut c_void, *mut c_void) -> NSRect =
+        mem::transmute(objc_msgSend as *const ());
+    send(object, selector(sel))
+}
+
+unsafe fn bounds(view: *mut c_void) -> NSRect {
+    send_rect(view, b"bounds\0")
+}
+
+struct PanelState {
+    content: *mut c_void,
+    parent: *mut c_void,
+    observer: *mut c_void,
+    layout: Option<Layout>,
+}
+
+impl PanelState {
+    fn apply(&mut self, bounds: Rect) {
+        unsafe {
+            // AppKit's origin is the bottom left corner, unless the parent is flipped.
+            let flipped: extern "C" fn(*mut c_void, *mut c_void) -> i8 =
+                mem::transmute(objc_msgSend as *const ());
+            let height = self::bounds(self.parent).size.height;
+            let y = if flipped(self.parent, selector(b"isFlipped\0")) != 0 {
+                bounds.y as f64
+            } else {
+                height - (bounds.y + bounds.height) as f64
+            };
+            let frame = NSRect {
+                origin: NSPoint {
+                    x: bounds.x as f64,
+                    y,
+                },
+                size: NSSize {
+                    width: bounds.width as f64,
+                    height: bounds.height as f64,
+                },
+            };
+            let set_frame: extern "C" fn(*mut c_void, *mut c_void, NSRect) =
+                mem::transmute(objc_msgSend as *const ());
+            set_frame(self.content, selector(b"setFrame:\0"), frame);
+        }
+    }
+
+    fn relayout(&mut self) {
+        let size = unsafe { bounds(self.parent).size };
+        if let Some(layout) = self.layout.as_mut() {
+            let bounds = layout(size.width as i32, size.height as i32);
+            self.apply(bounds);
+        }
+    }
+
+    unsafe fn observe(&mut self, state: *mut PanelState) {
+        let set_posts: extern "C" fn(*mut c_void, *mut c_void, i8) =
+            mem::transmute(objc_msgSend as *const ());
+        set_posts(
+            self.parent,
+            selector(b"setPostsFrameChangedNotifications:\0"),
+            1,
+        );
+        let observer = new_observer(observer_class(), state as *mut c_void);
+        add_observer(
+            send(class(b"NSNotificationCenter\0"), b"defaultCenter\0"),
+            observer,
+            b"frameChanged:\0",
+            NSViewFrameDidChangeNotification,
+            self.parent,
+        );
+        self.observer = observer;
+    }
+
+    unsafe fn unobserve(&mut self) {
+        send_object(
+            send(class(b"NSNotificationCenter\0"), b"defaultCenter\0"),
+            b"removeObserver:\0",
+            self.observer,
+        );
+        send(self.observer, b"release\0");
+        self.observer = null_mut();
+    }
+}
+
+extern "C" fn frame_changed(this: *mut c_void, _sel: *mut c_void, _notification: *mut c_void) {
+    unsafe {
+        let state = observer_state(this) as *mut PanelState;
+        (*state).relayout();
+    }
+}
+
+fn observer_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        define_observer(
+            b"WebviewOfficialPanelObserver\0",
+            b"frameChanged:\0",
+            frame_changed as *const c_void,
+        ) as usize
+    }) as *mut c_void
+}
+
+/// Registers an `NSObject` subclass with a `state` pointer and a notification handler `method`.
+unsafe fn define_observer(name: &[u8], method: &[u8], imp: *const c_void) -> *mut c_void {
+    let class = objc_allocateClassPair(class(b"NSObject\0"), name.as_ptr() as *const c_char, 0);
+    class_addIvar(
+        class,
+        b"state\0".as_ptr() as *const c_char,
+        mem::size_of::<*mut c_void>(),
+        mem::align_of::<*mut c_void>().trailing_zeros() as u8,
+        b"^v\0".as_ptr() as *const c_char,
+    );
+    class_addMethod(
+        class,
+        selector(method),
+        imp,
+        b"v@:@\0".as_ptr() as *const c_char,
+    );
+    objc_registerClassPair(class);
+    class
+}
+
+unsafe fn new_observer(class: *mut c_void, state: *mut c_void) -> *mut c_void {
+    let observer = send(send(class, b"alloc\0"), b"init\0");
+    let ivar = class_getInstanceVariable(class, b"state\0".as_ptr() as *const c_char);
+    object_setIvar(observer, ivar, state);
+    observer
+}
+
+unsafe fn add_observer(
+    center: *mut c_void,
+    observer: *mut c_void,
+    method: &[u8],
+    name: *mut c_void,
+    object: *mut c_void,
+) {
+    let add: extern "C" fn(
+        *mut c_void,
+        *mut c_void,
+        *mut c_void,
+        *mut c_void,
+        *mut c_void,
+        *mut c_void,
+    ) = mem::transmute(objc_msgSend as *const ());
+    add(
+        center,
+        selector(b"addObserver:selector:name:object:\0"),
+        observer,
+        selector(method),
+        name,
+        object,
+    );
+}
+
+unsafe fn observer_state(observer: *mut c_void) -> *mut c_void {
+    let ivar = class_getInstanceVariable(
+        object_getClass(observer),
+        b"state\0".as_ptr() as *const c_char,
+    );
+    object_getIvar(observer, ivar)
+}
+
+/// The web view moved out of its window into a host `NSView`.
+pub(crate) struct Panel(*mut PanelState);
+
+/// The host's `NSView`, the panel is added as a subview of it.
+#[cfg(feature = "raw-window-handle")]
+pub(crate) fn foreign_parent(handle: RawWindowHandle) -> Result<*mut c_void, Error> {
+    match handle {
+        RawWindowHandle::AppKit(handle) => Ok(handle.ns_view.as_ptr()),
+        _ => Err(Error::BackendUnavailable(
+            "WKWebView can only be attached to AppKit views".to_string(),
+        )),
+    }
+}
+
+/// Panes are subviews of the window's content view, which is the web view itself.
+pub(crate) fn pane_parent(webview: sys::webview_t) -> *mut c_void {
+    unsafe { send(sys::webview_get_window(webview), b"contentView\0") }
+}
+
+impl Panel {
+    pub(crate) fn new(webview: sys::webview_t, parent: *mut c_void) -> Panel {
+        unsafe {
+            let window = sys::webview_get_window(webview);
+            let content = send(send(window, b"contentView\0"), b"retain\0");
+            send_object(
+                window,
+                b"setContentView:\0",
+                send(send(class(b"NSView\0"), b"alloc\0"), b"init\0"),
+            );
+            send_object(parent, b"addSubview:\0", content);
+            send(content, b"release\0");
+            send_object(window, b"orderOut:\0", null_mut());
+
+            let state = Box::into_raw(Box::new(PanelState {
+                content,
+                parent,
+                observer: null_mut(),
+                layout: None,
+            }));
+            (*state).observe(state);
+            Panel(state)
+        }
+    }
+
+    pub(crate) fn reparent(&mut self, parent: *mut c_void) {
+        unsafe {
+            let state = &mut *self.0;
+            state.unobserve();
+            send_object(parent, b"addSubview:\0", state.content);
+            state.parent = parent;
+            state.observe(self.0);
+            state.relayout();
+        }
+    }
+
+    pub(crate) fn set_bounds(&mut self, bounds: Rect) {
+        unsafe { (*self.0).apply(bounds) }
+    }
+
+    pub(crate) fn set_layout(&mut self, layout: Layout) {
+        unsafe {
+            (*self.0).layout = Some(layout);
+            (*self.0).relayout();
+        }
+    }
+}
+
+impl Drop for Panel {
+    fn drop(&mut self) {
+        unsafe {
+            let mut state = Box::from_raw(self.0);
+            state.unobserve();
+            send(state.content, b"removeFromSuperview\0");
+        }
+    }
+}
+
+pub(crate) const BACKEND: Backend = Backend::WebKit;
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    bind: true,
+    scripts: true,
+    window_handle: true,
+    reparent: true,
+    external_loop: true,
+    transparency: false,
+    offscreen: false,
+};
+
+/// The engine can't render offscreen, there is never one, see `CAPABILITIES`.
+pub(crate) enum Offscreen {}
+
+impl Offscreen {
+    pub(crate) fn new(_webview: sys::webview_t, _callback: PaintCallback) -> Option<Offscreen> {
+        None
+    }
+}
+
+extern "C" {
+    fn class_getInstanceMethod(class: *mut c_void, name: *mut c_void) -> *mut c_void;
+    fn method_setImplementation(method: *mut c_void, imp: *const c_void) -> *const c_void;
+}
+
+type InitWithFrame = extern "C" fn(*mut c_void, *mut c_void, NSRect, *mut c_void) -> *mut c_void;
+
+static NATIVE_INIT_WITH_FRAME: OnceLock<usize> = OnceLock::new();
+
+thread_local! {
+    static PENDING_OPTIONS: Cell<*const Options> = Cell::new(null());
+}
+
+unsafe fn ns_string(s: &[u8]) -> *mut c_void {
+    let send: extern "C" fn(*mut c_void, *mut c_void, *const c_char) -> *mut c_void =
+        mem::transmute(objc_msgSend as *const ());
+    send(
+        class(b"NSString\0"),
+        selector(b"stringWithUTF8String:\0"),
+        s.as_ptr() as *const c_char,
+    )
+}
+
+unsafe fn configure(options: &Options, configuration: *mut c_void) {
+    if let Some(enabled) = options.developer_extras {
+        let number: extern "C" fn(*mut c_void, *mut c_void, i8) -> *mut c_void =
+            mem::transmute(objc_msgSend as *const ());
+        let set_value: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) =
+            mem::transmute(objc_msgSend as *const ());
+        set_value(
+            send(configuration, b"preferences\0"),
+            selector(b"setValue:forKey:\0"),
+            number(
+                class(b"NSNumber\0"),
+                selector(b"numberWithBool:\0"),
+                enabled as i8,
+            ),
+            ns_string(b"developerExtrasEnabled\0"),
+        );
+    }
+    if let Some(playback) = options.media_playback {
+        let types = match playback {
+            MediaPlayback::Autoplay => 0,
+            MediaPlayback::AudioRequiresUserAction => 1,
+            MediaPlayback::VideoRequiresUserAction => 2,
+            MediaPlayback::RequiresUserAction => usize::MAX,
+        };
+        let set_types: extern "C" fn(*mut c_void, *mut c_void, usize) =
+            mem::transmute(objc_msgSend as *const ());
+        set_types(
+            configuration,
+            selector(b"setMediaTypesRequiringUserActionForPlayback:\0"),
+            types,
+        );
+    }
+    if let Some(store) = options.data_store {
+        let store = match store {
+            DataStore::Default => send(class(b"WKWebsiteDataStore\0"), b"defaultDataStore\0"),
+            DataStore::NonPersistent => {
+                send(class(b"WKWebsiteDataStore\0"), b"nonPersistentDataStore\0")
+            }
+        };
+        send_object(configuration, b"setWebsiteDataStore:\0", store);
+    }
+    if options.scheme_handler {
+        let handler = send(send(scheme_handler_class(), b"alloc\0"), b"init\0");
+        let set_handler: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) =
+            mem::transmute(objc_msgSend as *const ());
+        let scheme = cstring::text(host::SCHEME);
+        set_handler(
+            configuration,
+            selector(b"setURLSchemeHandler:forURLScheme:\0"),
+            handler,
+            ns_string(scheme.as_bytes_with_nul()),
+        );
+        send(handler, b"release\0");
+    }
+}
+
+extern "C" fn init_with_frame(
+    this: *mut c_void,
+    sel: *mut c_void,
+    frame: NSRect,
+    configuration: *mut c_void,
+) -> *mut c_void {
+    let options = PENDING_OPTIONS.with(|options| options.get());
+    if !options.is_null() {
+        unsafe { configure(&*options, configuration) };
+    }
+    let native: InitWithFrame = unsafe { mem::transmute(*NATIVE_INIT_WITH_FRAME.get().unwrap()) };
+    native(this, sel, frame, configuration)
+}
+
+/// Runs `create` with `options` applied to the configuration of the `WKWebView` it creates.
+///
+/// The native backend builds the configuration itself, so `initWithFrame:configuration:` is
+/// hooked to adjust it before the view takes it over.
+pub(crate) fn with_webkit_options<R>(options: &Options, create: impl FnOnce() -> R) -> R {
+    NATIVE_INIT_WITH_FRAME.get_or_init(|| unsafe {
+        let method = class_getInstanceMethod(
+            class(b"WKWebView\0"),
+            selector(b"initWithFrame:configuration:\0"),
+        );
+        method_setImplementation(method, init_with_frame as *const c_void) as usize
+    });
+    PENDING_OPTIONS.with(|pending| pending.set(options));
+    let result = create();
+    PENDING_OPTIONS.with(|pending| pending.set(null()));
+    result
+}
+
+extern "C" {
+    fn objc_getProtocol(name: *const c_char) -> *mut c_void;
+    fn class_addProtocol(class: *mut c_void, protocol: *mut c_void) -> i8;
+}
+
+/// Answers the `webview:` urls of mapped hosts, with a 404 for those `host::load` can't.
+extern "C" fn start_url_scheme_task(
+    _this: *mut c_void,
+    _sel: *mut c_void,
+    _web_view: *mut c_void,
+    task: *mut c_void,
+) {
+    unsafe {
+        let url = send(send(task, b"request\0"), b"URL\0");
+        let string =
+            CStr::from_ptr(send(send(url, b"absoluteString\0"), b"UTF8String\0") as *const c_char);
+        let (status, body, mime_type) = match host::load(&string.to_string_lossy()) {
+            Some((body, mime_type)) => (200, body, mime_type),
+            None => (404, Vec::new(), "text/plain"),
+        };
+        let mime_type = cstring::text(mime_type);
+        let length = CString::new(body.len().to_string()).unwrap();
+        let keys = [ns_string(b"Content-Type\0"), ns_string(b"Content-Length\0")];
+        let values = [
+            ns_string(mime_type.as_bytes_with_nul()),
+            ns_string(length.as_bytes_with_nul()),
+        ];
+        let dictionary: extern "C" fn(
+            *mut c_void,
+            *mut c_void,
+            *const *mut c_void,
+            *const *mut c_void,
+            usize,
+        ) -> *mut c_void = mem::transmute(objc_msgSend as *const ());
+        let headers = dictionary(
+            class(b"NSDictionary\0"),
+            selector(b"dictionaryWithObjects:forKeys:count:\0"),
+            values.as_ptr(),
+            keys.as_ptr(),
+            keys.len(),
+        );
+        let init: extern "C" fn(
+            *mut c_void,
+            *mut c_void,
+            *mut c_void,
+            isize,
+            *mut c_void,
+            *mut c_void,
+        ) -> *mut c_void = mem::transmute(objc_msgSend as *const ());
+        let response = init(
+            send(class(b"NSHTTPURLResponse\0"), b"alloc\0"),
+            selector(b"initWithURL:statusCode:HTTPVersion:headerFields:\0"),
+            url,
+            status,
+            ns_string(b"HTTP/1.1\0"),
+            headers,
+        );
+        send_object(task, b"didReceiveResponse:\0", response);
+        send(response, b"release\0");
+        let data: extern "C" fn(*mut c_void, *mut c_void, *const c_void, usize) -> *mut c_void =
+            mem::transmute(objc_msgSend as *const ());
+        send_object(
+            task,
+            b"didReceiveData:\0",
+            data(
+                class(b"NSData\0"),
+                selector(b"dataWithBytes:length:\0"),
+                body.as_ptr() as *const c_void,
+                body.len(),
+            ),
+        );
+        send(task, b"didFinish\0");
+    }
+}
+
+/// Tasks are answered as they start, there is nothing left to stop.
+extern "C" fn stop_url_scheme_task(
+    _this: *mut c_void,
+    _sel: *mut c_void,
+    _web_view: *mut c_void,
+    _task: *mut c_void,
+) {
+}
+
+fn scheme_handler_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        let class = objc_allocateClassPair(
+            class(b"NSObject\0"),
+            b"WebviewOfficialSchemeHandler\0".as_ptr() as *const c_char,
+            0,
+        );
+        class_addProtocol(
+            class,
+            objc_getProtocol(b"WKURLSchemeHandler\0".as_ptr() as *const c_char),
+        );
+        class_addMethod(
+            class,
+            selector(b"webView:startURLSchemeTask:\0"),
+            start_url_scheme_task as *const c_void,
+            b"v@:@@\0".as_ptr() as *const c_char,
+        );
+        class_addMethod(
+            class,
+            selector(b"webView:stopURLSchemeTask:\0"),
+            stop_url_scheme_task as *const c_void,
+            b"v@:@@\0".as_ptr() as *const c_char,
+        );
+        objc_registerClassPair(class);
+        class as usize
+    }) as *mut c_void
+}
+
+/// Mapped hosts are served by the scheme handler the builder installs on the configuration,
+/// `WKWebView` takes none once it exists.
+pub(crate) fn map_host(webview: sys::webview_t, host: &str, folder: &Path) -> bool {
+    unsafe {
+        let view = send(sys::webview_get_window(webview), b"contentView\0");
+        let scheme = cstring::text(host::SCHEME);
+        let handler = send_object(
+            send(view, b"configuration\0"),
+            b"urlSchemeHandlerForURLScheme:\0",
+            ns_string(scheme.as_bytes_with_nul()),
+        );
+        if handler.is_null() {
+            return false;
+        }
+    }
+    host::map(host, folder);
+    true
+}
+
+/// `WKWebView` always composites on the GPU.
+pub(crate) fn disable_hardware_acceleration(_webview: sys::webview_t) -> bool {
+    false
+}
+
+/// Sets the preferences' `inactiveSchedulingPolicy`, macOS 14 and later. Before, the engine
+/// decides, so only `Throttle` is honored.
+pub(crate) fn set_background_throttling(
+    webview: sys::webview_t,
+    throttling: BackgroundThrottling,
+) -> bool {
+    unsafe {
+        let view = send(sys::webview_get_window(webview), b"contentView\0");
+        let preferences = send(send(view, b"configuration\0"), b"preferences\0");
+        if !send_bool_object(
+            preferences,
+            b"respondsToSelector:\0",
+            selector(b"setInactiveSchedulingPolicy:\0"),
+        ) {
+            return throttling == BackgroundThrottling::Throttle;
+        }
+        // WKInactiveSchedulingPolicy
+        let policy: isize = match throttling {
+            BackgroundThrottling::Suspend => 0,
+            BackgroundThrottling::Throttle => 1,
+            BackgroundThrottling::Disabled => 2,
+        };
+        let set: extern "C" fn(*mut c_void, *mut c_void, isize) =
+            mem::transmute(objc_msgSend as *const ());
+        set(
+            preferences,
+            selector(b"setInactiveSchedulingPolicy:\0"),
+            policy,
+        );
+    }
+    true
+}
+
+const MACH_TASK_BASIC_INFO: c_int = 20;
+
+#[repr(C)]
+#[derive(Default)]
+struct MachTaskBasicInfo {
+    virtual_size: u64,
+    resident_size: u64,
+    resident_size_max: u64,
+    user_time: [c_int; 2],
+    system_time: [c_int; 2],
+    policy: c_int,
+    suspend_count: c_int,
+}
+
+extern "C" {
+    static mach_task_self_: c_uint;
+    fn task_info(task: c_uint, flavor: c_int, info: *mut c_int, count: *mut c_uint) -> c_int;
+}
+
+/// The resident memory of this process. `WKWebView`'s processes are XPC services the system
+/// starts, not children of the application.
+pub(crate) fn process_memory() -> ProcessMemory {
+    let mut info = MachTaskBasicInfo::default();
+    let mut count = (mem::size_of::<MachTaskBasicInfo>() / mem::size_of::<c_int>()) as c_uint;
+    let result = unsafe {
+        task_info(
+            mach_task_self_,
+            MACH_TASK_BASIC_INFO,
+            &mut info as *mut MachTaskBasicInfo as *mut c_int,
+            &mut count,
+        )
+    };
+    ProcessMemory {
+        app: if result == 0 {
+            Some(info.resident_size)
+        } else {
+            None
+        },
+        engine: None,
+    }
+}
+
+/// Returns the cache settings `WKWebView` doesn't support, disabling it is handled by
+/// `with_webkit_options`.
+pub(crate) fn apply_cache(_webview: sys::webview_t, cache: &Cache) -> Vec<&'static str> {
+    let mut unsupported = Vec::new();
+    if cache.dir.is_some() {
+        unsupported.push("WebviewBuilder::cache_dir");
+    }
+    if cache.max_size.is_some() {
+        unsupported.push("WebviewBuilder::max_cache_size");
+    }
+    unsupported
+}
+
+/// The `WKWebView`, taken before `Panel` can move it out of the window.
+pub(crate) struct View(*mut c_void);
+
+impl View {
+    pub(crate) fn new(webview: sys::webview_t) -> View {
+        View(unsafe { send(sys::webview_get_window(webview), b"contentView\0") })
+    }
+
+    pub(crate) fn go(&self, action: HistoryAction) -> bool {
+        let sel: &[u8] = match action {
+            HistoryAction::Back => b"goBack\0",
+            HistoryAction::Forward => b"goForward\0",
+            HistoryAction::Reload => b"reload\0",
+            HistoryAction::ReloadIgnoringCache => b"reloadFromOrigin\0",
+            HistoryAction::Stop => b"stopLoading\0",
+        };
+        unsafe { send(self.0, sel) };
+        true
+    }
+
+    /// Loads `request`, returns `false` if part of it had to be left out.
+    pub(crate) fn load(&self, request: &Request) -> bool {
+        let c_url = cstring::url(&request.url);
+        unsafe {
+            let url = send_object(
+                class(b"NSURL\0"),
+                b"URLWithString:\0",
+                ns_string(c_url.as_bytes_with_nul()),
+            );
+            if url.is_null() {
+                return true;
+            }
+            let ns_request =
+                send_object(class(b"NSMutableURLRequest\0"), b"requestWithURL:\0", url);
+            let set: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) =
+                mem::transmute(objc_msgSend as *const ());
+            if let Some((body, content_type)) = &request.body {
+                send_object(ns_request, b"setHTTPMethod:\0", ns_string(b"POST\0"));
+                let data: extern "C" fn(*mut c_void, *mut c_void, *const u8, usize) -> *mut c_void =
+                    mem::transmute(objc_msgSend as *const ());
+                send_object(
+                    ns_request,
+                    b"setHTTPBody:\0",
+                    data(
+                        class(b"NSData\0"),
+                        selector(b"dataWithBytes:length:\0"),
+                        body.as_ptr(),
+                        body.len(),
+                    ),
+                );
+                let content_type = cstring::text(content_type);
+                set(
+                    ns_request,
+                    selector(b"setValue:forHTTPHeaderField:\0"),
+                    ns_string(content_type.as_bytes_with_nul()),
+                    ns_string(b"Content-Type\0"),
+                );
+            }
+            for (name, value) in &request.headers {
+                let name = cstring::text(name);
+                let value = cstring::text(value);
+                set(
+                    ns_request,
+                    selector(b"setValue:forHTTPHeaderField:\0"),
+                    ns_string(value.as_bytes_with_nul()),
+                    ns_string(name.as_bytes_with_nul()),
+                );
+            }
+            send_object(self.0, b"loadRequest:\0", ns_request);
+        }
+        true
+    }
+
+    /// Stops the web view from drawing its own background and paints the window's.
+    /// Activates the application, makes the view's window the key window and the view its first
+    /// responder.
+    pub(crate) fn focus(&self) {
+        unsafe {
+            let activate: extern "C" fn(*mut c_void, *mut c_void, i8) =
+                mem::transmute(objc_msgSend as *const ());
+            activate(
+                shared_application(),
+                selector(b"activateIgnoringOtherApps:\0"),
+                1,
+            );
+            let window = send(self.0, b"window\0");
+            if !window.is_null() {
+                send_object(window, b"makeKeyAndOrderFront:\0", null_mut());
+                send_object(window, b"makeFirstResponder:\0", self.0);
+            }
+        }
+    }
+
+    pub(crate) fn is_focused(&self) -> bool {
+        unsafe {
+            let window = send(self.0, b"window\0");
+            !window.is_null() && send_bool(window, b"isKeyWindow\0")
+        }
+    }
+
+    pub(crate) fn set_background_color(&self, color: Color) -> bool {
+        unsafe {
+            let rgba: extern "C" fn(*mut c_void, *mut c_void, f64, f64, f64, f64) -> *mut c_void =
+                mem::transmute(objc_msgSend as *const ());
+            let channel = |value: u8| value as f64 / 255.0;
+            let ns_color = rgba(
+                class(b"NSColor\0"),
+                selector(b"colorWithSRGBRed:green:blue:alpha:\0"),
+                channel(color.red),
+                channel(color.green),
+                channel(color.blue),
+                channel(color.alpha),
+            );
+            let number: extern "C" fn(*mut c_void, *mut c_void, i8) -> *mut c_void =
+                mem::transmute(objc_msgSend as *const ());
+            let no = number(class(b"NSNumber\0"), selector(b"numberWithBool:\0"), 0);
+            let set: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) =
+                mem::transmute(objc_msgSend as *const ());
+            set(
+                self.0,
+                selector(b"setValue:forKey:\0"),
+                no,
+                ns_string(b"drawsBackground\0"),
+            );
+            if send_bool_object(
+                self.0,
+                b"respondsToSelector:\0",
+                selector(b"setUnderPageBackgroundColor:\0"),
+            ) {
+                send_object(self.0, b"setUnderPageBackgroundColor:\0", ns_color);
+            }
+            let window = send(self.0, b"window\0");
+            if !window.is_null() {
+                send_object(window, b"setBackgroundColor:\0", ns_color);
+            }
+        }
+        true
+    }
+
+    /// Sets the web view's appearance, which the page's color scheme follows.
+    pub(crate) fn set_theme(&self, theme: Theme) -> bool {
+        unsafe {
+            let appearance = match theme {
+                Theme::Light => appearance(b"NSAppearanceNameAqua\0"),
+                Theme::Dark => appearance(b"NSAppearanceNameDarkAqua\0"),
+                _ => null_mut(),
+            };
+            send_object(self.0, b"setAppearance:\0", appearance);
+        }
+        true
+    }
+
+    pub(crate) fn system_theme(&self) -> Theme {
+        system_theme()
+    }
+
+    pub(crate) fn can_go_back(&self) -> bool {
+        unsafe { send_bool(self.0, b"canGoBack\0") }
+    }
+
+    pub(crate) fn can_go_forward(&self) -> bool {
+        unsafe { send_bool(self.0, b"canGoForward\0") }
+    }
+
+    /// The url of the page the view shows, as WebKit has it rather than the page.
+    pub(crate) fn url(&self) -> Option<String> {
+        unsafe {
+            let url = send(self.0, b"URL\0");
+            if url.is_null() {
+                return None;
+            }
+            let string = send(send(url, b"absoluteString\0"), b"UTF8String\0") as *const c_char;
+            Some(CStr::from_ptr(string).to_string_lossy().into_owned())
+        }
+    }
+}
+
+unsafe fn send_bool(object: *mut c_void, sel: &[u8]) -> bool {
+    let send: extern "C" fn(*mut c_void, *mut c_void) -> i8 =
+        mem::transmute(objc_msgSend as *const ());
+    send(object, selector(sel)) != 0
+}
+
+/// Opens `url` with the user's default handler, e.g. their browser.
+pub(crate) fn open_external(_webview: sys::webview_t, url: &str) -> bool {
+    let c_url = match CString::new(url) {
+        Ok(c_url) => c_url,
+        Err(_) => return false,
+    };
+    unsafe {
+        let url = send_object(
+            class(b"NSURL\0"),
+            b"URLWithString:\0",
+            ns_string(c_url.as_bytes_with_nul()),
+        );
+        if url.is_null() {
+            return false;
+        }
+        let open: extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> i8 =
+            mem::transmute(objc_msgSend as *const ());
+        open(
+            send(class(b"NSWorkspace\0"), b"sharedWorkspace\0"),
+            selector(b"openURL:\0"),
+            url,
+        ) != 0
+    }
+}
+
+const SCREEN_LOCKED: &[u8] = b"com.apple.screenIsLocked\0";
+const SCREEN_UNLOCKED: &[u8] = b"com.apple.screenIsUnlocked\0";
+
+unsafe fn workspace_center() -> *mut c_void {
+    send(
+        send(class(b"NSWorkspace\0"), b"sharedWorkspace\0"),
+        b"notificationCenter\0",
+    )
+}
+
+unsafe fn distributed_center() -> *mut c_void {
+    send(
+        class(b"NSDistributedNotificationCenter\0"),
+        b"defaultCenter\0",
+    )
+}
+
+extern "C" fn session_changed(this: *mut c_void, _sel: *mut c_void, notification: *mut c_void) {
+    unsafe {
+        let name = send(notification, b"name\0");
+        let is: extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> i8 =
+            mem::transmute(objc_msgSend as *const ());
+        let is = |other: *mut c_void| is(name, selector(b"isEqualToString:\0"), other) != 0;
+        let event = if is(NSWorkspaceSessionDidResignActiveNotification) {
+            SessionEvent::Detached
+        } else if is(NSWorkspaceSessionDidBecomeActiveNotification) {
+            SessionEvent::Attached
+        } else if is(ns_string(SCREEN_LOCKED)) {
+            SessionEvent::Locked
+        } else if is(ns_string(SCREEN_UNLOCKED)) {
+            SessionEvent::Unlocked
+        } else {
+            return;
+        };
+        let callback = observer_state(this) as *mut SessionCallback;
+        (*callback)(event);
+    }
+}
+
+fn session_observer_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        define_observer(
+            b"WebviewOfficialSessionObserver\0",
+            b"sessionChanged:\0",
+            session_changed as *const c_void,
+        ) as usize
+    }) as *mut c_void
+}
+
+/// Observes fast user switching through `NSWorkspace` and the screen lock through the
+/// distributed notifications the system posts for it.
+pub(crate) struct SessionWatch {
+    observer: *mut c_void,
+    callback: *mut SessionCallback,
+}
+
+impl SessionWatch {
+    pub(crate) fn new(_webview: sys::webview_t, callback: SessionCallback) -> SessionWatch {
+        unsafe {
+            let callback = Box::into_raw(Box::new(callback));
+            let observer = new_observer(session_observer_class(), callback as *mut c_void);
+            for &name in &[
+                NSWorkspaceSessionDidBecomeActiveNotification,
+                NSWorkspaceSessionDidResignActiveNotification,
+            ] {
+                add_observer(
+                    workspace_center(),
+                    observer,
+                    b"sessionChanged:\0",
+                    name,
+                    null_mut(),
+                );
+            }
+            for name in &[SCREEN_LOCKED, SCREEN_UNLOCKED] {
+                add_observer(
+                    distributed_center(),
+                    observer,
+                    b"sessionChanged:\0",
+                    ns_string(name),
+                    null_mut(),
+                );
+            }
+            SessionWatch { observer, callback }
+        }
+    }
+}
+
+impl Drop for SessionWatch {
+    fn drop(&mut self) {
+        unsafe {
+            send_object(workspace_center(), b"removeObserver:\0", self.observer);
+            send_object(distributed_center(), b"removeObserver:\0", self.observer);
+            send(self.observer, b"release\0");
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// Windows have no icon of their own on macOS, `image` becomes the application's Dock icon.
+pub(crate) fn set_window_icon(_webview: sys::webview_t, image: &[u8]) -> bool {
+    unsafe {
+        let data: extern "C" fn(*mut c_void, *mut c_void, *const u8, usize) -> *mut c_void =
+            mem::transmute(objc_msgSend as *const ());
+        let data = data(
+            class(b"NSData\0"),
+            selector(b"dataWithBytes:length:\0"),
+            image.as_ptr(),
+            image.len(),
+        );
+        let icon = send_object(
+            send(class(b"NSImage\0"), b"alloc\0"),
+            b"initWithData:\0",
+            data,
+        );
+        if icon.is_null() {
+            return false;
+        }
+        send_object(shared_application(), b"setApplicationIconImage:\0", icon);
+        send(icon, b"release\0");
+        true
+    }
+}
+
+const NS_CRITICAL_REQUEST: isize = 0;
+const NS_INFORMATIONAL_REQUEST: isize = 10;
+
+/// Bounces the Dock icon, unless the application is active.
+pub(crate) fn request_user_attention(_webview: sys::webview_t, level: UserAttention) {
+    let request = match level {
+        UserAttention::Informational => NS_INFORMATIONAL_REQUEST,
+        UserAttention::Critical => NS_CRITICAL_REQUEST,
+    };
+    unsafe {
+        let send: extern "C" fn(*mut c_void, *mut c_void, isize) -> isize =
+            mem::transmute(objc_msgSend as *const ());
+        send(
+            shared_application(),
+            selector(b"requestUserAttention:\0"),
+            request,
+        );
+    }
+}
+
+/// Sets the badge label of the application's Dock tile.
+pub(crate) fn set_badge_count(_webview: sys::webview_t, count: Option<u32>) -> bool {
+    unsafe {
+        let label = match count {
+            Some(count) => ns_string(format!("{}\0", count).as_bytes()),
+            None => null_mut(),
+        };
+        send_object(
+            send(shared_application(), b"dockTile\0"),
+            b"setBadgeLabel:\0",
+            label,
+        );
+    }
+    true
+}
+
+const NS_PROGRESS_INDICATOR_STYLE_BAR: isize = 0;
+
+/// Draws a progress bar over the application icon in the Dock. AppKit has no such bar of its
+/// own, so the Dock tile gets a content view with one. The states only differ when indeterminate.
+pub(crate) fn set_progress(_webview: sys::webview_t, state: ProgressState, fraction: f64) {
+    unsafe {
+        let tile = send(shared_application(), b"dockTile\0");
+        if state == ProgressState::None {
+            send_object(tile, b"setContentView:\0", null_mut());
+            send(tile, b"display\0");
+            return;
+        }
+        let size: extern "C" fn(*mut c_void, *mut c_void) -> NSSize =
+            mem::transmute(objc_msgSend as *const ());
+        let size = size(tile, selector(b"size\0"));
+        let init: InitWithFrameOnly = mem::transmute(objc_msgSend as *const ());
+        let content = init(
+            send(class(b"NSImageView\0"), b"alloc\0"),
+            selector(b"initWithFrame:\0"),
+            NSRect {
+                origin: NSPoint { x: 0.0, y: 0.0 },
+                size,
+            },
+        );
+        send_object(
+            content,
+            b"setImage:\0",
+            send(shared_application(), b"applicationIconImage\0"),
+        );
+        let bar = init(
+            send(class(b"NSProgressIndicator\0"), b"alloc\0"),
+            selector(b"initWithFrame:\0"),
+            NSRect {
+                origin: NSPoint {
+                    x: size.width * 0.1,
+                    y: size.height * 0.1,
+                },
+                size: NSSize {
+                    width: size.width * 0.8,
+                    height: size.height * 0.15,
+                },
+            },
+        );
+        let set_integer: extern "C" fn(*mut c_void, *mut c_void, isize) =
+            mem::transmute(objc_msgSend as *const ());
+        let set_bool: extern "C" fn(*mut c_void, *mut c_void, i8) =
+            mem::transmute(objc_msgSend as *const ());
+        let set_double: extern "C" fn(*mut c_void, *mut c_void, f64) =
+            mem::transmute(objc_msgSend as *const ());
+        set_integer(
+            bar,
+            selector(b"setStyle:\0"),
+            NS_PROGRESS_INDICATOR_STYLE_BAR,
+        );
+        set_bool(
+            bar,
+            selector(b"setIndeterminate:\0"),
+            (state == ProgressState::Indeterminate) as i8,
+        );
+        set_double(bar, selector(b"setMinValue:\0"), 0.0);
+        set_double(bar, selector(b"setMaxValue:\0"), 1.0);
+        set_double(bar, selector(b"setDoubleValue:\0"), fraction);
+        send_object(content, b"addSubview:\0", bar);
+        send(bar, b"release\0");
+        send_object(tile, b"setContentView:\0", content);
+        send(content, b"release\0");
+        send(tile, b"display\0");
+    }
+}
+
+type InitWithFrameOnly = extern "C" fn(*mut c_void, *mut c_void, NSRect) -> *mut c_void;
+
+pub(crate) fn set_visible(webview: sys::webview_t, visible: bool) {
+    unsafe {
+        let window = sys::webview_get_window(webview);
+        if visible {
+            send_object(window, b"makeKeyAndOrderFront:\0", null_mut());
+        } else {
+            send_object(window, b"orderOut:\0", null_mut());
+        }
+    }
+}
+
+/// Moves the window with the mouse for as long as the button of the event being handled is held.
+pub(crate) fn start_window_drag(webview: sys::webview_t) -> bool {
+    unsafe {
+        let event = send(shared_application(), b"currentEvent\0");
+        if event.is_null() {
+            return false;
+        }
+        send_object(
+            sys::webview_get_window(webview),
+            b"performWindowDragWithEvent:\0",
+            event,
+        );
+        true
+    }
+}
+
+const NS_DRAG_OPERATION_COPY: usize = 1;
+
+extern "C" fn source_operation_mask(
+    _this: *mut c_void,
+    _sel: *mut c_void,
+    _session: *mut c_void,
+    _context: isize,
+) -> usize {
+    NS_DRAG_OPERATION_COPY
+}
+
+/// The `NSDraggingSource` of the drags `start_drag` starts, which offers to copy.
+fn drag_source() -> *mut c_void {
+    static SOURCE: OnceLock<usize> = OnceLock::new();
+    *SOURCE.get_or_init(|| unsafe {
+        let class = objc_allocateClassPair(
+            class(b"NSObject\0"),
+            b"WebviewOfficialDragSource\0".as_ptr() as *const c_char,
+            0,
+        );
+        class_addMethod(
+            class,
+            selector(b"draggingSession:sourceOperationMaskForDraggingContext:\0"),
+            source_operation_mask as *const c_void,
+            b"Q@:@q\0".as_ptr() as *const c_char,
+        );
+        objc_registerClassPair(class);
+        send(send(class, b"alloc\0"), b"init\0") as usize
+    }) as *mut c_void
+}
+
+/// Drags `item` out of the web view, for the mouse event being handled.
+pub(crate) fn start_drag(view: &View, item: &DragItem) -> bool {
+    unsafe {
+        let event = send(shared_application(), b"currentEvent\0");
+        if event.is_null() {
+            return false;
+        }
+        let location: extern "C" fn(*mut c_void, *mut c_void) -> NSPoint =
+            mem::transmute(objc_msgSend as *const ());
+        let convert: extern "C" fn(*mut c_void, *mut c_void, NSPoint, *mut c_void) -> NSPoint =
+            mem::transmute(objc_msgSend as *const ());
+        let point = convert(
+            view.0,
+            selector(b"convertPoint:fromView:\0"),
+            location(event, selector(b"locationInWindow\0")),
+            null_mut(),
+        );
+        // The pasteboard writers with the image shown for them.
+        let mut writers = Vec::new();
+        match item {
+            DragItem::Files(paths) => {
+                let workspace = send(class(b"NSWorkspace\0"), b"sharedWorkspace\0");
+                for path in paths {
+                    let path = match CString::new(path.to_string_lossy().into_owned()) {
+                        Ok(path) => ns_string(path.as_bytes_with_nul()),
+                        Err(_) => continue,
+                    };
+                    writers.push((
+                        send_object(class(b"NSURL\0"), b"fileURLWithPath:\0", path),
+                        send_object(workspace, b"iconForFile:\0", path),
+                    ));
+                }
+            }
+            DragItem::Text(text) => match CString::new(text.as_str()) {
+                Ok(text) => writers.push((ns_string(text.as_bytes_with_nul()), null_mut())),
+                Err(_) => return false,
+            },
+        }
+        if writers.is_empty() {
+            return false;
+        }
+        let set_frame: extern "C" fn(*mut c_void, *mut c_void, NSRect, *mut c_void) =
+            mem::transmute(objc_msgSend as *const ());
+        let items = send(class(b"NSMutableArray\0"), b"array\0");
+        for (index, &(writer, image)) in writers.iter().enumerate() {
+            let item = send_object(
+                send(class(b"NSDraggingItem\0"), b"alloc\0"),
+                b"initWithPasteboardWriter:\0",
+                writer,
+            );
+            let offset = 8.0 * index as f64;
+            set_frame(
+                item,
+                selector(b"setDraggingFrame:contents:\0"),
+                NSRect {
+                    origin: NSPoint {
+                        x: point.x - 16.0 + offset,
+                        y: point.y - 16.0 + offset,
+                    },
+                    size: NSSize {
+                        width: 32.0,
+                        height: 32.0,
+                    },
+                },
+                image,
+            );
+            send_object(items, b"addObject:\0", item);
+            send(item, b"release\0");
+        }
+        let begin: extern "C" fn(
+            *mut c_void,
+            *mut c_void,
+            *mut c_void,
+            *mut c_void,
+            *mut c_void,
+        ) -> *mut c_void = mem::transmute(objc_msgSend as *const ());
+        !begin(
+            view.0,
+            selector(b"beginDraggingSessionWithItems:event:source:\0"),
+            items,
+            event,
+            drag_source(),
+        )
+        .is_null()
+    }
+}
+
+const NS_VIEW_WIDTH_SIZABLE: usize = 2;
+const NS_VIEW_HEIGHT_SIZABLE: usize = 16;
+const NS_VISUAL_EFFECT_BLENDING_MODE_BEHIND_WINDOW: isize = 0;
+const NS_VISUAL_EFFECT_STATE_ACTIVE: isize = 1;
+
+/// Puts the web view on an `NSVisualEffectView` that becomes the window's content view.
+pub(crate) fn set_window_effect(webview: sys::webview_t, effect: WindowEffect) -> bool {
+    let material: isize = match effect {
+        WindowEffect::Vibrancy => 21, // NSVisualEffectMaterialUnderWindowBackground
+        WindowEffect::Acrylic => 13,  // NSVisualEffectMaterialHUDWindow
+        WindowEffect::Mica => 12,     // NSVisualEffectMaterialWindowBackground
+    };
+    unsafe {
+        let set: extern "C" fn(*mut c_void, *mut c_void, isize) =
+            mem::transmute(objc_msgSend as *const ());
+        let window = sys::webview_get_window(webview);
+        let content = send(send(window, b"contentView\0"), b"retain\0");
+        let effect_view = send(send(class(b"NSVisualEffectView\0"), b"alloc\0"), b"init\0");
+        set(effect_view, selector(b"setMaterial:\0"), material);
+        set(
+            effect_view,
+            selector(b"setBlendingMode:\0"),
+            NS_VISUAL_EFFECT_BLENDING_MODE_BEHIND_WINDOW,
+        );
+        set(
+            effect_view,
+            selector(b"setState:\0"),
+            NS_VISUAL_EFFECT_STATE_ACTIVE,
+        );
+        send_object(window, b"setContentView:\0", effect_view);
+        send(effect_view, b"release\0");
+
+        let set_frame: extern "C" fn(*mut c_void, *mut c_void, NSRect) =
+            mem::transmute(objc_msgSend as *const ());
+        set_frame(content, selector(b"setFrame:\0"), bounds(effect_view));
+        set(
+            content,
+            selector(b"setAutoresizingMask:\0"),
+            (NS_VIEW_WIDTH_SIZABLE | NS_VIEW_HEIGHT_SIZABLE) as isize,
+        );
+        send_object(effect_view, b"addSubview:\0", content);
+        send(content, b"release\0");
+    }
+    true
+}
+
+unsafe fn appearance(name: &[u8]) -> *mut c_void {
+    send_object(
+        class(b"NSAppearance\0"),
+        b"appearanceNamed:\0",
+        ns_string(name),
+    )
+}
+
+/// Dark when the user picked the dark appearance in the system settings.
+fn system_theme() -> Theme {
+    unsafe {
+        let style = send_object(
+            send(class(b"NSUserDefaults\0"), b"standardUserDefaults\0"),
+            b"stringForKey:\0",
+            ns_string(b"AppleInterfaceStyle\0"),
+        );
+        if !style.is_null() && send_bool_object(style, b"isEqualToString:\0", ns_string(b"Dark\0"))
+        {
+            Theme::Dark
+        } else {
+            Theme::Light
+        }
+    }
+}
+
+unsafe fn send_bool_object(object: *mut c_void, sel: &[u8], arg: *mut c_void) -> bool {
+    let send: extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> i8 =
+        mem::transmute(objc_msgSend as *const ());
+    send(object, selector(sel), arg) != 0
+}
+
+const THEME_CHANGED: &[u8] = b"AppleInterfaceThemeChangedNotification\0";
+
+extern "C" fn theme_changed(this: *mut c_void, _sel: *mut c_void, _notification: *mut c_void) {
+    unsafe {
+        let callback = observer_state(this) as *mut ThemeCallback;
+        (*callback)(system_theme());
+    }
+}
+
+fn theme_observer_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        define_observer(
+            b"WebviewOfficialThemeObserver\0",
+            b"themeChanged:\0",
+            theme_changed as *const c_void,
+        ) as usize
+    }) as *mut c_void
+}
+
+extern "C" fn key_changed(this: *mut c_void, _sel: *mut c_void, notification: *mut c_void) {
+    unsafe {
+        let name = send(notification, b"name\0");
+        let focused = send_bool_object(
+            name,
+            b"isEqualToString:\0",
+            NSWindowDidBecomeKeyNotification,
+        );
+        let callback = observer_state(this) as *mut FocusCallback;
+        (*callback)(focused);
+    }
+}
+
+fn focus_observer_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        define_observer(
+            b"WebviewOfficialFocusObserver\0",
+            b"keyChanged:\0",
+            key_changed as *const c_void,
+        ) as usize
+    }) as *mut c_void
+}
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn LSSetDefaultHandlerForURLScheme(scheme: CFStringRef, handler: CFStringRef) -> i32;
+}
+
+/// Makes the application's bundle the default handler of `scheme`, which also has to be listed
+/// under `CFBundleURLTypes` in its `Info.plist`. Links then arrive as Apple events.
+pub(crate) fn register_url_scheme(scheme: &str) -> io::Result<()> {
+    unsafe {
+        let bundle = send(
+            send(class(b"NSBundle\0"), b"mainBundle\0"),
+            b"bundleIdentifier\0",
+        );
+        if bundle.is_null() {
+            return Err(io::Error::other(
+                "the application doesn't run from a bundle",
+            ));
+        }
+        let scheme = cf_string(scheme);
+        let status = LSSetDefaultHandlerForURLScheme(scheme, bundle as CFStringRef);
+        CFRelease(scheme);
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "LaunchServices refused the handler ({})",
+                status
+            )))
+        }
+    }
+}
+
+/// `'GURL'`, the class and id of the Apple event asking to open a url.
+const K_AE_GET_URL: u32 = 0x4755_524c;
+/// `'----'`, the keyword of the event's url.
+const KEY_DIRECT_OBJECT: u32 = 0x2d2d_2d2d;
+
+extern "C" fn handle_url_event(
+    this: *mut c_void,
+    _sel: *mut c_void,
+    event: *mut c_void,
+    _reply: *mut c_void,
+) {
+    unsafe {
+        let descriptor: extern "C" fn(*mut c_void, *mut c_void, u32) -> *mut c_void =
+            mem::transmute(objc_msgSend as *const ());
+        let url = send(
+            descriptor(
+                event,
+                selector(b"paramDescriptorForKeyword:\0"),
+                KEY_DIRECT_OBJECT,
+            ),
+            b"stringValue\0",
+        );
+        if url.is_null() {
+            return;
+        }
+        let url = CStr::from_ptr(send(url, b"UTF8String\0") as *const c_char);
+        let callback = observer_state(this) as *mut DeepLinkCallback;
+        (*callback)(url.to_string_lossy().into_owned());
+    }
+}
+
+fn url_handler_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        define_observer(
+            b"WebviewOfficialUrlHandler\0",
+            b"handleURLEvent:withReplyEvent:\0",
+            handle_url_event as *const c_void,
+        ) as usize
+    }) as *mut c_void
+}
+
+unsafe fn apple_event_manager() -> *mut c_void {
+    send(
+        class(b"NSAppleEventManager\0"),
+        b"sharedAppleEventManager\0",
+    )
+}
+
+/// Handles the Apple events asking the application to open a url, the ones it is launched with
+/// included as long as the handler is in place before the run loop starts.
+pub(crate) struct DeepLinkWatch {
+    handler: *mut c_void,
+    callback: *mut DeepLinkCallback,
+}
+
+impl DeepLinkWatch {
+    pub(crate) fn new(_webview: sys::webview_t, callback: DeepLinkCallback) -> DeepLinkWatch {
+        unsafe {
+            let callback = Box::into_raw(Box::new(callback));
+            let handler = new_observer(url_handler_class(), callback as *mut c_void);
+            let set: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void, u32, u32) =
+                mem::transmute(objc_msgSend as *const ());
+            set(
+                apple_event_manager(),
+                selector(b"setEventHandler:andSelector:forEventClass:andEventID:\0"),
+                handler,
+                selector(b"handleURLEvent:withReplyEvent:\0"),
+                K_AE_GET_URL,
+                K_AE_GET_URL,
+            );
+            DeepLinkWatch { handler, callback }
+        }
+    }
+}
+
+impl Drop for DeepLinkWatch {
+    fn drop(&mut self) {
+        unsafe {
+            let remove: extern "C" fn(*mut c_void, *mut c_void, u32, u32) =
+                mem::transmute(objc_msgSend as *const ());
+            remove(
+                apple_event_manager(),
+                selector(b"removeEventHandlerForEventClass:andEventID:\0"),
+                K_AE_GET_URL,
+                K_AE_GET_URL,
+            );
+            send(self.handler, b"release\0");
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// Observes the window becoming and resigning the key window, the one keyboard events go to.
+pub(crate) struct FocusWatch {
+    observer: *mut c_void,
+    callback: *mut FocusCallback,
+}
+
+impl FocusWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FocusCallback) -> FocusWatch {
+        unsafe {
+            let callback = Box::into_raw(Box::new(callback));
+            let observer = new_observer(focus_observer_class(), callback as *mut c_void);
+            let window = sys::webview_get_window(webview);
+            for &name in &[
+                NSWindowDidBecomeKeyNotification,
+                NSWindowDidResignKeyNotification,
+            ] {
+                add_observer(
+                    send(class(b"NSNotificationCenter\0"), b"defaultCenter\0"),
+                    observer,
+                    b"keyChanged:\0",
+                    name,
+                    window,
+                );
+            }
+            FocusWatch { observer, callback }
+        }
+    }
+}
+
+impl Drop for FocusWatch {
+    fn drop(&mut self) {
+        unsafe {
+            send_object(
+                send(class(b"NSNotificationCenter\0"), b"defaultCenter\0"),
+                b"removeObserver:\0",
+                self.observer,
+            );
+            send(self.observer, b"release\0");
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+const NS_WINDOW_ABOVE: isize = 1;
+
+type PerformDragOperation = extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> i8;
+
+static NATIVE_PERFORM_DRAG_OPERATION: OnceLock<usize> = OnceLock::new();
+
+thread_local! {
+    /// The views with a `FileDropWatch` and its callback.
+    static FILE_DROPS: RefCell<Vec<(usize, *mut FileDropCallback)>> = RefCell::new(Vec::new());
+}
+
+/// The paths of the files on the pasteboard of the drag `info`.
+unsafe fn dragged_paths(info: *mut c_void) -> Vec<PathBuf> {
+    let pasteboard = send(info, b"draggingPasteboard\0");
+    let files = send_object(
+        pasteboard,
+        b"propertyListForType:\0",
+        ns_string(b"NSFilenamesPboardType\0"),
+    );
+    if files.is_null() {
+        return Vec::new();
+    }
+    let count: extern "C" fn(*mut c_void, *mut c_void) -> usize =
+        mem::transmute(objc_msgSend as *const ());
+    (0..count(files, selector(b"count\0")))
+        .map(|index| {
+            let file = send_index(files, b"objectAtIndex:\0", index);
+            let file = CStr::from_ptr(send(file, b"UTF8String\0") as *const c_char);
+            PathBuf::from(file.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// Where the drag `info` is, in the coordinates of `view`, which are flipped for `WKWebView`.
+unsafe fn drag_position(view: *mut c_void, info: *mut c_void) -> (i32, i32) {
+    let location: extern "C" fn(*mut c_void, *mut c_void) -> NSPoint =
+        mem::transmute(objc_msgSend as *const ());
+    let convert: extern "C" fn(*mut c_void, *mut c_void, NSPoint, *mut c_void) -> NSPoint =
+        mem::transmute(objc_msgSend as *const ());
+    let point = convert(
+        view,
+        selector(b"convertPoint:fromView:\0"),
+        location(info, selector(b"draggingLocation\0")),
+        null_mut(),
+    );
+    (point.x.round() as i32, point.y.round() as i32)
+}
+
+extern "C" fn perform_drag_operation(this: *mut c_void, sel: *mut c_void, info: *mut c_void) -> i8 {
+    let callback = FILE_DROPS.with(|drops| {
+        drops
+            .borrow()
+            .iter()
+            .find(|(view, _)| *view == this as usize)
+            .map(|&(_, callback)| callback)
+    });
+    if let Some(callback) = callback {
+        let paths = unsafe { dragged_paths(info) };
+        if !paths.is_empty() {
+            let position = unsafe { drag_position(this, info) };
+            unsafe { (*callback)(paths, position) };
+        }
+    }
+    // The page still gets the drop.
+    let native: PerformDragOperation =
+        unsafe { mem::transmute(*NATIVE_PERFORM_DRAG_OPERATION.get().unwrap()) };
+    native(this, sel, info)
+}
+
+/// Reports the files dropped on the `WKWebView`, whose `performDragOperation:` is hooked to see
+/// the drops before the page does.
+pub(crate) struct FileDropWatch {
+    view: *mut c_void,
+    callback: *mut FileDropCallback,
+}
+
+impl FileDropWatch {
+    pub(crate) fn new(view: &View, callback: FileDropCallback) -> Option<FileDropWatch> {
+        NATIVE_PERFORM_DRAG_OPERATION.get_or_init(|| unsafe {
+            let method = class_getInstanceMethod(
+                class(b"WKWebView\0"),
+                selector(b"performDragOperation:\0"),
+            );
+            method_setImplementation(method, perform_drag_operation as *const c_void) as usize
+        });
+        let callback = Box::into_raw(Box::new(callback));
+        FILE_DROPS.with(|drops| drops.borrow_mut().push((view.0 as usize, callback)));
+        Some(FileDropWatch {
+            view: view.0,
+            callback,
+        })
+    }
+}
+
+impl Drop for FileDropWatch {
+    fn drop(&mut self) {
+        FILE_DROPS.with(|drops| {
+            drops
+                .borrow_mut()
+                .retain(|&(view, _)| view != self.view as usize)
+        });
+        unsafe { drop(Box::from_raw(self.callback)) };
+    }
+}
+
+const NS_EVENT_MODIFIER_FLAG_SHIFT: usize = 1 << 17;
+const NS_EVENT_MODIFIER_FLAG_CONTROL: usize = 1 << 18;
+const NS_EVENT_MODIFIER_FLAG_OPTION: usize = 1 << 19;
+const NS_EVENT_MODIFIER_FLAG_COMMAND: usize = 1 << 20;
+
+type KeyDown = extern "C" fn(*mut c_void, *mut c_void, *mut c_void);
+type PerformKeyEquivalent = extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> i8;
+
+static NATIVE_KEY_DOWN: OnceLock<usize> = OnceLock::new();
+static NATIVE_PERFORM_KEY_EQUIVALENT: OnceLock<usize> = OnceLock::new();
+
+thread_local! {
+    /// The views with a `KeyWatch` and its callback.
+    static KEY_WATCHES: RefCell<Vec<(usize, *mut KeyCallback)>> = RefCell::new(Vec::new());
+    /// The event last reported, key equivalents come through `keyDown:` as well when nobody
+    /// takes them.
+    static LAST_KEY_EVENT: Cell<usize> = Cell::new(0);
+}
+
+/// The DOM's name for the key of `event`.
+unsafe fn key_name(event: *mut c_void) -> Option<String> {
+    let characters = send(event, b"charactersIgnoringModifiers\0");
+    if characters.is_null() {
+        return None;
+    }
+    let characters = CStr::from_ptr(send(characters, b"UTF8String\0") as *const c_char);
+    let c = characters.to_str().ok()?.chars().next()?;
+    let name = match c as u32 {
+        0x3 | 0xd => "Enter",
+        0x9 | 0x19 => "Tab",
+        0x1b => "Escape",
+        0x7f => "Backspace",
+        0xf700 => "ArrowUp",
+        0xf701 => "ArrowDown",
+        0xf702 => "ArrowLeft",
+        0xf703 => "ArrowRight",
+        n @ 0xf704..=0xf726 => return Some(format!("F{}", n - 0xf704 + 1)),
+        0xf727 => "Insert",
+        0xf728 => "Delete",
+        0xf729 => "Home",
+        0xf72b => "End",
+        0xf72c => "PageUp",
+        0xf72d => "PageDown",
+        _ if c.is_control() => return None,
+        _ => return Some(c.to_string()),
+    };
+    Some(name.to_string())
+}
+
+/// Reports `event` to the callback of `view`, returns `true` if it handled the key.
+unsafe fn report_key(view: *mut c_void, event: *mut c_void) -> bool {
+    if LAST_KEY_EVENT.with(|last| last.replace(event as usize)) == event as usize {
+        return false;
+    }
+    let callback = KEY_WATCHES.with(|watches| {
+        watches
+            .borrow()
+            .iter()
+            .find(|(v, _)| *v == view as usize)
+            .map(|&(_, callback)| callback)
+    });
+    let (callback, key) = match (callback, key_name(event)) {
+        (Some(callback), Some(key)) => (callback, key),
+        _ => return false,
+    };
+    let flags: extern "C" fn(*mut c_void, *mut c_void) -> usize =
+        mem::transmute(objc_msgSend as *const ());
+    let flags = flags(event, selector(b"modifierFlags\0"));
+    (*callback)(KeyEvent {
+        key,
+        ctrl: flags & NS_EVENT_MODIFIER_FLAG_CONTROL != 0,
+        shift: flags & NS_EVENT_MODIFIER_FLAG_SHIFT != 0,
+        alt: flags & NS_EVENT_MODIFIER_FLAG_OPTION != 0,
+        meta: flags & NS_EVENT_MODIFIER_FLAG_COMMAND != 0,
+    })
+}
+
+extern "C" fn key_down(this: *mut c_void, sel: *mut c_void, event: *mut c_void) {
+    if unsafe { report_key(this, event) } {
+        return;
+    }
+    let native: KeyDown = unsafe { mem::transmute(*NATIVE_KEY_DOWN.get().unwrap()) };
+    native(this, sel, event)
+}
+
+extern "C" fn perform_key_equivalent(
+    this: *mut c_void,
+    sel: *mut c_void,
+    event: *mut c_void,
+) -> i8 {
+    // Every view of the window is asked, only the focused one reports.
+    let window = unsafe { send(this, b"window\0") };
+    if !window.is_null() && unsafe { send(window, b"firstResponder\0") } == this {
+        if unsafe { report_key(this, event) } {
+            return 1;
+        }
+    }
+    let native: PerformKeyEquivalent =
+        unsafe { mem::transmute(*NATIVE_PERFORM_KEY_EQUIVALENT.get().unwrap()) };
+    native(this, sel, event)
+}
+
+/// Sees the keys pressed in the `WKWebView` before the page, its `keyDown:` and
+/// `performKeyEquivalent:` are hooked.
+pub(crate) struct KeyWatch {
+    view: *mut c_void,
+    callback: *mut KeyCallback,
+}
+
+impl KeyWatch {
+    pub(crate) fn new(view: &View, callback: KeyCallback) -> Option<KeyWatch> {
+        unsafe {
+            let class = class(b"WKWebView\0");
+            NATIVE_KEY_DOWN.get_or_init(|| {
+                let method = class_getInstanceMethod(class, selector(b"keyDown:\0"));
+                method_setImplementation(method, key_down as *const c_void) as usize
+            });
+            NATIVE_PERFORM_KEY_EQUIVALENT.get_or_init(|| {
+                let method = class_getInstanceMethod(class, selector(b"performKeyEquivalent:\0"));
+                method_setImplementation(method, perform_key_equivalent as *const c_void) as usize
+            });
+        }
+        let callback = Box::into_raw(Box::new(callback));
+        KEY_WATCHES.with(|watches| watches.borrow_mut().push((view.0 as usize, callback)));
+        Some(KeyWatch {
+            view: view.0,
+            callback,
+        })
+    }
+}
+
+impl Drop for KeyWatch {
+    fn drop(&mut self) {
+        KEY_WATCHES.with(|watches| {
+            watches
+                .borrow_mut()
+                .retain(|&(view, _)| view != self.view as usize)
+        });
+        unsafe { drop(Box::from_raw(self.callback)) };
+    }
+}
+
+const NS_KEY_VALUE_OBSERVING_OPTION_NEW: usize = 1;
+
+extern "C" fn loading_changed(
+    this: *mut c_void,
+    _sel: *mut c_void,
+    _key_path: *mut c_void,
+    view: *mut c_void,
+    _change: *mut c_void,
+    _context: *mut c_void,
+) {
+    unsafe {
+        if !send_bool(view, b"isLoading\0") {
+            let callback = observer_state(this) as *mut LoadCallback;
+            (*callback)();
+        }
+    }
+}
+
+/// Like the classes of `define_observer`, for key-value observing, whose handler takes more
+/// arguments.
+fn load_observer_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        let class = objc_allocateClassPair(
+            class(b"NSObject\0"),
+            b"WebviewOfficialLoadObserver\0".as_ptr() as *const c_char,
+            0,
+        );
+        class_addIvar(
+            class,
+            b"state\0".as_ptr() as *const c_char,
+            mem::size_of::<*mut c_void>(),
+            mem::align_of::<*mut c_void>().trailing_zeros() as u8,
+            b"^v\0".as_ptr() as *const c_char,
+        );
+        class_addMethod(
+            class,
+            selector(b"observeValueForKeyPath:ofObject:change:context:\0"),
+            loading_changed as *const c_void,
+            b"v@:@@@^v\0".as_ptr() as *const c_char,
+        );
+        objc_registerClassPair(class);
+        class as usize
+    }) as *mut c_void
+}
+
+/// Observes the `WKWebView`'s `loading` key, a load ended when it turns false.
+pub(crate) struct LoadWatch {
+    view: *mut c_void,
+    observer: *mut c_void,
+    callback: *mut LoadCallback,
+}
+
+impl LoadWatch {
+    pub(crate) fn new(view: &View, callback: LoadCallback) -> Option<LoadWatch> {
+        unsafe {
+            let callback = Box::into_raw(Box::new(callback));
+            let observer = new_observer(load_observer_class(), callback as *mut c_void);
+            let add: extern "C" fn(
+                *mut c_void,
+                *mut c_void,
+                *mut c_void,
+                *mut c_void,
+                usize,
+                *mut c_void,
+            ) = mem::transmute(objc_msgSend as *const ());
+            add(
+                view.0,
+                selector(b"addObserver:forKeyPath:options:context:\0"),
+                observer,
+                ns_string(b"loading\0"),
+                NS_KEY_VALUE_OBSERVING_OPTION_NEW,
+                null_mut(),
+            );
+            Some(LoadWatch {
+                view: view.0,
+                observer,
+                callback,
+            })
+        }
+    }
+}
+
+impl Drop for LoadWatch {
+    fn drop(&mut self) {
+        unsafe {
+            let remove: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) =
+                mem::transmute(objc_msgSend as *const ());
+            remove(
+                self.view,
+                selector(b"removeObserver:forKeyPath:\0"),
+                self.observer,
+                ns_string(b"loading\0"),
+            );
+            send(self.observer, b"release\0");
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+const WK_NAVIGATION_ACTION_POLICY_CANCEL: isize = 0;
+const WK_NAVIGATION_ACTION_POLICY_ALLOW: isize = 1;
+
+/// The start of an Objective-C block, enough to call it.
+#[repr(C)]
+struct Block {
+    isa: *const c_void,
+    flags: c_int,
+    reserved: c_int,
+    invoke: extern "C" fn(*mut Block, isize),
+}
+
+extern "C" fn decide_policy(
+    this: *mut c_void,
+    _sel: *mut c_void,
+    _view: *mut c_void,
+    action: *mut c_void,
+    decision_handler: *mut Block,
+) {
+    unsafe {
+        let frame = send(action, b"targetFrame\0");
+        // Frames are not restricted, a missing frame is a new window.
+        let allowed = if !frame.is_null() && !send_bool(frame, b"isMainFrame\0") {
+            true
+        } else {
+            let url = send(send(action, b"request\0"), b"URL\0");
+            let string = send(send(url, b"absoluteString\0"), b"UTF8String\0") as *const c_char;
+            if url.is_null() || string.is_null() {
+                true
+            } else {
+                let callback = observer_state(this) as *mut NavigationCallback;
+                (*callback)(&CStr::from_ptr(string).to_string_lossy())
+            }
+        };
+        let policy = if allowed {
+            WK_NAVIGATION_ACTION_POLICY_ALLOW
+        } else {
+            WK_NAVIGATION_ACTION_POLICY_CANCEL
+        };
+        ((*decision_handler).invoke)(decision_handler, policy);
+    }
+}
+
+/// A `WKNavigationDelegate` with a `state` pointer, which only decides navigations.
+fn navigation_delegate_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        let class = objc_allocateClassPair(
+            class(b"NSObject\0"),
+            b"WebviewOfficialNavigationDelegate\0".as_ptr() as *const c_char,
+            0,
+        );
+        class_addIvar(
+            class,
+            b"state\0".as_ptr() as *const c_char,
+            mem::size_of::<*mut c_void>(),
+            mem::align_of::<*mut c_void>().trailing_zeros() as u8,
+            b"^v\0".as_ptr() as *const c_char,
+        );
+        class_addMethod(
+            class,
+            selector(b"webView:decidePolicyForNavigationAction:decisionHandler:\0"),
+            decide_policy as *const c_void,
+            b"v@:@@@?\0".as_ptr() as *const c_char,
+        );
+        objc_registerClassPair(class);
+        class as usize
+    }) as *mut c_void
+}
+
+/// Decides the `WKWebView`'s navigations as its navigation delegate, before anything is
+/// requested. `None` if the view already has a delegate.
+pub(crate) struct NavigationWatch {
+    view: *mut c_void,
+    delegate: *mut c_void,
+    callback: *mut NavigationCallback,
+}
+
+impl NavigationWatch {
+    pub(crate) fn new(view: &View, callback: NavigationCallback) -> Option<NavigationWatch> {
+        unsafe {
+            if !send(view.0, b"navigationDelegate\0").is_null() {
+                return None;
+            }
+            let callback = Box::into_raw(Box::new(callback));
+            // The view holds its delegate weakly, the watch keeps it.
+            let delegate = new_observer(navigation_delegate_class(), callback as *mut c_void);
+            send_object(view.0, b"setNavigationDelegate:\0", delegate);
+            Some(NavigationWatch {
+                view: view.0,
+                delegate,
+                callback,
+            })
+        }
+    }
+}
+
+impl Drop for NavigationWatch {
+    fn drop(&mut self) {
+        unsafe {
+            send_object(self.view, b"setNavigationDelegate:\0", null_mut());
+            send(self.delegate, b"release\0");
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// Attaches the window to its owner's as a child window, or as a sheet when it is modal. A sheet
+/// takes the owner's input until it ends.
+pub(crate) struct Owner {
+    window: *mut c_void,
+    owner: *mut c_void,
+    modal: bool,
+}
+
+impl Owner {
+    pub(crate) fn new(webview: sys::webview_t, owner: sys::webview_t, modal: bool) -> Owner {
+        unsafe {
+            let window = sys::webview_get_window(webview);
+            let owner = sys::webview_get_window(owner);
+            if modal {
+                let begin: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) =
+                    mem::transmute(objc_msgSend as *const ());
+                begin(
+                    owner,
+                    selector(b"beginSheet:completionHandler:\0"),
+                    window,
+                    null_mut(),
+                );
+            } else {
+                let add: extern "C" fn(*mut c_void, *mut c_void, *mut c_void, isize) =
+                    mem::transmute(objc_msgSend as *const ());
+                add(
+                    owner,
+                    selector(b"addChildWindow:ordered:\0"),
+                    window,
+                    NS_WINDOW_ABOVE,
+                );
+            }
+            Owner {
+                window,
+                owner,
+                modal,
+            }
+        }
+    }
+}
+
+impl Drop for Owner {
+    fn drop(&mut self) {
+        unsafe {
+            if self.modal {
+                send_object(self.owner, b"endSheet:\0", self.window);
+            } else {
+                send_object(self.owner, b"removeChildWindow:\0", self.window);
+            }
+        }
+    }
+}
+
+/// The window's content aspect ratio, which AppKit keeps while the user resizes.
+pub(crate) struct AspectRatio(*mut c_void);
+
+impl AspectRatio {
+    pub(crate) fn new(webview: sys::webview_t, width: u32, height: u32) -> AspectRatio {
+        unsafe {
+            let window = sys::webview_get_window(webview);
+            set_size(
+                window,
+                b"setContentAspectRatio:\0",
+                NSSize {
+                    width: width as f64,
+                    height: height as f64,
+                },
+            );
+            AspectRatio(window)
+        }
+    }
+}
+
+impl Drop for AspectRatio {
+    fn drop(&mut self) {
+        // Resize increments and the aspect ratio exclude each other, setting one clears the other.
+        unsafe {
+            set_size(
+                self.0,
+                b"setContentResizeIncrements:\0",
+                NSSize {
+                    width: 1.0,
+                    height: 1.0,
+                },
+            )
+        };
+    }
+}
+
+unsafe fn set_size(object: *mut c_void, sel: &[u8], size: NSSize) {
+    let send: extern "C" fn(*mut c_void, *mut c_void, NSSize) =
+        mem::transmute(objc_msgSend as *const ());
+    send(object, selector(sel), size)
+}
+
+/// Observes the distributed notification the system posts when the appearance changes.
+pub(crate) struct ThemeWatch {
+    observer: *mut c_void,
+    callback: *mut ThemeCallback,
+}
+
+impl ThemeWatch {
+    pub(crate) fn new(_webview: sys::webview_t, callback: ThemeCallback) -> ThemeWatch {
+        unsafe {
+            let callback = Box::into_raw(Box::new(callback));
+            let observer = new_observer(theme_observer_class(), callback as *mut c_void);
+            add_observer(
+                distributed_center(),
+                observer,
+                b"themeChanged:\0",
+                ns_string(THEME_CHANGED),
+                null_mut(),
+            );
+            ThemeWatch { observer, callback }
+        }
+    }
+}
+
+impl Drop for ThemeWatch {
+    fn drop(&mut self) {
+        unsafe {
+            send_object(distributed_center(), b"removeObserver:\0", self.observer);
+            send(self.observer, b"release\0");
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// The height of the primary screen, AppKit's screen coordinates grow upwards from its bottom.
+unsafe fn primary_height() -> f64 {
+    let screens = send(class(b"NSScreen\0"), b"screens\0");
+    let primary = send_index(screens, b"objectAtIndex:\0", 0);
+    send_rect(primary, b"frame\0").size.height
+}
+
+unsafe fn send_index(object: *mut c_void, sel: &[u8], index: usize) -> *mut c_void {
+    let send: extern "C" fn(*mut c_void, *mut c_void, usize) -> *mut c_void =
+        mem::transmute(objc_msgSend as *const ());
+    send(object, selector(sel), index)
+}
+
+/// `frame` with the origin at the top left of the primary screen.
+unsafe fn flip(frame: NSRect) -> Rect {
+    Rect {
+        x: frame.origin.x.round() as i32,
+        y: (primary_height() - frame.origin.y - frame.size.height).round() as i32,
+        width: frame.size.width.round() as i32,
+        height: frame.size.height.round() as i32,
+    }
+}
+
+pub(crate) fn window_frame(webview: sys::webview_t) -> Rect {
+    unsafe { flip(send_rect(sys::webview_get_window(webview), b"frame\0")) }
+}
+
+pub(crate) fn set_window_frame(webview: sys::webview_t, frame: Rect) {
+    unsafe {
+        let frame = NSRect {
+            origin: NSPoint {
+                x: frame.x as f64,
+                y: primary_height() - (frame.y + frame.height) as f64,
+            },
+            size: NSSize {
+                width: frame.width as f64,
+                height: frame.height as f64,
+            },
+        };
+        let set_frame: extern "C" fn(*mut c_void, *mut c_void, NSRect, i8) =
+            mem::transmute(objc_msgSend as *const ());
+        set_frame(
+            sys::webview_get_window(webview),
+            selector(b"setFrame:display:\0"),
+            frame,
+            1,
+        );
+    }
+}
+
+/// Zoomed, AppKit's closest to maximized.
+pub(crate) fn is_maximized(webview: sys::webview_t) -> bool {
+    unsafe { send_bool(sys::webview_get_window(webview), b"isZoomed\0") }
+}
+
+pub(crate) fn is_minimized(webview: sys::webview_t) -> bool {
+    unsafe { send_bool(sys::webview_get_window(webview), b"isMiniaturized\0") }
+}
+
+pub(crate) fn maximize(webview: sys::webview_t) {
+    unsafe {
+        let window = sys::webview_get_window(webview);
+        if !send_bool(window, b"isZoomed\0") {
+            send_object(window, b"zoom:\0", null_mut());
+        }
+    }
+}
+
+const NS_WINDOW_STYLE_MASK_FULL_SCREEN: usize = 1 << 14;
+
+pub(crate) fn is_fullscreen(webview: sys::webview_t) -> bool {
+    unsafe {
+        let style_mask: extern "C" fn(*mut c_void, *mut c_void) -> usize =
+            mem::transmute(objc_msgSend as *const ());
+        let window = sys::webview_get_window(webview);
+        style_mask(window, selector(b"styleMask\0")) & NS_WINDOW_STYLE_MASK_FULL_SCREEN != 0
+    }
+}
+
+/// Moves the window to a fullscreen space of its own, or back, with AppKit's animation.
+pub(crate) fn set_fullscreen(webview: sys::webview_t, fullscreen: bool) {
+    if is_fullscreen(webview) != fullscreen {
+        unsafe {
+            send_object(
+                sys::webview_get_window(webview),
+                b"toggleFullScreen:\0",
+                null_mut(),
+            );
+        }
+    }
+}
+
+/// The visible frames of the screens, without the menu bar and the Dock, the primary one first.
+pub(crate) fn monitors(_webview: sys::webview_t) -> Vec<Rect> {
+    unsafe {
+        let screens = send(class(b"NSScreen\0"), b"screens\0");
+        let count: extern "C" fn(*mut c_void, *mut c_void) -> usize =
+            mem::transmute(objc_msgSend as *const ());
+        (0..count(screens, selector(b"count\0")))
+            .map(|index| {
+                let screen = send_index(screens, b"objectAtIndex:\0", index);
+                flip(send_rect(screen, b"visibleFrame\0"))
+            })
+            .collect()
+    }
+}
+
+extern "C" fn frame_event(this: *mut c_void, _sel: *mut c_void, _notification: *mut c_void) {
+    unsafe {
+        let callback = observer_state(this) as *mut FrameCallback;
+        (*callback)();
+    }
+}
+
+fn frame_observer_class() -> *mut c_void {
+    static CLASS: OnceLock<usize> = OnceLock::new();
+    *CLASS.get_or_init(|| unsafe {
+        define_observer(
+            b"WebviewOfficialFrameObserver\0",
+            b"frameChanged:\0",
+            frame_event as *const c_void,
+        ) as usize
+    }) as *mut c_void
+}
+
+/// Observes the window moving, resizing, which zooming does as well, and being minimized.
+pub(crate) struct FrameWatch {
+    observer: *mut c_void,
+    callback: *mut FrameCallback,
+}
+
+impl FrameWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FrameCallback) -> FrameWatch {
+        unsafe {
+            let callback = Box::into_raw(Box::new(callback));
+            let observer = new_observer(frame_observer_class(), callback as *mut c_void);
+            let window = sys::webview_get_window(webview);
+            for &name in &[
+                NSWindowDidMoveNotification,
+                NSWindowDidResizeNotification,
+                NSWindowDidMiniaturizeNotification,
+                NSWindowDidDeminiaturizeNotification,
+            ] {
+                add_observer(
+                    send(class(b"NSNotificationCenter\0"), b"defaultCenter\0"),
+                    observer,
+                    b"frameChanged:\0",
+                    name,
+                    window,
+                );
+            }
+            FrameWatch { observer, callback }
+        }
+    }
+}
+
+impl Drop for FrameWatch {
+    fn drop(&mut self) {
+        unsafe {
+            send_object(
+                send(class(b"NSNotificationCenter\0"), b"defaultCenter\0"),
+                b"removeObserver:\0",
+                self.observer,
+            );
+            send(self.observer, b"release\0");
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// The user's locale, e.g. `en-US`.
+pub(crate) fn locale() -> Option<String> {
+    unsafe {
+        let locale = send(class(b"NSLocale\0"), b"currentLocale\0");
+        let identifier = send(locale, b"localeIdentifier\0");
+        if identifier.is_null() {
+            return None;
+        }
+        let identifier = CStr::from_ptr(send(identifier, b"UTF8String\0") as *const c_char);
+        Some(identifier.to_string_lossy().replace('_', "-"))
+    }
+}
+
+const K_IOPM_ASSERTION_LEVEL_ON: u32 = 255;
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOPMAssertionCreateWithName(
+        assertion_type: CFStringRef,
+        level: u32,
+        name: CFStringRef,
+        id: *mut u32,
+    ) -> c_int;
+    fn IOPMAssertionRelease(id: u32) -> c_int;
+}
+
+/// Keeps the display from sleeping while it is held, with a power assertion.
+pub(crate) struct SleepInhibitor(u32);
+
+impl SleepInhibitor {
+    pub(crate) fn new(_webview: sys::webview_t, reason: &str) -> Option<SleepInhibitor> {
+        let assertion_type = cf_string("PreventUserIdleDisplaySleep");
+        let name = cf_string(reason);
+        let mut id = 0;
+        let result = unsafe {
+            let result = IOPMAssertionCreateWithName(
+                assertion_type,
+                K_IOPM_ASSERTION_LEVEL_ON,
+                name,
+                &mut id,
+            );
+            CFRelease(assertion_type);
+            CFRelease(name);
+            result
+        };
+        if result != 0 {
+            return None;
+        }
+        Some(SleepInhibitor(id))
+    }
+}
+
+impl Drop for SleepInhibitor {
+    fn drop(&mut self) {
+        unsafe { IOPMAssertionRelease(self.0) };
+    }
+}
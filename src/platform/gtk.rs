@@ -0,0 +1,2275 @@
+use std::env;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::io;
+use std::iter;
+use std::mem;
+use std::os::raw::*;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr::{null, null_mut};
+use std::sync::Mutex;
+
+#[cfg(feature = "raw-window-handle")]
+use raw_window_handle::RawWindowHandle;
+use webview_official_sys as sys;
+
+use super::{
+    DeepLinkCallback, FileDropCallback, FocusCallback, FrameCallback, HistoryAction, KeyCallback,
+    Layout, LoadCallback, NavigationCallback, PaintCallback, ProcessMemory, Request,
+    SessionCallback, ThemeCallback,
+};
+use crate::builder::Cache;
+use crate::cstring;
+use crate::gtk::{HardwareAcceleration, Options};
+use crate::host;
+use crate::{
+    Backend, BackgroundThrottling, Capabilities, Color, DragItem, Error, KeyEvent, ProgressState,
+    Rect, SessionEvent, Theme, UserAttention, WindowEffect,
+};
+
+const G_SIGNAL_MATCH_DATA: c_uint = 1 << 4;
+#[cfg(feature = "raw-window-handle")]
+const G_CONNECT_AFTER_SWAPPED: c_int = 1 | 2;
+
+extern "C" {
+    fn g_signal_handlers_disconnect_matched(
+        instance: *mut c_void,
+        mask: c_uint,
+        signal_id: c_uint,
+        detail: u32,
+        closure: *mut c_void,
+        func: *mut c_void,
+        data: *mut c_void,
+    ) -> c_uint;
+}
+
+extern "C" {
+    fn gtk_init_check(argc: *mut c_int, argv: *mut *mut *mut c_char) -> c_int;
+}
+
+/// GTK needs a display to connect to, which the native webview assumes there is.
+pub(crate) fn check_backend() -> Result<(), Error> {
+    if unsafe { gtk_init_check(null_mut(), null_mut()) } != 0 {
+        Ok(())
+    } else {
+        Err(Error::BackendUnavailable(
+            "GTK can't open a display".to_string(),
+        ))
+    }
+}
+
+/// Stops the window from quitting the GTK main loop when it is destroyed.
+///
+/// The native backend connects the window's `destroy` signal to `gtk_main_quit`, with the
+/// webview itself as user data.
+pub(crate) fn detach_main_loop(webview: sys::webview_t) {
+    unsafe {
+        g_signal_handlers_disconnect_matched(
+            sys::webview_get_window(webview),
+            G_SIGNAL_MATCH_DATA,
+            0,
+            0,
+            null_mut(),
+            null_mut(),
+            webview,
+        );
+    }
+}
+
+pub(crate) fn terminate(webview: sys::webview_t) {
+    unsafe { sys::webview_terminate(webview) }
+}
+
+/// Destroys the native widgets, the native backend leaves them around when deleted.
+///
+/// A window supplied by the host is kept, only the web view is taken out of it.
+pub(crate) fn destroy(webview: sys::webview_t, owns_window: bool) {
+    // The `destroy` handler would otherwise call into the deleted webview.
+    detach_main_loop(webview);
+    unsafe {
+        let window = sys::webview_get_window(webview);
+        if owns_window {
+            gtk_widget_destroy(window);
+        } else {
+            let child = gtk_bin_get_child(window);
+            if !child.is_null() {
+                gtk_widget_destroy(child);
+            }
+        }
+    }
+}
+
+/// The native dispatch goes through the GTK main context, which the host loop drains as well.
+pub(crate) fn dispatch_external(webview: sys::webview_t, f: sys::DispatchFn, arg: *mut c_void) {
+    unsafe { sys::webview_dispatch(webview, Some(f), arg) }
+}
+
+const GTK_DIALOG_MODAL: c_int = 1;
+const GTK_DIALOG_DESTROY_WITH_PARENT: c_int = 2;
+const GTK_MESSAGE_ERROR: c_int = 3;
+const GTK_BUTTONS_CLOSE: c_int = 2;
+
+extern "C" {
+    fn gtk_message_dialog_new(
+        parent: *mut c_void,
+        flags: c_int,
+        type_: c_int,
+        buttons: c_int,
+        message_format: *const c_char,
+        ...
+    ) -> *mut c_void;
+    fn gtk_window_set_title(window: *mut c_void, title: *const c_char);
+    fn gtk_dialog_run(dialog: *mut c_void) -> c_int;
+    fn gtk_widget_destroy(widget: *mut c_void);
+    fn gtk_bin_get_child(bin: *mut c_void) -> *mut c_void;
+}
+
+/// Shows a modal error dialog, must be called from the UI thread.
+pub(crate) fn show_error_dialog(window: *mut c_void, title: &str, message: &str) {
+    let c_title = cstring::text(title);
+    let c_message = cstring::text(message);
+    unsafe {
+        let dialog = gtk_message_dialog_new(
+            window,
+            GTK_DIALOG_MODAL | GTK_DIALOG_DESTROY_WITH_PARENT,
+            GTK_MESSAGE_ERROR,
+            GTK_BUTTONS_CLOSE,
+            b"%s\0".as_ptr() as *const c_char,
+            c_message.as_ptr(),
+        );
+        gtk_window_set_title(dialog, c_title.as_ptr());
+        gtk_dialog_run(dialog);
+        gtk_widget_destroy(dialog);
+    }
+}
+
+#[repr(C)]
+struct GtkAllocation {
+    x: c_int,
+    y: c_int,
+    width: c_int,
+    height: c_int,
+}
+
+extern "C" {
+    fn g_object_ref(object: *mut c_void) -> *mut c_void;
+    fn g_object_unref(object: *mut c_void);
+    fn g_signal_connect_data(
+        instance: *mut c_void,
+        detailed_signal: *const c_char,
+        handler: *const c_void,
+        data: *mut c_void,
+        destroy_data: *const c_void,
+        connect_flags: c_int,
+    ) -> c_ulong;
+    fn gtk_container_add(container: *mut c_void, widget: *mut c_void);
+    fn gtk_container_remove(container: *mut c_void, widget: *mut c_void);
+    fn gtk_fixed_new() -> *mut c_void;
+    fn gtk_fixed_put(fixed: *mut c_void, widget: *mut c_void, x: c_int, y: c_int);
+    fn gtk_fixed_move(fixed: *mut c_void, widget: *mut c_void, x: c_int, y: c_int);
+    fn gtk_widget_get_parent(widget: *mut c_void) -> *mut c_void;
+    fn gtk_widget_hide(widget: *mut c_void);
+    fn gtk_widget_queue_resize(widget: *mut c_void);
+    fn gtk_widget_set_size_request(widget: *mut c_void, width: c_int, height: c_int);
+    fn gtk_widget_show_all(widget: *mut c_void);
+    fn g_object_get_data(object: *mut c_void, key: *const c_char) -> *mut c_void;
+    fn g_object_set_data(object: *mut c_void, key: *const c_char, data: *mut c_void);
+    fn gtk_overlay_new() -> *mut c_void;
+    #[cfg(feature = "raw-window-handle")]
+    fn gtk_plug_new(socket_id: c_ulong) -> *mut c_void;
+    fn gtk_overlay_add_overlay(overlay: *mut c_void, widget: *mut c_void);
+    fn gtk_overlay_set_overlay_pass_through(
+        overlay: *mut c_void,
+        widget: *mut c_void,
+        pass_through: c_int,
+    );
+}
+
+/// The key of a window's `GtkOverlay` for panes, see `pane_parent`.
+const PANES: &[u8] = b"webview-panes\0";
+
+/// Panes are overlays over the web view, which is put into a `GtkOverlay` in its window for the
+/// first one.
+pub(crate) fn pane_parent(webview: sys::webview_t) -> *mut c_void {
+    unsafe {
+        let window = sys::webview_get_window(webview);
+        let overlay = g_object_get_data(window, PANES.as_ptr() as *const c_char);
+        if !overlay.is_null() {
+            return overlay;
+        }
+        let content = gtk_bin_get_child(window);
+        let overlay = gtk_overlay_new();
+        g_object_ref(content);
+        gtk_container_remove(window, content);
+        gtk_container_add(overlay, content);
+        g_object_unref(content);
+        gtk_container_add(window, overlay);
+        gtk_widget_show_all(overlay);
+        g_object_set_data(window, PANES.as_ptr() as *const c_char, overlay);
+        overlay
+    }
+}
+
+/// Adds a panel's `fixed` to `parent`, over the page if it's the overlay of `pane_parent`.
+unsafe fn add_panel(parent: *mut c_void, fixed: *mut c_void) {
+    let window = gtk_widget_get_toplevel(parent);
+    if g_object_get_data(window, PANES.as_ptr() as *const c_char) == parent {
+        gtk_overlay_add_overlay(parent, fixed);
+        // The fixed covers the whole page, only the web view in it takes the input.
+        gtk_overlay_set_overlay_pass_through(parent, fixed, 1);
+    } else {
+        gtk_container_add(parent, fixed);
+    }
+}
+
+/// A `GtkPlug` embedded into a foreign X11 window, which holds the panel until it's removed.
+#[cfg(feature = "raw-window-handle")]
+pub(crate) fn foreign_parent(handle: RawWindowHandle) -> Result<*mut c_void, Error> {
+    let window = match handle {
+        RawWindowHandle::Xlib(handle) => handle.window,
+        RawWindowHandle::Xcb(handle) => handle.window.get() as c_ulong,
+        _ => {
+            return Err(Error::BackendUnavailable(
+                "WebKitGTK can only be attached to X11 windows".to_string(),
+            ))
+        }
+    };
+    unsafe {
+        let plug = gtk_plug_new(window);
+        g_signal_connect_data(
+            plug,
+            b"remove\0".as_ptr() as *const c_char,
+            gtk_widget_destroy as *const c_void,
+            plug,
+            null(),
+            G_CONNECT_AFTER_SWAPPED,
+        );
+        gtk_widget_show(plug);
+        Ok(plug)
+    }
+}
+
+struct PanelState {
+    fixed: *mut c_void,
+    content: *mut c_void,
+    layout: Option<Layout>,
+}
+
+impl PanelState {
+    fn apply(&mut self, bounds: Rect) {
+        if self.fixed.is_null() {
+            return;
+        }
+        unsafe {
+            gtk_fixed_move(self.fixed, self.content, bounds.x, bounds.y);
+            gtk_widget_set_size_request(self.content, bounds.width, bounds.height);
+        }
+    }
+}
+
+extern "C" fn on_size_allocate(
+    _widget: *mut c_void,
+    allocation: *const GtkAllocation,
+    data: *mut c_void,
+) {
+    let state = unsafe { &mut *(data as *mut PanelState) };
+    let allocation = unsafe { &*allocation };
+    if let Some(layout) = state.layout.as_mut() {
+        let bounds = layout(allocation.width, allocation.height);
+        state.apply(bounds);
+    }
+}
+
+extern "C" fn on_scale_factor(widget: *mut c_void, _pspec: *mut c_void, _data: *mut c_void) {
+    // Sizes are in logical pixels, a new allocation is all that is needed to follow the change.
+    unsafe { gtk_widget_queue_resize(widget) }
+}
+
+extern "C" fn on_destroy(_widget: *mut c_void, data: *mut c_void) {
+    let state = unsafe { &mut *(data as *mut PanelState) };
+    state.fixed = null_mut();
+    state.content = null_mut();
+}
+
+/// The web view moved out of its window into a `GtkFixed` added to a host container.
+pub(crate) struct Panel(*mut PanelState);
+
+impl Panel {
+    pub(crate) fn new(webview: sys::webview_t, parent: *mut c_void) -> Panel {
+        unsafe {
+            let window = sys::webview_get_window(webview);
+            let content = gtk_bin_get_child(window);
+            let fixed = gtk_fixed_new();
+            let state = Box::into_raw(Box::new(PanelState {
+                fixed,
+                content,
+                layout: None,
+            }));
+            g_signal_connect_data(
+                fixed,
+                b"size-allocate\0".as_ptr() as *const c_char,
+                on_size_allocate as *const c_void,
+                state as *mut c_void,
+                null(),
+                0,
+            );
+            g_signal_connect_data(
+                fixed,
+                b"notify::scale-factor\0".as_ptr() as *const c_char,
+                on_scale_factor as *const c_void,
+                null_mut(),
+                null(),
+                0,
+            );
+            g_signal_connect_data(
+                fixed,
+                b"destroy\0".as_ptr() as *const c_char,
+                on_destroy as *const c_void,
+                state as *mut c_void,
+                null(),
+                0,
+            );
+
+            g_object_ref(content);
+            gtk_container_remove(window, content);
+            gtk_fixed_put(fixed, content, 0, 0);
+            g_object_unref(content);
+            add_panel(parent, fixed);
+            gtk_widget_show_all(fixed);
+            gtk_widget_hide(window);
+            Panel(state)
+        }
+    }
+
+    pub(crate) fn reparent(&mut self, parent: *mut c_void) {
+        let fixed = unsafe { (*self.0).fixed };
+        if fixed.is_null() {
+            return;
+        }
+        unsafe {
+            g_object_ref(fixed);
+            gtk_container_remove(gtk_widget_get_parent(fixed), fixed);
+            add_panel(parent, fixed);
+            g_object_unref(fixed);
+        }
+    }
+
+    pub(crate) fn set_bounds(&mut self, bounds: Rect) {
+        unsafe { (*self.0).apply(bounds) }
+    }
+
+    pub(crate) fn set_layout(&mut self, layout: Layout) {
+        unsafe {
+            (*self.0).layout = Some(layout);
+            if !(*self.0).fixed.is_null() {
+                gtk_widget_queue_resize((*self.0).fixed);
+            }
+        }
+    }
+}
+
+impl Drop for Panel {
+    fn drop(&mut self) {
+        unsafe {
+            let state = Box::from_raw(self.0);
+            if !state.fixed.is_null() {
+                gtk_widget_destroy(state.fixed);
+            }
+        }
+    }
+}
+
+pub(crate) const BACKEND: Backend = Backend::WebKitGtk;
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    bind: true,
+    scripts: true,
+    window_handle: true,
+    reparent: true,
+    external_loop: true,
+    transparency: false,
+    offscreen: false,
+};
+
+/// The engine can't render offscreen, there is never one, see `CAPABILITIES`.
+pub(crate) enum Offscreen {}
+
+impl Offscreen {
+    pub(crate) fn new(_webview: sys::webview_t, _callback: PaintCallback) -> Option<Offscreen> {
+        None
+    }
+}
+
+extern "C" {
+    fn webkit_web_view_get_settings(web_view: *mut c_void) -> *mut c_void;
+    fn webkit_settings_set_hardware_acceleration_policy(settings: *mut c_void, policy: c_int);
+    fn webkit_settings_set_enable_developer_extras(settings: *mut c_void, enabled: c_int);
+    fn webkit_settings_set_user_agent(settings: *mut c_void, user_agent: *const c_char);
+    fn webkit_settings_set_enable_smooth_scrolling(settings: *mut c_void, enabled: c_int);
+    fn webkit_web_view_set_input_method_context(web_view: *mut c_void, context: *mut c_void);
+}
+
+pub(crate) fn disable_hardware_acceleration(webview: sys::webview_t) -> bool {
+    unsafe {
+        let view = gtk_bin_get_child(sys::webview_get_window(webview));
+        let settings = webkit_web_view_get_settings(view);
+        // WEBKIT_HARDWARE_ACCELERATION_POLICY_NEVER
+        webkit_settings_set_hardware_acceleration_policy(settings, 2);
+    }
+    true
+}
+
+/// WebKitGTK throttles hidden pages and has no setting for it.
+pub(crate) fn set_background_throttling(
+    _webview: sys::webview_t,
+    throttling: BackgroundThrottling,
+) -> bool {
+    throttling == BackgroundThrottling::Throttle
+}
+
+extern "C" {
+    fn webkit_web_context_register_uri_scheme(
+        context: *mut c_void,
+        scheme: *const c_char,
+        callback: *const c_void,
+        user_data: *mut c_void,
+        user_data_destroy_func: *const c_void,
+    );
+    fn webkit_web_context_get_security_manager(context: *mut c_void) -> *mut c_void;
+    fn webkit_security_manager_register_uri_scheme_as_secure(
+        security_manager: *mut c_void,
+        scheme: *const c_char,
+    );
+    fn webkit_security_manager_register_uri_scheme_as_cors_enabled(
+        security_manager: *mut c_void,
+        scheme: *const c_char,
+    );
+    fn webkit_uri_scheme_request_get_uri(request: *mut c_void) -> *const c_char;
+    fn webkit_uri_scheme_request_finish(
+        request: *mut c_void,
+        stream: *mut c_void,
+        stream_length: i64,
+        content_type: *const c_char,
+    );
+    fn webkit_uri_scheme_request_finish_error(request: *mut c_void, error: *mut c_void);
+    fn g_bytes_new(data: *const c_void, size: usize) -> *mut c_void;
+    fn g_bytes_unref(bytes: *mut c_void);
+    fn g_memory_input_stream_new_from_bytes(bytes: *mut c_void) -> *mut c_void;
+    fn g_quark_from_static_string(string: *const c_char) -> u32;
+    fn g_error_new_literal(domain: u32, code: c_int, message: *const c_char) -> *mut c_void;
+}
+
+extern "C" fn on_scheme_request(request: *mut c_void, _data: *mut c_void) {
+    unsafe {
+        let url = CStr::from_ptr(webkit_uri_scheme_request_get_uri(request)).to_string_lossy();
+        match host::load(&url) {
+            Some((bytes, mime_type)) => {
+                let data = g_bytes_new(bytes.as_ptr() as *const c_void, bytes.len());
+                let stream = g_memory_input_stream_new_from_bytes(data);
+                let mime_type = cstring::text(mime_type);
+                webkit_uri_scheme_request_finish(
+                    request,
+                    stream,
+                    bytes.len() as i64,
+                    mime_type.as_ptr(),
+                );
+                g_object_unref(stream);
+                g_bytes_unref(data);
+            }
+            None => {
+                let error = g_error_new_literal(
+                    g_quark_from_static_string(b"webview-host\0".as_ptr() as *const c_char),
+                    404,
+                    b"Not found\0".as_ptr() as *const c_char,
+                );
+                webkit_uri_scheme_request_finish_error(request, error);
+                g_error_free(error);
+            }
+        }
+    }
+}
+
+/// Serves mapped hosts with a scheme handler on the web context, registered once for each
+/// context. The scheme is registered as secure, so pages get a secure context, and as CORS
+/// enabled for `fetch`.
+pub(crate) fn map_host(webview: sys::webview_t, host: &str, folder: &Path) -> bool {
+    static CONTEXTS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+    host::map(host, folder);
+    unsafe {
+        let view = gtk_bin_get_child(sys::webview_get_window(webview));
+        let context = webkit_web_view_get_context(view);
+        let mut contexts = CONTEXTS.lock().unwrap();
+        if !contexts.contains(&(context as usize)) {
+            contexts.push(context as usize);
+            let scheme = cstring::text(host::SCHEME);
+            webkit_web_context_register_uri_scheme(
+                context,
+                scheme.as_ptr(),
+                on_scheme_request as *const c_void,
+                null_mut(),
+                null(),
+            );
+            let security = webkit_web_context_get_security_manager(context);
+            webkit_security_manager_register_uri_scheme_as_secure(security, scheme.as_ptr());
+            webkit_security_manager_register_uri_scheme_as_cors_enabled(security, scheme.as_ptr());
+        }
+    }
+    true
+}
+
+/// Reads `/proc` for the resident memory of this process and of the WebKit processes it
+/// started, its web, network and GPU processes.
+#[cfg(target_os = "linux")]
+pub(crate) fn process_memory() -> ProcessMemory {
+    let resident = |pid: u32| -> Option<u64> {
+        let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+        let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+        let kib: u64 = line["VmRSS:".len()..]
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()?;
+        Some(kib * 1024)
+    };
+    let processes: Vec<(u32, u32, bool)> = fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let stat = fs::read_to_string(entry.path().join("stat")).ok()?;
+            // The name in parentheses may hold anything, the parent follows the state after it.
+            let (name, rest) = stat.split_at(stat.rfind(')')?);
+            let parent = rest[1..].split_whitespace().nth(1)?.parse().ok()?;
+            let is_engine = name.contains("(WebKit");
+            Some((pid, parent, is_engine))
+        })
+        .collect();
+    let mut descendants = vec![std::process::id()];
+    let mut i = 0;
+    while i < descendants.len() {
+        let parent = descendants[i];
+        descendants.extend(
+            processes
+                .iter()
+                .filter(|&&(_, p, _)| p == parent)
+                .map(|&(pid, _, _)| pid),
+        );
+        i += 1;
+    }
+    let engine: Vec<u64> = processes
+        .iter()
+        .filter(|&&(pid, _, is_engine)| is_engine && descendants[1..].contains(&pid))
+        .filter_map(|&(pid, _, _)| resident(pid))
+        .collect();
+    ProcessMemory {
+        app: resident(std::process::id()),
+        engine: Some((engine.iter().sum(), engine.len())),
+    }
+}
+
+/// Only Linux has `/proc` in the format `process_memory` reads.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn process_memory() -> ProcessMemory {
+    ProcessMemory::default()
+}
+
+/// Applies `options` to the web view's settings, before `run` loads the first page.
+pub(crate) fn apply_webkit_settings(webview: sys::webview_t, options: &Options) {
+    unsafe {
+        let view = gtk_bin_get_child(sys::webview_get_window(webview));
+        let settings = webkit_web_view_get_settings(view);
+        if let Some(policy) = options.hardware_acceleration {
+            let policy = match policy {
+                HardwareAcceleration::OnDemand => 0,
+                HardwareAcceleration::Always => 1,
+                HardwareAcceleration::Never => 2,
+            };
+            webkit_settings_set_hardware_acceleration_policy(settings, policy);
+        }
+        if let Some(enabled) = options.developer_extras {
+            webkit_settings_set_enable_developer_extras(settings, enabled as c_int);
+        }
+        if let Some(user_agent) = &options.user_agent {
+            let c_user_agent = cstring::text(user_agent);
+            webkit_settings_set_user_agent(settings, c_user_agent.as_ptr());
+        }
+        if let Some(enabled) = options.smooth_scrolling {
+            webkit_settings_set_enable_smooth_scrolling(settings, enabled as c_int);
+        }
+        // The view starts out with the platform's context, without one it uses none.
+        if options.input_method == Some(false) {
+            webkit_web_view_set_input_method_context(view, null_mut());
+        }
+    }
+}
+
+const WEBKIT_CACHE_MODEL_DOCUMENT_VIEWER: c_int = 0;
+
+extern "C" {
+    fn webkit_web_view_get_context(web_view: *mut c_void) -> *mut c_void;
+    fn webkit_web_context_set_cache_model(context: *mut c_void, cache_model: c_int);
+}
+
+/// Applies the builder's cache settings, returns the ones WebKitGTK doesn't support.
+///
+/// The cache belongs to the web context, which all webviews of the process share.
+pub(crate) fn apply_cache(webview: sys::webview_t, cache: &Cache) -> Vec<&'static str> {
+    let mut unsupported = Vec::new();
+    if cache.dir.is_some() {
+        unsupported.push("WebviewBuilder::cache_dir");
+    }
+    if cache.max_size.is_some() {
+        unsupported.push("WebviewBuilder::max_cache_size");
+    }
+    if cache.disabled {
+        unsafe {
+            let view = gtk_bin_get_child(sys::webview_get_window(webview));
+            webkit_web_context_set_cache_model(
+                webkit_web_view_get_context(view),
+                WEBKIT_CACHE_MODEL_DOCUMENT_VIEWER,
+            );
+        }
+    }
+    unsupported
+}
+
+extern "C" {
+    fn webkit_web_view_go_back(web_view: *mut c_void);
+    fn webkit_web_view_go_forward(web_view: *mut c_void);
+    fn webkit_web_view_reload(web_view: *mut c_void);
+    fn webkit_web_view_reload_bypass_cache(web_view: *mut c_void);
+    fn webkit_web_view_stop_loading(web_view: *mut c_void);
+    fn webkit_web_view_can_go_back(web_view: *mut c_void) -> c_int;
+    fn webkit_web_view_can_go_forward(web_view: *mut c_void) -> c_int;
+    fn webkit_web_view_get_uri(web_view: *mut c_void) -> *const c_char;
+    fn webkit_web_view_load_request(web_view: *mut c_void, request: *mut c_void);
+    fn webkit_uri_request_new(uri: *const c_char) -> *mut c_void;
+    fn webkit_uri_request_get_http_headers(request: *mut c_void) -> *mut c_void;
+    fn webkit_web_view_set_background_color(web_view: *mut c_void, rgba: *const GdkRGBA);
+    fn soup_message_headers_append(headers: *mut c_void, name: *const c_char, value: *const c_char);
+}
+
+#[repr(C)]
+struct GdkRGBA {
+    red: f64,
+    green: f64,
+    blue: f64,
+    alpha: f64,
+}
+
+/// The `WebKitWebView`, which stays the same view when it is reparented.
+pub(crate) struct View(*mut c_void);
+
+impl View {
+    pub(crate) fn new(webview: sys::webview_t) -> View {
+        View(unsafe { gtk_bin_get_child(sys::webview_get_window(webview)) })
+    }
+
+    pub(crate) fn go(&self, action: HistoryAction) -> bool {
+        unsafe {
+            match action {
+                HistoryAction::Back => webkit_web_view_go_back(self.0),
+                HistoryAction::Forward => webkit_web_view_go_forward(self.0),
+                HistoryAction::Reload => webkit_web_view_reload(self.0),
+                HistoryAction::ReloadIgnoringCache => webkit_web_view_reload_bypass_cache(self.0),
+                HistoryAction::Stop => webkit_web_view_stop_loading(self.0),
+            }
+        }
+        true
+    }
+
+    /// Loads `request`, returns `false` if part of it had to be left out.
+    ///
+    /// WebKitGTK only gets pages, requests with a body are not loaded.
+    pub(crate) fn load(&self, request: &Request) -> bool {
+        if request.body.is_some() {
+            return false;
+        }
+        let c_url = cstring::url(&request.url);
+        unsafe {
+            let uri_request = webkit_uri_request_new(c_url.as_ptr());
+            // Only HTTP requests have headers.
+            let headers = webkit_uri_request_get_http_headers(uri_request);
+            if !headers.is_null() {
+                for (name, value) in &request.headers {
+                    let name = cstring::text(name);
+                    let value = cstring::text(value);
+                    soup_message_headers_append(headers, name.as_ptr(), value.as_ptr());
+                }
+            }
+            webkit_web_view_load_request(self.0, uri_request);
+            g_object_unref(uri_request);
+        }
+        true
+    }
+
+    /// Raises the window the view is in and puts the keyboard focus on the view.
+    pub(crate) fn focus(&self) {
+        unsafe {
+            gtk_window_present(gtk_widget_get_toplevel(self.0));
+            gtk_widget_grab_focus(self.0);
+        }
+    }
+
+    pub(crate) fn is_focused(&self) -> bool {
+        unsafe { gtk_window_is_active(gtk_widget_get_toplevel(self.0)) != 0 }
+    }
+
+    pub(crate) fn set_background_color(&self, color: Color) -> bool {
+        let channel = |value: u8| value as f64 / 255.0;
+        let rgba = GdkRGBA {
+            red: channel(color.red),
+            green: channel(color.green),
+            blue: channel(color.blue),
+            alpha: channel(color.alpha),
+        };
+        unsafe { webkit_web_view_set_background_color(self.0, &rgba) };
+        true
+    }
+
+    /// Sets GTK's dark theme preference, which WebKitGTK follows. It applies to every window of
+    /// the process.
+    pub(crate) fn set_theme(&self, theme: Theme) -> bool {
+        let dark = match theme {
+            Theme::Dark => true,
+            Theme::System => self.system_theme() == Theme::Dark,
+            _ => false,
+        };
+        unsafe {
+            g_object_set(
+                gtk_settings_get_default(),
+                b"gtk-application-prefer-dark-theme\0".as_ptr() as *const c_char,
+                dark as c_int,
+                null::<c_void>(),
+            );
+        }
+        true
+    }
+
+    /// The color scheme of the desktop portal's appearance settings, or failing that whether the
+    /// GTK theme is a dark one.
+    pub(crate) fn system_theme(&self) -> Theme {
+        unsafe {
+            let connection = g_bus_get_sync(G_BUS_TYPE_SESSION, null_mut(), null_mut());
+            if !connection.is_null() {
+                let reply = g_dbus_connection_call_sync(
+                    connection,
+                    PORTAL.as_ptr() as *const c_char,
+                    PORTAL_PATH.as_ptr() as *const c_char,
+                    PORTAL_SETTINGS.as_ptr() as *const c_char,
+                    b"Read\0".as_ptr() as *const c_char,
+                    g_variant_new(
+                        b"(ss)\0".as_ptr() as *const c_char,
+                        APPEARANCE.as_ptr() as *const c_char,
+                        COLOR_SCHEME.as_ptr() as *const c_char,
+                    ),
+                    null(),
+                    G_DBUS_CALL_FLAGS_NONE,
+                    -1,
+                    null_mut(),
+                    null_mut(),
+                );
+                g_object_unref(connection);
+                if !reply.is_null() {
+                    let value = g_variant_get_child_value(reply, 0);
+                    let scheme = color_scheme(value);
+                    g_variant_unref(value);
+                    g_variant_unref(reply);
+                    if let Some(theme) = scheme {
+                        return theme;
+                    }
+                }
+            }
+            let mut name: *mut c_char = null_mut();
+            g_object_get(
+                gtk_settings_get_default(),
+                b"gtk-theme-name\0".as_ptr() as *const c_char,
+                &mut name,
+                null::<c_void>(),
+            );
+            if name.is_null() {
+                return Theme::Light;
+            }
+            let dark = CStr::from_ptr(name)
+                .to_string_lossy()
+                .to_lowercase()
+                .contains("dark");
+            g_free(name as *mut c_void);
+            if dark {
+                Theme::Dark
+            } else {
+                Theme::Light
+            }
+        }
+    }
+
+    pub(crate) fn can_go_back(&self) -> bool {
+        unsafe { webkit_web_view_can_go_back(self.0) != 0 }
+    }
+
+    pub(crate) fn can_go_forward(&self) -> bool {
+        unsafe { webkit_web_view_can_go_forward(self.0) != 0 }
+    }
+
+    /// The url of the page the view shows, as WebKit has it rather than the page.
+    pub(crate) fn url(&self) -> Option<String> {
+        unsafe {
+            let uri = webkit_web_view_get_uri(self.0);
+            if uri.is_null() {
+                return None;
+            }
+            Some(CStr::from_ptr(uri).to_string_lossy().into_owned())
+        }
+    }
+}
+
+extern "C" {
+    fn g_app_info_launch_default_for_uri(
+        uri: *const c_char,
+        context: *mut c_void,
+        error: *mut *mut c_void,
+    ) -> c_int;
+}
+
+/// Opens `url` with the user's default handler, e.g. their browser.
+pub(crate) fn open_external(_webview: sys::webview_t, url: &str) -> bool {
+    let c_url = match CString::new(url) {
+        Ok(c_url) => c_url,
+        Err(_) => return false,
+    };
+    unsafe {
+        let mut error = null_mut();
+        let launched = g_app_info_launch_default_for_uri(c_url.as_ptr(), null_mut(), &mut error);
+        if launched == 0 {
+            g_error_free(error);
+        }
+        launched != 0
+    }
+}
+
+const G_BUS_TYPE_SYSTEM: c_int = 1;
+const G_DBUS_CALL_FLAGS_NONE: c_int = 0;
+const G_DBUS_SIGNAL_FLAGS_NONE: c_int = 0;
+const LOGIN1: &[u8] = b"org.freedesktop.login1\0";
+
+type SignalCallback = extern "C" fn(
+    *mut c_void,
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *mut c_void,
+    *mut c_void,
+);
+
+extern "C" {
+    fn g_bus_get_sync(
+        bus_type: c_int,
+        cancellable: *mut c_void,
+        error: *mut *mut c_void,
+    ) -> *mut c_void;
+    fn g_dbus_connection_call_sync(
+        connection: *mut c_void,
+        bus_name: *const c_char,
+        object_path: *const c_char,
+        interface_name: *const c_char,
+        method_name: *const c_char,
+        parameters: *mut c_void,
+        reply_type: *const c_void,
+        flags: c_int,
+        timeout_msec: c_int,
+        cancellable: *mut c_void,
+        error: *mut *mut c_void,
+    ) -> *mut c_void;
+    fn g_dbus_connection_signal_subscribe(
+        connection: *mut c_void,
+        sender: *const c_char,
+        interface_name: *const c_char,
+        member: *const c_char,
+        object_path: *const c_char,
+        arg0: *const c_char,
+        flags: c_int,
+        callback: SignalCallback,
+        user_data: *mut c_void,
+        user_data_free_func: *const c_void,
+    ) -> c_uint;
+    fn g_dbus_connection_signal_unsubscribe(connection: *mut c_void, subscription_id: c_uint);
+    fn g_variant_new(format_string: *const c_char, ...) -> *mut c_void;
+    fn g_variant_get(value: *mut c_void, format_string: *const c_char, ...);
+    fn g_variant_get_child_value(value: *mut c_void, index: usize) -> *mut c_void;
+    fn g_variant_lookup(
+        dictionary: *mut c_void,
+        key: *const c_char,
+        format_string: *const c_char,
+        ...
+    ) -> c_int;
+    fn g_variant_unref(value: *mut c_void);
+    fn g_variant_ref(value: *mut c_void) -> *mut c_void;
+    fn g_error_free(error: *mut c_void);
+}
+
+const G_BUS_TYPE_SESSION: c_int = 2;
+const PORTAL: &[u8] = b"org.freedesktop.portal.Desktop\0";
+const PORTAL_PATH: &[u8] = b"/org/freedesktop/portal/desktop\0";
+const PORTAL_SETTINGS: &[u8] = b"org.freedesktop.portal.Settings\0";
+const APPEARANCE: &[u8] = b"org.freedesktop.appearance\0";
+const COLOR_SCHEME: &[u8] = b"color-scheme\0";
+
+extern "C" {
+    fn gtk_settings_get_default() -> *mut c_void;
+    fn g_object_set(object: *mut c_void, first_property_name: *const c_char, ...);
+    fn g_object_get(object: *mut c_void, first_property_name: *const c_char, ...);
+    fn g_free(mem: *mut c_void);
+    fn g_variant_is_of_type(value: *mut c_void, type_: *const c_char) -> c_int;
+    fn g_variant_get_variant(value: *mut c_void) -> *mut c_void;
+    fn g_variant_get_uint32(value: *mut c_void) -> u32;
+}
+
+/// The theme for a `color-scheme` setting, which older portals wrap in an extra variant.
+unsafe fn color_scheme(value: *mut c_void) -> Option<Theme> {
+    let mut value = g_variant_ref(value);
+    while g_variant_is_of_type(value, b"v\0".as_ptr() as *const c_char) != 0 {
+        let inner = g_variant_get_variant(value);
+        g_variant_unref(value);
+        value = inner;
+    }
+    let scheme = if g_variant_is_of_type(value, b"u\0".as_ptr() as *const c_char) != 0 {
+        g_variant_get_uint32(value)
+    } else {
+        0
+    };
+    g_variant_unref(value);
+    match scheme {
+        1 => Some(Theme::Dark),
+        2 => Some(Theme::Light),
+        _ => None,
+    }
+}
+
+extern "C" fn on_setting_changed(
+    _connection: *mut c_void,
+    _sender: *const c_char,
+    _path: *const c_char,
+    _interface: *const c_char,
+    _signal: *const c_char,
+    parameters: *mut c_void,
+    data: *mut c_void,
+) {
+    unsafe {
+        let key = g_variant_get_child_value(parameters, 1);
+        let mut name: *const c_char = null();
+        g_variant_get(key, b"&s\0".as_ptr() as *const c_char, &mut name);
+        let is_color_scheme = CStr::from_ptr(name).to_bytes_with_nul() == COLOR_SCHEME;
+        g_variant_unref(key);
+        if !is_color_scheme {
+            return;
+        }
+        let value = g_variant_get_child_value(parameters, 2);
+        let theme = color_scheme(value).unwrap_or(Theme::Light);
+        g_variant_unref(value);
+        (*(data as *mut ThemeCallback))(theme);
+    }
+}
+
+/// Follows the desktop portal's `color-scheme` setting on the session bus.
+pub(crate) struct ThemeWatch {
+    connection: *mut c_void,
+    subscription: c_uint,
+    callback: *mut ThemeCallback,
+}
+
+impl ThemeWatch {
+    pub(crate) fn new(_webview: sys::webview_t, callback: ThemeCallback) -> ThemeWatch {
+        let callback = Box::into_raw(Box::new(callback));
+        unsafe {
+            let connection = g_bus_get_sync(G_BUS_TYPE_SESSION, null_mut(), null_mut());
+            let subscription = if connection.is_null() {
+                0
+            } else {
+                g_dbus_connection_signal_subscribe(
+                    connection,
+                    PORTAL.as_ptr() as *const c_char,
+                    PORTAL_SETTINGS.as_ptr() as *const c_char,
+                    b"SettingChanged\0".as_ptr() as *const c_char,
+                    PORTAL_PATH.as_ptr() as *const c_char,
+                    APPEARANCE.as_ptr() as *const c_char,
+                    G_DBUS_SIGNAL_FLAGS_NONE,
+                    on_setting_changed,
+                    callback as *mut c_void,
+                    null(),
+                )
+            };
+            ThemeWatch {
+                connection,
+                subscription,
+                callback,
+            }
+        }
+    }
+}
+
+impl Drop for ThemeWatch {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.connection.is_null() {
+                g_dbus_connection_signal_unsubscribe(self.connection, self.subscription);
+                g_object_unref(self.connection);
+            }
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+extern "C" fn on_session_signal(
+    _connection: *mut c_void,
+    _sender: *const c_char,
+    _path: *const c_char,
+    _interface: *const c_char,
+    signal: *const c_char,
+    parameters: *mut c_void,
+    data: *mut c_void,
+) {
+    unsafe {
+        let event = match CStr::from_ptr(signal).to_bytes() {
+            b"Lock" => SessionEvent::Locked,
+            b"Unlock" => SessionEvent::Unlocked,
+            b"PropertiesChanged" => {
+                let changed = g_variant_get_child_value(parameters, 1);
+                let mut active: c_int = 0;
+                let found = g_variant_lookup(
+                    changed,
+                    b"Active\0".as_ptr() as *const c_char,
+                    b"b\0".as_ptr() as *const c_char,
+                    &mut active,
+                );
+                g_variant_unref(changed);
+                match (found != 0, active != 0) {
+                    (false, _) => return,
+                    (true, true) => SessionEvent::Attached,
+                    (true, false) => SessionEvent::Detached,
+                }
+            }
+            _ => return,
+        };
+        (*(data as *mut SessionCallback))(event);
+    }
+}
+
+/// Asks logind for the process' session, `None` when the system bus or logind isn't there.
+unsafe fn session_path(connection: *mut c_void) -> Option<CString> {
+    let mut error = null_mut();
+    let reply = g_dbus_connection_call_sync(
+        connection,
+        LOGIN1.as_ptr() as *const c_char,
+        b"/org/freedesktop/login1\0".as_ptr() as *const c_char,
+        b"org.freedesktop.login1.Manager\0".as_ptr() as *const c_char,
+        b"GetSessionByPID\0".as_ptr() as *const c_char,
+        g_variant_new(b"(u)\0".as_ptr() as *const c_char, std::process::id()),
+        null(),
+        G_DBUS_CALL_FLAGS_NONE,
+        -1,
+        null_mut(),
+        &mut error,
+    );
+    if reply.is_null() {
+        g_error_free(error);
+        return None;
+    }
+    let mut path: *const c_char = null();
+    g_variant_get(reply, b"(&o)\0".as_ptr() as *const c_char, &mut path);
+    let path = CStr::from_ptr(path).to_owned();
+    g_variant_unref(reply);
+    Some(path)
+}
+
+/// Follows the logind session of the process on the system bus, it does nothing where logind
+/// doesn't run.
+pub(crate) struct SessionWatch {
+    connection: *mut c_void,
+    subscription: c_uint,
+    callback: *mut SessionCallback,
+}
+
+impl SessionWatch {
+    pub(crate) fn new(_webview: sys::webview_t, callback: SessionCallback) -> SessionWatch {
+        let callback = Box::into_raw(Box::new(callback));
+        let mut watch = SessionWatch {
+            connection: null_mut(),
+            subscription: 0,
+            callback,
+        };
+        unsafe {
+            let mut error = null_mut();
+            let connection = g_bus_get_sync(G_BUS_TYPE_SYSTEM, null_mut(), &mut error);
+            if connection.is_null() {
+                g_error_free(error);
+                return watch;
+            }
+            let path = match session_path(connection) {
+                Some(path) => path,
+                None => {
+                    g_object_unref(connection);
+                    return watch;
+                }
+            };
+            watch.subscription = g_dbus_connection_signal_subscribe(
+                connection,
+                LOGIN1.as_ptr() as *const c_char,
+                null(),
+                null(),
+                path.as_ptr(),
+                null(),
+                G_DBUS_SIGNAL_FLAGS_NONE,
+                on_session_signal,
+                callback as *mut c_void,
+                null(),
+            );
+            watch.connection = connection;
+        }
+        watch
+    }
+}
+
+impl Drop for SessionWatch {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.connection.is_null() {
+                g_dbus_connection_signal_unsubscribe(self.connection, self.subscription);
+                g_object_unref(self.connection);
+            }
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+extern "C" {
+    fn gdk_pixbuf_loader_new() -> *mut c_void;
+    fn gdk_pixbuf_loader_write(
+        loader: *mut c_void,
+        buf: *const u8,
+        count: usize,
+        error: *mut *mut c_void,
+    ) -> c_int;
+    fn gdk_pixbuf_loader_close(loader: *mut c_void, error: *mut *mut c_void) -> c_int;
+    fn gdk_pixbuf_loader_get_pixbuf(loader: *mut c_void) -> *mut c_void;
+    fn gtk_window_set_icon(window: *mut c_void, icon: *mut c_void);
+}
+
+/// Decodes `image` with gdk-pixbuf and makes it the window's icon.
+pub(crate) fn set_window_icon(webview: sys::webview_t, image: &[u8]) -> bool {
+    unsafe {
+        let loader = gdk_pixbuf_loader_new();
+        let mut error = null_mut();
+        let written = gdk_pixbuf_loader_write(loader, image.as_ptr(), image.len(), &mut error);
+        if written == 0 {
+            g_error_free(error);
+            error = null_mut();
+        }
+        // The loader has to be closed even after a failed write.
+        let closed = gdk_pixbuf_loader_close(loader, &mut error);
+        if closed == 0 {
+            g_error_free(error);
+        }
+        let pixbuf = if written != 0 && closed != 0 {
+            gdk_pixbuf_loader_get_pixbuf(loader)
+        } else {
+            null_mut()
+        };
+        if !pixbuf.is_null() {
+            gtk_window_set_icon(sys::webview_get_window(webview), pixbuf);
+        }
+        g_object_unref(loader);
+        !pixbuf.is_null()
+    }
+}
+
+extern "C" {
+    fn gdk_display_get_default() -> *mut c_void;
+    fn gdk_display_get_default_seat(display: *mut c_void) -> *mut c_void;
+    fn gdk_seat_get_pointer(seat: *mut c_void) -> *mut c_void;
+    fn gdk_device_get_position(
+        device: *mut c_void,
+        screen: *mut *mut c_void,
+        x: *mut c_int,
+        y: *mut c_int,
+    );
+    fn gtk_window_begin_move_drag(
+        window: *mut c_void,
+        button: c_int,
+        root_x: c_int,
+        root_y: c_int,
+        timestamp: u32,
+    );
+}
+
+/// Lets the window manager move the window with the pointer while the primary button is held.
+pub(crate) fn start_window_drag(webview: sys::webview_t) -> bool {
+    unsafe {
+        let display = gdk_display_get_default();
+        if display.is_null() {
+            return false;
+        }
+        let pointer = gdk_seat_get_pointer(gdk_display_get_default_seat(display));
+        let (mut x, mut y) = (0, 0);
+        gdk_device_get_position(pointer, null_mut(), &mut x, &mut y);
+        gtk_window_begin_move_drag(sys::webview_get_window(webview), 1, x, y, 0);
+        true
+    }
+}
+
+const GDK_ACTION_COPY: c_int = 1 << 1;
+
+extern "C" {
+    fn gtk_target_list_new(targets: *const c_void, ntargets: c_uint) -> *mut c_void;
+    fn gtk_target_list_add_uri_targets(list: *mut c_void, info: c_uint);
+    fn gtk_target_list_add_text_targets(list: *mut c_void, info: c_uint);
+    fn gtk_target_list_unref(list: *mut c_void);
+    fn gtk_drag_begin_with_coordinates(
+        widget: *mut c_void,
+        targets: *mut c_void,
+        actions: c_int,
+        button: c_int,
+        event: *mut c_void,
+        x: c_int,
+        y: c_int,
+    ) -> *mut c_void;
+    fn gtk_selection_data_set_uris(selection_data: *mut c_void, uris: *mut *mut c_char) -> c_int;
+    fn gtk_selection_data_set_text(
+        selection_data: *mut c_void,
+        text: *const c_char,
+        len: c_int,
+    ) -> c_int;
+    fn g_filename_to_uri(
+        filename: *const c_char,
+        hostname: *const c_char,
+        error: *mut *mut c_void,
+    ) -> *mut c_char;
+}
+
+/// A drag `start_drag` started, until it ends.
+struct DragSource {
+    view: *mut c_void,
+    context: *mut c_void,
+    handlers: [c_ulong; 2],
+    uris: Vec<CString>,
+    text: Option<CString>,
+}
+
+extern "C" fn on_drag_data_get(
+    _widget: *mut c_void,
+    context: *mut c_void,
+    data: *mut c_void,
+    _info: c_uint,
+    _time: c_uint,
+    source: *mut c_void,
+) {
+    let source = unsafe { &*(source as *const DragSource) };
+    // The web view's own drags go through the same signal.
+    if context != source.context {
+        return;
+    }
+    unsafe {
+        match &source.text {
+            Some(text) => {
+                gtk_selection_data_set_text(data, text.as_ptr(), -1);
+            }
+            None => {
+                let mut uris: Vec<*mut c_char> = source
+                    .uris
+                    .iter()
+                    .map(|uri| uri.as_ptr() as *mut c_char)
+                    .chain(iter::once(null_mut()))
+                    .collect();
+                gtk_selection_data_set_uris(data, uris.as_mut_ptr());
+            }
+        }
+    }
+}
+
+extern "C" fn on_drag_end(_widget: *mut c_void, context: *mut c_void, source: *mut c_void) {
+    let source = source as *mut DragSource;
+    if context != unsafe { (*source).context } {
+        return;
+    }
+    unsafe {
+        let source = Box::from_raw(source);
+        for &handler in &source.handlers {
+            g_signal_handler_disconnect(source.view, handler);
+        }
+    }
+}
+
+/// The `file://` uri of `path`.
+fn file_uri(path: &Path) -> Option<CString> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    unsafe {
+        let uri = g_filename_to_uri(path.as_ptr(), null(), null_mut());
+        if uri.is_null() {
+            return None;
+        }
+        let owned = CStr::from_ptr(uri).to_owned();
+        g_free(uri as *mut c_void);
+        Some(owned)
+    }
+}
+
+/// Drags `item` out of the web view with the primary button, from where the pointer is.
+pub(crate) fn start_drag(view: &View, item: &DragItem) -> bool {
+    let (uris, text) = match item {
+        DragItem::Files(paths) => (
+            paths.iter().filter_map(|path| file_uri(path)).collect(),
+            None,
+        ),
+        DragItem::Text(text) => match CString::new(text.as_str()) {
+            Ok(text) => (Vec::new(), Some(text)),
+            Err(_) => return false,
+        },
+    };
+    unsafe {
+        let targets = gtk_target_list_new(null(), 0);
+        if text.is_some() {
+            gtk_target_list_add_text_targets(targets, 0);
+        } else {
+            gtk_target_list_add_uri_targets(targets, 0);
+        }
+        let source = Box::into_raw(Box::new(DragSource {
+            view: view.0,
+            context: null_mut(),
+            handlers: [0; 2],
+            uris,
+            text,
+        }));
+        (*source).handlers = [
+            g_signal_connect_data(
+                view.0,
+                b"drag-data-get\0".as_ptr() as *const c_char,
+                on_drag_data_get as *const c_void,
+                source as *mut c_void,
+                null(),
+                0,
+            ),
+            g_signal_connect_data(
+                view.0,
+                b"drag-end\0".as_ptr() as *const c_char,
+                on_drag_end as *const c_void,
+                source as *mut c_void,
+                null(),
+                0,
+            ),
+        ];
+        let context = gtk_drag_begin_with_coordinates(
+            view.0,
+            targets,
+            GDK_ACTION_COPY,
+            1,
+            null_mut(),
+            -1,
+            -1,
+        );
+        gtk_target_list_unref(targets);
+        if context.is_null() {
+            let source = Box::from_raw(source);
+            for &handler in &source.handlers {
+                g_signal_handler_disconnect(source.view, handler);
+            }
+            return false;
+        }
+        (*source).context = context;
+        true
+    }
+}
+
+/// Blurring what is behind the window is up to the compositor, GTK 3 has no way to ask for it.
+pub(crate) fn set_window_effect(_webview: sys::webview_t, _effect: WindowEffect) -> bool {
+    false
+}
+
+const GDK_HINT_ASPECT: c_int = 1 << 4;
+
+#[repr(C)]
+#[derive(Default)]
+struct GdkGeometry {
+    min_width: c_int,
+    min_height: c_int,
+    max_width: c_int,
+    max_height: c_int,
+    base_width: c_int,
+    base_height: c_int,
+    width_inc: c_int,
+    height_inc: c_int,
+    min_aspect: f64,
+    max_aspect: f64,
+    win_gravity: c_int,
+}
+
+extern "C" {
+    fn gtk_window_set_geometry_hints(
+        window: *mut c_void,
+        geometry_widget: *mut c_void,
+        geometry: *const GdkGeometry,
+        geometry_mask: c_int,
+    );
+}
+
+/// An aspect hint for the window manager. GTK keeps one set of hints per window, so this
+/// replaces the size limits of `Webview::set_size` and the other way around.
+pub(crate) struct AspectRatio(*mut c_void);
+
+impl AspectRatio {
+    pub(crate) fn new(webview: sys::webview_t, width: u32, height: u32) -> AspectRatio {
+        let ratio = width as f64 / height as f64;
+        let geometry = GdkGeometry {
+            min_aspect: ratio,
+            max_aspect: ratio,
+            ..GdkGeometry::default()
+        };
+        unsafe {
+            let window = sys::webview_get_window(webview);
+            gtk_window_set_geometry_hints(window, null_mut(), &geometry, GDK_HINT_ASPECT);
+            AspectRatio(window)
+        }
+    }
+}
+
+impl Drop for AspectRatio {
+    fn drop(&mut self) {
+        unsafe { gtk_window_set_geometry_hints(self.0, null_mut(), &GdkGeometry::default(), 0) };
+    }
+}
+
+extern "C" {
+    fn g_signal_handler_disconnect(instance: *mut c_void, handler_id: c_ulong);
+    fn gtk_widget_get_toplevel(widget: *mut c_void) -> *mut c_void;
+    fn gtk_widget_grab_focus(widget: *mut c_void);
+    fn gtk_window_is_active(window: *mut c_void) -> c_int;
+    fn gtk_window_present(window: *mut c_void);
+}
+
+extern "C" fn on_is_active(window: *mut c_void, _pspec: *mut c_void, callback: *mut c_void) {
+    let callback = unsafe { &mut *(callback as *mut FocusCallback) };
+    callback(unsafe { gtk_window_is_active(window) } != 0);
+}
+
+/// Follows the window's `is-active` property, which GTK sets while it has the keyboard focus.
+pub(crate) struct FocusWatch {
+    window: *mut c_void,
+    handler: c_ulong,
+    callback: *mut FocusCallback,
+}
+
+impl FocusWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FocusCallback) -> FocusWatch {
+        let callback = Box::into_raw(Box::new(callback));
+        unsafe {
+            let window = sys::webview_get_window(webview);
+            let handler = g_signal_connect_data(
+                window,
+                b"notify::is-active\0".as_ptr() as *const c_char,
+                on_is_active as *const c_void,
+                callback as *mut c_void,
+                null(),
+                0,
+            );
+            FocusWatch {
+                window,
+                handler,
+                callback,
+            }
+        }
+    }
+}
+
+impl Drop for FocusWatch {
+    fn drop(&mut self) {
+        unsafe {
+            g_signal_handler_disconnect(self.window, self.handler);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+extern "C" {
+    fn gtk_selection_data_get_uris(selection_data: *mut c_void) -> *mut *mut c_char;
+    fn g_filename_from_uri(
+        uri: *const c_char,
+        hostname: *mut *mut c_char,
+        error: *mut *mut c_void,
+    ) -> *mut c_char;
+    fn g_strfreev(str_array: *mut *mut c_char);
+}
+
+struct FileDropState {
+    /// The files of the drag over the view, WebKit asks for them as soon as it enters.
+    paths: Vec<PathBuf>,
+    /// Where the files were dropped before they arrived.
+    pending: Option<(i32, i32)>,
+    callback: FileDropCallback,
+}
+
+/// The local files of a `text/uri-list` selection.
+unsafe fn selection_paths(data: *mut c_void) -> Vec<PathBuf> {
+    let uris = gtk_selection_data_get_uris(data);
+    if uris.is_null() {
+        return Vec::new();
+    }
+    let mut paths = Vec::new();
+    let mut uri = uris;
+    while !(*uri).is_null() {
+        let path = g_filename_from_uri(*uri, null_mut(), null_mut());
+        if !path.is_null() {
+            paths.push(PathBuf::from(
+                CStr::from_ptr(path).to_string_lossy().into_owned(),
+            ));
+            g_free(path as *mut c_void);
+        }
+        uri = uri.add(1);
+    }
+    g_strfreev(uris);
+    paths
+}
+
+extern "C" fn on_drag_data_received(
+    _widget: *mut c_void,
+    _context: *mut c_void,
+    _x: c_int,
+    _y: c_int,
+    data: *mut c_void,
+    _info: c_uint,
+    _time: c_uint,
+    state: *mut c_void,
+) {
+    let state = unsafe { &mut *(state as *mut FileDropState) };
+    let paths = unsafe { selection_paths(data) };
+    match state.pending.take() {
+        Some(position) if !paths.is_empty() => (state.callback)(paths, position),
+        Some(_) => {}
+        None => state.paths = paths,
+    }
+}
+
+extern "C" fn on_drag_drop(
+    _widget: *mut c_void,
+    _context: *mut c_void,
+    x: c_int,
+    y: c_int,
+    _time: c_uint,
+    state: *mut c_void,
+) -> c_int {
+    let state = unsafe { &mut *(state as *mut FileDropState) };
+    let paths = mem::take(&mut state.paths);
+    if paths.is_empty() {
+        state.pending = Some((x, y));
+    } else {
+        (state.callback)(paths, (x, y));
+    }
+    // WebKit still hands the drop to the page.
+    0
+}
+
+/// Follows the drags over the web view, reporting the files of those dropped on it.
+pub(crate) struct FileDropWatch {
+    view: *mut c_void,
+    handlers: [c_ulong; 2],
+    state: *mut FileDropState,
+}
+
+impl FileDropWatch {
+    pub(crate) fn new(view: &View, callback: FileDropCallback) -> Option<FileDropWatch> {
+        let state = Box::into_raw(Box::new(FileDropState {
+            paths: Vec::new(),
+            pending: None,
+            callback,
+        }));
+        unsafe {
+            let received = g_signal_connect_data(
+                view.0,
+                b"drag-data-received\0".as_ptr() as *const c_char,
+                on_drag_data_received as *const c_void,
+                state as *mut c_void,
+                null(),
+                0,
+            );
+            let dropped = g_signal_connect_data(
+                view.0,
+                b"drag-drop\0".as_ptr() as *const c_char,
+                on_drag_drop as *const c_void,
+                state as *mut c_void,
+                null(),
+                0,
+            );
+            Some(FileDropWatch {
+                view: view.0,
+                handlers: [received, dropped],
+                state,
+            })
+        }
+    }
+}
+
+impl Drop for FileDropWatch {
+    fn drop(&mut self) {
+        unsafe {
+            for &handler in &self.handlers {
+                g_signal_handler_disconnect(self.view, handler);
+            }
+            drop(Box::from_raw(self.state));
+        }
+    }
+}
+
+const GDK_SHIFT_MASK: c_uint = 1 << 0;
+const GDK_CONTROL_MASK: c_uint = 1 << 2;
+const GDK_MOD1_MASK: c_uint = 1 << 3;
+const GDK_SUPER_MASK: c_uint = 1 << 26;
+const GDK_META_MASK: c_uint = 1 << 28;
+
+#[repr(C)]
+struct GdkEventKey {
+    type_: c_int,
+    window: *mut c_void,
+    send_event: i8,
+    time: u32,
+    state: c_uint,
+    keyval: c_uint,
+}
+
+extern "C" {
+    fn gdk_keyval_to_unicode(keyval: c_uint) -> u32;
+    fn gdk_keyval_name(keyval: c_uint) -> *const c_char;
+}
+
+/// The DOM's name for `keyval`.
+unsafe fn key_name(keyval: c_uint) -> Option<String> {
+    if let Some(c) = char::from_u32(gdk_keyval_to_unicode(keyval)).filter(|c| !c.is_control()) {
+        return Some(c.to_string());
+    }
+    let name = gdk_keyval_name(keyval);
+    if name.is_null() {
+        return None;
+    }
+    let name = match CStr::from_ptr(name).to_str().ok()? {
+        "Return" | "KP_Enter" => "Enter",
+        "BackSpace" => "Backspace",
+        "ISO_Left_Tab" => "Tab",
+        "Page_Up" => "PageUp",
+        "Page_Down" => "PageDown",
+        "Left" => "ArrowLeft",
+        "Right" => "ArrowRight",
+        "Up" => "ArrowUp",
+        "Down" => "ArrowDown",
+        "Caps_Lock" => "CapsLock",
+        "Control_L" | "Control_R" => "Control",
+        "Shift_L" | "Shift_R" => "Shift",
+        "Alt_L" | "Alt_R" => "Alt",
+        "Super_L" | "Super_R" | "Meta_L" | "Meta_R" => "Meta",
+        // `Escape`, `Tab`, `Delete`, `Home`, `End`, `Insert` and `F1` to `F35` match already.
+        name => name,
+    };
+    Some(name.to_string())
+}
+
+extern "C" fn on_key_press(
+    _widget: *mut c_void,
+    event: *const GdkEventKey,
+    callback: *mut c_void,
+) -> c_int {
+    let callback = unsafe { &mut *(callback as *mut KeyCallback) };
+    let event = unsafe { &*event };
+    match unsafe { key_name(event.keyval) } {
+        Some(key) => callback(KeyEvent {
+            key,
+            ctrl: event.state & GDK_CONTROL_MASK != 0,
+            shift: event.state & GDK_SHIFT_MASK != 0,
+            alt: event.state & GDK_MOD1_MASK != 0,
+            meta: event.state & (GDK_SUPER_MASK | GDK_META_MASK) != 0,
+        }) as c_int,
+        None => 0,
+    }
+}
+
+/// Sees the keys pressed in the web view before WebKit, whose handler runs last.
+pub(crate) struct KeyWatch {
+    view: *mut c_void,
+    handler: c_ulong,
+    callback: *mut KeyCallback,
+}
+
+impl KeyWatch {
+    pub(crate) fn new(view: &View, callback: KeyCallback) -> Option<KeyWatch> {
+        let callback = Box::into_raw(Box::new(callback));
+        let handler = unsafe {
+            g_signal_connect_data(
+                view.0,
+                b"key-press-event\0".as_ptr() as *const c_char,
+                on_key_press as *const c_void,
+                callback as *mut c_void,
+                null(),
+                0,
+            )
+        };
+        Some(KeyWatch {
+            view: view.0,
+            handler,
+            callback,
+        })
+    }
+}
+
+impl Drop for KeyWatch {
+    fn drop(&mut self) {
+        unsafe {
+            g_signal_handler_disconnect(self.view, self.handler);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+const WEBKIT_POLICY_DECISION_TYPE_NAVIGATION_ACTION: c_int = 0;
+const WEBKIT_POLICY_DECISION_TYPE_NEW_WINDOW_ACTION: c_int = 1;
+
+extern "C" {
+    fn webkit_navigation_policy_decision_get_navigation_action(
+        decision: *mut c_void,
+    ) -> *mut c_void;
+    fn webkit_navigation_action_get_request(action: *mut c_void) -> *mut c_void;
+    fn webkit_uri_request_get_uri(request: *mut c_void) -> *const c_char;
+    fn webkit_policy_decision_ignore(decision: *mut c_void);
+}
+
+extern "C" fn on_decide_policy(
+    _view: *mut c_void,
+    decision: *mut c_void,
+    decision_type: c_int,
+    callback: *mut c_void,
+) -> c_int {
+    if decision_type != WEBKIT_POLICY_DECISION_TYPE_NAVIGATION_ACTION
+        && decision_type != WEBKIT_POLICY_DECISION_TYPE_NEW_WINDOW_ACTION
+    {
+        return 0;
+    }
+    let callback = unsafe { &mut *(callback as *mut NavigationCallback) };
+    let url = unsafe {
+        let action = webkit_navigation_policy_decision_get_navigation_action(decision);
+        let uri = webkit_uri_request_get_uri(webkit_navigation_action_get_request(action));
+        if uri.is_null() {
+            return 0;
+        }
+        CStr::from_ptr(uri).to_string_lossy().into_owned()
+    };
+    if callback(&url) {
+        return 0;
+    }
+    unsafe { webkit_policy_decision_ignore(decision) };
+    1
+}
+
+/// Decides the web view's navigations and new windows in its `decide-policy` signal, before
+/// anything is requested. WebKitGTK doesn't tell frames apart, their navigations are decided
+/// too.
+pub(crate) struct NavigationWatch {
+    view: *mut c_void,
+    handler: c_ulong,
+    callback: *mut NavigationCallback,
+}
+
+impl NavigationWatch {
+    pub(crate) fn new(view: &View, callback: NavigationCallback) -> Option<NavigationWatch> {
+        let callback = Box::into_raw(Box::new(callback));
+        let handler = unsafe {
+            g_signal_connect_data(
+                view.0,
+                b"decide-policy\0".as_ptr() as *const c_char,
+                on_decide_policy as *const c_void,
+                callback as *mut c_void,
+                null(),
+                0,
+            )
+        };
+        Some(NavigationWatch {
+            view: view.0,
+            handler,
+            callback,
+        })
+    }
+}
+
+impl Drop for NavigationWatch {
+    fn drop(&mut self) {
+        unsafe {
+            g_signal_handler_disconnect(self.view, self.handler);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// `WEBKIT_LOAD_FINISHED`, also emitted after a load failed.
+const WEBKIT_LOAD_FINISHED: c_int = 3;
+
+extern "C" fn on_load_changed(_view: *mut c_void, event: c_int, callback: *mut c_void) {
+    if event == WEBKIT_LOAD_FINISHED {
+        let callback = unsafe { &mut *(callback as *mut LoadCallback) };
+        callback();
+    }
+}
+
+/// Follows the web view's `load-changed` signal to the end of each load.
+pub(crate) struct LoadWatch {
+    view: *mut c_void,
+    handler: c_ulong,
+    callback: *mut LoadCallback,
+}
+
+impl LoadWatch {
+    pub(crate) fn new(view: &View, callback: LoadCallback) -> Option<LoadWatch> {
+        let callback = Box::into_raw(Box::new(callback));
+        let handler = unsafe {
+            g_signal_connect_data(
+                view.0,
+                b"load-changed\0".as_ptr() as *const c_char,
+                on_load_changed as *const c_void,
+                callback as *mut c_void,
+                null(),
+                0,
+            )
+        };
+        Some(LoadWatch {
+            view: view.0,
+            handler,
+            callback,
+        })
+    }
+}
+
+impl Drop for LoadWatch {
+    fn drop(&mut self) {
+        unsafe {
+            g_signal_handler_disconnect(self.view, self.handler);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// Registers the running executable as the handler of `scheme` with a desktop entry, links are
+/// then passed to new instances as their argument.
+pub(crate) fn register_url_scheme(scheme: &str) -> io::Result<()> {
+    let exe = env::current_exe()?;
+    let applications = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?
+        .join("applications");
+    let name = exe
+        .file_stem()
+        .map_or_else(|| "webview".into(), |stem| stem.to_string_lossy());
+    let entry = format!("{}-{}.desktop", name, scheme);
+    // Quoted as the Exec key wants it, then escaped as any string value.
+    let mut exec = String::from("\"");
+    for c in exe.to_string_lossy().chars() {
+        match c {
+            '"' | '`' | '$' => exec.push_str("\\\\"),
+            '\\' => exec.push_str("\\\\\\"),
+            _ => {}
+        }
+        exec.push(c);
+    }
+    exec.push('"');
+    fs::create_dir_all(&applications)?;
+    fs::write(
+        applications.join(&entry),
+        format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={} %u\nNoDisplay=true\n\
+             MimeType=x-scheme-handler/{};\n",
+            name, exec, scheme
+        ),
+    )?;
+    let status = Command::new("xdg-mime")
+        .arg("default")
+        .arg(&entry)
+        .arg(format!("x-scheme-handler/{}", scheme))
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other("xdg-mime failed"))
+    }
+}
+
+/// Links arrive as command line arguments, there is nothing to watch.
+pub(crate) struct DeepLinkWatch;
+
+impl DeepLinkWatch {
+    pub(crate) fn new(_webview: sys::webview_t, _callback: DeepLinkCallback) -> DeepLinkWatch {
+        DeepLinkWatch
+    }
+}
+
+extern "C" {
+    fn gtk_window_set_urgency_hint(window: *mut c_void, setting: c_int);
+}
+
+/// Sets the urgency hint, which window managers show in their own way and clear once the window
+/// is activated.
+pub(crate) fn request_user_attention(webview: sys::webview_t, _level: UserAttention) {
+    unsafe { gtk_window_set_urgency_hint(sys::webview_get_window(webview), 1) };
+}
+
+extern "C" {
+    fn g_dbus_connection_emit_signal(
+        connection: *mut c_void,
+        destination_bus_name: *const c_char,
+        object_path: *const c_char,
+        interface_name: *const c_char,
+        signal_name: *const c_char,
+        parameters: *mut c_void,
+        error: *mut *mut c_void,
+    ) -> c_int;
+    fn g_get_prgname() -> *const c_char;
+    fn g_variant_new_parsed(format: *const c_char, ...) -> *mut c_void;
+    fn g_variant_ref_sink(value: *mut c_void) -> *mut c_void;
+}
+
+/// Sends `properties`, an `a{sv}` dictionary, to the launcher entry of the `<program name>.desktop`
+/// application through the Unity launcher API, which docks like KDE Plasma's and Dash to Dock
+/// implement.
+unsafe fn update_launcher_entry(properties: *mut c_void) {
+    let name = g_get_prgname();
+    let connection = if name.is_null() {
+        null_mut()
+    } else {
+        g_bus_get_sync(G_BUS_TYPE_SESSION, null_mut(), null_mut())
+    };
+    if connection.is_null() {
+        g_variant_unref(g_variant_ref_sink(properties));
+        return;
+    }
+    let uri = CString::new(format!(
+        "application://{}.desktop",
+        CStr::from_ptr(name).to_string_lossy()
+    ))
+    .unwrap();
+    let parameters = g_variant_new_parsed(
+        b"(%s, %@a{sv})\0".as_ptr() as *const c_char,
+        uri.as_ptr(),
+        properties,
+    );
+    let mut error = null_mut();
+    if g_dbus_connection_emit_signal(
+        connection,
+        null(),
+        b"/\0".as_ptr() as *const c_char,
+        b"com.canonical.Unity.LauncherEntry\0".as_ptr() as *const c_char,
+        b"Update\0".as_ptr() as *const c_char,
+        parameters,
+        &mut error,
+    ) == 0
+    {
+        g_error_free(error);
+    }
+    g_object_unref(connection);
+}
+
+/// The launcher API has no states, only a fraction that is shown or not.
+pub(crate) fn set_progress(_webview: sys::webview_t, state: ProgressState, fraction: f64) {
+    unsafe {
+        update_launcher_entry(g_variant_new_parsed(
+            b"{'progress': <%d>, 'progress-visible': <%b>}\0".as_ptr() as *const c_char,
+            fraction,
+            (state != ProgressState::None) as c_int,
+        ))
+    }
+}
+
+/// Shows the count on the launcher entry, see `set_progress`.
+pub(crate) fn set_badge_count(_webview: sys::webview_t, count: Option<u32>) -> bool {
+    unsafe {
+        update_launcher_entry(g_variant_new_parsed(
+            b"{'count': <%x>, 'count-visible': <%b>}\0".as_ptr() as *const c_char,
+            count.unwrap_or(0) as i64,
+            count.is_some() as c_int,
+        ))
+    };
+    true
+}
+
+extern "C" {
+    fn gtk_window_set_modal(window: *mut c_void, modal: c_int);
+    fn gtk_window_set_transient_for(window: *mut c_void, parent: *mut c_void);
+}
+
+/// Makes the window transient for its owner, so it stays above it. A modal window blocks input
+/// to the application's other windows until it is destroyed.
+pub(crate) struct Owner;
+
+impl Owner {
+    pub(crate) fn new(webview: sys::webview_t, owner: sys::webview_t, modal: bool) -> Owner {
+        unsafe {
+            let window = sys::webview_get_window(webview);
+            gtk_window_set_transient_for(window, sys::webview_get_window(owner));
+            gtk_window_set_modal(window, modal as c_int);
+        }
+        Owner
+    }
+}
+
+extern "C" {
+    fn gtk_widget_show(widget: *mut c_void);
+}
+
+pub(crate) fn set_visible(webview: sys::webview_t, visible: bool) {
+    unsafe {
+        let window = sys::webview_get_window(webview);
+        if visible {
+            gtk_widget_show(window);
+        } else {
+            gtk_widget_hide(window);
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct GdkRectangle {
+    x: c_int,
+    y: c_int,
+    width: c_int,
+    height: c_int,
+}
+
+const GDK_WINDOW_STATE_ICONIFIED: c_int = 1 << 1;
+const GDK_WINDOW_STATE_FULLSCREEN: c_int = 1 << 4;
+
+extern "C" {
+    fn gtk_widget_get_window(widget: *mut c_void) -> *mut c_void;
+    fn gtk_window_get_position(window: *mut c_void, x: *mut c_int, y: *mut c_int);
+    fn gtk_window_get_size(window: *mut c_void, width: *mut c_int, height: *mut c_int);
+    fn gtk_window_move(window: *mut c_void, x: c_int, y: c_int);
+    fn gtk_window_resize(window: *mut c_void, width: c_int, height: c_int);
+    fn gtk_window_is_maximized(window: *mut c_void) -> c_int;
+    fn gtk_window_maximize(window: *mut c_void);
+    fn gtk_window_fullscreen(window: *mut c_void);
+    fn gtk_window_unfullscreen(window: *mut c_void);
+    fn gdk_window_get_state(window: *mut c_void) -> c_int;
+    fn gdk_display_get_n_monitors(display: *mut c_void) -> c_int;
+    fn gdk_display_get_monitor(display: *mut c_void, index: c_int) -> *mut c_void;
+    fn gdk_display_get_primary_monitor(display: *mut c_void) -> *mut c_void;
+    fn gdk_monitor_get_workarea(monitor: *mut c_void, workarea: *mut GdkRectangle);
+}
+
+/// The position and size as the window manager reports them, positions are 0 on Wayland.
+pub(crate) fn window_frame(webview: sys::webview_t) -> Rect {
+    let (mut x, mut y, mut width, mut height) = (0, 0, 0, 0);
+    unsafe {
+        let window = sys::webview_get_window(webview);
+        gtk_window_get_position(window, &mut x, &mut y);
+        gtk_window_get_size(window, &mut width, &mut height);
+    }
+    Rect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+pub(crate) fn set_window_frame(webview: sys::webview_t, frame: Rect) {
+    unsafe {
+        let window = sys::webview_get_window(webview);
+        gtk_window_move(window, frame.x, frame.y);
+        gtk_window_resize(window, frame.width, frame.height);
+    }
+}
+
+pub(crate) fn is_maximized(webview: sys::webview_t) -> bool {
+    unsafe { gtk_window_is_maximized(sys::webview_get_window(webview)) != 0 }
+}
+
+pub(crate) fn is_minimized(webview: sys::webview_t) -> bool {
+    unsafe {
+        let window = gtk_widget_get_window(sys::webview_get_window(webview));
+        !window.is_null() && gdk_window_get_state(window) & GDK_WINDOW_STATE_ICONIFIED != 0
+    }
+}
+
+pub(crate) fn maximize(webview: sys::webview_t) {
+    unsafe { gtk_window_maximize(sys::webview_get_window(webview)) }
+}
+
+pub(crate) fn is_fullscreen(webview: sys::webview_t) -> bool {
+    unsafe {
+        let window = gtk_widget_get_window(sys::webview_get_window(webview));
+        !window.is_null() && gdk_window_get_state(window) & GDK_WINDOW_STATE_FULLSCREEN != 0
+    }
+}
+
+pub(crate) fn set_fullscreen(webview: sys::webview_t, fullscreen: bool) {
+    unsafe {
+        let window = sys::webview_get_window(webview);
+        if fullscreen {
+            gtk_window_fullscreen(window);
+        } else {
+            gtk_window_unfullscreen(window);
+        }
+    }
+}
+
+/// The work areas of the display's monitors, the primary one first.
+pub(crate) fn monitors(_webview: sys::webview_t) -> Vec<Rect> {
+    let mut monitors = Vec::new();
+    unsafe {
+        let display = gdk_display_get_default();
+        let primary = gdk_display_get_primary_monitor(display);
+        for index in 0..gdk_display_get_n_monitors(display) {
+            let monitor = gdk_display_get_monitor(display, index);
+            let mut area = GdkRectangle::default();
+            gdk_monitor_get_workarea(monitor, &mut area);
+            let area = Rect {
+                x: area.x,
+                y: area.y,
+                width: area.width,
+                height: area.height,
+            };
+            if monitor == primary {
+                monitors.insert(0, area);
+            } else {
+                monitors.push(area);
+            }
+        }
+    }
+    monitors
+}
+
+extern "C" fn on_frame_event(
+    _window: *mut c_void,
+    _event: *mut c_void,
+    callback: *mut c_void,
+) -> c_int {
+    let callback = unsafe { &mut *(callback as *mut FrameCallback) };
+    callback();
+    0
+}
+
+/// Follows the `configure-event` GTK sends as the window moves or is resized, and the
+/// `window-state-event` as it is maximized or minimized.
+pub(crate) struct FrameWatch {
+    window: *mut c_void,
+    handlers: [c_ulong; 2],
+    callback: *mut FrameCallback,
+}
+
+impl FrameWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FrameCallback) -> FrameWatch {
+        let callback = Box::into_raw(Box::new(callback));
+        unsafe {
+            let window = sys::webview_get_window(webview);
+            let connect = |signal: &[u8]| {
+                g_signal_connect_data(
+                    window,
+                    signal.as_ptr() as *const c_char,
+                    on_frame_event as *const c_void,
+                    callback as *mut c_void,
+                    null(),
+                    0,
+                )
+            };
+            let handlers = [
+                connect(b"configure-event\0"),
+                connect(b"window-state-event\0"),
+            ];
+            FrameWatch {
+                window,
+                handlers,
+                callback,
+            }
+        }
+    }
+}
+
+impl Drop for FrameWatch {
+    fn drop(&mut self) {
+        unsafe {
+            for &handler in &self.handlers {
+                g_signal_handler_disconnect(self.window, handler);
+            }
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+const SCREEN_SAVER: &[u8] = b"org.freedesktop.ScreenSaver\0";
+const SCREEN_SAVER_PATH: &[u8] = b"/org/freedesktop/ScreenSaver\0";
+
+/// Keeps the screen saver from starting while it is held, through the `org.freedesktop.ScreenSaver`
+/// service of the session bus. The desktop drops the inhibition if the process exits.
+pub(crate) struct SleepInhibitor {
+    connection: *mut c_void,
+    cookie: u32,
+}
+
+impl SleepInhibitor {
+    pub(crate) fn new(_webview: sys::webview_t, reason: &str) -> Option<SleepInhibitor> {
+        let reason = CString::new(reason).ok()?;
+        unsafe {
+            let name = g_get_prgname();
+            let name = if name.is_null() {
+                b"webview\0".as_ptr() as *const c_char
+            } else {
+                name
+            };
+            let connection = g_bus_get_sync(G_BUS_TYPE_SESSION, null_mut(), null_mut());
+            if connection.is_null() {
+                return None;
+            }
+            let mut error = null_mut();
+            let reply = g_dbus_connection_call_sync(
+                connection,
+                SCREEN_SAVER.as_ptr() as *const c_char,
+                SCREEN_SAVER_PATH.as_ptr() as *const c_char,
+                SCREEN_SAVER.as_ptr() as *const c_char,
+                b"Inhibit\0".as_ptr() as *const c_char,
+                g_variant_new(b"(ss)\0".as_ptr() as *const c_char, name, reason.as_ptr()),
+                null(),
+                G_DBUS_CALL_FLAGS_NONE,
+                -1,
+                null_mut(),
+                &mut error,
+            );
+            if reply.is_null() {
+                g_error_free(error);
+                g_object_unref(connection);
+                return None;
+            }
+            let mut cookie: u32 = 0;
+            g_variant_get(reply, b"(u)\0".as_ptr() as *const c_char, &mut cookie);
+            g_variant_unref(reply);
+            Some(SleepInhibitor { connection, cookie })
+        }
+    }
+}
+
+impl Drop for SleepInhibitor {
+    fn drop(&mut self) {
+        unsafe {
+            let reply = g_dbus_connection_call_sync(
+                self.connection,
+                SCREEN_SAVER.as_ptr() as *const c_char,
+                SCREEN_SAVER_PATH.as_ptr() as *const c_char,
+                SCREEN_SAVER.as_ptr() as *const c_char,
+                b"UnInhibit\0".as_ptr() as *const c_char,
+                g_variant_new(b"(u)\0".as_ptr() as *const c_char, self.cookie),
+                null(),
+                G_DBUS_CALL_FLAGS_NONE,
+                -1,
+                null_mut(),
+                null_mut(),
+            );
+            if !reply.is_null() {
+                g_variant_unref(reply);
+            }
+            g_object_unref(self.connection);
+        }
+    }
+}
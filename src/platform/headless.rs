@@ -0,0 +1,451 @@
+use std::os::raw::*;
+use std::path::Path;
+use std::ptr::null_mut;
+
+use super::{
+    DeepLinkCallback, FileDropCallback, FocusCallback, FrameCallback, HistoryAction, KeyCallback,
+    Layout, LoadCallback, NavigationCallback, PaintCallback, Request, SessionCallback,
+    ThemeCallback,
+};
+
+use crate::builder::Cache;
+use crate::host;
+use crate::mock::{self, sys};
+use crate::{
+    Backend, BackgroundThrottling, Capabilities, Color, DragItem, ProgressState, Rect, Theme,
+    UserAttention, WindowEffect,
+};
+
+pub(crate) fn detach_main_loop(_webview: sys::webview_t) {}
+
+pub(crate) fn terminate(webview: sys::webview_t) {
+    unsafe { sys::webview_terminate(webview) }
+}
+
+pub(crate) fn destroy(_webview: sys::webview_t, _owns_window: bool) {}
+
+pub(crate) fn dispatch_external(webview: sys::webview_t, f: sys::DispatchFn, arg: *mut c_void) {
+    unsafe { sys::webview_dispatch(webview, Some(f), arg) }
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) unsafe fn handle_message(_webview: sys::webview_t, _msg: *const c_void) -> bool {
+    false
+}
+
+pub(crate) struct Panel;
+
+impl Panel {
+    pub(crate) fn new(_webview: sys::webview_t, _parent: *mut c_void) -> Panel {
+        Panel
+    }
+
+    pub(crate) fn reparent(&mut self, _parent: *mut c_void) {}
+
+    pub(crate) fn set_bounds(&mut self, _bounds: Rect) {}
+
+    pub(crate) fn set_layout(&mut self, _layout: Layout) {}
+}
+
+/// The model has no widgets to host panes in.
+pub(crate) fn pane_parent(_webview: sys::webview_t) -> *mut c_void {
+    null_mut()
+}
+
+/// Records `url` in the model instead of opening it.
+pub(crate) fn open_external(webview: sys::webview_t, url: &str) -> bool {
+    mock::open_external(webview, url);
+    true
+}
+
+/// Records `image` in the model.
+pub(crate) fn set_window_icon(webview: sys::webview_t, image: &[u8]) -> bool {
+    mock::set_window_icon(webview, image);
+    true
+}
+
+/// Records the drag in the model.
+pub(crate) fn start_window_drag(webview: sys::webview_t) -> bool {
+    mock::start_window_drag(webview);
+    true
+}
+
+/// Records the drag in the model.
+pub(crate) fn start_drag(view: &View, item: &DragItem) -> bool {
+    mock::start_drag(view.0, item);
+    true
+}
+
+/// Records `effect` in the model.
+pub(crate) fn set_window_effect(webview: sys::webview_t, effect: WindowEffect) -> bool {
+    mock::set_window_effect(webview, effect);
+    true
+}
+
+/// Records the request in the model.
+pub(crate) fn request_user_attention(webview: sys::webview_t, level: UserAttention) {
+    mock::request_user_attention(webview, level);
+}
+
+/// Records the progress in the model.
+pub(crate) fn set_progress(webview: sys::webview_t, state: ProgressState, fraction: f64) {
+    mock::set_progress(webview, state, fraction);
+}
+
+/// Records the count in the model.
+pub(crate) fn set_badge_count(webview: sys::webview_t, count: Option<u32>) -> bool {
+    mock::set_badge_count(webview, count);
+    true
+}
+
+pub(crate) fn set_visible(webview: sys::webview_t, visible: bool) {
+    mock::set_visible(webview, visible);
+}
+
+pub(crate) fn window_frame(webview: sys::webview_t) -> Rect {
+    mock::window_frame(webview)
+}
+
+pub(crate) fn set_window_frame(webview: sys::webview_t, frame: Rect) {
+    mock::set_window_frame(webview, frame);
+}
+
+pub(crate) fn is_maximized(webview: sys::webview_t) -> bool {
+    mock::is_maximized(webview)
+}
+
+/// The model's windows are never minimized.
+pub(crate) fn is_minimized(_webview: sys::webview_t) -> bool {
+    false
+}
+
+pub(crate) fn maximize(webview: sys::webview_t) {
+    mock::set_maximized(webview, true);
+}
+
+pub(crate) fn is_fullscreen(webview: sys::webview_t) -> bool {
+    mock::is_fullscreen(webview)
+}
+
+pub(crate) fn set_fullscreen(webview: sys::webview_t, fullscreen: bool) {
+    mock::set_fullscreen(webview, fullscreen);
+}
+
+pub(crate) fn monitors(webview: sys::webview_t) -> Vec<Rect> {
+    mock::monitors(webview)
+}
+
+/// Receives the changes simulated with `Mock::move_window` and `Mock::set_maximized`.
+pub(crate) struct FrameWatch(sys::webview_t);
+
+impl FrameWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FrameCallback) -> FrameWatch {
+        mock::set_frame_callback(webview, Some(callback));
+        FrameWatch(webview)
+    }
+}
+
+impl Drop for FrameWatch {
+    fn drop(&mut self) {
+        mock::set_frame_callback(self.0, None);
+    }
+}
+
+/// The history and requests the model records.
+pub(crate) struct View(sys::webview_t);
+
+impl View {
+    pub(crate) fn new(webview: sys::webview_t) -> View {
+        View(webview)
+    }
+
+    pub(crate) fn load(&self, request: &Request) -> bool {
+        mock::load(self.0, request);
+        true
+    }
+
+    pub(crate) fn go(&self, action: HistoryAction) -> bool {
+        mock::go(self.0, action);
+        true
+    }
+
+    /// Gives the window the focus, as a window manager would right away.
+    pub(crate) fn focus(&self) {
+        mock::focus_changed(self.0, true);
+    }
+
+    pub(crate) fn is_focused(&self) -> bool {
+        mock::is_focused(self.0)
+    }
+
+    pub(crate) fn set_background_color(&self, color: Color) -> bool {
+        mock::set_background_color(self.0, color);
+        true
+    }
+
+    pub(crate) fn set_theme(&self, theme: Theme) -> bool {
+        mock::set_theme(self.0, theme);
+        true
+    }
+
+    pub(crate) fn system_theme(&self) -> Theme {
+        mock::system_theme(self.0)
+    }
+
+    pub(crate) fn can_go_back(&self) -> bool {
+        mock::can_go(self.0).0
+    }
+
+    pub(crate) fn can_go_forward(&self) -> bool {
+        mock::can_go(self.0).1
+    }
+
+    pub(crate) fn url(&self) -> Option<String> {
+        mock::url(self.0)
+    }
+}
+
+/// Receives the session changes simulated with `Mock::session_event`.
+pub(crate) struct SessionWatch(sys::webview_t);
+
+impl SessionWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: SessionCallback) -> SessionWatch {
+        mock::set_session_callback(webview, Some(callback));
+        SessionWatch(webview)
+    }
+}
+
+impl Drop for SessionWatch {
+    fn drop(&mut self) {
+        mock::set_session_callback(self.0, None);
+    }
+}
+
+/// Receives the focus changes simulated with `Mock::focus_changed`.
+pub(crate) struct FocusWatch(sys::webview_t);
+
+impl FocusWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FocusCallback) -> FocusWatch {
+        mock::set_focus_callback(webview, Some(callback));
+        FocusWatch(webview)
+    }
+}
+
+impl Drop for FocusWatch {
+    fn drop(&mut self) {
+        mock::set_focus_callback(self.0, None);
+    }
+}
+
+/// Receives the drops simulated with `Mock::drop_files`.
+pub(crate) struct FileDropWatch(sys::webview_t);
+
+impl FileDropWatch {
+    pub(crate) fn new(view: &View, callback: FileDropCallback) -> Option<FileDropWatch> {
+        mock::set_file_drop_callback(view.0, Some(callback));
+        Some(FileDropWatch(view.0))
+    }
+}
+
+impl Drop for FileDropWatch {
+    fn drop(&mut self) {
+        mock::set_file_drop_callback(self.0, None);
+    }
+}
+
+/// Receives the keys simulated with `Mock::press_key`.
+pub(crate) struct KeyWatch(sys::webview_t);
+
+impl KeyWatch {
+    pub(crate) fn new(view: &View, callback: KeyCallback) -> Option<KeyWatch> {
+        mock::set_key_callback(view.0, Some(callback));
+        Some(KeyWatch(view.0))
+    }
+}
+
+impl Drop for KeyWatch {
+    fn drop(&mut self) {
+        mock::set_key_callback(self.0, None);
+    }
+}
+
+/// Decides the navigations started with `Mock::follow_link`.
+pub(crate) struct NavigationWatch(sys::webview_t);
+
+impl NavigationWatch {
+    pub(crate) fn new(view: &View, callback: NavigationCallback) -> Option<NavigationWatch> {
+        mock::set_navigation_callback(view.0, Some(callback));
+        Some(NavigationWatch(view.0))
+    }
+}
+
+impl Drop for NavigationWatch {
+    fn drop(&mut self) {
+        mock::set_navigation_callback(self.0, None);
+    }
+}
+
+/// Receives the loads finished with `Mock::finish_load`.
+pub(crate) struct LoadWatch(sys::webview_t);
+
+impl LoadWatch {
+    pub(crate) fn new(view: &View, callback: LoadCallback) -> Option<LoadWatch> {
+        mock::set_load_callback(view.0, Some(callback));
+        Some(LoadWatch(view.0))
+    }
+}
+
+impl Drop for LoadWatch {
+    fn drop(&mut self) {
+        mock::set_load_callback(self.0, None);
+    }
+}
+
+/// Receives the links opened with `Mock::open_deep_link`.
+pub(crate) struct DeepLinkWatch(sys::webview_t);
+
+impl DeepLinkWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: DeepLinkCallback) -> DeepLinkWatch {
+        mock::set_deep_link_callback(webview, Some(callback));
+        DeepLinkWatch(webview)
+    }
+}
+
+impl Drop for DeepLinkWatch {
+    fn drop(&mut self) {
+        mock::set_deep_link_callback(self.0, None);
+    }
+}
+
+/// Records the owner in the model, disabling it while a modal window is held.
+pub(crate) struct Owner {
+    owner: sys::webview_t,
+    modal: bool,
+}
+
+impl Owner {
+    pub(crate) fn new(webview: sys::webview_t, owner: sys::webview_t, modal: bool) -> Owner {
+        mock::set_owned(webview);
+        if modal {
+            mock::add_modal(owner, 1);
+        }
+        Owner { owner, modal }
+    }
+}
+
+impl Drop for Owner {
+    fn drop(&mut self) {
+        if self.modal {
+            mock::add_modal(self.owner, -1);
+        }
+    }
+}
+
+/// Records the ratio in the model while it is held.
+pub(crate) struct AspectRatio(sys::webview_t);
+
+impl AspectRatio {
+    pub(crate) fn new(webview: sys::webview_t, width: u32, height: u32) -> AspectRatio {
+        mock::set_aspect_ratio(webview, Some((width, height)));
+        AspectRatio(webview)
+    }
+}
+
+impl Drop for AspectRatio {
+    fn drop(&mut self) {
+        mock::set_aspect_ratio(self.0, None);
+    }
+}
+
+/// Records the reason in the model while it is held.
+pub(crate) struct SleepInhibitor(sys::webview_t);
+
+impl SleepInhibitor {
+    pub(crate) fn new(webview: sys::webview_t, reason: &str) -> Option<SleepInhibitor> {
+        mock::set_sleep_prevented(webview, Some(reason.to_string()));
+        Some(SleepInhibitor(webview))
+    }
+}
+
+impl Drop for SleepInhibitor {
+    fn drop(&mut self) {
+        mock::set_sleep_prevented(self.0, None);
+    }
+}
+
+/// Receives the theme changes simulated with `Mock::system_theme_changed`.
+pub(crate) struct ThemeWatch(sys::webview_t);
+
+impl ThemeWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: ThemeCallback) -> ThemeWatch {
+        mock::set_theme_callback(webview, Some(callback));
+        ThemeWatch(webview)
+    }
+}
+
+impl Drop for ThemeWatch {
+    fn drop(&mut self) {
+        mock::set_theme_callback(self.0, None);
+    }
+}
+
+pub(crate) const BACKEND: Backend = Backend::Mock;
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    bind: true,
+    scripts: true,
+    window_handle: false,
+    reparent: false,
+    external_loop: true,
+    transparency: false,
+    offscreen: true,
+};
+
+/// Hands the frames simulated with `Mock::paint` to the callback, with the window hidden.
+pub(crate) struct Offscreen(sys::webview_t);
+
+impl Offscreen {
+    pub(crate) fn new(webview: sys::webview_t, callback: PaintCallback) -> Option<Offscreen> {
+        mock::set_visible(webview, false);
+        mock::set_paint_callback(webview, Some(callback));
+        Some(Offscreen(webview))
+    }
+}
+
+impl Drop for Offscreen {
+    fn drop(&mut self) {
+        mock::set_paint_callback(self.0, None);
+    }
+}
+
+pub(crate) fn set_background_throttling(
+    webview: sys::webview_t,
+    throttling: BackgroundThrottling,
+) -> bool {
+    mock::set_background_throttling(webview, throttling);
+    true
+}
+
+/// Mapped hosts are loaded with `Mock::load_mapped`.
+pub(crate) fn map_host(_webview: sys::webview_t, host: &str, folder: &Path) -> bool {
+    host::map(host, folder);
+    true
+}
+
+/// The model doesn't render.
+pub(crate) fn disable_hardware_acceleration(_webview: sys::webview_t) -> bool {
+    true
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub(crate) fn apply_webkit_settings(_webview: sys::webview_t, _options: &crate::gtk::Options) {}
+
+pub(crate) fn apply_cache(_webview: sys::webview_t, _cache: &Cache) -> Vec<&'static str> {
+    Vec::new()
+}
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use crate::{Backend, Capabilities, Frame, KeyEvent, Rect, SessionEvent, Theme};
+
+/// Computes a panel's bounds from its parent's width and height.
+pub(crate) type Layout = Box<dyn FnMut(i32, i32) -> Rect>;
+
+/// Receives the system's theme whenever it changes, on the UI thread.
+pub(crate) type ThemeCallback = Box<dyn FnMut(Theme)>;
+
+/// Receives whether the window has the keyboard focus whenever that changes, on the UI thread.
+pub(crate) type FocusCallback = Box<dyn FnMut(bool)>;
+
+/// Called whenever the window moved, was resized, maximized or minimized, on the UI thread.
+pub(crate) type FrameCallback = Box<dyn FnMut()>;
+
+/// Receives the paths of the files dropped on the view and where they were dropped, in the
+/// view's coordinates, on the UI thread.
+pub(crate) type FileDropCallback = Box<dyn FnMut(Vec<PathBuf>, (i32, i32))>;
+
+/// Receives the keys pressed in the view before the page, returns `true` to keep them from it.
+pub(crate) type KeyCallback = Box<dyn FnMut(KeyEvent) -> bool>;
+
+/// Called whenever the view finished loading a page or gave up on it, on the UI thread.
+pub(crate) type LoadCallback = Box<dyn FnMut()>;
+
+/// Receives the urls the view is about to load, returns `false` to keep it from loading them.
+pub(crate) type NavigationCallback = Box<dyn FnMut(&str) -> bool>;
+
+/// Receives the links the system hands to the application while it runs, on the UI thread.
+pub(crate) type DeepLinkCallback = Box<dyn FnMut(String)>;
+
+/// Receives the frames an `Offscreen` renders, on the UI thread.
+pub(crate) type PaintCallback = Box<dyn FnMut(&Frame<'_>)>;
+
+/// A step through the webview's session history, see `View::go`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistoryAction {
+    Back,
+    Forward,
+    Reload,
+    ReloadIgnoringCache,
+    Stop,
+}
+
+/// A page load with more to it than a url, see `View::load`.
+pub(crate) struct Request {
+    pub(crate) url: String,
+    pub(crate) headers: Vec<(String, String)>,
+    /// Posts the body with its content type instead of getting the page.
+    pub(crate) body: Option<(Vec<u8>, String)>,
+}
+
+/// The resident memory of the application's process and, where they are its children, of the
+/// engine's processes with their count, see `process_memory`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ProcessMemory {
+    pub(crate) app: Option<u64>,
+    pub(crate) engine: Option<(u64, usize)>,
+}
+
+/// Receives the session changes a `SessionWatch` observes, on the UI thread.
+pub(crate) type SessionCallback = Box<dyn FnMut(SessionEvent)>;
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+mod gtk;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+use self::gtk as native;
+
+#[cfg(target_os = "macos")]
+mod cocoa;
+#[cfg(target_os = "macos")]
+use self::cocoa as native;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use self::win32 as native;
+
+#[cfg(not(feature = "mock"))]
+pub(crate) use self::native::*;
+
+// Webviews built with `WebviewBuilder::mock` run on the model, the others stay native.
+#[cfg(feature = "mock")]
+mod headless;
+#[cfg(feature = "mock")]
+mod routed;
+#[cfg(feature = "mock")]
+pub(crate) use self::routed::*;
+
+/// The engine behind `webview`.
+#[cfg_attr(not(feature = "mock"), allow(unused_variables))]
+pub(crate) fn backend(webview: webview_official_sys::webview_t) -> Backend {
+    #[cfg(feature = "mock")]
+    if crate::mock::is_model(webview) {
+        return headless::BACKEND;
+    }
+    native::BACKEND
+}
+
+/// What the engine behind `webview` supports.
+#[cfg_attr(not(feature = "mock"), allow(unused_variables))]
+pub(crate) fn capabilities(webview: webview_official_sys::webview_t) -> Capabilities {
+    #[cfg(feature = "mock")]
+    if crate::mock::is_model(webview) {
+        return headless::CAPABILITIES;
+    }
+    native::CAPABILITIES
+}
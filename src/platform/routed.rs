@@ -0,0 +1,385 @@
+use std::os::raw::*;
+use std::path::Path;
+
+use super::{
+    headless, native, DeepLinkCallback, FileDropCallback, FocusCallback, FrameCallback,
+    HistoryAction, KeyCallback, Layout, LoadCallback, NavigationCallback, PaintCallback, Request,
+    SessionCallback, ThemeCallback,
+};
+
+use crate::builder::Cache;
+use crate::mock::{self, sys};
+use crate::{
+    BackgroundThrottling, Color, DragItem, ProgressState, Rect, Theme, UserAttention, WindowEffect,
+};
+
+// Nothing to route without a webview, they are always native.
+#[cfg(feature = "raw-window-handle")]
+pub(crate) use super::native::foreign_parent;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+pub(crate) use super::native::locale;
+pub(crate) use super::native::{
+    check_backend, process_memory, register_url_scheme, show_error_dialog,
+};
+#[cfg(target_os = "macos")]
+pub(crate) use super::native::{set_activation_policy, with_webkit_options};
+
+/// Calls `function` of the model's backend if `webview` is backed by it, of the native one
+/// otherwise.
+macro_rules! route {
+    ($webview:expr, $function:ident($($arg:expr),*)) => {
+        if mock::is_model($webview) {
+            headless::$function($($arg),*)
+        } else {
+            native::$function($($arg),*)
+        }
+    };
+}
+
+/// Calls `method` on the value of whichever backend `self` holds.
+macro_rules! delegate {
+    ($self:expr, $method:ident($($arg:expr),*)) => {
+        match $self {
+            Self::Native(value) => value.$method($($arg),*),
+            Self::Mock(value) => value.$method($($arg),*),
+        }
+    };
+}
+
+/// Declares a type holding the value of either backend.
+macro_rules! either {
+    ($($name:ident),*) => {$(
+        // The guards are only held to be dropped.
+        #[allow(dead_code)]
+        pub(crate) enum $name {
+            Native(native::$name),
+            Mock(headless::$name),
+        }
+    )*};
+}
+
+either!(
+    Panel,
+    View,
+    FrameWatch,
+    SessionWatch,
+    FocusWatch,
+    FileDropWatch,
+    KeyWatch,
+    LoadWatch,
+    NavigationWatch,
+    DeepLinkWatch,
+    Owner,
+    AspectRatio,
+    SleepInhibitor,
+    ThemeWatch,
+    Offscreen
+);
+
+/// Creates `$name` on the backend `webview` is on, passing the arguments to its `new`.
+macro_rules! new {
+    ($name:ident, $webview:expr, ($($arg:expr),*)) => {
+        if mock::is_model($webview) {
+            $name::Mock(headless::$name::new($($arg),*))
+        } else {
+            $name::Native(native::$name::new($($arg),*))
+        }
+    };
+}
+
+/// Like `new!`, for values the backend may not have.
+macro_rules! try_new {
+    ($name:ident, $webview:expr, ($($arg:expr),*)) => {
+        if mock::is_model($webview) {
+            headless::$name::new($($arg),*).map($name::Mock)
+        } else {
+            native::$name::new($($arg),*).map($name::Native)
+        }
+    };
+}
+
+pub(crate) fn detach_main_loop(webview: sys::webview_t) {
+    route!(webview, detach_main_loop(webview))
+}
+
+pub(crate) fn terminate(webview: sys::webview_t) {
+    route!(webview, terminate(webview))
+}
+
+pub(crate) fn destroy(webview: sys::webview_t, owns_window: bool) {
+    route!(webview, destroy(webview, owns_window))
+}
+
+pub(crate) fn dispatch_external(webview: sys::webview_t, f: sys::DispatchFn, arg: *mut c_void) {
+    route!(webview, dispatch_external(webview, f, arg))
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) unsafe fn handle_message(webview: sys::webview_t, msg: *const c_void) -> bool {
+    route!(webview, handle_message(webview, msg))
+}
+
+pub(crate) fn pane_parent(webview: sys::webview_t) -> *mut c_void {
+    route!(webview, pane_parent(webview))
+}
+
+pub(crate) fn open_external(webview: sys::webview_t, url: &str) -> bool {
+    route!(webview, open_external(webview, url))
+}
+
+pub(crate) fn set_window_icon(webview: sys::webview_t, image: &[u8]) -> bool {
+    route!(webview, set_window_icon(webview, image))
+}
+
+pub(crate) fn start_window_drag(webview: sys::webview_t) -> bool {
+    route!(webview, start_window_drag(webview))
+}
+
+pub(crate) fn start_drag(view: &View, item: &DragItem) -> bool {
+    match view {
+        View::Native(view) => native::start_drag(view, item),
+        View::Mock(view) => headless::start_drag(view, item),
+    }
+}
+
+pub(crate) fn set_window_effect(webview: sys::webview_t, effect: WindowEffect) -> bool {
+    route!(webview, set_window_effect(webview, effect))
+}
+
+pub(crate) fn request_user_attention(webview: sys::webview_t, level: UserAttention) {
+    route!(webview, request_user_attention(webview, level))
+}
+
+pub(crate) fn set_progress(webview: sys::webview_t, state: ProgressState, fraction: f64) {
+    route!(webview, set_progress(webview, state, fraction))
+}
+
+pub(crate) fn set_badge_count(webview: sys::webview_t, count: Option<u32>) -> bool {
+    route!(webview, set_badge_count(webview, count))
+}
+
+pub(crate) fn set_visible(webview: sys::webview_t, visible: bool) {
+    route!(webview, set_visible(webview, visible))
+}
+
+pub(crate) fn window_frame(webview: sys::webview_t) -> Rect {
+    route!(webview, window_frame(webview))
+}
+
+pub(crate) fn set_window_frame(webview: sys::webview_t, frame: Rect) {
+    route!(webview, set_window_frame(webview, frame))
+}
+
+pub(crate) fn is_maximized(webview: sys::webview_t) -> bool {
+    route!(webview, is_maximized(webview))
+}
+
+pub(crate) fn is_minimized(webview: sys::webview_t) -> bool {
+    route!(webview, is_minimized(webview))
+}
+
+pub(crate) fn maximize(webview: sys::webview_t) {
+    route!(webview, maximize(webview))
+}
+
+pub(crate) fn is_fullscreen(webview: sys::webview_t) -> bool {
+    route!(webview, is_fullscreen(webview))
+}
+
+pub(crate) fn set_fullscreen(webview: sys::webview_t, fullscreen: bool) {
+    route!(webview, set_fullscreen(webview, fullscreen))
+}
+
+pub(crate) fn monitors(webview: sys::webview_t) -> Vec<Rect> {
+    route!(webview, monitors(webview))
+}
+
+pub(crate) fn set_background_throttling(
+    webview: sys::webview_t,
+    throttling: BackgroundThrottling,
+) -> bool {
+    route!(webview, set_background_throttling(webview, throttling))
+}
+
+pub(crate) fn map_host(webview: sys::webview_t, host: &str, folder: &Path) -> bool {
+    route!(webview, map_host(webview, host, folder))
+}
+
+pub(crate) fn disable_hardware_acceleration(webview: sys::webview_t) -> bool {
+    route!(webview, disable_hardware_acceleration(webview))
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub(crate) fn apply_webkit_settings(webview: sys::webview_t, options: &crate::gtk::Options) {
+    route!(webview, apply_webkit_settings(webview, options))
+}
+
+pub(crate) fn apply_cache(webview: sys::webview_t, cache: &Cache) -> Vec<&'static str> {
+    route!(webview, apply_cache(webview, cache))
+}
+
+impl Panel {
+    pub(crate) fn new(webview: sys::webview_t, parent: *mut c_void) -> Panel {
+        new!(Panel, webview, (webview, parent))
+    }
+
+    pub(crate) fn reparent(&mut self, parent: *mut c_void) {
+        delegate!(self, reparent(parent))
+    }
+
+    pub(crate) fn set_bounds(&mut self, bounds: Rect) {
+        delegate!(self, set_bounds(bounds))
+    }
+
+    pub(crate) fn set_layout(&mut self, layout: Layout) {
+        delegate!(self, set_layout(layout))
+    }
+}
+
+impl View {
+    pub(crate) fn new(webview: sys::webview_t) -> View {
+        new!(View, webview, (webview))
+    }
+
+    pub(crate) fn load(&self, request: &Request) -> bool {
+        delegate!(self, load(request))
+    }
+
+    pub(crate) fn go(&self, action: HistoryAction) -> bool {
+        delegate!(self, go(action))
+    }
+
+    pub(crate) fn focus(&self) {
+        delegate!(self, focus())
+    }
+
+    pub(crate) fn is_focused(&self) -> bool {
+        delegate!(self, is_focused())
+    }
+
+    pub(crate) fn set_background_color(&self, color: Color) -> bool {
+        delegate!(self, set_background_color(color))
+    }
+
+    pub(crate) fn set_theme(&self, theme: Theme) -> bool {
+        delegate!(self, set_theme(theme))
+    }
+
+    pub(crate) fn system_theme(&self) -> Theme {
+        delegate!(self, system_theme())
+    }
+
+    pub(crate) fn can_go_back(&self) -> bool {
+        delegate!(self, can_go_back())
+    }
+
+    pub(crate) fn can_go_forward(&self) -> bool {
+        delegate!(self, can_go_forward())
+    }
+
+    pub(crate) fn url(&self) -> Option<String> {
+        delegate!(self, url())
+    }
+}
+
+impl FrameWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FrameCallback) -> FrameWatch {
+        new!(FrameWatch, webview, (webview, callback))
+    }
+}
+
+impl SessionWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: SessionCallback) -> SessionWatch {
+        new!(SessionWatch, webview, (webview, callback))
+    }
+}
+
+impl FocusWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FocusCallback) -> FocusWatch {
+        new!(FocusWatch, webview, (webview, callback))
+    }
+}
+
+impl FileDropWatch {
+    pub(crate) fn new(view: &View, callback: FileDropCallback) -> Option<FileDropWatch> {
+        match view {
+            View::Native(view) => native::FileDropWatch::new(view, callback).map(Self::Native),
+            View::Mock(view) => headless::FileDropWatch::new(view, callback).map(Self::Mock),
+        }
+    }
+}
+
+impl KeyWatch {
+    pub(crate) fn new(view: &View, callback: KeyCallback) -> Option<KeyWatch> {
+        match view {
+            View::Native(view) => native::KeyWatch::new(view, callback).map(Self::Native),
+            View::Mock(view) => headless::KeyWatch::new(view, callback).map(Self::Mock),
+        }
+    }
+}
+
+impl LoadWatch {
+    pub(crate) fn new(view: &View, callback: LoadCallback) -> Option<LoadWatch> {
+        match view {
+            View::Native(view) => native::LoadWatch::new(view, callback).map(Self::Native),
+            View::Mock(view) => headless::LoadWatch::new(view, callback).map(Self::Mock),
+        }
+    }
+}
+
+impl NavigationWatch {
+    pub(crate) fn new(view: &View, callback: NavigationCallback) -> Option<NavigationWatch> {
+        match view {
+            View::Native(view) => native::NavigationWatch::new(view, callback).map(Self::Native),
+            View::Mock(view) => headless::NavigationWatch::new(view, callback).map(Self::Mock),
+        }
+    }
+}
+
+impl DeepLinkWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: DeepLinkCallback) -> DeepLinkWatch {
+        new!(DeepLinkWatch, webview, (webview, callback))
+    }
+}
+
+impl Owner {
+    /// `webview` and `owner` have to be on the same backend, there is no owning across them.
+    pub(crate) fn new(webview: sys::webview_t, owner: sys::webview_t, modal: bool) -> Owner {
+        assert_eq!(
+            mock::is_model(webview),
+            mock::is_model(owner),
+            "A mock webview can't be owned by a native one or the other way around"
+        );
+        new!(Owner, webview, (webview, owner, modal))
+    }
+}
+
+impl AspectRatio {
+    pub(crate) fn new(webview: sys::webview_t, width: u32, height: u32) -> AspectRatio {
+        new!(AspectRatio, webview, (webview, width, height))
+    }
+}
+
+impl SleepInhibitor {
+    pub(crate) fn new(webview: sys::webview_t, reason: &str) -> Option<SleepInhibitor> {
+        try_new!(SleepInhibitor, webview, (webview, reason))
+    }
+}
+
+impl ThemeWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: ThemeCallback) -> ThemeWatch {
+        new!(ThemeWatch, webview, (webview, callback))
+    }
+}
+
+impl Offscreen {
+    pub(crate) fn new(webview: sys::webview_t, callback: PaintCallback) -> Option<Offscreen> {
+        try_new!(Offscreen, webview, (webview, callback))
+    }
+}
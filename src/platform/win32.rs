@@ -0,0 +1,1918 @@
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::ffi::{CStr, CString, OsStr};
+use std::io;
+use std::iter::once;
+use std::mem;
+use std::os::raw::*;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::OnceLock;
+
+#[cfg(feature = "raw-window-handle")]
+use raw_window_handle::RawWindowHandle;
+use webview_official_sys as sys;
+
+use super::{
+    DeepLinkCallback, FileDropCallback, FocusCallback, FrameCallback, HistoryAction, KeyCallback,
+    Layout, LoadCallback, NavigationCallback, PaintCallback, ProcessMemory, Request,
+    SessionCallback, ThemeCallback,
+};
+use crate::builder::Cache;
+use crate::cstring;
+use crate::{
+    json, Backend, BackgroundThrottling, Capabilities, Color, DragItem, Error, ProgressState, Rect,
+    SessionEvent, Theme, UserAttention, WindowEffect,
+};
+
+const GWLP_WNDPROC: c_int = -4;
+const GWLP_USERDATA: c_int = -21;
+const WM_DESTROY: c_uint = 0x0002;
+
+#[repr(C)]
+struct MSG {
+    hwnd: *mut c_void,
+    message: c_uint,
+    wparam: usize,
+    lparam: isize,
+    time: u32,
+    pt_x: i32,
+    pt_y: i32,
+}
+
+extern "system" {
+    fn DestroyWindow(hwnd: *mut c_void) -> c_int;
+    fn CallWindowProcW(
+        prev: isize,
+        hwnd: *mut c_void,
+        msg: c_uint,
+        wparam: usize,
+        lparam: isize,
+    ) -> isize;
+    fn GetWindowThreadProcessId(hwnd: *mut c_void, process_id: *mut u32) -> u32;
+    fn PostThreadMessageW(thread_id: u32, msg: c_uint, wparam: usize, lparam: isize) -> c_int;
+    fn RegisterWindowMessageW(name: *const u16) -> c_uint;
+}
+
+#[cfg(target_pointer_width = "64")]
+extern "system" {
+    fn GetWindowLongPtrW(hwnd: *mut c_void, index: c_int) -> isize;
+    fn SetWindowLongPtrW(hwnd: *mut c_void, index: c_int, value: isize) -> isize;
+}
+
+#[cfg(target_pointer_width = "32")]
+extern "system" {
+    #[link_name = "GetWindowLongW"]
+    fn GetWindowLongPtrW(hwnd: *mut c_void, index: c_int) -> isize;
+    #[link_name = "SetWindowLongW"]
+    fn SetWindowLongPtrW(hwnd: *mut c_void, index: c_int, value: isize) -> isize;
+}
+
+static NATIVE_WNDPROC: AtomicIsize = AtomicIsize::new(0);
+
+extern "system" fn detached_wndproc(
+    hwnd: *mut c_void,
+    msg: c_uint,
+    wparam: usize,
+    lparam: isize,
+) -> isize {
+    // The native window procedure posts `WM_QUIT` here, which would stop the host loop.
+    if msg == WM_DESTROY {
+        return 0;
+    }
+    unsafe {
+        CallWindowProcW(
+            NATIVE_WNDPROC.load(Ordering::Relaxed),
+            hwnd,
+            msg,
+            wparam,
+            lparam,
+        )
+    }
+}
+
+/// The WebView2 runtime is checked for on its own, see `windows::runtime_version`.
+pub(crate) fn check_backend() -> Result<(), Error> {
+    Ok(())
+}
+
+/// Stops the window from posting `WM_QUIT` to the thread when it is destroyed.
+///
+/// Windows supplied by the host don't get the native window procedure, so they are left alone.
+pub(crate) fn detach_main_loop(webview: sys::webview_t) {
+    unsafe {
+        let hwnd = sys::webview_get_window(webview);
+        if GetWindowLongPtrW(hwnd, GWLP_USERDATA) != webview as isize {
+            return;
+        }
+        let native = SetWindowLongPtrW(hwnd, GWLP_WNDPROC, detached_wndproc as *const () as isize);
+        NATIVE_WNDPROC.store(native, Ordering::Relaxed);
+    }
+}
+
+pub(crate) fn terminate(webview: sys::webview_t) {
+    unsafe { sys::webview_terminate(webview) }
+}
+
+/// Destroys the window if the native backend created it.
+pub(crate) fn destroy(webview: sys::webview_t, owns_window: bool) {
+    // Destroying the window posts `WM_QUIT` otherwise, which would end the next webview's loop
+    // before it even starts.
+    detach_main_loop(webview);
+    if owns_window {
+        unsafe { DestroyWindow(sys::webview_get_window(webview)) };
+    }
+}
+
+fn dispatch_message() -> c_uint {
+    static MESSAGE: OnceLock<c_uint> = OnceLock::new();
+    *MESSAGE.get_or_init(|| unsafe {
+        RegisterWindowMessageW(to_wide("webview_official_dispatch").as_ptr())
+    })
+}
+
+/// Posts `f` to the UI thread as a message picked up by `handle_message`.
+///
+/// `webview_dispatch` posts `std::function`s that only the native loop knows how to run.
+pub(crate) fn dispatch_external(webview: sys::webview_t, f: sys::DispatchFn, arg: *mut c_void) {
+    unsafe {
+        let thread_id = GetWindowThreadProcessId(sys::webview_get_window(webview), null_mut());
+        PostThreadMessageW(thread_id, dispatch_message(), f as usize, arg as isize);
+    }
+}
+
+/// Runs a callback posted by `dispatch_external`, returns `false` for any other message.
+pub(crate) unsafe fn handle_message(webview: sys::webview_t, msg: *const c_void) -> bool {
+    let msg = &*(msg as *const MSG);
+    if !msg.hwnd.is_null() || msg.message != dispatch_message() {
+        return false;
+    }
+    let f: sys::DispatchFn = mem::transmute(msg.wparam);
+    f(webview, msg.lparam as *mut c_void);
+    true
+}
+
+const MB_OK: c_uint = 0x0;
+const MB_ICONERROR: c_uint = 0x10;
+
+extern "system" {
+    fn MessageBoxW(
+        hwnd: *mut c_void,
+        text: *const u16,
+        caption: *const u16,
+        type_: c_uint,
+    ) -> c_int;
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(once(0)).collect()
+}
+
+/// Shows a modal error dialog.
+pub(crate) fn show_error_dialog(window: *mut c_void, title: &str, message: &str) {
+    let title = to_wide(title);
+    let message = to_wide(message);
+    unsafe {
+        MessageBoxW(
+            window,
+            message.as_ptr(),
+            title.as_ptr(),
+            MB_OK | MB_ICONERROR,
+        );
+    }
+}
+
+const GWL_STYLE: c_int = -16;
+const WS_CHILD: isize = 0x4000_0000;
+const WS_VISIBLE: isize = 0x1000_0000;
+const WM_SIZE: c_uint = 0x0005;
+const WM_DPICHANGED: c_uint = 0x02E0;
+const WM_DPICHANGED_AFTERPARENT: c_uint = 0x02E3;
+const USER_DEFAULT_SCREEN_DPI: u32 = 96;
+
+#[repr(C)]
+struct RECT {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+type SubclassProc = extern "system" fn(*mut c_void, c_uint, usize, isize, usize, usize) -> isize;
+
+extern "system" {
+    fn GetClientRect(hwnd: *mut c_void, rect: *mut RECT) -> c_int;
+    fn GetWindowRect(hwnd: *mut c_void, rect: *mut RECT) -> c_int;
+    fn MoveWindow(
+        hwnd: *mut c_void,
+        x: c_int,
+        y: c_int,
+        width: c_int,
+        height: c_int,
+        repaint: c_int,
+    ) -> c_int;
+    fn SetParent(child: *mut c_void, parent: *mut c_void) -> *mut c_void;
+    fn GetModuleHandleW(name: *const u16) -> *mut c_void;
+    fn GetProcAddress(module: *mut c_void, name: *const c_char) -> *const c_void;
+}
+
+/// The window's scale factor, 1.0 where `GetDpiForWindow` isn't available (before Windows 10).
+fn scale_factor(hwnd: *mut c_void) -> f64 {
+    type GetDpiForWindow = extern "system" fn(*mut c_void) -> u32;
+    static GET_DPI_FOR_WINDOW: OnceLock<Option<GetDpiForWindow>> = OnceLock::new();
+    let get_dpi = GET_DPI_FOR_WINDOW.get_or_init(|| unsafe {
+        let user32 = GetModuleHandleW(to_wide("user32.dll").as_ptr());
+        let f = GetProcAddress(user32, b"GetDpiForWindow\0".as_ptr() as *const c_char);
+        if f.is_null() {
+            None
+        } else {
+            Some(mem::transmute::<*const c_void, GetDpiForWindow>(f))
+        }
+    });
+    match get_dpi.map(|f| f(hwnd)) {
+        Some(dpi) if dpi > 0 => dpi as f64 / USER_DEFAULT_SCREEN_DPI as f64,
+        _ => 1.0,
+    }
+}
+
+#[link(name = "comctl32")]
+extern "system" {
+    fn SetWindowSubclass(
+        hwnd: *mut c_void,
+        proc_: SubclassProc,
+        id: usize,
+        ref_data: usize,
+    ) -> c_int;
+    fn RemoveWindowSubclass(hwnd: *mut c_void, proc_: SubclassProc, id: usize) -> c_int;
+    fn DefSubclassProc(hwnd: *mut c_void, msg: c_uint, wparam: usize, lparam: isize) -> isize;
+}
+
+struct PanelState {
+    hwnd: *mut c_void,
+    parent: *mut c_void,
+    layout: Option<Layout>,
+}
+
+impl PanelState {
+    /// Moves the window to `bounds`, given in logical pixels.
+    fn apply(&mut self, bounds: Rect) {
+        let scale = scale_factor(self.parent);
+        let physical = |v: i32| (v as f64 * scale).round() as c_int;
+        unsafe {
+            MoveWindow(
+                self.hwnd,
+                physical(bounds.x),
+                physical(bounds.y),
+                physical(bounds.width),
+                physical(bounds.height),
+                1,
+            );
+        }
+    }
+
+    fn relayout(&mut self) {
+        let mut rect = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        unsafe { GetClientRect(self.parent, &mut rect) };
+        let scale = scale_factor(self.parent);
+        let logical = |v: i32| (v as f64 / scale).round() as i32;
+        if let Some(layout) = self.layout.as_mut() {
+            let bounds = layout(
+                logical(rect.right - rect.left),
+                logical(rect.bottom - rect.top),
+            );
+            self.apply(bounds);
+        }
+    }
+}
+
+extern "system" fn parent_proc(
+    hwnd: *mut c_void,
+    msg: c_uint,
+    wparam: usize,
+    lparam: isize,
+    _id: usize,
+    ref_data: usize,
+) -> isize {
+    // Let the host resize its window for the new DPI first, the layout follows the result.
+    let result = unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) };
+    if let WM_SIZE | WM_DPICHANGED | WM_DPICHANGED_AFTERPARENT = msg {
+        let state = unsafe { &mut *(ref_data as *mut PanelState) };
+        state.relayout();
+    }
+    result
+}
+
+/// The webview's window turned into a child window of a host window.
+pub(crate) struct Panel(*mut PanelState);
+
+/// The host's `HWND`, the panel is made a child window of it.
+#[cfg(feature = "raw-window-handle")]
+pub(crate) fn foreign_parent(handle: RawWindowHandle) -> Result<*mut c_void, Error> {
+    match handle {
+        RawWindowHandle::Win32(handle) => Ok(handle.hwnd.get() as *mut c_void),
+        _ => Err(Error::BackendUnavailable(
+            "WebView2 can only be attached to Win32 windows".to_string(),
+        )),
+    }
+}
+
+/// Panes are child windows of the webview's window, over its WebView2 controller.
+pub(crate) fn pane_parent(webview: sys::webview_t) -> *mut c_void {
+    unsafe { sys::webview_get_window(webview) }
+}
+
+impl Panel {
+    pub(crate) fn new(webview: sys::webview_t, parent: *mut c_void) -> Panel {
+        // Destroying the child along with its parent must not end the host's loop.
+        detach_main_loop(webview);
+        unsafe {
+            let hwnd = sys::webview_get_window(webview);
+            SetWindowLongPtrW(hwnd, GWL_STYLE, WS_CHILD | WS_VISIBLE);
+            SetParent(hwnd, parent);
+            let state = Box::into_raw(Box::new(PanelState {
+                hwnd,
+                parent,
+                layout: None,
+            }));
+            SetWindowSubclass(parent, parent_proc, hwnd as usize, state as usize);
+            Panel(state)
+        }
+    }
+
+    pub(crate) fn reparent(&mut self, parent: *mut c_void) {
+        unsafe {
+            let state = &mut *self.0;
+            RemoveWindowSubclass(state.parent, parent_proc, state.hwnd as usize);
+            SetParent(state.hwnd, parent);
+            state.parent = parent;
+            SetWindowSubclass(parent, parent_proc, state.hwnd as usize, self.0 as usize);
+            state.relayout();
+        }
+    }
+
+    pub(crate) fn set_bounds(&mut self, bounds: Rect) {
+        unsafe { (*self.0).apply(bounds) }
+    }
+
+    pub(crate) fn set_layout(&mut self, layout: Layout) {
+        unsafe {
+            (*self.0).layout = Some(layout);
+            (*self.0).relayout();
+        }
+    }
+}
+
+impl Drop for Panel {
+    fn drop(&mut self) {
+        unsafe {
+            let state = Box::from_raw(self.0);
+            RemoveWindowSubclass(state.parent, parent_proc, state.hwnd as usize);
+        }
+    }
+}
+
+pub(crate) const BACKEND: Backend = Backend::WebView2;
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    bind: true,
+    scripts: true,
+    window_handle: true,
+    reparent: true,
+    external_loop: true,
+    transparency: false,
+    offscreen: false,
+};
+
+/// The engine can't render offscreen, there is never one, see `CAPABILITIES`.
+pub(crate) enum Offscreen {}
+
+impl Offscreen {
+    pub(crate) fn new(_webview: sys::webview_t, _callback: PaintCallback) -> Option<Offscreen> {
+        None
+    }
+}
+
+/// WebView2 was started with `--disable-gpu`, see `windows::Options`.
+pub(crate) fn disable_hardware_acceleration(_webview: sys::webview_t) -> bool {
+    true
+}
+
+/// WebView2 was started with the switches for `Disabled`, see `windows::Options`. Suspending
+/// needs the page hidden from WebView2 first, which the native backend doesn't do.
+pub(crate) fn set_background_throttling(
+    _webview: sys::webview_t,
+    throttling: BackgroundThrottling,
+) -> bool {
+    throttling != BackgroundThrottling::Suspend
+}
+
+/// WebView2 maps virtual hosts on its `ICoreWebView2`, which the native backend doesn't hand out.
+pub(crate) fn map_host(_webview: sys::webview_t, _host: &str, _folder: &Path) -> bool {
+    false
+}
+
+const TH32CS_SNAPPROCESS: u32 = 0x2;
+const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+#[repr(C)]
+struct ProcessEntry32W {
+    size: u32,
+    usage: u32,
+    process_id: u32,
+    default_heap_id: usize,
+    module_id: u32,
+    threads: u32,
+    parent_process_id: u32,
+    pri_class_base: c_long,
+    flags: u32,
+    exe_file: [u16; 260],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProcessMemoryCounters {
+    cb: u32,
+    page_fault_count: u32,
+    peak_working_set_size: usize,
+    working_set_size: usize,
+    quota_peak_paged_pool_usage: usize,
+    quota_paged_pool_usage: usize,
+    quota_peak_non_paged_pool_usage: usize,
+    quota_non_paged_pool_usage: usize,
+    pagefile_usage: usize,
+    peak_pagefile_usage: usize,
+}
+
+extern "system" {
+    fn CreateToolhelp32Snapshot(flags: u32, process_id: u32) -> *mut c_void;
+    fn Process32FirstW(snapshot: *mut c_void, entry: *mut ProcessEntry32W) -> c_int;
+    fn Process32NextW(snapshot: *mut c_void, entry: *mut ProcessEntry32W) -> c_int;
+    fn OpenProcess(access: u32, inherit: c_int, process_id: u32) -> *mut c_void;
+    fn GetCurrentProcess() -> *mut c_void;
+    fn K32GetProcessMemoryInfo(
+        process: *mut c_void,
+        counters: *mut ProcessMemoryCounters,
+        size: u32,
+    ) -> c_int;
+}
+
+/// The working set of `process`.
+fn working_set(process: *mut c_void) -> Option<u64> {
+    let mut counters = ProcessMemoryCounters {
+        cb: mem::size_of::<ProcessMemoryCounters>() as u32,
+        ..Default::default()
+    };
+    let found = unsafe { K32GetProcessMemoryInfo(process, &mut counters, counters.cb) } != 0;
+    if found {
+        Some(counters.working_set_size as u64)
+    } else {
+        None
+    }
+}
+
+/// The working set of this process and of the `msedgewebview2.exe` processes it started, the
+/// browser process and its renderer, GPU and utility processes.
+pub(crate) fn process_memory() -> ProcessMemory {
+    let mut processes = Vec::new();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot as isize != INVALID_HANDLE_VALUE {
+            let mut entry: ProcessEntry32W = mem::zeroed();
+            entry.size = mem::size_of::<ProcessEntry32W>() as u32;
+            let mut more = Process32FirstW(snapshot, &mut entry) != 0;
+            while more {
+                let len = entry.exe_file.iter().position(|&c| c == 0).unwrap_or(260);
+                let name = String::from_utf16_lossy(&entry.exe_file[..len]);
+                let is_engine = name.eq_ignore_ascii_case("msedgewebview2.exe");
+                processes.push((entry.process_id, entry.parent_process_id, is_engine));
+                more = Process32NextW(snapshot, &mut entry) != 0;
+            }
+            CloseHandle(snapshot);
+        }
+    }
+    let mut descendants = vec![std::process::id()];
+    let mut i = 0;
+    while i < descendants.len() {
+        let parent = descendants[i];
+        descendants.extend(
+            processes
+                .iter()
+                .filter(|&&(pid, p, _)| p == parent && pid != parent)
+                .map(|&(pid, _, _)| pid),
+        );
+        i += 1;
+    }
+    let engine: Vec<u64> = processes
+        .iter()
+        .filter(|&&(pid, _, is_engine)| is_engine && descendants[1..].contains(&pid))
+        .filter_map(|&(pid, _, _)| unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                return None;
+            }
+            let size = working_set(process);
+            CloseHandle(process);
+            size
+        })
+        .collect();
+    ProcessMemory {
+        app: working_set(unsafe { GetCurrentProcess() }),
+        engine: Some((engine.iter().sum(), engine.len())),
+    }
+}
+
+/// The cache is set up through the WebView2 environment, see `windows::Options::with_cache`.
+pub(crate) fn apply_cache(_webview: sys::webview_t, _cache: &Cache) -> Vec<&'static str> {
+    Vec::new()
+}
+
+const WM_WTSSESSION_CHANGE: c_uint = 0x02B1;
+const WTS_CONSOLE_CONNECT: usize = 0x1;
+const WTS_CONSOLE_DISCONNECT: usize = 0x2;
+const WTS_REMOTE_CONNECT: usize = 0x3;
+const WTS_REMOTE_DISCONNECT: usize = 0x4;
+const WTS_SESSION_LOCK: usize = 0x7;
+const WTS_SESSION_UNLOCK: usize = 0x8;
+const NOTIFY_FOR_THIS_SESSION: u32 = 0;
+const SESSION_SUBCLASS_ID: usize = 0x5e55;
+
+#[link(name = "wtsapi32")]
+extern "system" {
+    fn WTSRegisterSessionNotification(hwnd: *mut c_void, flags: u32) -> c_int;
+    fn WTSUnRegisterSessionNotification(hwnd: *mut c_void) -> c_int;
+}
+
+extern "system" fn session_proc(
+    hwnd: *mut c_void,
+    msg: c_uint,
+    wparam: usize,
+    lparam: isize,
+    _id: usize,
+    ref_data: usize,
+) -> isize {
+    if msg == WM_WTSSESSION_CHANGE {
+        let event = match wparam {
+            WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT => Some(SessionEvent::Attached),
+            WTS_CONSOLE_DISCONNECT | WTS_REMOTE_DISCONNECT => Some(SessionEvent::Detached),
+            WTS_SESSION_LOCK => Some(SessionEvent::Locked),
+            WTS_SESSION_UNLOCK => Some(SessionEvent::Unlocked),
+            _ => None,
+        };
+        if let Some(event) = event {
+            let callback = unsafe { &mut *(ref_data as *mut SessionCallback) };
+            callback(event);
+        }
+    }
+    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
+}
+
+/// Receives `WM_WTSSESSION_CHANGE` on the webview's window.
+pub(crate) struct SessionWatch {
+    hwnd: *mut c_void,
+    callback: *mut SessionCallback,
+}
+
+impl SessionWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: SessionCallback) -> SessionWatch {
+        unsafe {
+            let hwnd = sys::webview_get_window(webview);
+            let callback = Box::into_raw(Box::new(callback));
+            SetWindowSubclass(hwnd, session_proc, SESSION_SUBCLASS_ID, callback as usize);
+            WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION);
+            SessionWatch { hwnd, callback }
+        }
+    }
+}
+
+impl Drop for SessionWatch {
+    fn drop(&mut self) {
+        unsafe {
+            WTSUnRegisterSessionNotification(self.hwnd);
+            RemoveWindowSubclass(self.hwnd, session_proc, SESSION_SUBCLASS_ID);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+const SW_SHOWNORMAL: c_int = 1;
+
+#[link(name = "shell32")]
+extern "system" {
+    fn ShellExecuteW(
+        hwnd: *mut c_void,
+        operation: *const u16,
+        file: *const u16,
+        parameters: *const u16,
+        directory: *const u16,
+        show: c_int,
+    ) -> isize;
+}
+
+/// Opens `url` with the user's default handler, e.g. their browser.
+pub(crate) fn open_external(_webview: sys::webview_t, url: &str) -> bool {
+    let (operation, url) = (to_wide("open"), to_wide(url));
+    let result = unsafe {
+        ShellExecuteW(
+            null_mut(),
+            operation.as_ptr(),
+            url.as_ptr(),
+            null(),
+            null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // Values up to 32 are error codes.
+    result > 32
+}
+
+const HISTORY_BINDING: &str = "__webview_history";
+
+/// Reports whether the page can go back and forward, through the Navigation API of Chromium.
+const HISTORY_SCRIPT: &str = r#"(function () {
+  var report = function () {
+    if (window.navigation) {
+      window.__webview_history(String(navigation.canGoBack), String(navigation.canGoForward));
+    }
+  };
+  window.addEventListener("pageshow", report);
+  if (window.navigation) {
+    navigation.addEventListener("currententrychange", report);
+  }
+})();"#;
+
+extern "C" fn on_history(seq: *const c_char, req: *const c_char, arg: *mut c_void) {
+    unsafe {
+        let state = &*(arg as *const HistoryState);
+        let fields = json::strings(&CStr::from_ptr(req).to_string_lossy());
+        if let [back, forward] = fields.as_slice() {
+            state.can_go.set((back == "true", forward == "true"));
+        }
+        sys::webview_return(state.webview, seq, 0, b"null\0".as_ptr() as *const c_char);
+    }
+}
+
+struct HistoryState {
+    webview: sys::webview_t,
+    can_go: Cell<(bool, bool)>,
+}
+
+/// Drives the page from script, the C library doesn't expose the WebView2 controller. The page
+/// reports whether it can go back and forward after each navigation.
+pub(crate) struct View(Box<HistoryState>);
+
+impl View {
+    pub(crate) fn new(webview: sys::webview_t) -> View {
+        let state = Box::new(HistoryState {
+            webview,
+            can_go: Cell::new((false, false)),
+        });
+        let binding = CString::new(HISTORY_BINDING).unwrap();
+        let script = CString::new(HISTORY_SCRIPT).unwrap();
+        unsafe {
+            sys::webview_bind(
+                webview,
+                binding.as_ptr(),
+                Some(on_history),
+                &*state as *const HistoryState as *mut c_void,
+            );
+            sys::webview_init(webview, script.as_ptr());
+        }
+        View(state)
+    }
+
+    /// Returns `false` for `ReloadIgnoringCache`, which script can only approximate with a plain
+    /// reload.
+    pub(crate) fn go(&self, action: HistoryAction) -> bool {
+        let js: &[u8] = match action {
+            HistoryAction::Back => b"history.back();\0",
+            HistoryAction::Forward => b"history.forward();\0",
+            HistoryAction::Reload | HistoryAction::ReloadIgnoringCache => b"location.reload();\0",
+            HistoryAction::Stop => b"window.stop();\0",
+        };
+        unsafe { sys::webview_eval(self.0.webview, js.as_ptr() as *const c_char) };
+        action != HistoryAction::ReloadIgnoringCache
+    }
+
+    /// Loads the url of `request` without its headers, returns `false` if it had any.
+    ///
+    /// Requests with a body are not loaded.
+    pub(crate) fn load(&self, request: &Request) -> bool {
+        if request.body.is_some() {
+            return false;
+        }
+        let c_url = cstring::url(&request.url);
+        unsafe { sys::webview_navigate(self.0.webview, c_url.as_ptr()) };
+        request.headers.is_empty()
+    }
+
+    /// Brings the window to the foreground, the C library passes the focus on to WebView2.
+    pub(crate) fn focus(&self) {
+        unsafe {
+            let hwnd = GetAncestor(sys::webview_get_window(self.0.webview), GA_ROOT);
+            SetForegroundWindow(hwnd);
+            SetFocus(sys::webview_get_window(self.0.webview));
+        }
+    }
+
+    pub(crate) fn is_focused(&self) -> bool {
+        unsafe {
+            let hwnd = GetAncestor(sys::webview_get_window(self.0.webview), GA_ROOT);
+            !hwnd.is_null() && GetForegroundWindow() == hwnd
+        }
+    }
+
+    /// WebView2 only reads its background color on creation, see `windows::Options`.
+    pub(crate) fn set_background_color(&self, _color: Color) -> bool {
+        false
+    }
+
+    /// WebView2 follows the system theme, forcing one takes its controller.
+    pub(crate) fn set_theme(&self, theme: Theme) -> bool {
+        theme == Theme::System
+    }
+
+    pub(crate) fn system_theme(&self) -> Theme {
+        system_theme()
+    }
+
+    pub(crate) fn can_go_back(&self) -> bool {
+        self.0.can_go.get().0
+    }
+
+    pub(crate) fn can_go_forward(&self) -> bool {
+        self.0.can_go.get().1
+    }
+
+    /// `None`, only the page knows its url.
+    pub(crate) fn url(&self) -> Option<String> {
+        None
+    }
+}
+
+const WM_SETICON: c_uint = 0x0080;
+const ICON_SMALL: usize = 0;
+const ICON_BIG: usize = 1;
+const ICON_VERSION: u32 = 0x0003_0000;
+const LR_DEFAULTCOLOR: c_uint = 0;
+
+extern "system" {
+    fn CreateIconFromResourceEx(
+        bits: *const u8,
+        size: u32,
+        icon: c_int,
+        version: u32,
+        cx: c_int,
+        cy: c_int,
+        flags: c_uint,
+    ) -> *mut c_void;
+    fn DestroyIcon(icon: *mut c_void) -> c_int;
+    fn SendMessageW(hwnd: *mut c_void, msg: c_uint, wparam: usize, lparam: isize) -> isize;
+    fn SetPropW(hwnd: *mut c_void, name: *const u16, data: *mut c_void) -> c_int;
+    fn RemovePropW(hwnd: *mut c_void, name: *const u16) -> *mut c_void;
+}
+
+/// The largest image in an ICO file, a PNG or a headerless bitmap.
+fn ico_image(ico: &[u8]) -> Option<&[u8]> {
+    let u16_at = |at: usize| Some(u16::from_le_bytes([*ico.get(at)?, *ico.get(at + 1)?]));
+    let u32_at = |at: usize| Some(u16_at(at)? as u32 | (u16_at(at + 2)? as u32) << 16);
+    if u16_at(0)? != 0 || u16_at(2)? != 1 {
+        return None;
+    }
+    let entries = (0..u16_at(4)? as usize).map(|i| 6 + i * 16);
+    let largest = entries.max_by_key(|&entry| match ico.get(entry) {
+        Some(0) => 256,
+        Some(&width) => width as u32,
+        None => 0,
+    })?;
+    let size = u32_at(largest + 8)? as usize;
+    let offset = u32_at(largest + 12)? as usize;
+    ico.get(offset..offset.checked_add(size)?)
+}
+
+/// Makes a PNG or ICO `image` the window's icon, in the title bar and the taskbar.
+pub(crate) fn set_window_icon(webview: sys::webview_t, image: &[u8]) -> bool {
+    let bits = ico_image(image).unwrap_or(image);
+    let name = to_wide("WebviewOfficialIcon");
+    unsafe {
+        let icon = CreateIconFromResourceEx(
+            bits.as_ptr(),
+            bits.len() as u32,
+            1,
+            ICON_VERSION,
+            0,
+            0,
+            LR_DEFAULTCOLOR,
+        );
+        if icon.is_null() {
+            return false;
+        }
+        let hwnd = sys::webview_get_window(webview);
+        SendMessageW(hwnd, WM_SETICON, ICON_SMALL, icon as isize);
+        SendMessageW(hwnd, WM_SETICON, ICON_BIG, icon as isize);
+        // Only the icon set last is in use, the window doesn't destroy it.
+        let previous = RemovePropW(hwnd, name.as_ptr());
+        if !previous.is_null() {
+            DestroyIcon(previous);
+        }
+        SetPropW(hwnd, name.as_ptr(), icon);
+        true
+    }
+}
+
+const WM_NCLBUTTONDOWN: c_uint = 0x00A1;
+const HTCAPTION: usize = 2;
+
+extern "system" {
+    fn ReleaseCapture() -> c_int;
+}
+
+/// Hands the pressed primary button to the window as if it went down on the caption, which
+/// moves the window until it is released.
+pub(crate) fn start_window_drag(webview: sys::webview_t) -> bool {
+    unsafe {
+        ReleaseCapture();
+        SendMessageW(
+            sys::webview_get_window(webview),
+            WM_NCLBUTTONDOWN,
+            HTCAPTION,
+            0,
+        );
+    }
+    true
+}
+
+const IID_IDATA_OBJECT: Guid = Guid(
+    0x0000_010e,
+    0x0000,
+    0x0000,
+    [0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46],
+);
+const DROPEFFECT_COPY: u32 = 1;
+const CF_UNICODETEXT: u16 = 13;
+const DVASPECT_CONTENT: u32 = 1;
+const TYMED_HGLOBAL: u32 = 1;
+const GMEM_MOVEABLE: u32 = 0x0002;
+
+#[repr(C)]
+struct FormatEtc {
+    format: u16,
+    target_device: *mut c_void,
+    aspect: u32,
+    index: i32,
+    tymed: u32,
+}
+
+#[repr(C)]
+struct StgMedium {
+    tymed: u32,
+    global: *mut c_void,
+    release: *mut c_void,
+}
+
+/// The start of `IDataObject`'s vtable, up to the methods used here.
+#[repr(C)]
+struct DataObjectVtbl {
+    query_interface: usize,
+    add_ref: usize,
+    release: extern "system" fn(*mut DataObject) -> u32,
+    get_data: usize,
+    get_data_here: usize,
+    query_get_data: usize,
+    get_canonical_format_etc: usize,
+    set_data: extern "system" fn(*mut DataObject, *const FormatEtc, *const StgMedium, c_int) -> i32,
+}
+
+#[repr(C)]
+struct DataObject {
+    vtbl: *const DataObjectVtbl,
+}
+
+#[link(name = "shell32")]
+extern "system" {
+    fn ILCreateFromPathW(path: *const u16) -> *mut c_void;
+    fn ILFree(pidl: *mut c_void);
+    fn SHCreateDataObject(
+        folder: *const c_void,
+        count: c_uint,
+        children: *const *const c_void,
+        inner: *mut c_void,
+        iid: *const Guid,
+        object: *mut *mut c_void,
+    ) -> i32;
+    fn SHDoDragDrop(
+        hwnd: *mut c_void,
+        data: *mut c_void,
+        source: *mut c_void,
+        effects: u32,
+        effect: *mut u32,
+    ) -> i32;
+}
+
+extern "system" {
+    fn GlobalAlloc(flags: u32, bytes: usize) -> *mut c_void;
+    fn GlobalLock(memory: *mut c_void) -> *mut c_void;
+    fn GlobalUnlock(memory: *mut c_void) -> c_int;
+    fn GlobalFree(memory: *mut c_void) -> *mut c_void;
+}
+
+/// A data object holding the files, from their item ids relative to the desktop.
+unsafe fn files_data_object(paths: &[PathBuf]) -> *mut DataObject {
+    let ids: Vec<*mut c_void> = paths
+        .iter()
+        .map(|path| {
+            let path: Vec<u16> = path.as_os_str().encode_wide().chain(once(0)).collect();
+            ILCreateFromPathW(path.as_ptr())
+        })
+        .filter(|id| !id.is_null())
+        .collect();
+    let mut object = null_mut();
+    if !ids.is_empty() {
+        SHCreateDataObject(
+            null(),
+            ids.len() as c_uint,
+            ids.as_ptr() as *const *const c_void,
+            null_mut(),
+            &IID_IDATA_OBJECT,
+            &mut object,
+        );
+    }
+    for id in ids {
+        ILFree(id);
+    }
+    object as *mut DataObject
+}
+
+/// A data object holding `text` as `CF_UNICODETEXT`.
+unsafe fn text_data_object(text: &str) -> *mut DataObject {
+    let mut object = null_mut();
+    if SHCreateDataObject(
+        null(),
+        0,
+        null(),
+        null_mut(),
+        &IID_IDATA_OBJECT,
+        &mut object,
+    ) < 0
+        || object.is_null()
+    {
+        return null_mut();
+    }
+    let object = object as *mut DataObject;
+    let text = to_wide(text);
+    let global = GlobalAlloc(GMEM_MOVEABLE, text.len() * mem::size_of::<u16>());
+    if global.is_null() {
+        ((*(*object).vtbl).release)(object);
+        return null_mut();
+    }
+    let memory = GlobalLock(global) as *mut u16;
+    memory.copy_from_nonoverlapping(text.as_ptr(), text.len());
+    GlobalUnlock(global);
+    let format = FormatEtc {
+        format: CF_UNICODETEXT,
+        target_device: null_mut(),
+        aspect: DVASPECT_CONTENT,
+        index: -1,
+        tymed: TYMED_HGLOBAL,
+    };
+    let medium = StgMedium {
+        tymed: TYMED_HGLOBAL,
+        global,
+        release: null_mut(),
+    };
+    // The object owns the memory once it took it.
+    if ((*(*object).vtbl).set_data)(object, &format, &medium, 1) < 0 {
+        GlobalFree(global);
+        ((*(*object).vtbl).release)(object);
+        return null_mut();
+    }
+    object
+}
+
+/// Drags `item` out of the window with the shell's drop source, which follows the pressed
+/// button. The call returns once the drag ended.
+pub(crate) fn start_drag(view: &View, item: &DragItem) -> bool {
+    unsafe {
+        let object = match item {
+            DragItem::Files(paths) => files_data_object(paths),
+            DragItem::Text(text) => text_data_object(text),
+        };
+        if object.is_null() {
+            return false;
+        }
+        // WebView2 holds the mouse capture for the press.
+        ReleaseCapture();
+        let mut effect = 0;
+        let result = SHDoDragDrop(
+            sys::webview_get_window(view.0.webview),
+            object as *mut c_void,
+            null_mut(),
+            DROPEFFECT_COPY,
+            &mut effect,
+        );
+        ((*(*object).vtbl).release)(object);
+        result >= 0
+    }
+}
+
+const DWMWA_SYSTEMBACKDROP_TYPE: u32 = 38;
+const DWMSBT_MAINWINDOW: u32 = 2;
+const DWMSBT_TRANSIENTWINDOW: u32 = 3;
+
+#[repr(C)]
+struct Margins {
+    left: c_int,
+    right: c_int,
+    top: c_int,
+    bottom: c_int,
+}
+
+#[link(name = "dwmapi")]
+extern "system" {
+    fn DwmSetWindowAttribute(
+        hwnd: *mut c_void,
+        attribute: u32,
+        value: *const c_void,
+        size: u32,
+    ) -> i32;
+    fn DwmExtendFrameIntoClientArea(hwnd: *mut c_void, margins: *const Margins) -> i32;
+}
+
+/// Sets the window's system backdrop, which shows through the client area once the frame is
+/// extended over all of it. Fails before Windows 11 22H2.
+pub(crate) fn set_window_effect(webview: sys::webview_t, effect: WindowEffect) -> bool {
+    let backdrop = match effect {
+        WindowEffect::Mica => DWMSBT_MAINWINDOW,
+        WindowEffect::Acrylic | WindowEffect::Vibrancy => DWMSBT_TRANSIENTWINDOW,
+    };
+    let margins = Margins {
+        left: -1,
+        right: -1,
+        top: -1,
+        bottom: -1,
+    };
+    unsafe {
+        let hwnd = sys::webview_get_window(webview);
+        let set = DwmSetWindowAttribute(
+            hwnd,
+            DWMWA_SYSTEMBACKDROP_TYPE,
+            &backdrop as *const u32 as *const c_void,
+            mem::size_of::<u32>() as u32,
+        );
+        set >= 0 && DwmExtendFrameIntoClientArea(hwnd, &margins) >= 0
+    }
+}
+
+const HKEY_CURRENT_USER: isize = 0x8000_0001u32 as i32 as isize;
+const RRF_RT_REG_DWORD: u32 = 0x0000_0010;
+const WM_SETTINGCHANGE: c_uint = 0x001A;
+const THEME_SUBCLASS_ID: usize = 0x7e3e;
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegGetValueW(
+        key: isize,
+        sub_key: *const u16,
+        value: *const u16,
+        flags: u32,
+        type_: *mut u32,
+        data: *mut c_void,
+        size: *mut u32,
+    ) -> i32;
+    fn RegSetKeyValueW(
+        key: isize,
+        sub_key: *const u16,
+        value_name: *const u16,
+        type_: u32,
+        data: *const c_void,
+        size: u32,
+    ) -> i32;
+}
+
+const REG_SZ: u32 = 1;
+
+/// Sets a string value of the current user's registry, creating the key as needed.
+fn set_registry_string(key: &str, name: Option<&str>, value: &str) -> io::Result<()> {
+    let key = to_wide(key);
+    let name = name.map(to_wide);
+    let value = to_wide(value);
+    let status = unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            key.as_ptr(),
+            name.as_ref().map_or(null(), |name| name.as_ptr()),
+            REG_SZ,
+            value.as_ptr() as *const c_void,
+            (value.len() * mem::size_of::<u16>()) as u32,
+        )
+    };
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(status))
+    }
+}
+
+/// Registers the running executable as the current user's handler of `scheme`, links are then
+/// passed to new instances as their argument.
+pub(crate) fn register_url_scheme(scheme: &str) -> io::Result<()> {
+    let exe = env::current_exe()?;
+    let key = format!("Software\\Classes\\{}", scheme);
+    set_registry_string(&key, None, &format!("URL:{}", scheme))?;
+    set_registry_string(&key, Some("URL Protocol"), "")?;
+    set_registry_string(
+        &format!("{}\\shell\\open\\command", key),
+        None,
+        &format!("\"{}\" \"%1\"", exe.display()),
+    )
+}
+
+/// Links arrive as command line arguments, there is nothing to watch.
+pub(crate) struct DeepLinkWatch;
+
+impl DeepLinkWatch {
+    pub(crate) fn new(_webview: sys::webview_t, _callback: DeepLinkCallback) -> DeepLinkWatch {
+        DeepLinkWatch
+    }
+}
+
+/// Dark when apps are set to the dark mode in the personalization settings.
+fn system_theme() -> Theme {
+    let key = to_wide("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize");
+    let value = to_wide("AppsUseLightTheme");
+    let mut light: u32 = 1;
+    let mut size = mem::size_of::<u32>() as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_DWORD,
+            null_mut(),
+            &mut light as *mut u32 as *mut c_void,
+            &mut size,
+        );
+    }
+    if light == 0 {
+        Theme::Dark
+    } else {
+        Theme::Light
+    }
+}
+
+extern "system" fn theme_proc(
+    hwnd: *mut c_void,
+    msg: c_uint,
+    wparam: usize,
+    lparam: isize,
+    _id: usize,
+    ref_data: usize,
+) -> isize {
+    if msg == WM_SETTINGCHANGE && lparam != 0 {
+        let area = unsafe { wide_str(lparam as *const u16) };
+        if area == "ImmersiveColorSet" {
+            let callback = unsafe { &mut *(ref_data as *mut ThemeCallback) };
+            callback(system_theme());
+        }
+    }
+    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
+}
+
+unsafe fn wide_str(s: *const u16) -> String {
+    let len = (0..).take_while(|&i| *s.add(i) != 0).count();
+    String::from_utf16_lossy(std::slice::from_raw_parts(s, len))
+}
+
+/// Receives the `WM_SETTINGCHANGE` broadcast when the theme changes on the webview's window.
+pub(crate) struct ThemeWatch {
+    hwnd: *mut c_void,
+    callback: *mut ThemeCallback,
+}
+
+impl ThemeWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: ThemeCallback) -> ThemeWatch {
+        unsafe {
+            let hwnd = sys::webview_get_window(webview);
+            let callback = Box::into_raw(Box::new(callback));
+            SetWindowSubclass(hwnd, theme_proc, THEME_SUBCLASS_ID, callback as usize);
+            ThemeWatch { hwnd, callback }
+        }
+    }
+}
+
+impl Drop for ThemeWatch {
+    fn drop(&mut self) {
+        unsafe {
+            RemoveWindowSubclass(self.hwnd, theme_proc, THEME_SUBCLASS_ID);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+const WM_SIZING: c_uint = 0x0214;
+const WMSZ_TOP: usize = 3;
+const WMSZ_TOPLEFT: usize = 4;
+const WMSZ_TOPRIGHT: usize = 5;
+const WMSZ_BOTTOM: usize = 6;
+const ASPECT_SUBCLASS_ID: usize = 0xa59e;
+
+extern "system" fn aspect_proc(
+    hwnd: *mut c_void,
+    msg: c_uint,
+    wparam: usize,
+    lparam: isize,
+    _id: usize,
+    ref_data: usize,
+) -> isize {
+    if msg != WM_SIZING {
+        return unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) };
+    }
+    let ratio = unsafe { *(ref_data as *const f64) };
+    let rect = unsafe { &mut *(lparam as *mut RECT) };
+    // The ratio is the client area's, the frame around it keeps its size.
+    let mut window = RECT {
+        left: 0,
+        top: 0,
+        right: 0,
+        bottom: 0,
+    };
+    let mut client = RECT {
+        left: 0,
+        top: 0,
+        right: 0,
+        bottom: 0,
+    };
+    unsafe {
+        GetWindowRect(hwnd, &mut window);
+        GetClientRect(hwnd, &mut client);
+    }
+    let frame_width = window.right - window.left - client.right;
+    let frame_height = window.bottom - window.top - client.bottom;
+    let width = rect.right - rect.left - frame_width;
+    let height = rect.bottom - rect.top - frame_height;
+    match wparam {
+        WMSZ_TOP | WMSZ_BOTTOM => {
+            rect.right = rect.left + (height as f64 * ratio).round() as i32 + frame_width;
+        }
+        // The other edges and the corners: the width decides, the edge facing the dragged one
+        // stays in place.
+        _ => {
+            let height = (width as f64 / ratio).round() as i32 + frame_height;
+            if wparam == WMSZ_TOPLEFT || wparam == WMSZ_TOPRIGHT {
+                rect.top = rect.bottom - height;
+            } else {
+                rect.bottom = rect.top + height;
+            }
+        }
+    }
+    1
+}
+
+/// Adjusts the rectangle of each `WM_SIZING` to the ratio.
+pub(crate) struct AspectRatio {
+    hwnd: *mut c_void,
+    ratio: *mut f64,
+}
+
+impl AspectRatio {
+    pub(crate) fn new(webview: sys::webview_t, width: u32, height: u32) -> AspectRatio {
+        unsafe {
+            let hwnd = sys::webview_get_window(webview);
+            let ratio = Box::into_raw(Box::new(width as f64 / height as f64));
+            SetWindowSubclass(hwnd, aspect_proc, ASPECT_SUBCLASS_ID, ratio as usize);
+            AspectRatio { hwnd, ratio }
+        }
+    }
+}
+
+impl Drop for AspectRatio {
+    fn drop(&mut self) {
+        unsafe {
+            RemoveWindowSubclass(self.hwnd, aspect_proc, ASPECT_SUBCLASS_ID);
+            drop(Box::from_raw(self.ratio));
+        }
+    }
+}
+
+const WM_ACTIVATE: c_uint = 0x0006;
+const WA_INACTIVE: usize = 0;
+const GA_ROOT: c_uint = 2;
+const FOCUS_SUBCLASS_ID: usize = 0xf0c5;
+
+extern "system" {
+    fn GetAncestor(hwnd: *mut c_void, flags: c_uint) -> *mut c_void;
+    fn GetForegroundWindow() -> *mut c_void;
+    fn SetFocus(hwnd: *mut c_void) -> *mut c_void;
+    fn SetForegroundWindow(hwnd: *mut c_void) -> c_int;
+}
+
+extern "system" fn focus_proc(
+    hwnd: *mut c_void,
+    msg: c_uint,
+    wparam: usize,
+    lparam: isize,
+    _id: usize,
+    ref_data: usize,
+) -> isize {
+    if msg == WM_ACTIVATE {
+        let callback = unsafe { &mut *(ref_data as *mut FocusCallback) };
+        callback(wparam & 0xffff != WA_INACTIVE);
+    }
+    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
+}
+
+/// Receives `WM_ACTIVATE` as the webview's window is activated and deactivated.
+pub(crate) struct FocusWatch {
+    hwnd: *mut c_void,
+    callback: *mut FocusCallback,
+}
+
+impl FocusWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FocusCallback) -> FocusWatch {
+        unsafe {
+            let hwnd = sys::webview_get_window(webview);
+            let callback = Box::into_raw(Box::new(callback));
+            SetWindowSubclass(hwnd, focus_proc, FOCUS_SUBCLASS_ID, callback as usize);
+            FocusWatch { hwnd, callback }
+        }
+    }
+}
+
+impl Drop for FocusWatch {
+    fn drop(&mut self) {
+        unsafe {
+            RemoveWindowSubclass(self.hwnd, focus_proc, FOCUS_SUBCLASS_ID);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// WebView2 registers its own drop target on a window of its controller, which the native
+/// backend doesn't expose, and pages only see the names of dropped files.
+pub(crate) struct FileDropWatch;
+
+impl FileDropWatch {
+    pub(crate) fn new(_view: &View, _callback: FileDropCallback) -> Option<FileDropWatch> {
+        None
+    }
+}
+
+/// Keys go to the WebView2 process, its controller reports accelerators before the page but the
+/// native backend doesn't expose it.
+pub(crate) struct KeyWatch;
+
+impl KeyWatch {
+    pub(crate) fn new(_view: &View, _callback: KeyCallback) -> Option<KeyWatch> {
+        None
+    }
+}
+
+/// WebView2 asks before navigating through its `ICoreWebView2`, which the native backend doesn't
+/// hand out.
+pub(crate) struct NavigationWatch;
+
+impl NavigationWatch {
+    pub(crate) fn new(_view: &View, _callback: NavigationCallback) -> Option<NavigationWatch> {
+        None
+    }
+}
+
+/// WebView2 reports finished loads on its `ICoreWebView2`, which the native backend doesn't hand
+/// out.
+pub(crate) struct LoadWatch;
+
+impl LoadWatch {
+    pub(crate) fn new(_view: &View, _callback: LoadCallback) -> Option<LoadWatch> {
+        None
+    }
+}
+
+const FLASHW_TRAY: u32 = 0x0000_0002;
+const FLASHW_ALL: u32 = 0x0000_0003;
+const FLASHW_TIMERNOFG: u32 = 0x0000_000C;
+
+#[repr(C)]
+struct FLASHWINFO {
+    size: u32,
+    hwnd: *mut c_void,
+    flags: u32,
+    count: u32,
+    timeout: u32,
+}
+
+extern "system" {
+    fn FlashWindowEx(info: *const FLASHWINFO) -> c_int;
+}
+
+/// Flashes the taskbar entry, and the caption for critical requests.
+pub(crate) fn request_user_attention(webview: sys::webview_t, level: UserAttention) {
+    let (flags, count) = match level {
+        UserAttention::Informational => (FLASHW_TRAY, 3),
+        UserAttention::Critical => (FLASHW_ALL | FLASHW_TIMERNOFG, 0),
+    };
+    unsafe {
+        let info = FLASHWINFO {
+            size: mem::size_of::<FLASHWINFO>() as u32,
+            hwnd: sys::webview_get_window(webview),
+            flags,
+            count,
+            timeout: 0,
+        };
+        FlashWindowEx(&info);
+    }
+}
+
+#[repr(C)]
+struct Guid(u32, u16, u16, [u8; 8]);
+
+const CLSID_TASKBAR_LIST: Guid = Guid(
+    0x56fd_f344,
+    0xfd6d,
+    0x11d0,
+    [0x95, 0x8a, 0x00, 0x60, 0x97, 0xc9, 0xa0, 0x90],
+);
+const IID_ITASKBAR_LIST3: Guid = Guid(
+    0xea1a_fb91,
+    0x9e28,
+    0x4b86,
+    [0x90, 0xe9, 0x9e, 0x9f, 0x8a, 0x5e, 0xef, 0xaf],
+);
+const CLSCTX_INPROC_SERVER: u32 = 0x1;
+const TBPF_NOPROGRESS: u32 = 0x0;
+const TBPF_INDETERMINATE: u32 = 0x1;
+const TBPF_NORMAL: u32 = 0x2;
+const TBPF_ERROR: u32 = 0x4;
+const TBPF_PAUSED: u32 = 0x8;
+
+/// The start of `ITaskbarList3`'s vtable, up to the methods used here.
+#[repr(C)]
+struct TaskbarListVtbl {
+    query_interface: usize,
+    add_ref: usize,
+    release: extern "system" fn(*mut TaskbarList) -> u32,
+    hr_init: extern "system" fn(*mut TaskbarList) -> i32,
+    add_tab: usize,
+    delete_tab: usize,
+    activate_tab: usize,
+    set_active_alt: usize,
+    mark_fullscreen_window: usize,
+    set_progress_value: extern "system" fn(*mut TaskbarList, *mut c_void, u64, u64) -> i32,
+    set_progress_state: extern "system" fn(*mut TaskbarList, *mut c_void, u32) -> i32,
+}
+
+#[repr(C)]
+struct TaskbarList {
+    vtbl: *const TaskbarListVtbl,
+}
+
+#[link(name = "ole32")]
+extern "system" {
+    fn CoCreateInstance(
+        clsid: *const Guid,
+        outer: *mut c_void,
+        context: u32,
+        iid: *const Guid,
+        object: *mut *mut c_void,
+    ) -> i32;
+}
+
+/// Sets the progress of the window's taskbar button through `ITaskbarList3`. COM is already set
+/// up on the UI thread for WebView2.
+pub(crate) fn set_progress(webview: sys::webview_t, state: ProgressState, fraction: f64) {
+    let flags = match state {
+        ProgressState::None => TBPF_NOPROGRESS,
+        ProgressState::Normal => TBPF_NORMAL,
+        ProgressState::Indeterminate => TBPF_INDETERMINATE,
+        ProgressState::Paused => TBPF_PAUSED,
+        ProgressState::Error => TBPF_ERROR,
+    };
+    const TOTAL: u64 = 10_000;
+    unsafe {
+        let mut object = null_mut();
+        let created = CoCreateInstance(
+            &CLSID_TASKBAR_LIST,
+            null_mut(),
+            CLSCTX_INPROC_SERVER,
+            &IID_ITASKBAR_LIST3,
+            &mut object,
+        );
+        if created < 0 || object.is_null() {
+            return;
+        }
+        let list = object as *mut TaskbarList;
+        let vtbl = &*(*list).vtbl;
+        let hwnd = sys::webview_get_window(webview);
+        if (vtbl.hr_init)(list) >= 0 {
+            (vtbl.set_progress_state)(list, hwnd, flags);
+            if state != ProgressState::None && state != ProgressState::Indeterminate {
+                (vtbl.set_progress_value)(list, hwnd, (fraction * TOTAL as f64) as u64, TOTAL);
+            }
+        }
+        (vtbl.release)(list);
+    }
+}
+
+/// Taskbar buttons only take an overlay icon, drawing a count onto one is left to applications.
+pub(crate) fn set_badge_count(_webview: sys::webview_t, _count: Option<u32>) -> bool {
+    false
+}
+
+const GWLP_HWNDPARENT: c_int = -8;
+const WM_CLOSE: c_uint = 0x0010;
+const OWNER_SUBCLASS_ID: usize = 0x0e7e;
+
+extern "system" {
+    fn EnableWindow(hwnd: *mut c_void, enable: c_int) -> c_int;
+}
+
+extern "system" fn owner_proc(
+    hwnd: *mut c_void,
+    msg: c_uint,
+    wparam: usize,
+    lparam: isize,
+    _id: usize,
+    ref_data: usize,
+) -> isize {
+    // The owner has to take input again before its modal window goes, or Windows activates
+    // another application's window instead.
+    if msg == WM_CLOSE {
+        unsafe { EnableWindow(ref_data as *mut c_void, 1) };
+    }
+    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
+}
+
+/// Makes the owner's window the owner of the webview's, which keeps it above. A modal window
+/// disables its owner until it closes.
+pub(crate) struct Owner {
+    hwnd: *mut c_void,
+    owner: *mut c_void,
+    modal: bool,
+}
+
+impl Owner {
+    pub(crate) fn new(webview: sys::webview_t, owner: sys::webview_t, modal: bool) -> Owner {
+        unsafe {
+            let hwnd = sys::webview_get_window(webview);
+            let owner = sys::webview_get_window(owner);
+            SetWindowLongPtrW(hwnd, GWLP_HWNDPARENT, owner as isize);
+            if modal {
+                EnableWindow(owner, 0);
+                SetWindowSubclass(hwnd, owner_proc, OWNER_SUBCLASS_ID, owner as usize);
+            }
+            Owner { hwnd, owner, modal }
+        }
+    }
+}
+
+impl Drop for Owner {
+    fn drop(&mut self) {
+        if self.modal {
+            unsafe {
+                EnableWindow(self.owner, 1);
+                RemoveWindowSubclass(self.hwnd, owner_proc, OWNER_SUBCLASS_ID);
+            }
+        }
+    }
+}
+
+const SW_HIDE: c_int = 0;
+const SW_SHOW: c_int = 5;
+
+extern "system" {
+    fn ShowWindow(hwnd: *mut c_void, show: c_int) -> c_int;
+}
+
+pub(crate) fn set_visible(webview: sys::webview_t, visible: bool) {
+    let show = if visible { SW_SHOW } else { SW_HIDE };
+    unsafe { ShowWindow(sys::webview_get_window(webview), show) };
+}
+
+const SW_MAXIMIZE: c_int = 3;
+const WM_MOVE: c_uint = 0x0003;
+const MONITORINFOF_PRIMARY: u32 = 1;
+const FRAME_SUBCLASS_ID: usize = 0xf4a3;
+
+#[repr(C)]
+struct MONITORINFO {
+    size: u32,
+    monitor: RECT,
+    work: RECT,
+    flags: u32,
+}
+
+type MonitorEnumProc = extern "system" fn(*mut c_void, *mut c_void, *mut RECT, isize) -> c_int;
+
+extern "system" {
+    fn IsZoomed(hwnd: *mut c_void) -> c_int;
+    fn IsIconic(hwnd: *mut c_void) -> c_int;
+    fn EnumDisplayMonitors(
+        hdc: *mut c_void,
+        clip: *const RECT,
+        callback: MonitorEnumProc,
+        data: isize,
+    ) -> c_int;
+    fn GetMonitorInfoW(monitor: *mut c_void, info: *mut MONITORINFO) -> c_int;
+}
+
+fn to_rect(rect: &RECT) -> Rect {
+    Rect {
+        x: rect.left,
+        y: rect.top,
+        width: rect.right - rect.left,
+        height: rect.bottom - rect.top,
+    }
+}
+
+pub(crate) fn window_frame(webview: sys::webview_t) -> Rect {
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: 0,
+        bottom: 0,
+    };
+    unsafe { GetWindowRect(sys::webview_get_window(webview), &mut rect) };
+    to_rect(&rect)
+}
+
+pub(crate) fn set_window_frame(webview: sys::webview_t, frame: Rect) {
+    unsafe {
+        MoveWindow(
+            sys::webview_get_window(webview),
+            frame.x,
+            frame.y,
+            frame.width,
+            frame.height,
+            1,
+        );
+    }
+}
+
+pub(crate) fn is_maximized(webview: sys::webview_t) -> bool {
+    unsafe { IsZoomed(sys::webview_get_window(webview)) != 0 }
+}
+
+pub(crate) fn is_minimized(webview: sys::webview_t) -> bool {
+    unsafe { IsIconic(sys::webview_get_window(webview)) != 0 }
+}
+
+pub(crate) fn maximize(webview: sys::webview_t) {
+    unsafe { ShowWindow(sys::webview_get_window(webview), SW_MAXIMIZE) };
+}
+
+const WS_OVERLAPPEDWINDOW: isize = 0x00CF_0000;
+const MONITOR_DEFAULTTONEAREST: u32 = 2;
+const SWP_NOZORDER: c_uint = 0x0004;
+const SWP_FRAMECHANGED: c_uint = 0x0020;
+const SWP_NOOWNERZORDER: c_uint = 0x0200;
+
+extern "system" {
+    fn MonitorFromWindow(hwnd: *mut c_void, flags: u32) -> *mut c_void;
+    fn SetWindowPos(
+        hwnd: *mut c_void,
+        insert_after: *mut c_void,
+        x: c_int,
+        y: c_int,
+        width: c_int,
+        height: c_int,
+        flags: c_uint,
+    ) -> c_int;
+}
+
+thread_local! {
+    /// The windows made fullscreen with the style and frame they go back to.
+    static FULLSCREEN: RefCell<Vec<(usize, isize, Rect)>> = RefCell::new(Vec::new());
+}
+
+unsafe fn set_window_pos(hwnd: *mut c_void, frame: Rect) {
+    SetWindowPos(
+        hwnd,
+        null_mut(),
+        frame.x,
+        frame.y,
+        frame.width,
+        frame.height,
+        SWP_NOZORDER | SWP_NOOWNERZORDER | SWP_FRAMECHANGED,
+    );
+}
+
+pub(crate) fn is_fullscreen(webview: sys::webview_t) -> bool {
+    let hwnd = unsafe { sys::webview_get_window(webview) } as usize;
+    FULLSCREEN.with(|windows| windows.borrow().iter().any(|&(w, _, _)| w == hwnd))
+}
+
+/// Takes the frame off the window and lays it over its monitor, or puts it back.
+pub(crate) fn set_fullscreen(webview: sys::webview_t, fullscreen: bool) {
+    if is_fullscreen(webview) == fullscreen {
+        return;
+    }
+    unsafe {
+        let hwnd = sys::webview_get_window(webview);
+        if fullscreen {
+            let mut info = MONITORINFO {
+                size: mem::size_of::<MONITORINFO>() as u32,
+                monitor: RECT {
+                    left: 0,
+                    top: 0,
+                    right: 0,
+                    bottom: 0,
+                },
+                work: RECT {
+                    left: 0,
+                    top: 0,
+                    right: 0,
+                    bottom: 0,
+                },
+                flags: 0,
+            };
+            let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+            if GetMonitorInfoW(monitor, &mut info) == 0 {
+                return;
+            }
+            let style = GetWindowLongPtrW(hwnd, GWL_STYLE);
+            FULLSCREEN.with(|windows| {
+                windows
+                    .borrow_mut()
+                    .push((hwnd as usize, style, window_frame(webview)))
+            });
+            SetWindowLongPtrW(hwnd, GWL_STYLE, style & !WS_OVERLAPPEDWINDOW);
+            set_window_pos(hwnd, to_rect(&info.monitor));
+        } else {
+            let saved = FULLSCREEN.with(|windows| {
+                let mut windows = windows.borrow_mut();
+                let index = windows.iter().position(|&(w, _, _)| w == hwnd as usize)?;
+                Some(windows.remove(index))
+            });
+            if let Some((_, style, frame)) = saved {
+                SetWindowLongPtrW(hwnd, GWL_STYLE, style);
+                set_window_pos(hwnd, frame);
+            }
+        }
+    }
+}
+
+extern "system" fn add_monitor(
+    monitor: *mut c_void,
+    _hdc: *mut c_void,
+    _rect: *mut RECT,
+    data: isize,
+) -> c_int {
+    let monitors = unsafe { &mut *(data as *mut Vec<Rect>) };
+    let mut info = MONITORINFO {
+        size: mem::size_of::<MONITORINFO>() as u32,
+        monitor: RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        },
+        work: RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        },
+        flags: 0,
+    };
+    if unsafe { GetMonitorInfoW(monitor, &mut info) } != 0 {
+        if info.flags & MONITORINFOF_PRIMARY != 0 {
+            monitors.insert(0, to_rect(&info.work));
+        } else {
+            monitors.push(to_rect(&info.work));
+        }
+    }
+    1
+}
+
+/// The work areas of the monitors, without the taskbar, the primary one first.
+pub(crate) fn monitors(_webview: sys::webview_t) -> Vec<Rect> {
+    let mut monitors: Vec<Rect> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(
+            null_mut(),
+            null(),
+            add_monitor,
+            &mut monitors as *mut Vec<Rect> as isize,
+        );
+    }
+    monitors
+}
+
+extern "system" fn frame_proc(
+    hwnd: *mut c_void,
+    msg: c_uint,
+    wparam: usize,
+    lparam: isize,
+    _id: usize,
+    ref_data: usize,
+) -> isize {
+    let result = unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) };
+    if msg == WM_MOVE || msg == WM_SIZE {
+        let callback = unsafe { &mut *(ref_data as *mut FrameCallback) };
+        callback();
+    }
+    result
+}
+
+/// Receives `WM_MOVE` and `WM_SIZE`, which is also sent as the window is maximized, minimized
+/// and restored.
+pub(crate) struct FrameWatch {
+    hwnd: *mut c_void,
+    callback: *mut FrameCallback,
+}
+
+impl FrameWatch {
+    pub(crate) fn new(webview: sys::webview_t, callback: FrameCallback) -> FrameWatch {
+        unsafe {
+            let hwnd = sys::webview_get_window(webview);
+            let callback = Box::into_raw(Box::new(callback));
+            SetWindowSubclass(hwnd, frame_proc, FRAME_SUBCLASS_ID, callback as usize);
+            FrameWatch { hwnd, callback }
+        }
+    }
+}
+
+impl Drop for FrameWatch {
+    fn drop(&mut self) {
+        unsafe {
+            RemoveWindowSubclass(self.hwnd, frame_proc, FRAME_SUBCLASS_ID);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+const LOCALE_NAME_MAX_LENGTH: usize = 85;
+
+extern "system" {
+    fn GetUserDefaultLocaleName(name: *mut u16, len: c_int) -> c_int;
+}
+
+/// The user's locale, e.g. `en-US`.
+pub(crate) fn locale() -> Option<String> {
+    let mut name = [0u16; LOCALE_NAME_MAX_LENGTH];
+    let len = unsafe { GetUserDefaultLocaleName(name.as_mut_ptr(), name.len() as c_int) };
+    if len <= 1 {
+        return None;
+    }
+    // The length includes the terminating null.
+    Some(String::from_utf16_lossy(&name[..len as usize - 1]))
+}
+
+const POWER_REQUEST_CONTEXT_VERSION: c_ulong = 0;
+const POWER_REQUEST_CONTEXT_SIMPLE_STRING: c_ulong = 0x1;
+const POWER_REQUEST_DISPLAY_REQUIRED: c_int = 0;
+const POWER_REQUEST_SYSTEM_REQUIRED: c_int = 1;
+const INVALID_HANDLE_VALUE: isize = -1;
+
+#[repr(C)]
+struct ReasonContext {
+    version: c_ulong,
+    flags: c_ulong,
+    simple_reason_string: *mut u16,
+}
+
+extern "system" {
+    fn PowerCreateRequest(context: *const ReasonContext) -> *mut c_void;
+    fn PowerSetRequest(request: *mut c_void, request_type: c_int) -> c_int;
+    fn PowerClearRequest(request: *mut c_void, request_type: c_int) -> c_int;
+    fn CloseHandle(handle: *mut c_void) -> c_int;
+}
+
+/// Keeps the display on and the system awake while it is held, with a power request, which
+/// `powercfg /requests` lists with its reason.
+pub(crate) struct SleepInhibitor(*mut c_void);
+
+impl SleepInhibitor {
+    pub(crate) fn new(_webview: sys::webview_t, reason: &str) -> Option<SleepInhibitor> {
+        let mut reason = to_wide(reason);
+        let context = ReasonContext {
+            version: POWER_REQUEST_CONTEXT_VERSION,
+            flags: POWER_REQUEST_CONTEXT_SIMPLE_STRING,
+            simple_reason_string: reason.as_mut_ptr(),
+        };
+        unsafe {
+            let request = PowerCreateRequest(&context);
+            if request.is_null() || request as isize == INVALID_HANDLE_VALUE {
+                return None;
+            }
+            PowerSetRequest(request, POWER_REQUEST_DISPLAY_REQUIRED);
+            PowerSetRequest(request, POWER_REQUEST_SYSTEM_REQUIRED);
+            Some(SleepInhibitor(request))
+        }
+    }
+}
+
+impl Drop for SleepInhibitor {
+    fn drop(&mut self) {
+        unsafe {
+            PowerClearRequest(self.0, POWER_REQUEST_DISPLAY_REQUIRED);
+            PowerClearRequest(self.0, POWER_REQUEST_SYSTEM_REQUIRED);
+            CloseHandle(self.0);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Functionality packaged to be shared between applications, see `WebviewBuilder::plugin`.
+
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+use crate::json;
+use crate::Webview;
+
+/// The name of the binding the page reports its loads through.
+pub(crate) const BINDING: &str = "__webview_plugin_page";
+
+/// Reports every page to the plugins, `init` scripts run before the page's own scripts.
+pub(crate) const INIT_SCRIPT: &str = r#"
+window.__webview_plugin_page(window.location.href);
+"#;
+
+/// Reusable functionality for webviews, e.g. file access or dialogs, packaged as a crate of its
+/// own and added with `WebviewBuilder::plugin` or `Webview::add_plugin`.
+///
+/// The plugin is its own state, it lives as long as the webview and `Webview::with_plugin`
+/// reaches it. Bindings and other handlers are registered in `setup`, a handler reaching the
+/// plugin has to use `with_plugin` as well. The hooks run on the UI thread, and the plugin is
+/// locked while they do, so they mustn't call `with_plugin` for the same plugin.
+pub trait Plugin: 'static {
+    /// Tells the plugin apart in messages, e.g. the name of its crate.
+    fn name(&self) -> &str;
+
+    /// Scripts that run on every page before its own scripts, in the order the plugins were
+    /// added, e.g. to put the plugin's JS API in place.
+    fn init_scripts(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Called once, right after the plugin is added and its scripts were registered, to bind
+    /// functions and register handlers such as `Webview::on_focus_changed`.
+    fn setup(&mut self, _webview: &mut Webview<'_>) {}
+
+    /// Called when a page starts loading, with its url, before any of its scripts ran.
+    ///
+    /// The url is the one the view shows, pages can't pass another. On Windows, where the view
+    /// doesn't tell, it's the one the page reports.
+    fn on_page_load(&mut self, _webview: &mut Webview<'_>, _url: &str) {}
+
+    /// Called once before the webview quits, along with the `on_before_quit` hooks.
+    fn on_quit(&mut self) {}
+}
+
+struct Entry {
+    plugin: Arc<Mutex<dyn Plugin>>,
+    /// The same plugin as an `Arc<Mutex<P>>`, to hand it out with its own type.
+    typed: Box<dyn Any>,
+}
+
+/// The plugins of a webview, in the order they were added.
+#[derive(Default)]
+pub(crate) struct Plugins {
+    entries: Vec<Entry>,
+}
+
+impl Plugins {
+    pub(crate) fn push<P: Plugin>(&mut self, plugin: P) -> Arc<Mutex<dyn Plugin>> {
+        let typed = Arc::new(Mutex::new(plugin));
+        self.entries.push(Entry {
+            plugin: typed.clone(),
+            typed: Box::new(typed.clone()),
+        });
+        typed
+    }
+
+    /// The first plugin of type `P`.
+    pub(crate) fn get<P: Plugin>(&self) -> Option<Arc<Mutex<P>>> {
+        self.entries
+            .iter()
+            .find_map(|entry| entry.typed.downcast_ref::<Arc<Mutex<P>>>())
+            .cloned()
+    }
+
+    /// All plugins, to call a hook on each without keeping the list locked.
+    pub(crate) fn all(&self) -> Vec<Arc<Mutex<dyn Plugin>>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.plugin.clone())
+            .collect()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The url the init script passed to the binding.
+pub(crate) fn page_url(req: &str) -> Option<String> {
+    json::strings(req).into_iter().next()
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+
+    use crate::WebviewBuilder;
+
+    #[derive(Default)]
+    struct Pages(Vec<String>);
+
+    impl Plugin for Pages {
+        fn name(&self) -> &str {
+            "pages"
+        }
+
+        fn on_page_load(&mut self, _webview: &mut Webview<'_>, url: &str) {
+            self.0.push(url.to_string());
+        }
+    }
+
+    #[test]
+    fn pages_load_at_the_url_the_view_shows() {
+        let mut webview = WebviewBuilder::new().mock().build();
+        webview.add_plugin(Pages::default());
+        assert!(webview.mock().follow_link("https://example.com/"));
+        webview
+            .mock()
+            .call(BINDING, r#"["https://evil.net/"]"#)
+            .unwrap();
+        webview.mock().run_pending();
+        let pages = webview.with_plugin(|pages: &mut Pages| pages.0.clone());
+        assert_eq!(pages.unwrap(), ["https://example.com/"]);
+    }
+}
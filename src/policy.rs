@@ -0,0 +1,288 @@
+use crate::json;
+
+/// The name of the binding the page reports blocked navigations through.
+pub(crate) const BINDING: &str = "__webview_blocked";
+
+/// Where a webview may navigate, see `WebviewBuilder::navigation_policy`.
+///
+/// Patterns match whole urls, `*` standing for any run of characters, e.g.
+/// `https://example.com/*`. In the host a `*` doesn't reach past it: `https://*.example.com/*`
+/// matches the subdomains of `example.com`, and `https://example.com*` any port and page of
+/// `example.com` but not `https://example.com.evil.net`. A url is allowed when it matches none of the deny patterns and,
+/// unless there are none, one of the allow patterns. `about:blank` is always allowed, `data:`
+/// urls only when a pattern allows them.
+#[derive(Debug, Clone, Default)]
+pub struct NavigationPolicy {
+    allow: Vec<String>,
+    deny: Vec<String>,
+    open_externally: bool,
+}
+
+impl NavigationPolicy {
+    pub fn new() -> Self {
+        NavigationPolicy::default()
+    }
+
+    pub fn allow(mut self, pattern: &str) -> Self {
+        self.allow.push(pattern.to_string());
+        self
+    }
+
+    pub fn deny(mut self, pattern: &str) -> Self {
+        self.deny.push(pattern.to_string());
+        self
+    }
+
+    /// Opens blocked urls in the default browser instead of dropping them.
+    pub fn open_externally(mut self, open_externally: bool) -> Self {
+        self.open_externally = open_externally;
+        self
+    }
+
+    pub fn allows(&self, url: &str) -> bool {
+        url == "about:blank"
+            || !self.deny.iter().any(|pattern| matches(pattern, url))
+                && (self.allow.is_empty() || self.allow.iter().any(|pattern| matches(pattern, url)))
+    }
+
+    pub(crate) fn opens_externally(&self) -> bool {
+        self.open_externally
+    }
+
+    /// Enforces the policy in the page: links, form submissions and `window.open` to blocked
+    /// urls are cancelled, and a page that was loaded anyway, e.g. after a redirect, is left.
+    ///
+    /// Only a best effort fallback for where the engine can't be asked, the page's own scripts
+    /// run alongside it and can get around it.
+    pub(crate) fn script(&self) -> String {
+        let patterns = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| format!("new RegExp({})", json::string(&regex(pattern))))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            r#"(function () {{
+  var allow = [{allow}], deny = [{deny}];
+  var resolve = function (url) {{ return new URL(url, location.href).href; }};
+  var allowed = function (url) {{
+    var test = function (pattern) {{ return pattern.test(url); }};
+    return url === "about:blank" || !deny.some(test) && (allow.length === 0 || allow.some(test));
+  }};
+  var block = function (url) {{ window.{binding}(url); }};
+  if (window === window.top && !allowed(location.href)) {{
+    block(location.href);
+    window.stop();
+    if (history.length > 1) {{
+      history.back();
+    }} else {{
+      location.replace("about:blank");
+    }}
+  }}
+  document.addEventListener("click", function (event) {{
+    var link = event.target.closest && event.target.closest("a[href]");
+    if (link && !allowed(resolve(link.href))) {{
+      event.preventDefault();
+      block(resolve(link.href));
+    }}
+  }}, true);
+  document.addEventListener("submit", function (event) {{
+    var action = resolve(event.target.action || location.href);
+    if (!allowed(action)) {{
+      event.preventDefault();
+      block(action);
+    }}
+  }}, true);
+  var open = window.open;
+  window.open = function (url) {{
+    if (url !== undefined && url !== "" && !allowed(resolve(String(url)))) {{
+      block(resolve(String(url)));
+      return null;
+    }}
+    return open.apply(window, arguments);
+  }};
+}})();"#,
+            allow = patterns(&self.allow),
+            deny = patterns(&self.deny),
+            binding = BINDING,
+        )
+    }
+}
+
+/// What a `*` of a pattern stands for, depending on where it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wildcard {
+    /// In the path, query or fragment: anything.
+    Any,
+    /// Within the host, e.g. `https://*.example.com/`: anything short of the authority's end.
+    Authority,
+    /// A whole host or a port ending the pattern, e.g. `https://*` or `http://localhost:*`: the
+    /// rest of the authority and anything after it.
+    AuthorityAndRest,
+    /// Right after a host it would otherwise extend, e.g. `https://example.com*/`: a port.
+    Port,
+    /// The same, ending the pattern, e.g. `https://example.com*`: a port and anything after the
+    /// authority.
+    PortAndRest,
+}
+
+impl Wildcard {
+    fn admits(self, s: &str) -> bool {
+        let end = s.find(['/', '?', '#']).unwrap_or(s.len());
+        match self {
+            Wildcard::Any => true,
+            Wildcard::Authority => end == s.len() && !s.contains('@'),
+            Wildcard::AuthorityAndRest => !s[..end].contains('@'),
+            Wildcard::Port => end == s.len() && is_port(s),
+            Wildcard::PortAndRest => is_port(&s[..end]),
+        }
+    }
+
+    fn regex(self) -> &'static str {
+        match self {
+            Wildcard::Any => ".*",
+            Wildcard::Authority => "[^/?#@]*",
+            Wildcard::AuthorityAndRest => "[^/?#@]*(?:[/?#].*)?",
+            Wildcard::Port => "(?::[0-9]+)?",
+            Wildcard::PortAndRest => "(?::[0-9]+)?(?:[/?#].*)?",
+        }
+    }
+}
+
+/// Nothing or a `:` followed by digits.
+fn is_port(s: &str) -> bool {
+    s.is_empty()
+        || s.strip_prefix(':')
+            .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Wildcard(Wildcard),
+}
+
+/// Splits `pattern` at its `*`s, telling those in the authority, after `://`, apart so they
+/// don't reach past the host.
+fn parts(pattern: &str) -> Vec<Part<'_>> {
+    let authority = pattern.find("://").map(|at| {
+        let start = at + 3;
+        let end = pattern[start..]
+            .find('/')
+            .map_or(pattern.len(), |end| start + end);
+        (start, end)
+    });
+    let mut parts = Vec::new();
+    let mut text = 0;
+    for (i, c) in pattern.char_indices() {
+        if c != '*' {
+            continue;
+        }
+        if text < i {
+            parts.push(Part::Text(&pattern[text..i]));
+        }
+        text = i + 1;
+        let wildcard = match authority {
+            Some((start, end)) if start <= i && i < end => {
+                let after_host = i > start && !matches!(pattern.as_bytes()[i - 1], b'.' | b':');
+                match (i + 1 == end, after_host, i + 1 == pattern.len()) {
+                    (false, _, _) => Wildcard::Authority,
+                    (true, true, false) => Wildcard::Port,
+                    (true, true, true) => Wildcard::PortAndRest,
+                    (true, false, false) => Wildcard::Authority,
+                    (true, false, true) => Wildcard::AuthorityAndRest,
+                }
+            }
+            _ => Wildcard::Any,
+        };
+        parts.push(Part::Wildcard(wildcard));
+    }
+    if text < pattern.len() {
+        parts.push(Part::Text(&pattern[text..]));
+    }
+    parts
+}
+
+/// Whether `url` matches `pattern` from start to end.
+pub(crate) fn matches(pattern: &str, url: &str) -> bool {
+    fn rest(parts: &[Part<'_>], url: &str) -> bool {
+        match parts.split_first() {
+            None => url.is_empty(),
+            Some((Part::Text(text), parts)) => {
+                url.strip_prefix(text).is_some_and(|url| rest(parts, url))
+            }
+            Some((Part::Wildcard(wildcard), parts)) => url
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(url.len()))
+                .any(|i| wildcard.admits(&url[..i]) && rest(parts, &url[i..])),
+        }
+    }
+    rest(&parts(pattern), url)
+}
+
+/// The JavaScript regular expression for `pattern`, matching what `matches` does.
+pub(crate) fn regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    for part in parts(pattern) {
+        match part {
+            Part::Wildcard(wildcard) => regex.push_str(wildcard.regex()),
+            Part::Text(text) => {
+                for c in text.chars() {
+                    if "\\^$.|?+*()[]{}/".contains(c) {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+            }
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn wildcards_in_the_path_match_anything() {
+        assert!(matches("https://example.com/*", "https://example.com/"));
+        assert!(matches(
+            "https://example.com/*",
+            "https://example.com/a/b?c#d"
+        ));
+        assert!(!matches("https://example.com/*", "https://example.com"));
+        assert!(matches("data:*", "data:text/html,hi"));
+        assert!(matches("about:blank", "about:blank"));
+    }
+
+    #[test]
+    fn wildcards_after_a_host_stop_at_the_origin() {
+        let pattern = "https://example.com*";
+        assert!(matches(pattern, "https://example.com"));
+        assert!(matches(pattern, "https://example.com:8443/app"));
+        assert!(matches(pattern, "https://example.com/app?x#y"));
+        assert!(!matches(pattern, "https://example.com.evil.net"));
+        assert!(!matches(pattern, "https://example.com.evil.net/"));
+        assert!(!matches(pattern, "https://example.com@evil.net/"));
+        assert!(!matches(
+            "https://example.com*/",
+            "https://example.comevil.net/"
+        ));
+    }
+
+    #[test]
+    fn wildcards_in_the_host_stay_in_it() {
+        let pattern = "https://*.example.com/*";
+        assert!(matches(pattern, "https://app.example.com/"));
+        assert!(matches(pattern, "https://a.b.example.com/x"));
+        assert!(!matches(pattern, "https://evil.net/.example.com/"));
+        assert!(!matches(pattern, "https://evil.net?.example.com/"));
+        assert!(!matches(pattern, "https://user@evil.net#.example.com/"));
+        assert!(matches("https://*", "https://anything.net/at/all"));
+        assert!(!matches("https://*", "http://anything.net/"));
+        assert!(matches("http://localhost:*", "http://localhost:5173/"));
+        assert!(matches("http://localhost:*/*", "http://localhost:5173/src"));
+    }
+}
@@ -0,0 +1,72 @@
+//! The threads pooled binding handlers run on, see `Webview::bind_pooled`.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::json;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// How many threads a pool has unless `WebviewBuilder::worker_threads` says otherwise.
+pub(crate) fn default_threads() -> usize {
+    thread::available_parallelism().map_or(2, |n| n.get().min(4))
+}
+
+/// A fixed number of threads taking jobs in the order they were queued.
+///
+/// Dropping it lets the threads finish the jobs queued so far and exit, without waiting for
+/// them.
+pub(crate) struct Pool {
+    sender: Mutex<Sender<Job>>,
+}
+
+impl Pool {
+    pub(crate) fn new(threads: usize) -> Pool {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("webview-worker-{}", i))
+                .spawn(move || work(&receiver))
+                .expect("Spawning a worker thread failed");
+        }
+        Pool {
+            sender: Mutex::new(sender),
+        }
+    }
+
+    pub(crate) fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let _ = self.sender.lock().unwrap().send(Box::new(job));
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is released before the job runs, so the others can take the next one.
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        job();
+    }
+}
+
+/// Runs `f`, turning a panic into a rejection for the page so the thread keeps serving.
+pub(crate) fn call<F>(f: F, req: &str) -> Result<String, String>
+where
+    F: Fn(&str) -> Result<String, String>,
+{
+    panic::catch_unwind(AssertUnwindSafe(|| f(req))).unwrap_or_else(|panic| {
+        let message = match panic.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match panic.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "The handler panicked".to_string(),
+            },
+        };
+        Err(json::string(&message))
+    })
+}
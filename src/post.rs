@@ -0,0 +1,46 @@
+use crate::json;
+
+/// The content type `form_page` can submit.
+pub(crate) const FORM: &str = "application/x-www-form-urlencoded";
+
+/// A `data:` url of a page posting `body`, a url-encoded form, to `url`.
+///
+/// Engines that can't load a POST request load this page instead, which submits the form as
+/// soon as it is parsed.
+pub(crate) fn form_page(url: &str, body: &str) -> String {
+    // Keep `</script>` in the strings from ending the script.
+    let literal = |s: &str| json::string(s).replace('<', "\\u003c");
+    let html = format!(
+        r#"<!DOCTYPE html><script>
+var form = document.createElement("form");
+form.method = "post";
+form.action = {url};
+new URLSearchParams({body}).forEach(function (value, name) {{
+  var input = document.createElement("input");
+  input.type = "hidden";
+  input.name = name;
+  input.value = value;
+  form.appendChild(input);
+}});
+document.documentElement.appendChild(form);
+form.submit();
+</script>"#,
+        url = literal(url),
+        body = literal(body),
+    );
+    html_url(&html)
+}
+
+/// A `data:` url of the page `html`.
+pub(crate) fn html_url(html: &str) -> String {
+    let mut data = String::from("data:text/html,");
+    for byte in html.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                data.push(byte as char)
+            }
+            _ => data.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    data
+}
@@ -0,0 +1,16 @@
+/// What the progress shown on the taskbar entry or the Dock icon means, see
+/// `Webview::set_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ProgressState {
+    /// No progress is shown.
+    #[default]
+    None,
+    Normal,
+    /// Busy for an unknown time, the fraction is ignored.
+    Indeterminate,
+    /// Shown in yellow on Windows.
+    Paused,
+    /// Shown in red on Windows.
+    Error,
+}
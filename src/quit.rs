@@ -0,0 +1,89 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+type Hook = Box<dyn FnOnce()>;
+type AsyncHook = Box<dyn FnOnce(QuitGuard)>;
+type Finish = Box<dyn FnOnce() + Send + Sync>;
+
+/// Delays quitting while asynchronous cleanup is still running.
+///
+/// Handed to hooks registered with `Webview::on_before_quit_async`. The webview is only torn
+/// down once every clone of the guard has been dropped or finished.
+#[derive(Clone)]
+pub struct QuitGuard {
+    _ticket: Arc<Ticket>,
+}
+
+impl QuitGuard {
+    /// Marks this part of the cleanup as done. Equivalent to dropping the guard.
+    pub fn finish(self) {}
+}
+
+struct Ticket(Option<Finish>);
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if let Some(finish) = self.0.take() {
+            finish();
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct QuitHooks {
+    hooks: Vec<Hook>,
+    async_hooks: Vec<AsyncHook>,
+    done: bool,
+}
+
+impl QuitHooks {
+    pub(crate) fn push(&mut self, hook: Hook) {
+        self.hooks.push(hook);
+    }
+
+    pub(crate) fn push_async(&mut self, hook: AsyncHook) {
+        self.async_hooks.push(hook);
+    }
+
+    /// Takes the registered hooks out for running, or `None` if quitting already started.
+    pub(crate) fn take(&mut self) -> Option<QuitHooks> {
+        if self.done {
+            return None;
+        }
+        self.done = true;
+        Some(QuitHooks {
+            hooks: self.hooks.drain(..).collect(),
+            async_hooks: self.async_hooks.drain(..).collect(),
+            done: true,
+        })
+    }
+
+    /// Runs every hook and calls `finish` once all handed out guards are released.
+    pub(crate) fn run<F>(self, finish: F)
+    where
+        F: FnOnce() + Send + Sync + 'static,
+    {
+        for hook in self.hooks {
+            hook();
+        }
+        let guard = QuitGuard {
+            _ticket: Arc::new(Ticket(Some(Box::new(finish)))),
+        };
+        for hook in self.async_hooks {
+            hook(guard.clone());
+        }
+    }
+
+    /// Runs every hook and blocks until all handed out guards are released.
+    pub(crate) fn run_blocking(self) {
+        let done = Arc::new((Mutex::new(false), Condvar::new()));
+        let signal = done.clone();
+        self.run(move || {
+            *signal.0.lock().unwrap() = true;
+            signal.1.notify_all();
+        });
+        let mut finished = done.0.lock().unwrap();
+        while !*finished {
+            finished = done.1.wait(finished).unwrap();
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Random bytes from the operating system's CSPRNG, for tokens that pages and other processes
+//! must not guess.
+
+#[cfg(unix)]
+fn fill(buf: &mut [u8]) {
+    use std::fs::File;
+    use std::io::Read;
+
+    File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(buf))
+        .expect("Reading /dev/urandom failed");
+}
+
+#[cfg(windows)]
+#[link(name = "bcrypt")]
+extern "system" {
+    fn BCryptGenRandom(
+        algorithm: *mut std::os::raw::c_void,
+        buffer: *mut u8,
+        size: u32,
+        flags: u32,
+    ) -> i32;
+}
+
+#[cfg(windows)]
+fn fill(buf: &mut [u8]) {
+    const BCRYPT_USE_SYSTEM_PREFERRED_RNG: u32 = 0x2;
+    let status = unsafe {
+        BCryptGenRandom(
+            std::ptr::null_mut(),
+            buf.as_mut_ptr(),
+            buf.len() as u32,
+            BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+        )
+    };
+    assert!(status >= 0, "BCryptGenRandom failed with {:#x}", status);
+}
+
+/// 128 random bits in hex.
+pub(crate) fn token() -> String {
+    let mut bytes = [0; 16];
+    fill(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
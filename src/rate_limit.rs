@@ -0,0 +1,130 @@
+//! Limiting how often the page may call bindings, see `Webview::limit_binding`.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::json;
+
+/// How often the page may call a binding, see `Webview::limit_binding`.
+///
+/// Calls are admitted at `calls` per `period` on average, with bursts of up to `calls` at once.
+/// `max_pending` also caps the calls whose promise is still waiting on a `return`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    calls: u32,
+    period: Duration,
+    max_pending: Option<usize>,
+}
+
+impl RateLimit {
+    pub fn new(calls: u32, period: Duration) -> Self {
+        RateLimit {
+            calls,
+            period,
+            max_pending: None,
+        }
+    }
+
+    /// Rejects calls while `max_pending` earlier calls haven't been returned yet, e.g. because
+    /// their handler hands the work to another thread.
+    pub fn max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = Some(max_pending);
+        self
+    }
+}
+
+/// Keeps track of the calls to one binding.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    limit: RateLimit,
+    /// The calls admitted right now, refilling up to `calls` over the period.
+    allowance: f64,
+    updated: Instant,
+    pending: usize,
+}
+
+impl Limiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Limiter {
+            limit,
+            allowance: limit.calls as f64,
+            updated: Instant::now(),
+            pending: 0,
+        }
+    }
+
+    /// Admits a call, or says why it is rejected.
+    fn admit(&mut self, now: Instant) -> Result<(), &'static str> {
+        let RateLimit { calls, period, .. } = self.limit;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let refill = if period.is_zero() {
+            f64::INFINITY
+        } else {
+            elapsed * calls as f64 / period.as_secs_f64()
+        };
+        self.allowance = (self.allowance + refill).min(calls as f64);
+        self.updated = now;
+        if matches!(self.limit.max_pending, Some(max) if self.pending >= max) {
+            return Err("Too many calls to this binding are pending");
+        }
+        if self.allowance < 1.0 {
+            return Err("This binding is called too often");
+        }
+        self.allowance -= 1.0;
+        if self.limit.max_pending.is_some() {
+            self.pending += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The limits of the bindings and the calls they still have to return.
+#[derive(Debug, Default)]
+pub(crate) struct Limits {
+    limiters: HashMap<String, Limiter>,
+    /// The binding of each pending call by its `seq`.
+    pending: HashMap<String, String>,
+}
+
+impl Limits {
+    pub(crate) fn set(&mut self, name: &str, limit: RateLimit) {
+        self.limiters.insert(name.to_string(), Limiter::new(limit));
+    }
+
+    /// Forgets the pending calls, they belong to a page that is gone and will never be returned.
+    pub(crate) fn page_changed(&mut self) {
+        self.pending.clear();
+        for limiter in self.limiters.values_mut() {
+            limiter.pending = 0;
+        }
+    }
+
+    /// Admits the call `seq` to `name`, or returns the rejection for the page.
+    pub(crate) fn admit(&mut self, name: &str, seq: &str) -> Result<(), String> {
+        let limiter = match self.limiters.get_mut(name) {
+            Some(limiter) => limiter,
+            None => return Ok(()),
+        };
+        match limiter.admit(Instant::now()) {
+            Ok(()) => {
+                if limiter.limit.max_pending.is_some() {
+                    self.pending.insert(seq.to_string(), name.to_string());
+                }
+                Ok(())
+            }
+            Err(message) => Err(format!(
+                r#"{{"name":"RateLimitError","message":{}}}"#,
+                json::string(message)
+            )),
+        }
+    }
+
+    /// Notes that the call `seq` was returned.
+    pub(crate) fn returned(&mut self, seq: &str) {
+        if let Some(name) = self.pending.remove(seq) {
+            if let Some(limiter) = self.limiters.get_mut(&name) {
+                limiter.pending = limiter.pending.saturating_sub(1);
+            }
+        }
+    }
+}
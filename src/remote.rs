@@ -0,0 +1,389 @@
+//! A control endpoint for administering deployed webviews from afar.
+//!
+//! Clients connect over TCP and exchange lines of text. The first line must be `AUTH <token>`,
+//! after which these commands are accepted, each answered by a line starting with `OK` or `ERR`:
+//!
+//! - `RELOAD` reloads the page.
+//! - `NAVIGATE <url>` loads `url` if it has the origin of an allowed url and its path is at or
+//!   below the allowed one. Paths with `.` or `..` segments, even percent-encoded, are refused.
+//! - `HEALTH` reports whether the webview is alive and its page responds.
+//! - `SCREENSHOT` is not supported by any backend yet and always fails.
+//!
+//! Clients have a few seconds to authenticate and lines are at most a few kilobytes long. Only a
+//! few clients are served at a time, fewer may be waiting to authenticate and only two of them
+//! from the same address, so clients that don't know the token can't take all the places. Failed
+//! attempts give their place back and are answered one at a time, a second apart, so tokens
+//! can't be guessed quickly. Tokens are at least 16 bytes long.
+//!
+//! The connection is not encrypted, expose the endpoint through a VPN or an SSH tunnel only.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use sha2::{Digest, Sha512};
+
+use crate::{json, WebviewHandle};
+
+/// How long `HEALTH` waits for the page to answer.
+const RESPONSIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client has to send `AUTH`.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long failed attempts are answered apart.
+const FAILED_AUTH_DELAY: Duration = Duration::from_secs(1);
+
+/// How long a line may be, without its line ending.
+const MAX_LINE: u64 = 4096;
+
+/// How many authenticated clients are served at a time, others are told the endpoint is busy.
+const MAX_CONNECTIONS: usize = 8;
+
+/// How many clients may be authenticating at a time, others are disconnected right away.
+const MAX_UNAUTHENTICATED: usize = 4;
+
+/// How many clients from one address may be authenticating or waiting for their failure to be
+/// answered, others are disconnected right away.
+const MAX_UNAUTHENTICATED_PER_PEER: usize = 2;
+
+/// How long tokens have to be at least.
+const MIN_TOKEN_LEN: usize = 16;
+
+/// The clients from each address still authenticating.
+type Peers = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+/// The settings of a control endpoint, see the module documentation.
+pub struct RemoteAdmin {
+    token: String,
+    allowed_urls: Vec<String>,
+    /// Held while a failed attempt is answered.
+    failures: Mutex<()>,
+}
+
+impl RemoteAdmin {
+    /// Clients have to authenticate with `token`. Fails with `InvalidInput` for tokens shorter
+    /// than 16 bytes, which could be guessed.
+    pub fn new(token: &str) -> io::Result<Self> {
+        if token.len() < MIN_TOKEN_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the token is shorter than 16 bytes",
+            ));
+        }
+        Ok(RemoteAdmin {
+            token: token.to_string(),
+            allowed_urls: Vec::new(),
+            failures: Mutex::new(()),
+        })
+    }
+
+    /// Allows `NAVIGATE` to urls with the origin of `prefix` and a path at or below its path,
+    /// e.g. `https://example.com/app/` allows `https://example.com/app/settings`.
+    pub fn allow_navigation(mut self, prefix: &str) -> Self {
+        self.allowed_urls.push(prefix.to_string());
+        self
+    }
+
+    /// Starts accepting clients on `addr` in the background, returns the address listened on.
+    pub fn serve<A: ToSocketAddrs>(
+        self,
+        addr: A,
+        webview: WebviewHandle,
+    ) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let admin = Arc::new(self);
+        let peers = Peers::default();
+        let unauthenticated = Arc::new(AtomicUsize::new(0));
+        let connections = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let peer = match stream.peer_addr() {
+                    Ok(addr) => match PeerSlot::take(&peers, addr.ip()) {
+                        Some(peer) => peer,
+                        None => continue,
+                    },
+                    Err(_) => continue,
+                };
+                let slot = match Slot::take(&unauthenticated, MAX_UNAUTHENTICATED) {
+                    Some(slot) => slot,
+                    None => continue,
+                };
+                let (admin, webview, connections) =
+                    (admin.clone(), webview.clone(), connections.clone());
+                thread::spawn(move || {
+                    let _ = admin.handle(stream, webview, (peer, slot), &connections);
+                });
+            }
+        });
+        Ok(local_addr)
+    }
+
+    /// Serves a client, which holds the places of `unauthenticated` until it authenticated and
+    /// then one of the `connections`.
+    fn handle(
+        &self,
+        stream: TcpStream,
+        mut webview: WebviewHandle,
+        unauthenticated: (PeerSlot, Slot),
+        connections: &Arc<AtomicUsize>,
+    ) -> io::Result<()> {
+        let (peer, unauthenticated) = unauthenticated;
+        let mut writer = stream.try_clone()?;
+        stream.set_read_timeout(Some(AUTH_TIMEOUT))?;
+        let mut reader = BufReader::new(stream);
+
+        let authorized = match read_line(&mut reader)? {
+            Some(line) => line
+                .strip_prefix("AUTH ")
+                .is_some_and(|token| same(token.as_bytes(), self.token.as_bytes())),
+            None => return Ok(()),
+        };
+        if !authorized {
+            // Waiting in line only holds the place of the client's address.
+            drop(unauthenticated);
+            let _failing = self.failures.lock().unwrap_or_else(PoisonError::into_inner);
+            thread::sleep(FAILED_AUTH_DELAY);
+            return writeln!(writer, "ERR unauthorized");
+        }
+        let _slot = match Slot::take(connections, MAX_CONNECTIONS) {
+            Some(slot) => slot,
+            None => return writeln!(writer, "ERR busy"),
+        };
+        drop((peer, unauthenticated));
+        writeln!(writer, "OK")?;
+        reader.get_ref().set_read_timeout(None)?;
+
+        while let Some(line) = read_line(&mut reader)? {
+            let (command, argument) = match line.find(' ') {
+                Some(i) => (&line[..i], line[i + 1..].trim()),
+                None => (line.trim(), ""),
+            };
+            let response = match command {
+                "RELOAD" => self.eval(&mut webview, "location.reload()"),
+                "NAVIGATE" if self.allowed(argument) => {
+                    let js = format!("location.href = {}", json::string(argument));
+                    self.eval(&mut webview, &js)
+                }
+                "NAVIGATE" => "ERR url not allowed".to_string(),
+                "HEALTH" => health(&mut webview),
+                "SCREENSHOT" => "ERR unsupported".to_string(),
+                _ => "ERR unknown command".to_string(),
+            };
+            writeln!(writer, "{}", response)?;
+        }
+        Ok(())
+    }
+
+    fn allowed(&self, url: &str) -> bool {
+        let (origin, path) = match split(url) {
+            Some(url) => url,
+            None => return false,
+        };
+        // The browser resolves them, the page loaded wouldn't be at the path compared.
+        if has_dot_segments(path) {
+            return false;
+        }
+        self.allowed_urls
+            .iter()
+            .any(|allowed| match split(allowed) {
+                Some((allowed_origin, allowed_path)) => {
+                    origin == allowed_origin
+                        && path.starts_with(allowed_path)
+                        && (allowed_path.ends_with('/')
+                            || matches!(
+                                path[allowed_path.len()..].chars().next(),
+                                None | Some('/' | '?' | '#')
+                            ))
+                }
+                None => false,
+            })
+    }
+
+    fn eval(&self, webview: &mut WebviewHandle, js: &str) -> String {
+        let js = js.to_string();
+        match webview.dispatch(move |w| w.eval(&js)) {
+            Ok(()) => "OK".to_string(),
+            Err(e) => format!("ERR {}", e),
+        }
+    }
+}
+
+/// One of a limited number of connections, given back when dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    /// Takes one of the `max` places counted by `count`, `None` if they are all taken.
+    fn take(count: &Arc<AtomicUsize>, max: usize) -> Option<Slot> {
+        if count.fetch_add(1, Ordering::SeqCst) >= max {
+            count.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Slot(count.clone()))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// One of the places of a peer address for its clients still authenticating, given back when
+/// dropped.
+struct PeerSlot {
+    peers: Peers,
+    ip: IpAddr,
+}
+
+impl PeerSlot {
+    /// Takes one of the places of `ip`, `None` if it has none left.
+    fn take(peers: &Peers, ip: IpAddr) -> Option<PeerSlot> {
+        let mut counts = peers.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = counts.entry(ip).or_insert(0);
+        if *count >= MAX_UNAUTHENTICATED_PER_PEER {
+            return None;
+        }
+        *count += 1;
+        Some(PeerSlot {
+            peers: peers.clone(),
+            ip,
+        })
+    }
+}
+
+impl Drop for PeerSlot {
+    fn drop(&mut self) {
+        let mut counts = self.peers.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = counts.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&self.ip);
+            }
+        }
+    }
+}
+
+fn health(webview: &mut WebviewHandle) -> String {
+    let responsive = webview
+        .eval_with_result("true")
+        .map(|reply| reply.wait_timeout(RESPONSIVE_TIMEOUT).is_ok());
+    match responsive {
+        Ok(responsive) => format!("OK alive=true responsive={}", responsive),
+        Err(_) => "OK alive=false responsive=false".to_string(),
+    }
+}
+
+/// Reads a line without its line ending, `None` at the end. Fails on longer lines than
+/// `MAX_LINE`.
+fn read_line(reader: &mut BufReader<TcpStream>) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    let read = reader.take(MAX_LINE + 2).read_line(&mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && read as u64 > MAX_LINE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+    }
+    let len = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(len);
+    Ok(Some(line))
+}
+
+/// The lowercase origin of `url` and the rest, starting with its path. `None` for urls with
+/// credentials, which could make the host look like another.
+fn split(url: &str) -> Option<(String, &str)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let end = rest.find(['/', '\\', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..end];
+    if authority.is_empty() || authority.contains('@') || url.chars().any(char::is_control) {
+        return None;
+    }
+    let origin = format!("{}://{}", scheme, authority).to_ascii_lowercase();
+    Some((origin, &rest[end..]))
+}
+
+/// Whether the path of `rest`, as `split` returns it, has `.` or `..` segments, also when
+/// percent-encoded.
+fn has_dot_segments(rest: &str) -> bool {
+    let path = rest.split(['?', '#']).next().unwrap_or("");
+    path.split(['/', '\\']).any(|segment| {
+        let segment = segment.to_ascii_lowercase().replace("%2e", ".");
+        segment == "." || segment == ".."
+    })
+}
+
+/// Compares secrets in constant time, digests of them so not even their lengths show.
+fn same(a: &[u8], b: &[u8]) -> bool {
+    let (a, b) = (Sha512::digest(a), Sha512::digest(b));
+    a.iter().zip(&b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    #[test]
+    fn short_tokens_are_refused() {
+        assert!(RemoteAdmin::new("").is_err());
+        assert!(RemoteAdmin::new("0123456789abcde").is_err());
+        assert!(RemoteAdmin::new(TOKEN).is_ok());
+    }
+
+    #[test]
+    fn peers_only_have_a_few_places() {
+        let peers = Peers::default();
+        let ip = IpAddr::from([192, 0, 2, 1]);
+        let first = PeerSlot::take(&peers, ip).unwrap();
+        let _second = PeerSlot::take(&peers, ip).unwrap();
+        assert!(PeerSlot::take(&peers, ip).is_none());
+        assert!(PeerSlot::take(&peers, IpAddr::from([192, 0, 2, 2])).is_some());
+        drop(first);
+        assert!(PeerSlot::take(&peers, ip).is_some());
+    }
+
+    #[test]
+    fn navigation_stays_within_the_allowed_origin_and_path() {
+        let admin = RemoteAdmin::new(TOKEN)
+            .unwrap()
+            .allow_navigation("https://example.com/app")
+            .allow_navigation("https://status.example.com/");
+        assert!(admin.allowed("https://example.com/app"));
+        assert!(admin.allowed("https://EXAMPLE.com/app/settings?tab=1"));
+        assert!(admin.allowed("https://status.example.com/"));
+        assert!(!admin.allowed("https://example.com/application"));
+        assert!(!admin.allowed("https://example.com.evil.test/app"));
+        assert!(!admin.allowed("https://example.com@evil.test/app"));
+        assert!(!admin.allowed("https://example.com:8443/app"));
+        assert!(!admin.allowed("http://example.com/app"));
+    }
+
+    #[test]
+    fn navigation_refuses_dot_segments() {
+        let admin = RemoteAdmin::new(TOKEN)
+            .unwrap()
+            .allow_navigation("https://example.com/app");
+        assert!(!admin.allowed("https://example.com/app/../secret"));
+        assert!(!admin.allowed("https://example.com/app/%2e%2e/secret"));
+        assert!(!admin.allowed("https://example.com/app/%2E./secret"));
+        assert!(!admin.allowed("https://example.com/app/.%2e"));
+        assert!(!admin.allowed("https://example.com/app\\..\\secret"));
+        assert!(!admin.allowed("https://example.com/app/./settings"));
+        assert!(admin.allowed("https://example.com/app/..settings"));
+        assert!(admin.allowed("https://example.com/app?next=/../secret"));
+    }
+
+    #[test]
+    fn tokens_compare_by_content() {
+        assert!(same(b"secret", b"secret"));
+        assert!(!same(b"secret", b"secreT"));
+        assert!(!same(b"secret", b"secret2"));
+    }
+}
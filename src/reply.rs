@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::{json, random, Error};
+
+/// The name of the binding the page hands results back through.
+pub(crate) const BINDING: &str = "__webview_reply";
+
+/// Evaluates `js` in the page and reports its result, or the exception it threw, as reply `id`.
+/// Results without a JSON form, e.g. functions, are reported as `null`.
+pub(crate) fn script(id: &str, js: &str) -> String {
+    format!(
+        r#"Promise.resolve().then(function () {{
+  return window.eval({js});
+}}).then(function (result) {{
+  return JSON.stringify(result) ?? "null";
+}}).then(function (json) {{
+  window.{binding}({id}, "ok", json);
+}}, function (error) {{
+  window.{binding}({id}, "error", String(error));
+}});"#,
+        js = json::string(js),
+        binding = BINDING,
+        id = json::string(id),
+    )
+}
+
+#[derive(Default)]
+struct Slot {
+    value: Option<Result<String, Error>>,
+    waker: Option<Waker>,
+}
+
+type Shared = Arc<(Mutex<Slot>, Condvar)>;
+
+/// A result the page hands back later, e.g. from `Webview::eval_with_result`.
+///
+/// Await it from async code or block on it with `wait`.
+pub struct Reply<T = String> {
+    shared: Shared,
+    parse: fn(String) -> Result<T, Error>,
+}
+
+impl Reply {
+    /// Turns the JSON the page replies with into a `U` once it arrives.
+    pub(crate) fn map<U>(self, parse: fn(String) -> Result<U, Error>) -> Reply<U> {
+        Reply {
+            shared: self.shared,
+            parse,
+        }
+    }
+}
+
+impl<T> Reply<T> {
+    /// Blocks until the result arrives.
+    ///
+    /// The page only answers while the UI thread runs the event loop, so this must not be called
+    /// from the UI thread.
+    pub fn wait(self) -> Result<T, Error> {
+        let (slot, ready) = &*self.shared;
+        let mut slot = slot.lock().unwrap();
+        loop {
+            if let Some(value) = slot.value.take() {
+                return value.and_then(self.parse);
+            }
+            slot = ready.wait(slot).unwrap();
+        }
+    }
+
+    /// Like `wait`, but fails with `Error::Timeout` once `timeout` has passed.
+    pub fn wait_timeout(self, timeout: Duration) -> Result<T, Error> {
+        let deadline = Instant::now() + timeout;
+        let (slot, ready) = &*self.shared;
+        let mut slot = slot.lock().unwrap();
+        loop {
+            if let Some(value) = slot.value.take() {
+                return value.and_then(self.parse);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+            slot = ready.wait_timeout(slot, deadline - now).unwrap().0;
+        }
+    }
+}
+
+impl<T> Future for Reply<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.shared.0.lock().unwrap();
+        match slot.value.take() {
+            Some(value) => Poll::Ready(value.and_then(self.parse)),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// A reply the webview waits for.
+struct Pending {
+    shared: Shared,
+    /// Whether its script was handed to the current page.
+    sent: bool,
+}
+
+/// The replies a webview still waits for, by id. Ids are random, so the page can't answer for
+/// scripts it didn't get.
+#[derive(Default)]
+pub(crate) struct Replies {
+    pending: Mutex<HashMap<String, Pending>>,
+    bound: AtomicBool,
+}
+
+impl Replies {
+    /// Returns `true` the first time it is called, when the binding still has to be set up.
+    pub(crate) fn bind_once(&self) -> bool {
+        !self.bound.swap(true, Ordering::SeqCst)
+    }
+
+    pub(crate) fn register(&self) -> (String, Reply) {
+        let id = random::token();
+        let shared = Shared::default();
+        let pending = Pending {
+            shared: shared.clone(),
+            sent: false,
+        };
+        self.pending.lock().unwrap().insert(id.clone(), pending);
+        (id, Reply { shared, parse: Ok })
+    }
+
+    /// Notes that the script of reply `id` was handed to the page, on the UI thread.
+    pub(crate) fn sent(&self, id: &str) {
+        if let Some(pending) = self.pending.lock().unwrap().get_mut(id) {
+            pending.sent = true;
+        }
+    }
+
+    /// Hands the page's answer, the binding's JSON arguments, to the waiting `Reply`. An answer
+    /// that can't be understood fails it with `Error::Ipc`.
+    pub(crate) fn resolve(&self, req: &str) {
+        let args = match json::parse(req) {
+            Some(json::Value::Array(args)) => args,
+            _ => Vec::new(),
+        };
+        let id = match args.first() {
+            Some(json::Value::String(id)) => id,
+            _ => return,
+        };
+        let value = match (args.get(1), args.get(2)) {
+            (Some(json::Value::String(status)), Some(json::Value::String(value))) => {
+                match status.as_str() {
+                    "ok" => Ok(value.clone()),
+                    _ => Err(Error::Script(value.clone())),
+                }
+            }
+            _ => Err(Error::Ipc(req.to_string())),
+        };
+        self.complete(id, value);
+    }
+
+    fn complete(&self, id: &str, value: Result<String, Error>) {
+        let pending = self.pending.lock().unwrap().remove(id);
+        if let Some(pending) = pending {
+            let (slot, ready) = &*pending.shared;
+            let mut slot = slot.lock().unwrap();
+            slot.value = Some(value);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+            ready.notify_all();
+        }
+    }
+
+    /// Fails the replies whose script went to the previous page with `Error::Navigated`, it
+    /// is gone and won't answer.
+    pub(crate) fn page_changed(&self) {
+        let ids: Vec<String> = self
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, pending)| pending.sent)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ids {
+            self.complete(&id, Err(Error::Navigated));
+        }
+    }
+
+    /// Fails every pending reply, the webview is gone.
+    pub(crate) fn cancel(&self) {
+        let ids: Vec<String> = self.pending.lock().unwrap().keys().cloned().collect();
+        for id in ids {
+            self.complete(&id, Err(Error::WebviewNull));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(id: &str, rest: &str) -> String {
+        format!("[{}{}]", json::string(id), rest)
+    }
+
+    #[test]
+    fn answers_complete_their_reply() {
+        let replies = Replies::default();
+        let (id, reply) = replies.register();
+        replies.resolve(&answer(&id, r#", "ok", "[1,2]""#));
+        assert_eq!(reply.wait().unwrap(), "[1,2]");
+        let (id, reply) = replies.register();
+        replies.resolve(&answer(&id, r#", "error", "ReferenceError: x""#));
+        assert!(
+            matches!(reply.wait(), Err(Error::Script(message)) if message == "ReferenceError: x")
+        );
+    }
+
+    #[test]
+    fn malformed_answers_fail_the_reply() {
+        let replies = Replies::default();
+        let (id, reply) = replies.register();
+        replies.resolve(&answer(&id, r#", "ok""#));
+        assert!(matches!(reply.wait(), Err(Error::Ipc(_))));
+    }
+
+    #[test]
+    fn ids_are_not_guessable() {
+        let replies = Replies::default();
+        let (first, reply) = replies.register();
+        let (second, _) = replies.register();
+        assert_ne!(first, second);
+        replies.resolve(r#"["1", "ok", "true"]"#);
+        assert!(matches!(
+            reply.wait_timeout(Duration::from_millis(10)),
+            Err(Error::Timeout)
+        ));
+    }
+
+    #[test]
+    fn navigating_fails_the_replies_sent_to_the_page() {
+        let replies = Replies::default();
+        let (sent, reply) = replies.register();
+        let (_, unsent) = replies.register();
+        replies.sent(&sent);
+        replies.page_changed();
+        assert!(matches!(reply.wait(), Err(Error::Navigated)));
+        assert!(matches!(
+            unsent.wait_timeout(Duration::from_millis(10)),
+            Err(Error::Timeout)
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Webview, WebviewHandle};
+
+/// How often the timer looks at the clock, which may jump forward when the system wakes up.
+const TICK: Duration = Duration::from_secs(30);
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// When a task scheduled with `Application::schedule` runs.
+///
+/// There is no calendar: the crate has no time zone database to tell local time, or when
+/// daylight saving time shifts it, so times of day are UTC. Tasks due at local times or on
+/// certain weekdays can run daily and check the date themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// Repeatedly, the first time one interval from now.
+    Every(Duration),
+    /// Every day at `hour:minute` UTC.
+    DailyUtc { hour: u8, minute: u8 },
+}
+
+impl Schedule {
+    /// The first time the task is due after `now`.
+    fn next_after(&self, now: SystemTime) -> SystemTime {
+        match *self {
+            Schedule::Every(interval) => now + interval.max(Duration::from_secs(1)),
+            Schedule::DailyUtc { hour, minute } => {
+                let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                let at = (hour as u64 % 24) * 60 * 60 + (minute as u64 % 60) * 60;
+                let today = secs - secs % DAY;
+                let next = if today + at > secs {
+                    today + at
+                } else {
+                    today + DAY + at
+                };
+                UNIX_EPOCH + Duration::from_secs(next)
+            }
+        }
+    }
+}
+
+/// A task scheduled with `Application::schedule`, it keeps running until cancelled or until the
+/// webview is dropped.
+pub struct ScheduledTask {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ScheduledTask {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+pub(crate) fn schedule<F>(mut webview: WebviewHandle, schedule: Schedule, task: F) -> ScheduledTask
+where
+    F: FnMut(&mut Webview) + Send + 'static,
+{
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let stop = cancelled.clone();
+    let task = Arc::new(Mutex::new(task));
+    thread::spawn(move || {
+        let mut due = schedule.next_after(SystemTime::now());
+        while !stop.load(Ordering::SeqCst) {
+            let now = SystemTime::now();
+            match due.duration_since(now) {
+                Ok(remaining) if !remaining.is_zero() => thread::sleep(remaining.min(TICK)),
+                // Due, or overdue after the system slept through it: run once and move on.
+                _ => {
+                    let task = task.clone();
+                    let dispatched = webview.dispatch(move |w| run(&task, w));
+                    if dispatched.is_err() {
+                        return;
+                    }
+                    due = schedule.next_after(now);
+                }
+            }
+        }
+    });
+    ScheduledTask { cancelled }
+}
+
+/// Runs `task` once. A panic is logged and the task still runs when it's next due, the panic
+/// doesn't reach the native loop.
+fn run<F>(task: &Mutex<F>, webview: &mut Webview)
+where
+    F: FnMut(&mut Webview),
+{
+    // Runs never overlap, the lock only hands the task to the UI thread.
+    let mut task = task.lock().unwrap_or_else(PoisonError::into_inner);
+    if panic::catch_unwind(AssertUnwindSafe(|| (*task)(webview))).is_err() {
+        warn!("a scheduled task panicked");
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::WebviewBuilder;
+
+    #[test]
+    fn a_panicking_run_keeps_the_task() {
+        let mut webview = WebviewBuilder::new().mock().build();
+        let mut runs = 0;
+        let task = Mutex::new(move |w: &mut Webview| {
+            runs += 1;
+            if runs == 1 {
+                panic!("first run");
+            }
+            w.set_title(&format!("Run {}", runs));
+        });
+        run(&task, &mut webview);
+        run(&task, &mut webview);
+        assert_eq!(webview.mock().title(), "Run 2");
+    }
+}
@@ -0,0 +1,481 @@
+//! Checking the arguments of binding calls, see `Webview::set_binding_schema`.
+
+use crate::json::{self, Value};
+use crate::Error;
+
+/// Keywords that only annotate a schema and are accepted without effect.
+const ANNOTATIONS: [&str; 7] = [
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+];
+
+/// A JSON Schema the arguments of a binding are checked against, see
+/// `Webview::set_binding_schema`.
+///
+/// The schema describes the array of arguments the page passed. It supports the keywords `type`,
+/// `enum`, `const`, `properties`, `required`, `additionalProperties`, `items`, `prefixItems`,
+/// `minItems`, `maxItems`, `minLength`, `maxLength`, `minimum`, `maximum`, `exclusiveMinimum`
+/// and `exclusiveMaximum`, as well as the annotations `title`, `description` and the like.
+/// Schemas using other keywords are refused rather than checked partially.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema(Value);
+
+impl Schema {
+    /// Parses `schema`, e.g.
+    /// `{"type": "array", "prefixItems": [{"type": "string"}], "minItems": 1, "maxItems": 1}`
+    /// for a binding taking a single string.
+    pub fn parse(schema: &str) -> Result<Schema, Error> {
+        let value =
+            json::parse(schema).ok_or_else(|| Error::Schema("it isn't valid JSON".to_string()))?;
+        check_schema(&value, "").map_err(Error::Schema)?;
+        Ok(Schema(value))
+    }
+
+    /// The places `value` doesn't conform, as JSON Pointers to them and what is wrong there.
+    pub(crate) fn violations(&self, value: &Value) -> Vec<(String, String)> {
+        let mut violations = Vec::new();
+        validate(&self.0, value, "", &mut violations);
+        violations
+    }
+}
+
+/// Checks that `schema` only uses supported keywords with values of the right kind.
+fn check_schema(schema: &Value, path: &str) -> Result<(), String> {
+    let members = match schema {
+        Value::Bool(_) => return Ok(()),
+        Value::Object(members) => members,
+        _ => return Err(format!("{} isn't a schema", pointer(path))),
+    };
+    for (keyword, value) in members {
+        let at = format!("{}/{}", path, escape(keyword));
+        let valid = match keyword.as_str() {
+            "type" => match value {
+                Value::String(name) => TYPES.contains(&name.as_str()),
+                Value::Array(names) => names
+                    .iter()
+                    .all(|name| matches!(name, Value::String(n) if TYPES.contains(&n.as_str()))),
+                _ => false,
+            },
+            "enum" => matches!(value, Value::Array(_)),
+            "const" => true,
+            "required" => match value {
+                Value::Array(names) => names.iter().all(|name| matches!(name, Value::String(_))),
+                _ => false,
+            },
+            "properties" => match value {
+                Value::Object(properties) => {
+                    for (name, property) in properties {
+                        check_schema(property, &format!("{}/{}", at, escape(name)))?;
+                    }
+                    true
+                }
+                _ => false,
+            },
+            "prefixItems" => match value {
+                Value::Array(items) => {
+                    for (i, item) in items.iter().enumerate() {
+                        check_schema(item, &format!("{}/{}", at, i))?;
+                    }
+                    true
+                }
+                _ => false,
+            },
+            "additionalProperties" | "items" => {
+                check_schema(value, &at)?;
+                true
+            }
+            "minItems" | "maxItems" | "minLength" | "maxLength" => {
+                matches!(value, Value::Number(n) if *n >= 0.0 && n.fract() == 0.0)
+            }
+            "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" => {
+                matches!(value, Value::Number(_))
+            }
+            keyword if ANNOTATIONS.contains(&keyword) => true,
+            _ => return Err(format!("the keyword {} isn't supported", pointer(&at))),
+        };
+        if !valid {
+            return Err(format!("{} has an invalid value", pointer(&at)));
+        }
+    }
+    Ok(())
+}
+
+const TYPES: [&str; 7] = [
+    "null", "boolean", "number", "integer", "string", "array", "object",
+];
+
+fn validate(schema: &Value, value: &Value, path: &str, violations: &mut Vec<(String, String)>) {
+    let members = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => return violations.push((path.to_string(), "isn't allowed".into())),
+        Value::Object(members) => members,
+        _ => return,
+    };
+    let mut fail = |message: String| violations.push((path.to_string(), message));
+    for (keyword, expected) in members {
+        match (keyword.as_str(), expected, value) {
+            ("type", Value::String(name), _) if !has_type(value, name) => {
+                fail(format!("must be {}", article(name)))
+            }
+            ("type", Value::Array(names), _) => {
+                let names: Vec<&str> = names
+                    .iter()
+                    .filter_map(|name| match name {
+                        Value::String(name) => Some(name.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                if !names.iter().any(|name| has_type(value, name)) {
+                    let names: Vec<String> = names.iter().map(|name| article(name)).collect();
+                    fail(format!("must be {}", names.join(" or ")))
+                }
+            }
+            ("enum", Value::Array(options), _) if !options.iter().any(|o| equal(o, value)) => {
+                let options: Vec<String> = options.iter().map(to_json).collect();
+                fail(format!("must be one of {}", options.join(", ")))
+            }
+            ("const", expected, _) if !equal(expected, value) => {
+                fail(format!("must be {}", to_json(expected)))
+            }
+            ("minimum", Value::Number(min), Value::Number(n)) if n < min => {
+                fail(format!("must be at least {}", min))
+            }
+            ("maximum", Value::Number(max), Value::Number(n)) if n > max => {
+                fail(format!("must be at most {}", max))
+            }
+            ("exclusiveMinimum", Value::Number(min), Value::Number(n)) if n <= min => {
+                fail(format!("must be greater than {}", min))
+            }
+            ("exclusiveMaximum", Value::Number(max), Value::Number(n)) if n >= max => {
+                fail(format!("must be less than {}", max))
+            }
+            ("minLength", Value::Number(min), Value::String(s))
+                if (s.chars().count() as f64) < *min =>
+            {
+                fail(format!("must have at least {} characters", min))
+            }
+            ("maxLength", Value::Number(max), Value::String(s))
+                if (s.chars().count() as f64) > *max =>
+            {
+                fail(format!("must have at most {} characters", max))
+            }
+            ("minItems", Value::Number(min), Value::Array(items))
+                if (items.len() as f64) < *min =>
+            {
+                fail(format!("must have at least {} items", min))
+            }
+            ("maxItems", Value::Number(max), Value::Array(items))
+                if (items.len() as f64) > *max =>
+            {
+                fail(format!("must have at most {} items", max))
+            }
+            ("required", Value::Array(names), Value::Object(_)) => {
+                for name in names {
+                    if let Value::String(name) = name {
+                        if value.get(name).is_none() {
+                            fail(format!("must have the property {}", json::string(name)));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    match value {
+        Value::Object(properties) => {
+            let declared = schema.get("properties");
+            for (name, property) in properties {
+                let at = format!("{}/{}", path, escape(name));
+                match declared.and_then(|declared| declared.get(name)) {
+                    Some(schema) => validate(schema, property, &at, violations),
+                    None => {
+                        if let Some(schema) = schema.get("additionalProperties") {
+                            validate(schema, property, &at, violations);
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            let prefix = match schema.get("prefixItems") {
+                Some(Value::Array(prefix)) => prefix.as_slice(),
+                _ => &[],
+            };
+            for (i, item) in items.iter().enumerate() {
+                let at = format!("{}/{}", path, i);
+                if let Some(schema) = prefix.get(i).or_else(|| schema.get("items")) {
+                    validate(schema, item, &at, violations);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match (name, value) {
+        ("null", Value::Null) => true,
+        ("boolean", Value::Bool(_)) => true,
+        ("number", Value::Number(_)) => true,
+        ("integer", Value::Number(n)) => n.fract() == 0.0,
+        ("string", Value::String(_)) => true,
+        ("array", Value::Array(_)) => true,
+        ("object", Value::Object(_)) => true,
+        _ => false,
+    }
+}
+
+fn article(name: &str) -> String {
+    match name {
+        "null" => "null".to_string(),
+        "array" | "integer" | "object" => format!("an {}", name),
+        name => format!("a {}", name),
+    }
+}
+
+/// Equality as JSON Schema defines it, which ignores the order of object members.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.iter().any(|(k, b)| k == key && equal(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn to_json(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => json::string(s),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(to_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        Value::Object(members) => {
+            let members: Vec<String> = members
+                .iter()
+                .map(|(key, value)| format!("{}:{}", json::string(key), to_json(value)))
+                .collect();
+            format!("{{{}}}", members.join(","))
+        }
+    }
+}
+
+/// Escapes a JSON Pointer reference token.
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// How a path is shown in messages, the root being the empty pointer.
+fn pointer(path: &str) -> String {
+    if path.is_empty() {
+        "the schema".to_string()
+    } else {
+        json::string(path)
+    }
+}
+
+/// The rejection the page gets for arguments with `violations`, a JSON object with a `message`
+/// and the `errors`, each with the JSON Pointer `path` to the offending argument and a `message`.
+pub(crate) fn rejection(violations: &[(String, String)]) -> String {
+    let errors: Vec<String> = violations
+        .iter()
+        .map(|(path, message)| {
+            format!(
+                r#"{{"path":{},"message":{}}}"#,
+                json::string(path),
+                json::string(message)
+            )
+        })
+        .collect();
+    let message = match violations.first() {
+        Some((path, message)) if path.is_empty() => format!("The arguments {}", message),
+        Some((path, message)) => format!("The argument at {} {}", path, message),
+        None => "The arguments are invalid".to_string(),
+    };
+    format!(
+        r#"{{"name":"ValidationError","message":{},"errors":[{}]}}"#,
+        json::string(&message),
+        errors.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The violations of `args` against `schema`.
+    fn check(schema: &str, args: &str) -> Vec<(String, String)> {
+        let schema = Schema::parse(schema).unwrap();
+        schema.violations(&json::parse(args).unwrap())
+    }
+
+    fn violation(path: &str, message: &str) -> Vec<(String, String)> {
+        vec![(path.to_string(), message.to_string())]
+    }
+
+    #[test]
+    fn refuses_unsupported_schemas() {
+        assert!(Schema::parse("{").is_err());
+        assert!(Schema::parse("1").is_err());
+        assert!(Schema::parse(r#"{"pattern": "^a"}"#).is_err());
+        assert!(Schema::parse(r##"{"items": {"$ref": "#"}}"##).is_err());
+        assert!(Schema::parse(r#"{"type": "date"}"#).is_err());
+        assert!(Schema::parse(r#"{"minItems": -1}"#).is_err());
+        assert!(Schema::parse(r#"{"maxLength": 1.5}"#).is_err());
+        assert!(Schema::parse(r#"{"required": [1]}"#).is_err());
+        assert!(Schema::parse(r#"{"title": "Args", "description": "", "default": []}"#).is_ok());
+        assert!(Schema::parse("true").is_ok());
+    }
+
+    #[test]
+    fn checks_types() {
+        assert_eq!(check(r#"{"type": "array"}"#, "[]"), []);
+        assert_eq!(
+            check(r#"{"type": "object"}"#, "[]"),
+            violation("", "must be an object")
+        );
+        assert_eq!(check(r#"{"items": {"type": "integer"}}"#, "[1, 2.0]"), []);
+        assert_eq!(
+            check(r#"{"items": {"type": "integer"}}"#, "[1.5]"),
+            violation("/0", "must be an integer")
+        );
+        assert_eq!(
+            check(
+                r#"{"items": {"type": ["string", "null"]}}"#,
+                r#"[null, "a", true]"#
+            ),
+            violation("/2", "must be a string or null")
+        );
+    }
+
+    #[test]
+    fn checks_enum_and_const() {
+        let schema = r#"{"items": {"enum": ["a", {"b": [1]}]}}"#;
+        assert_eq!(check(schema, r#"["a", {"b": [1]}]"#), []);
+        assert_eq!(
+            check(schema, r#"["c"]"#),
+            violation("/0", r#"must be one of "a", {"b":[1]}"#)
+        );
+        let schema = r#"{"items": {"const": {"x": 1, "y": 2}}}"#;
+        assert_eq!(check(schema, r#"[{"y": 2, "x": 1}]"#), []);
+        assert_eq!(
+            check(schema, r#"[{"x": 1}]"#),
+            violation("/0", r#"must be {"x":1,"y":2}"#)
+        );
+    }
+
+    #[test]
+    fn checks_bounds() {
+        let schema = r#"{"prefixItems": [
+            {"minimum": 1, "maximum": 3},
+            {"exclusiveMinimum": 1, "exclusiveMaximum": 3},
+            {"minLength": 2, "maxLength": 3}
+        ]}"#;
+        assert_eq!(check(schema, r#"[1, 2, "ab"]"#), []);
+        assert_eq!(
+            check(schema, r#"[0, 1, "a"]"#),
+            [
+                ("/0".to_string(), "must be at least 1".to_string()),
+                ("/1".to_string(), "must be greater than 1".to_string()),
+                (
+                    "/2".to_string(),
+                    "must have at least 2 characters".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            check(schema, r#"[4, 3, "abcd"]"#),
+            [
+                ("/0".to_string(), "must be at most 3".to_string()),
+                ("/1".to_string(), "must be less than 3".to_string()),
+                (
+                    "/2".to_string(),
+                    "must have at most 3 characters".to_string()
+                ),
+            ]
+        );
+        // Characters, not bytes.
+        assert_eq!(check(schema, r#"[1, 2, "ééé"]"#), []);
+        let schema = r#"{"minItems": 1, "maxItems": 2}"#;
+        assert_eq!(
+            check(schema, "[]"),
+            violation("", "must have at least 1 items")
+        );
+        assert_eq!(
+            check(schema, "[1, 2, 3]"),
+            violation("", "must have at most 2 items")
+        );
+    }
+
+    #[test]
+    fn checks_objects() {
+        let schema = r#"{"items": {
+            "properties": {"name": {"type": "string"}},
+            "required": ["name", "age"],
+            "additionalProperties": false
+        }}"#;
+        assert_eq!(
+            check(schema, r#"[{"name": 1, "extra/~": true}]"#),
+            [
+                (
+                    "/0".to_string(),
+                    r#"must have the property "age""#.to_string()
+                ),
+                ("/0/name".to_string(), "must be a string".to_string()),
+                ("/0/extra~1~0".to_string(), "isn't allowed".to_string()),
+            ]
+        );
+        let schema = r#"{"items": {"additionalProperties": {"type": "number"}}}"#;
+        assert_eq!(
+            check(schema, r#"[{"a": 1, "b": "2"}]"#),
+            violation("/0/b", "must be a number")
+        );
+    }
+
+    #[test]
+    fn prefix_items_come_before_items() {
+        let schema = r#"{"prefixItems": [{"type": "string"}], "items": {"type": "number"}}"#;
+        assert_eq!(check(schema, r#"["a", 1, 2]"#), []);
+        assert_eq!(
+            check(schema, r#"[1, "b"]"#),
+            [
+                ("/0".to_string(), "must be a string".to_string()),
+                ("/1".to_string(), "must be a number".to_string()),
+            ]
+        );
+        let schema = r#"{"prefixItems": [{"type": "string"}], "items": false}"#;
+        assert_eq!(
+            check(schema, r#"["a", 1]"#),
+            violation("/1", "isn't allowed")
+        );
+    }
+
+    #[test]
+    fn rejections_name_the_first_violation_and_list_them_all() {
+        assert_eq!(
+            rejection(&[
+                ("/0".to_string(), "must be a string".to_string()),
+                ("/1".to_string(), "isn't allowed".to_string()),
+            ]),
+            r#"{"name":"ValidationError","message":"The argument at /0 must be a string","errors":[{"path":"/0","message":"must be a string"},{"path":"/1","message":"isn't allowed"}]}"#
+        );
+        assert_eq!(
+            rejection(&violation("", "must be an array")),
+            r#"{"name":"ValidationError","message":"The arguments must be an array","errors":[{"path":"","message":"must be an array"}]}"#
+        );
+        assert!(json::parse(&rejection(&[])).is_some());
+    }
+}
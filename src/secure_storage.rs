@@ -0,0 +1,547 @@
+//! Keeping secrets such as auth tokens in the system's credential store.
+//!
+//! Secrets are stored by the Keychain on macOS, the Credential Manager on Windows and the Secret
+//! Service, e.g. GNOME Keyring or KWallet, elsewhere, through the `secret-tool` command that
+//! ships with libsecret. With the `mock` feature `SecureStorage::in_memory` keeps them in
+//! memory instead, for tests.
+//!
+//! Added as a plugin, `SecureStorage` lets the page use the store as well:
+//!
+//! - `window.__webview_secure_get(key)` resolves with the secret, or `null` if there is none.
+//! - `window.__webview_secure_set(key, secret)` stores the secret, replacing the previous one.
+//! - `window.__webview_secure_delete(key)` removes the secret, if there is one.
+//!
+//! Only pages allowed with `SecureStorage::allow_from` can call them, the calls of other pages
+//! are rejected before they reach the store. Failures reject the promise with a `CommandError`
+//! whose `code` is `"SECURE_STORAGE"`.
+
+use std::fmt::Display;
+
+use crate::{json, CommandError, Error, Plugin, Webview};
+
+const GET_BINDING: &str = "__webview_secure_get";
+const SET_BINDING: &str = "__webview_secure_set";
+const DELETE_BINDING: &str = "__webview_secure_delete";
+
+fn failed(reason: impl Display) -> Error {
+    Error::SecureStorage(reason.to_string())
+}
+
+/// Keys come from the page, and `secret-tool` would take one starting with `-` for an option.
+fn check_key(key: &str) -> Result<(), Error> {
+    if key.starts_with('-') {
+        return Err(failed("the key can't start with '-'"));
+    }
+    Ok(())
+}
+
+/// The secrets of one application, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct SecureStorage {
+    service: String,
+    origins: Vec<String>,
+    #[cfg(feature = "mock")]
+    in_memory: bool,
+}
+
+impl SecureStorage {
+    /// `service` tells the application's secrets apart from others', e.g. `com.example.app`.
+    pub fn new(service: &str) -> Self {
+        SecureStorage {
+            service: service.to_string(),
+            origins: Vec::new(),
+            #[cfg(feature = "mock")]
+            in_memory: false,
+        }
+    }
+
+    /// Keeps the secrets in memory instead of the system's store, e.g. for tests on the mock
+    /// backend. They are shared by the in-memory storages of the process and lost when it exits.
+    #[cfg(feature = "mock")]
+    pub fn in_memory(mut self) -> Self {
+        self.in_memory = true;
+        self
+    }
+
+    /// Lets pages whose url matches one of `patterns` call the plugin's functions, with the
+    /// same patterns as `NavigationPolicy`, e.g. `app://*`. No page can by default.
+    pub fn allow_from(mut self, patterns: &[&str]) -> Self {
+        self.origins = patterns.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /// The secret stored as `key`, `None` if there is none.
+    pub fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        check_key(key)?;
+        #[cfg(feature = "mock")]
+        if self.in_memory {
+            return memory::get(&self.service, key);
+        }
+        store::get(&self.service, key)
+    }
+
+    /// Stores `secret` as `key`, replacing the secret stored before. Keys can't start with `-`.
+    pub fn set(&self, key: &str, secret: &str) -> Result<(), Error> {
+        check_key(key)?;
+        #[cfg(feature = "mock")]
+        if self.in_memory {
+            return memory::set(&self.service, key, secret);
+        }
+        store::set(&self.service, key, secret)
+    }
+
+    /// Removes the secret stored as `key`, if there is one.
+    pub fn delete(&self, key: &str) -> Result<(), Error> {
+        check_key(key)?;
+        #[cfg(feature = "mock")]
+        if self.in_memory {
+            return memory::delete(&self.service, key);
+        }
+        store::delete(&self.service, key)
+    }
+}
+
+/// Turns `error` into a rejection for the page.
+fn rejection(error: Error) -> String {
+    let message = match error {
+        Error::SecureStorage(reason) => reason,
+        e => e.to_string(),
+    };
+    CommandError::new("SECURE_STORAGE", &message).script()
+}
+
+/// The string arguments the page passed.
+fn strings(req: &str) -> Result<Vec<String>, String> {
+    match json::parse(req) {
+        Some(json::Value::Array(args)) => args
+            .into_iter()
+            .map(|arg| match arg {
+                json::Value::String(arg) => Ok(arg),
+                _ => Err(
+                    CommandError::new("INVALID_ARGUMENT", "The arguments must be strings").script(),
+                ),
+            })
+            .collect(),
+        _ => Ok(Vec::new()),
+    }
+}
+
+impl Plugin for SecureStorage {
+    fn name(&self) -> &str {
+        "secure_storage"
+    }
+
+    fn setup(&mut self, webview: &mut Webview<'_>) {
+        let origins: Vec<&str> = self.origins.iter().map(|p| p.as_str()).collect();
+        for name in [GET_BINDING, SET_BINDING, DELETE_BINDING].iter() {
+            webview.restrict_binding(name, &origins);
+        }
+
+        let storage = self.clone();
+        webview.bind_pooled(GET_BINDING, move |req, _token| {
+            match strings(req)?.first() {
+                Some(key) => match storage.get(key).map_err(rejection)? {
+                    Some(secret) => Ok(json::string(&secret)),
+                    None => Ok("null".to_string()),
+                },
+                None => Err(CommandError::new("INVALID_ARGUMENT", "The key is missing").script()),
+            }
+        });
+
+        let storage = self.clone();
+        webview.bind_pooled(SET_BINDING, move |req, _token| match &strings(req)?[..] {
+            [key, secret, ..] => {
+                storage.set(key, secret).map_err(rejection)?;
+                Ok("null".to_string())
+            }
+            _ => {
+                Err(CommandError::new("INVALID_ARGUMENT", "The key or secret is missing").script())
+            }
+        });
+
+        let storage = self.clone();
+        webview.bind_pooled(DELETE_BINDING, move |req, _token| {
+            match strings(req)?.first() {
+                Some(key) => {
+                    storage.delete(key).map_err(rejection)?;
+                    Ok("null".to_string())
+                }
+                None => Err(CommandError::new("INVALID_ARGUMENT", "The key is missing").script()),
+            }
+        });
+    }
+}
+
+#[cfg(feature = "mock")]
+mod memory {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use crate::Error;
+
+    static SECRETS: Mutex<Option<HashMap<(String, String), String>>> = Mutex::new(None);
+
+    pub(super) fn get(service: &str, key: &str) -> Result<Option<String>, Error> {
+        let secrets = SECRETS.lock().unwrap();
+        Ok(secrets
+            .as_ref()
+            .and_then(|secrets| secrets.get(&(service.to_string(), key.to_string())))
+            .cloned())
+    }
+
+    pub(super) fn set(service: &str, key: &str, secret: &str) -> Result<(), Error> {
+        SECRETS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert((service.to_string(), key.to_string()), secret.to_string());
+        Ok(())
+    }
+
+    pub(super) fn delete(service: &str, key: &str) -> Result<(), Error> {
+        if let Some(secrets) = SECRETS.lock().unwrap().as_mut() {
+            secrets.remove(&(service.to_string(), key.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod store {
+    use std::os::raw::{c_char, c_void};
+    use std::ptr::{null, null_mut};
+    use std::slice;
+
+    use super::failed;
+    use crate::Error;
+
+    type OSStatus = i32;
+
+    const ERR_SEC_ITEM_NOT_FOUND: OSStatus = -25300;
+
+    #[link(name = "Security", kind = "framework")]
+    extern "C" {
+        fn SecKeychainFindGenericPassword(
+            keychain_or_array: *const c_void,
+            service_name_length: u32,
+            service_name: *const c_char,
+            account_name_length: u32,
+            account_name: *const c_char,
+            password_length: *mut u32,
+            password_data: *mut *mut c_void,
+            item_ref: *mut *mut c_void,
+        ) -> OSStatus;
+        fn SecKeychainAddGenericPassword(
+            keychain: *mut c_void,
+            service_name_length: u32,
+            service_name: *const c_char,
+            account_name_length: u32,
+            account_name: *const c_char,
+            password_length: u32,
+            password_data: *const c_void,
+            item_ref: *mut *mut c_void,
+        ) -> OSStatus;
+        fn SecKeychainItemModifyAttributesAndData(
+            item_ref: *mut c_void,
+            attr_list: *const c_void,
+            length: u32,
+            data: *const c_void,
+        ) -> OSStatus;
+        fn SecKeychainItemDelete(item_ref: *mut c_void) -> OSStatus;
+        fn SecKeychainItemFreeContent(attr_list: *mut c_void, data: *mut c_void) -> OSStatus;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    fn status(status: OSStatus) -> Result<(), Error> {
+        match status {
+            0 => Ok(()),
+            status => Err(failed(format!("the Keychain failed with {}", status))),
+        }
+    }
+
+    /// Finds the item of `key`, with its secret if `read`. The item has to be released.
+    unsafe fn find(
+        service: &str,
+        key: &str,
+        read: bool,
+    ) -> Result<Option<(*mut c_void, Option<String>)>, Error> {
+        let (mut len, mut data, mut item) = (0, null_mut(), null_mut());
+        let found = SecKeychainFindGenericPassword(
+            null(),
+            service.len() as u32,
+            service.as_ptr() as *const c_char,
+            key.len() as u32,
+            key.as_ptr() as *const c_char,
+            if read { &mut len } else { null_mut() },
+            if read { &mut data } else { null_mut() },
+            &mut item,
+        );
+        if found == ERR_SEC_ITEM_NOT_FOUND {
+            return Ok(None);
+        }
+        status(found)?;
+        let secret = if data.is_null() {
+            None
+        } else {
+            let bytes = slice::from_raw_parts(data as *const u8, len as usize);
+            let secret = String::from_utf8_lossy(bytes).into_owned();
+            SecKeychainItemFreeContent(null_mut(), data);
+            Some(secret)
+        };
+        Ok(Some((item, secret)))
+    }
+
+    pub(super) fn get(service: &str, key: &str) -> Result<Option<String>, Error> {
+        unsafe {
+            Ok(find(service, key, true)?.and_then(|(item, secret)| {
+                CFRelease(item);
+                secret
+            }))
+        }
+    }
+
+    pub(super) fn set(service: &str, key: &str, secret: &str) -> Result<(), Error> {
+        unsafe {
+            if let Some((item, _)) = find(service, key, false)? {
+                let modified = SecKeychainItemModifyAttributesAndData(
+                    item,
+                    null(),
+                    secret.len() as u32,
+                    secret.as_ptr() as *const c_void,
+                );
+                CFRelease(item);
+                return status(modified);
+            }
+            status(SecKeychainAddGenericPassword(
+                null_mut(),
+                service.len() as u32,
+                service.as_ptr() as *const c_char,
+                key.len() as u32,
+                key.as_ptr() as *const c_char,
+                secret.len() as u32,
+                secret.as_ptr() as *const c_void,
+                null_mut(),
+            ))
+        }
+    }
+
+    pub(super) fn delete(service: &str, key: &str) -> Result<(), Error> {
+        unsafe {
+            match find(service, key, false)? {
+                Some((item, _)) => {
+                    let deleted = SecKeychainItemDelete(item);
+                    CFRelease(item);
+                    status(deleted)
+                }
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod store {
+    use std::ffi::OsStr;
+    use std::io;
+    use std::iter::once;
+    use std::os::raw::c_void;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null_mut;
+    use std::slice;
+
+    use super::failed;
+    use crate::Error;
+
+    const CRED_TYPE_GENERIC: u32 = 1;
+    const CRED_PERSIST_LOCAL_MACHINE: u32 = 2;
+    const ERROR_NOT_FOUND: i32 = 1168;
+
+    #[repr(C)]
+    struct Credential {
+        flags: u32,
+        type_: u32,
+        target_name: *mut u16,
+        comment: *mut u16,
+        last_written: [u32; 2],
+        credential_blob_size: u32,
+        credential_blob: *mut u8,
+        persist: u32,
+        attribute_count: u32,
+        attributes: *mut c_void,
+        target_alias: *mut u16,
+        user_name: *mut u16,
+    }
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn CredReadW(
+            target_name: *const u16,
+            type_: u32,
+            flags: u32,
+            credential: *mut *mut Credential,
+        ) -> i32;
+        fn CredWriteW(credential: *const Credential, flags: u32) -> i32;
+        fn CredDeleteW(target_name: *const u16, type_: u32, flags: u32) -> i32;
+        fn CredFree(buffer: *mut c_void);
+    }
+
+    /// The name of the credential holding `key`.
+    fn target(service: &str, key: &str) -> Vec<u16> {
+        OsStr::new(&format!("{}/{}", service, key))
+            .encode_wide()
+            .chain(once(0))
+            .collect()
+    }
+
+    /// The last error, `None` if it says the credential wasn't found.
+    fn last_error() -> Option<Error> {
+        let error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(ERROR_NOT_FOUND) => None,
+            _ => Some(failed(format!("the Credential Manager failed, {}", error))),
+        }
+    }
+
+    pub(super) fn get(service: &str, key: &str) -> Result<Option<String>, Error> {
+        let target = target(service, key);
+        let mut credential = null_mut();
+        if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) } == 0 {
+            return last_error().map_or(Ok(None), Err);
+        }
+        let secret = unsafe {
+            let credential = &*credential;
+            let bytes = slice::from_raw_parts(
+                credential.credential_blob,
+                credential.credential_blob_size as usize,
+            );
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+        unsafe { CredFree(credential as *mut c_void) };
+        Ok(Some(secret))
+    }
+
+    pub(super) fn set(service: &str, key: &str, secret: &str) -> Result<(), Error> {
+        let mut target = target(service, key);
+        let mut user: Vec<u16> = OsStr::new(key).encode_wide().chain(once(0)).collect();
+        let credential = Credential {
+            flags: 0,
+            type_: CRED_TYPE_GENERIC,
+            target_name: target.as_mut_ptr(),
+            comment: null_mut(),
+            last_written: [0; 2],
+            credential_blob_size: secret.len() as u32,
+            credential_blob: secret.as_ptr() as *mut u8,
+            persist: CRED_PERSIST_LOCAL_MACHINE,
+            attribute_count: 0,
+            attributes: null_mut(),
+            target_alias: null_mut(),
+            user_name: user.as_mut_ptr(),
+        };
+        if unsafe { CredWriteW(&credential, 0) } == 0 {
+            return Err(last_error()
+                .unwrap_or_else(|| failed("the Credential Manager didn't store the secret")));
+        }
+        Ok(())
+    }
+
+    pub(super) fn delete(service: &str, key: &str) -> Result<(), Error> {
+        let target = target(service, key);
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            return last_error().map_or(Ok(()), Err);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod store {
+    use std::io::Write;
+    use std::process::{Command, Output, Stdio};
+
+    use super::failed;
+    use crate::Error;
+
+    /// Runs `secret-tool` with `args`, writing `input` to it.
+    fn secret_tool(args: &[&str], input: Option<&str>) -> Result<Output, Error> {
+        let mut child = Command::new("secret-tool")
+            .args(args)
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| failed(format!("running secret-tool failed, {}", e)))?;
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin.write_all(input.as_bytes()).map_err(failed)?;
+        }
+        child.wait_with_output().map_err(failed)
+    }
+
+    fn stderr(output: &Output) -> Error {
+        failed(String::from_utf8_lossy(&output.stderr).trim())
+    }
+
+    pub(super) fn get(service: &str, key: &str) -> Result<Option<String>, Error> {
+        let output = secret_tool(&["lookup", "service", service, "account", key], None)?;
+        // Nothing found is a failure without a message.
+        if !output.status.success() {
+            if output.stderr.is_empty() {
+                return Ok(None);
+            }
+            return Err(stderr(&output));
+        }
+        Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+
+    pub(super) fn set(service: &str, key: &str, secret: &str) -> Result<(), Error> {
+        let label = format!("{} {}", service, key);
+        let output = secret_tool(
+            &[
+                "store", "--label", &label, "service", service, "account", key,
+            ],
+            Some(secret),
+        )?;
+        if !output.status.success() {
+            return Err(stderr(&output));
+        }
+        Ok(())
+    }
+
+    pub(super) fn delete(service: &str, key: &str) -> Result<(), Error> {
+        let output = secret_tool(&["clear", "service", service, "account", key], None)?;
+        if !output.status.success() && !output.stderr.is_empty() {
+            return Err(stderr(&output));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::SecureStorage;
+
+    #[test]
+    fn keeps_secrets_in_memory() {
+        let storage = SecureStorage::new("com.example.tests").in_memory();
+        assert_eq!(storage.get("token").unwrap(), None);
+        storage.set("token", "secret").unwrap();
+        assert_eq!(storage.get("token").unwrap(), Some("secret".to_string()));
+        storage.delete("token").unwrap();
+        assert_eq!(storage.get("token").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_keys_that_look_like_options() {
+        let storage = SecureStorage::new("com.example.tests").in_memory();
+        assert!(storage.set("--label", "secret").is_err());
+        assert!(storage.get("-x").is_err());
+        assert!(storage.delete("-").is_err());
+    }
+}
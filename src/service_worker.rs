@@ -0,0 +1,37 @@
+//! Service worker scripts, see `WebviewBuilder::service_workers` and
+//! `Webview::clear_service_workers`.
+
+/// Unregisters the workers left over from earlier runs and removes `navigator.serviceWorker`,
+/// so pages checking for it don't register new ones.
+pub(crate) const DISABLE_SCRIPT: &str = r#"(function () {
+  var container = navigator.serviceWorker;
+  if (!container) {
+    return;
+  }
+  container.getRegistrations().then(function (registrations) {
+    registrations.forEach(function (registration) { registration.unregister(); });
+  });
+  delete Navigator.prototype.serviceWorker;
+})();"#;
+
+/// Unregisters the origin's workers and deletes its Cache API caches.
+pub(crate) const CLEAR_SCRIPT: &str = r#"(function () {
+  var pending = [];
+  if (navigator.serviceWorker) {
+    pending.push(navigator.serviceWorker.getRegistrations().then(function (registrations) {
+      return Promise.all(registrations.map(function (registration) {
+        return registration.unregister();
+      }));
+    }));
+  }
+  if (window.caches) {
+    pending.push(caches.keys().then(function (keys) {
+      return Promise.all(keys.map(function (key) { return caches.delete(key); }));
+    }));
+  }
+  return Promise.all(pending).then(function () { return null; });
+})()"#;
+
+pub(crate) fn parse_cleared(_reply: String) -> Result<(), crate::Error> {
+    Ok(())
+}
@@ -0,0 +1,39 @@
+/// A change of the operating system session the application runs in, see
+/// `Webview::on_session_event`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionEvent {
+    /// The screen was locked.
+    Locked,
+    Unlocked,
+    /// Another user took over the console, or the remote desktop connection was closed.
+    Detached,
+    /// The session is shown again after it was detached.
+    Attached,
+}
+
+impl SessionEvent {
+    fn name(self) -> &'static str {
+        match self {
+            SessionEvent::Locked => "locked",
+            SessionEvent::Unlocked => "unlocked",
+            SessionEvent::Detached => "detached",
+            SessionEvent::Attached => "attached",
+        }
+    }
+}
+
+/// Tells the page about `event` with a `webviewsession` event, pausing its media first when the
+/// session detaches so it lets go of audio and video devices.
+pub(crate) fn script(event: SessionEvent) -> String {
+    let pause = if event == SessionEvent::Detached {
+        r#"document.querySelectorAll("video, audio").forEach(function (media) { media.pause(); });"#
+    } else {
+        ""
+    };
+    format!(
+        r#"{}window.dispatchEvent(new CustomEvent("webviewsession", {{ detail: "{}" }}));"#,
+        pause,
+        event.name()
+    )
+}
@@ -0,0 +1,285 @@
+//! Letting the page run commands the application declared up front, with the `Shell` plugin.
+//!
+//! Each command has a name, a program and argument templates, in which `{key}` is replaced with
+//! the `key` member of the arguments the page passes. The page can't run anything else, and as
+//! no shell is involved its values can't add arguments either. Values starting with `-` are
+//! rejected, so they can't be taken for options.
+//!
+//! The page calls these, which return promises:
+//!
+//! - `window.__webview_shell_spawn(name, args)` starts the command `name` with `args`, an object
+//!   of strings, and resolves with an id for the process. Its output is then reported line by
+//!   line as `webviewshell` events, whose `detail` has the `id`, a `kind` of `"stdout"` or
+//!   `"stderr"` and the `line`, followed by one with a `kind` of `"exit"` and the exit `code`,
+//!   which is `null` if the process was killed.
+//! - `window.__webview_shell_kill(id)` kills the process.
+//!
+//! Only pages allowed with `Shell::allow_from` can call them, the calls of other pages are
+//! rejected before they reach the plugin. Calls to undeclared commands are rejected with a
+//! `CommandError` whose `code` is `"FORBIDDEN"`, missing or invalid values with
+//! `"INVALID_ARGUMENT"` and failing to start the process with `"IO"`. Processes still running
+//! are killed when the page navigates away and when the webview quits.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::{json, CommandError, Plugin, Webview, WebviewHandle};
+
+const SPAWN_BINDING: &str = "__webview_shell_spawn";
+const KILL_BINDING: &str = "__webview_shell_kill";
+
+/// A command the page may run, see `Shell::command`.
+#[derive(Debug, Clone)]
+struct Declared {
+    program: PathBuf,
+    args: Vec<String>,
+    dir: Option<PathBuf>,
+}
+
+impl Declared {
+    /// The arguments with the placeholders filled in from `values`.
+    fn args(&self, values: &json::Value) -> Result<Vec<String>, CommandError> {
+        self.args.iter().map(|arg| fill(arg, values)).collect()
+    }
+}
+
+/// Replaces each `{key}` in `template` with the string `key` of `values`.
+fn fill(template: &str, values: &json::Value) -> Result<String, CommandError> {
+    let invalid = |message: String| CommandError::new("INVALID_ARGUMENT", &message);
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let key = &rest[start + 1..end];
+        let value = match values.get(key) {
+            Some(json::Value::String(value)) => value,
+            _ => return Err(invalid(format!("The value {} must be a string", key))),
+        };
+        if value.starts_with('-') || value.contains('\0') {
+            return Err(invalid(format!("The value {} isn't allowed", key)));
+        }
+        filled.push_str(&rest[..start]);
+        filled.push_str(value);
+        rest = &rest[end + 1..];
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// The processes started by the page, by id.
+#[derive(Default)]
+struct Processes {
+    children: HashMap<u64, Child>,
+    next_id: u64,
+}
+
+impl Processes {
+    fn kill_all(&mut self) {
+        for child in self.children.values_mut() {
+            let _ = child.kill();
+        }
+    }
+}
+
+/// The plugin running declared commands for the page, see the module documentation.
+#[derive(Default)]
+pub struct Shell {
+    commands: HashMap<String, Declared>,
+    origins: Vec<String>,
+    processes: Arc<Mutex<Processes>>,
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        Shell::default()
+    }
+
+    /// Lets the page run `program` with `args` as `name`, see the module documentation for the
+    /// placeholders `args` may contain. A command declared again under the same name replaces
+    /// the first one.
+    pub fn command(mut self, name: &str, program: &Path, args: &[&str]) -> Self {
+        self.commands.insert(
+            name.to_string(),
+            Declared {
+                program: program.to_path_buf(),
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                dir: None,
+            },
+        );
+        self
+    }
+
+    /// Lets pages whose url matches one of `patterns` call the plugin's functions, with the
+    /// same patterns as `NavigationPolicy`, e.g. `app://*`. No page can by default.
+    pub fn allow_from(mut self, patterns: &[&str]) -> Self {
+        self.origins = patterns.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /// Runs the command `name`, declared before, in `dir` instead of the application's current
+    /// directory.
+    pub fn current_dir(mut self, name: &str, dir: &Path) -> Self {
+        if let Some(command) = self.commands.get_mut(name) {
+            command.dir = Some(dir.to_path_buf());
+        }
+        self
+    }
+}
+
+fn spawn(
+    commands: &HashMap<String, Declared>,
+    processes: &Arc<Mutex<Processes>>,
+    webview: &WebviewHandle,
+    req: &str,
+) -> Result<String, CommandError> {
+    let args = match json::parse(req) {
+        Some(json::Value::Array(args)) => args,
+        _ => Vec::new(),
+    };
+    let name = match args.first() {
+        Some(json::Value::String(name)) => name,
+        _ => {
+            return Err(CommandError::new(
+                "INVALID_ARGUMENT",
+                "Argument 1 must be a string",
+            ))
+        }
+    };
+    let declared = commands
+        .get(name)
+        .ok_or_else(|| CommandError::new("FORBIDDEN", "The command wasn't declared"))?;
+    let values = args
+        .get(1)
+        .cloned()
+        .unwrap_or(json::Value::Object(Vec::new()));
+    let mut command = Command::new(&declared.program);
+    command
+        .args(declared.args(&values)?)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = &declared.dir {
+        command.current_dir(dir);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| CommandError::new("IO", &e.to_string()))?;
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+
+    let id = {
+        let mut processes = processes.lock().unwrap();
+        processes.next_id += 1;
+        let id = processes.next_id;
+        processes.children.insert(id, child);
+        id
+    };
+
+    let errors = stderr.map(|stderr| {
+        let webview = webview.clone();
+        thread::Builder::new()
+            .name("webview-shell-stderr".to_string())
+            .spawn(move || forward(&webview, id, "stderr", stderr))
+            .expect("Spawning the shell stderr thread failed")
+    });
+    let (processes, webview) = (processes.clone(), webview.clone());
+    thread::Builder::new()
+        .name("webview-shell-stdout".to_string())
+        .spawn(move || {
+            if let Some(stdout) = stdout {
+                forward(&webview, id, "stdout", stdout);
+            }
+            if let Some(errors) = errors {
+                let _ = errors.join();
+            }
+            // Both pipes are closed, the process is done or about to be.
+            let child = processes.lock().unwrap().children.remove(&id);
+            let code = child
+                .and_then(|mut child| child.wait().ok())
+                .and_then(|status| status.code());
+            let code = code.map_or("null".to_string(), |code| code.to_string());
+            report(
+                &webview,
+                format!(r#"{{"id":{},"kind":"exit","code":{}}}"#, id, code),
+            );
+        })
+        .expect("Spawning the shell stdout thread failed");
+    Ok(id.to_string())
+}
+
+/// Reports each line of `output` to the page.
+fn forward(webview: &WebviewHandle, id: u64, kind: &str, output: impl Read) {
+    for line in BufReader::new(output).split(b'\n') {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        let line = String::from_utf8_lossy(&line);
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        report(
+            webview,
+            format!(
+                r#"{{"id":{},"kind":"{}","line":{}}}"#,
+                id,
+                kind,
+                json::string(line)
+            ),
+        );
+    }
+}
+
+fn report(webview: &WebviewHandle, detail: String) {
+    let script = format!(
+        r#"window.dispatchEvent(new CustomEvent("webviewshell", {{ detail: {} }}));"#,
+        detail
+    );
+    let _ = webview.clone().dispatch(move |w| w.eval(&script));
+}
+
+impl Plugin for Shell {
+    fn name(&self) -> &str {
+        "shell"
+    }
+
+    fn setup(&mut self, webview: &mut Webview<'_>) {
+        let origins: Vec<&str> = self.origins.iter().map(|p| p.as_str()).collect();
+        for name in [SPAWN_BINDING, KILL_BINDING].iter() {
+            webview.restrict_binding(name, &origins);
+        }
+
+        let commands = self.commands.clone();
+        let (processes, handle) = (self.processes.clone(), webview.handle());
+        webview.bind(SPAWN_BINDING, move |seq, req| {
+            let _ = match spawn(&commands, &processes, &handle, req) {
+                Ok(id) => handle.r#return(seq, 0, &id),
+                Err(e) => handle.reject(seq, &e),
+            };
+        });
+
+        let (processes, handle) = (self.processes.clone(), webview.handle());
+        webview.bind(KILL_BINDING, move |seq, req| {
+            if let Some(json::Value::Array(args)) = json::parse(req) {
+                if let Some(json::Value::Number(id)) = args.first() {
+                    if let Some(child) = processes.lock().unwrap().children.get_mut(&(*id as u64)) {
+                        let _ = child.kill();
+                    }
+                }
+            }
+            let _ = handle.r#return(seq, 0, "null");
+        });
+    }
+
+    fn on_page_load(&mut self, _webview: &mut Webview<'_>, _url: &str) {
+        self.processes.lock().unwrap().kill_all();
+    }
+
+    fn on_quit(&mut self) {
+        self.processes.lock().unwrap().kill_all();
+    }
+}
@@ -0,0 +1,218 @@
+//! Helper processes tied to a webview, see `Webview::spawn_sidecar`.
+
+use std::io::{self, BufRead, BufReader};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Webview, WebviewHandle};
+
+/// How long a crashed sidecar is first left down before it is restarted, doubled on each
+/// crash in a row up to `MAX_DELAY`.
+const FIRST_DELAY: Duration = Duration::from_secs(1);
+
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Running this long counts as a clean start again, the next crash is restarted quickly.
+const STABLE: Duration = Duration::from_secs(60);
+
+/// How often the exit of a sidecar that closed its output is checked.
+const POLL: Duration = Duration::from_millis(100);
+
+/// What happened to a sidecar, handed to the handler of `Webview::spawn_sidecar`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SidecarEvent {
+    /// A line the process wrote to its stdout, without the line ending.
+    Output(String),
+    /// The process exited with its code, `None` if a signal ended it.
+    Exited(Option<i32>),
+    /// The process was started again after crashing.
+    Restarted,
+}
+
+/// A helper process started with `Webview::spawn_sidecar`.
+///
+/// Clones share the process. It is killed once the webview quits, or with `kill`.
+#[derive(Clone)]
+pub struct Sidecar(Arc<State>);
+
+struct State {
+    name: String,
+    command: Mutex<Command>,
+    child: Mutex<Option<Child>>,
+    killed: AtomicBool,
+    restarts: AtomicU32,
+}
+
+impl Sidecar {
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// The id of the running process, `None` while it is down.
+    pub fn id(&self) -> Option<u32> {
+        self.0.child().as_ref().map(|child| child.id())
+    }
+
+    /// How often the process was restarted after crashing.
+    pub fn restarts(&self) -> u32 {
+        self.0.restarts.load(Ordering::SeqCst)
+    }
+
+    /// Kills the process, it isn't restarted. Processes it started itself are left running.
+    pub fn kill(&self) {
+        self.0.killed.store(true, Ordering::SeqCst);
+        if let Some(child) = self.0.child().as_mut() {
+            let _ = child.kill();
+        }
+    }
+}
+
+impl State {
+    fn child(&self) -> MutexGuard<'_, Option<Child>> {
+        // Only ever replaced or killed, a panic while it was locked left nothing half done.
+        self.child.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Starts the process with its stdout piped, returns the pipe.
+    fn start(&self) -> io::Result<BufReader<ChildStdout>> {
+        let mut child = self
+            .command
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        *self.child() = Some(child);
+        // Killed while it was starting.
+        if self.killed.load(Ordering::SeqCst) {
+            if let Some(child) = self.child().as_mut() {
+                let _ = child.kill();
+            }
+        }
+        Ok(BufReader::new(stdout))
+    }
+
+    /// Waits for the process to exit once its output ended.
+    fn wait(&self) -> Option<i32> {
+        loop {
+            let mut child = self.child();
+            let status = match child.as_mut().map(|child| child.try_wait()) {
+                Some(Ok(Some(status))) => Some(status.code()),
+                Some(Ok(None)) => None,
+                Some(Err(_)) | None => Some(None),
+            };
+            if let Some(code) = status {
+                *child = None;
+                return code;
+            }
+            drop(child);
+            thread::sleep(POLL);
+        }
+    }
+}
+
+pub(crate) fn spawn<F>(
+    webview: &mut Webview,
+    name: &str,
+    command: Command,
+    handler: F,
+) -> io::Result<Sidecar>
+where
+    F: FnMut(&mut Webview, SidecarEvent) + Send + 'static,
+{
+    let state = Arc::new(State {
+        name: name.to_string(),
+        command: Mutex::new(command),
+        child: Mutex::new(None),
+        killed: AtomicBool::new(false),
+        restarts: AtomicU32::new(0),
+    });
+    let output = state.start()?;
+    let sidecar = Sidecar(state.clone());
+    let quitting = sidecar.clone();
+    webview.on_before_quit(move || quitting.kill());
+    let handle = webview.handle();
+    let handler = Arc::new(Mutex::new(handler));
+    thread::Builder::new()
+        .name(format!("sidecar {}", name))
+        .spawn(move || supervise(state, output, handle, handler))?;
+    Ok(sidecar)
+}
+
+/// Hands the process's output and exit to `handler` and restarts it after a crash, until it
+/// is killed or the webview is gone.
+fn supervise<F>(
+    state: Arc<State>,
+    mut output: BufReader<ChildStdout>,
+    mut webview: WebviewHandle,
+    handler: Arc<Mutex<F>>,
+) where
+    F: FnMut(&mut Webview, SidecarEvent) + Send + 'static,
+{
+    #[cfg(feature = "futures")]
+    let sidecar = state.name.clone();
+    let mut notify = |event: SidecarEvent| {
+        let handler = handler.clone();
+        #[cfg(feature = "futures")]
+        let name = sidecar.clone();
+        webview
+            .dispatch(move |w| {
+                #[cfg(feature = "futures")]
+                w.emit(crate::Event::Sidecar {
+                    name,
+                    event: event.clone(),
+                });
+                (*handler.lock().unwrap_or_else(PoisonError::into_inner))(w, event)
+            })
+            .is_ok()
+    };
+    let mut delay = FIRST_DELAY;
+    loop {
+        let started = Instant::now();
+        let mut line = String::new();
+        let mut alive = true;
+        while alive && matches!(output.read_line(&mut line), Ok(n) if n > 0) {
+            let text = line.trim_end_matches(['\n', '\r']).to_string();
+            alive = notify(SidecarEvent::Output(text));
+            line.clear();
+        }
+        if !alive {
+            // The webview is gone, so is what the process was helping with.
+            Sidecar(state.clone()).kill();
+            return;
+        }
+        let code = state.wait();
+        if !notify(SidecarEvent::Exited(code)) || state.killed.load(Ordering::SeqCst) {
+            return;
+        }
+        if code == Some(0) {
+            debug!("sidecar exited", sidecar = %state.name);
+            return;
+        }
+        warn!("sidecar crashed, restarting it", sidecar = %state.name, code = ?code);
+        if started.elapsed() >= STABLE {
+            delay = FIRST_DELAY;
+        }
+        thread::sleep(delay);
+        delay = (delay * 2).min(MAX_DELAY);
+        if state.killed.load(Ordering::SeqCst) {
+            return;
+        }
+        output = match state.start() {
+            Ok(output) => output,
+            Err(error) => {
+                warn!("restarting the sidecar failed", sidecar = %state.name, error = %error);
+                return;
+            }
+        };
+        state.restarts.fetch_add(1, Ordering::SeqCst);
+        if !notify(SidecarEvent::Restarted) {
+            Sidecar(state.clone()).kill();
+            return;
+        }
+    }
+}
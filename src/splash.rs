@@ -0,0 +1,75 @@
+use crate::post;
+use crate::{SizeHint, Webview};
+
+/// The name of the binding the page reports readiness through.
+pub(crate) const BINDING: &str = "__webview_ready";
+
+/// A window shown from startup until the main page is ready, see `WebviewBuilder::splash`.
+#[derive(Debug, Clone)]
+pub struct Splash {
+    html: String,
+    width: i32,
+    height: i32,
+}
+
+impl Splash {
+    /// Shows `html`, a page that needs nothing from the network.
+    pub fn html(html: &str) -> Splash {
+        Splash {
+            html: html.to_string(),
+            width: 400,
+            height: 300,
+        }
+    }
+
+    /// Shows `image` centered on its window, `mime` is its type, e.g. `image/png`.
+    pub fn image(image: &[u8], mime: &str) -> Splash {
+        Splash::html(&format!(
+            "<!DOCTYPE html><style>html,body{{height:100%;margin:0}}\
+             body{{display:flex;align-items:center;justify-content:center}}\
+             img{{max-width:100%;max-height:100%}}</style>\
+             <img src=\"data:{};base64,{}\">",
+            mime,
+            encode_base64(image)
+        ))
+    }
+
+    /// Sets the size of the window, 400 by 300 by default.
+    pub fn size(mut self, width: i32, height: i32) -> Splash {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Opens the window, which runs on the loop of the webview it is shown for.
+    /// Opens the window, `None` if it can't be created.
+    pub(crate) fn open(&self, title: Option<&str>) -> Option<Webview<'static>> {
+        let url = post::html_url(&self.html);
+        let mut w = Webview::new(false, None, true).ok()?;
+        if let Some(title) = title {
+            w.set_title(title);
+        }
+        w.set_size(self.width, self.height, SizeHint::FIXED);
+        w.navigate(&url);
+        Some(w.share())
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
@@ -0,0 +1,79 @@
+use crate::{json, Error};
+
+/// How much web storage the loaded page's origin uses, in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StorageReport {
+    pub origin: String,
+    /// Everything the origin stores, as estimated by the engine.
+    pub total: u64,
+    /// Keys and values in `localStorage`.
+    pub local_storage: u64,
+    /// `None` where the engine doesn't break the total down (WebKit).
+    pub indexed_db: Option<u64>,
+    /// The Cache API's caches, `None` where the engine doesn't break the total down (WebKit).
+    pub cache: Option<u64>,
+}
+
+/// Reports the usage as an array of strings, empty where the engine has no number.
+pub(crate) const USAGE_SCRIPT: &str = r#"(function () {
+  var local = 0;
+  for (var i = 0; i < localStorage.length; i++) {
+    var key = localStorage.key(i);
+    local += (key.length + localStorage.getItem(key).length) * 2;
+  }
+  var estimate = navigator.storage && navigator.storage.estimate
+    ? navigator.storage.estimate()
+    : Promise.resolve({ usage: local });
+  return estimate.then(function (estimate) {
+    var details = estimate.usageDetails || {};
+    var part = function (n) { return n === undefined ? "" : String(n); };
+    return [location.origin, String(estimate.usage || 0), String(local),
+      part(details.indexedDB), part(details.caches)];
+  });
+})()"#;
+
+/// Clears the page's storage if it belongs to `origin`.
+pub(crate) fn evict_script(origin: &str) -> String {
+    format!(
+        r#"(function (origin) {{
+  if (location.origin !== origin) {{
+    throw new Error("the loaded page's origin is " + location.origin);
+  }}
+  localStorage.clear();
+  sessionStorage.clear();
+  var pending = [];
+  if (window.indexedDB && indexedDB.databases) {{
+    pending.push(indexedDB.databases().then(function (databases) {{
+      databases.forEach(function (db) {{ indexedDB.deleteDatabase(db.name); }});
+    }}));
+  }}
+  if (window.caches) {{
+    pending.push(caches.keys().then(function (keys) {{
+      return Promise.all(keys.map(function (key) {{ return caches.delete(key); }}));
+    }}));
+  }}
+  return Promise.all(pending).then(function () {{ return null; }});
+}})({})"#,
+        json::string(origin)
+    )
+}
+
+pub(crate) fn parse_report(reply: String) -> Result<StorageReport, Error> {
+    let fields = json::strings(&reply);
+    let number = |i: usize| fields.get(i).and_then(|field| field.parse().ok());
+    match (fields.first(), number(1), number(2)) {
+        (Some(origin), Some(total), Some(local_storage)) => Ok(StorageReport {
+            origin: origin.clone(),
+            total,
+            local_storage,
+            indexed_db: number(3),
+            cache: number(4),
+        }),
+        _ => Err(Error::Ipc(reply)),
+    }
+}
+
+pub(crate) fn parse_evicted(_reply: String) -> Result<(), Error> {
+    Ok(())
+}
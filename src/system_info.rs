@@ -0,0 +1,158 @@
+//! Telling the page about the system and the application, see `SystemInfo`.
+
+use std::env;
+use std::path::PathBuf;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crate::platform;
+use crate::{json, Plugin, Webview};
+
+/// The plugin putting a description of the system and the application on every page, as
+/// `window.__webview_system_info`, before the page's own scripts run.
+///
+/// The object holds the `os` (`linux`, `macos`, `windows`, ...), its `family` (`unix` or
+/// `windows`), the CPU `arch` (`x86_64`, `aarch64`, ...), the `app_version`, the user's
+/// `locale`, e.g. `en-US`, or `null` if it isn't known, the command line `args` without the
+/// executable, and the user's `dirs`: `home`, `config`, `data`, `cache` and `temp`, each `null`
+/// if it isn't known.
+///
+/// Every page the webview loads sees it, see `WebviewBuilder::navigation_policy` to keep remote
+/// pages out, or `SystemInfo::hide_args` when the arguments hold secrets.
+#[derive(Debug, Clone)]
+pub struct SystemInfo {
+    app_version: String,
+    hide_args: bool,
+}
+
+impl SystemInfo {
+    /// `app_version` is the version running, e.g. `env!("CARGO_PKG_VERSION")`.
+    pub fn new(app_version: &str) -> Self {
+        SystemInfo {
+            app_version: app_version.to_string(),
+            hide_args: false,
+        }
+    }
+
+    /// Leaves the command line arguments out, `args` is empty.
+    pub fn hide_args(mut self, hide: bool) -> Self {
+        self.hide_args = hide;
+        self
+    }
+
+    fn script(&self) -> String {
+        let args: Vec<String> = if self.hide_args {
+            Vec::new()
+        } else {
+            env::args_os()
+                .skip(1)
+                .map(|arg| json::string(&arg.to_string_lossy()))
+                .collect()
+        };
+        let path = |dir: Option<PathBuf>| {
+            dir.map_or("null".to_string(), |dir| {
+                json::string(&dir.to_string_lossy())
+            })
+        };
+        let dirs = Dirs::new();
+        format!(
+            r#"window.__webview_system_info = Object.freeze({{"os":{},"family":{},"arch":{},"app_version":{},"locale":{},"args":[{}],"dirs":Object.freeze({{"home":{},"config":{},"data":{},"cache":{},"temp":{}}})}});"#,
+            json::string(env::consts::OS),
+            json::string(env::consts::FAMILY),
+            json::string(env::consts::ARCH),
+            json::string(&self.app_version),
+            locale().map_or("null".to_string(), |locale| json::string(&locale)),
+            args.join(","),
+            path(dirs.home),
+            path(dirs.config),
+            path(dirs.data),
+            path(dirs.cache),
+            path(Some(env::temp_dir())),
+        )
+    }
+}
+
+impl Plugin for SystemInfo {
+    fn name(&self) -> &str {
+        "system_info"
+    }
+
+    fn init_scripts(&self) -> Vec<String> {
+        vec![self.script()]
+    }
+
+    fn setup(&mut self, webview: &mut Webview<'_>) {
+        // The page already loading when the plugin is added gets it as well.
+        webview.eval(&self.script());
+    }
+}
+
+/// The user's locale, e.g. `en-US`.
+fn locale() -> Option<String> {
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    if let Some(locale) = platform::locale() {
+        return Some(locale);
+    }
+    // E.g. `en_US.UTF-8` or `de_DE@euro`, `C` and `POSIX` say nothing about the language.
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .split(['.', '@'])
+                .next()
+                .unwrap_or("")
+                .replace('_', "-")
+        })
+        .filter(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+}
+
+/// Where applications keep the user's files.
+struct Dirs {
+    home: Option<PathBuf>,
+    config: Option<PathBuf>,
+    data: Option<PathBuf>,
+    cache: Option<PathBuf>,
+}
+
+impl Dirs {
+    #[cfg(target_os = "windows")]
+    fn new() -> Dirs {
+        let var = |name| env::var_os(name).map(PathBuf::from);
+        Dirs {
+            home: var("USERPROFILE"),
+            config: var("APPDATA"),
+            data: var("APPDATA"),
+            cache: var("LOCALAPPDATA"),
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn new() -> Dirs {
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let library = home.as_ref().map(|home| home.join("Library"));
+        Dirs {
+            config: library.as_ref().map(|dir| dir.join("Application Support")),
+            data: library.as_ref().map(|dir| dir.join("Application Support")),
+            cache: library.as_ref().map(|dir| dir.join("Caches")),
+            home,
+        }
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn new() -> Dirs {
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let xdg = |var: &str, default: &str| {
+            env::var_os(var)
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .or_else(|| home.as_ref().map(|home| home.join(default)))
+        };
+        Dirs {
+            config: xdg("XDG_CONFIG_HOME", ".config"),
+            data: xdg("XDG_DATA_HOME", ".local/share"),
+            cache: xdg("XDG_CACHE_HOME", ".cache"),
+            home,
+        }
+    }
+}
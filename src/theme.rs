@@ -0,0 +1,10 @@
+/// A light or dark appearance, see `Webview::set_theme`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Whichever of the two the system prefers.
+    #[default]
+    System,
+}
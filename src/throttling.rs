@@ -0,0 +1,13 @@
+/// How the page runs while its window is hidden, minimized or covered, see
+/// `WebviewBuilder::background_throttling`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum BackgroundThrottling {
+    /// Timers and rendering keep their pace, e.g. for a dashboard that has to stay live.
+    Disabled,
+    /// The engine slows timers and rendering down.
+    #[default]
+    Throttle,
+    /// The page is paused until the window shows again, e.g. for a utility window.
+    Suspend,
+}
@@ -0,0 +1,90 @@
+//! Rejecting binding calls whose handler never returns, see `Webview::set_binding_timeout`.
+
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// What the page's promise is rejected with when the handler takes too long.
+pub(crate) const REJECTION: &str =
+    r#"{"name":"TimeoutError","message":"The binding didn't answer in time"}"#;
+
+/// How long the watcher sleeps at most, to notice that the webview is gone.
+pub(crate) const TICK: Duration = Duration::from_secs(1);
+
+/// The time the handlers have to return, and the calls that haven't returned yet.
+#[derive(Default)]
+pub(crate) struct Timeouts {
+    default: Option<Duration>,
+    bindings: HashMap<String, Duration>,
+    /// The binding and the deadline of each call, by its `seq`.
+    pending: HashMap<String, (String, Instant)>,
+}
+
+impl Timeouts {
+    pub(crate) fn set_default(&mut self, timeout: Duration) {
+        self.default = Some(timeout);
+    }
+
+    pub(crate) fn set(&mut self, name: &str, timeout: Duration) {
+        self.bindings.insert(name.to_string(), timeout);
+    }
+
+    /// Starts the clock for the call `seq`, `false` if the binding has no timeout.
+    pub(crate) fn start(&mut self, name: &str, seq: &str, now: Instant) -> bool {
+        let timeout = match self.bindings.get(name).copied().or(self.default) {
+            Some(timeout) => timeout,
+            None => return false,
+        };
+        self.pending
+            .insert(seq.to_string(), (name.to_string(), now + timeout));
+        true
+    }
+
+    pub(crate) fn returned(&mut self, seq: &str) {
+        self.pending.remove(seq);
+    }
+
+    /// Forgets all calls, when the page making them is gone.
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Takes the calls that ran out of time, as their `seq` and binding.
+    pub(crate) fn expired(&mut self, now: Instant) -> Vec<(String, String)> {
+        let seqs: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, deadline))| *deadline <= now)
+            .map(|(seq, _)| seq.clone())
+            .collect();
+        seqs.into_iter()
+            .filter_map(|seq| {
+                let (name, _) = self.pending.remove(&seq)?;
+                Some((seq, name))
+            })
+            .collect()
+    }
+
+    /// How long until the next call runs out of time, at most `TICK`.
+    pub(crate) fn wait(&self, now: Instant) -> Duration {
+        self.pending
+            .values()
+            .map(|(_, deadline)| deadline.saturating_duration_since(now))
+            .min()
+            .map_or(TICK, |wait| wait.min(TICK))
+    }
+}
+
+/// The timeouts shared with the thread watching them.
+#[derive(Default)]
+pub(crate) struct Watch {
+    pub(crate) timeouts: Mutex<Timeouts>,
+    /// Woken when a call starts, its deadline may be the next one.
+    pub(crate) wake: Condvar,
+}
+
+impl Watch {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, Timeouts> {
+        self.timeouts.lock().unwrap()
+    }
+}
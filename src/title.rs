@@ -0,0 +1,22 @@
+/// The name of the binding the page reports its title through.
+pub(crate) const BINDING: &str = "__webview_title";
+
+/// Reports `document.title` whenever it changes, in the top frame only.
+pub(crate) const INIT_SCRIPT: &str = r#"(function () {
+  if (window !== window.top) {
+    return;
+  }
+  var last;
+  var report = function () {
+    if (document.title !== last) {
+      last = document.title;
+      window.__webview_title(last);
+    }
+  };
+  new MutationObserver(report).observe(document, {
+    subtree: true,
+    childList: true,
+    characterData: true,
+  });
+  document.addEventListener("DOMContentLoaded", report);
+})();"#;
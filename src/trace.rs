@@ -0,0 +1,95 @@
+//! Diagnostics, as `tracing` events and spans with the `webview` target when the `tracing`
+//! feature is enabled.
+//!
+//! Fields are written `key = %value` for their `Display` or `key = ?value` for their `Debug`.
+//! Without the feature the events go to the `log` crate instead, with the same target, and
+//! spans aren't recorded.
+
+/// What `span!` returns without the feature, with it the span's guard.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Entered;
+
+#[cfg(feature = "tracing")]
+macro_rules! value {
+    (%, $value:expr) => {
+        tracing::field::display(&$value)
+    };
+    (?, $value:expr) => {
+        tracing::field::debug(&$value)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! value {
+    (%, $value:expr) => {
+        format!("{}", $value)
+    };
+    (?, $value:expr) => {
+        format!("{:?}", $value)
+    };
+}
+
+macro_rules! event {
+    ($level:ident, $log_level:ident, $message:literal $(, $key:ident = $sigil:tt $value:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        tracing::event!(
+            target: "webview",
+            tracing::Level::$level,
+            $($key = value!($sigil, $value),)*
+            $message
+        );
+        #[cfg(not(feature = "tracing"))]
+        {
+            if log::log_enabled!(target: "webview", log::Level::$log_level) {
+                let fields: &[String] =
+                    &[$(format!(" {}={}", stringify!($key), value!($sigil, $value))),*];
+                log::log!(
+                    target: "webview",
+                    log::Level::$log_level,
+                    concat!($message, "{}"),
+                    fields.concat()
+                );
+            } else {
+                $(let _ = &$value;)*
+            }
+        }
+    }};
+}
+
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        event!(DEBUG, Debug, $($arg)+)
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)+) => {
+        event!(INFO, Info, $($arg)+)
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        event!(WARN, Warn, $($arg)+)
+    };
+}
+
+/// Enters a span until the returned guard is dropped.
+macro_rules! span {
+    ($level:ident, $name:literal $(, $key:ident = $sigil:tt $value:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let entered = tracing::span!(
+            target: "webview",
+            tracing::Level::$level,
+            $name,
+            $($key = value!($sigil, $value)),*
+        )
+        .entered();
+        #[cfg(not(feature = "tracing"))]
+        let entered = {
+            $(let _ = &$value;)*
+            $crate::trace::Entered
+        };
+        entered
+    }};
+}
@@ -0,0 +1,546 @@
+//! Updating the application from signed releases published in a feed.
+//!
+//! The feed is a url answering with a JSON object that describes the latest release:
+//!
+//! ```json
+//! {
+//!   "version": "1.2.0",
+//!   "notes": "Starts faster.",
+//!   "url": "https://example.com/downloads/app-1.2.0",
+//!   "signature": "<base64>"
+//! }
+//! ```
+//!
+//! `{target}`, `{arch}` and `{current_version}` in the feed url are replaced with the operating
+//! system (`linux`, `macos`, `windows`, ...), the CPU architecture (`x86_64`, `aarch64`, ...) and
+//! the running version, so each platform can be served its own release.
+//!
+//! The feed and `url` have to be https urls. The file at `url` is the new executable.
+//! `signature` is the Ed25519 signature of the version followed by the SHA-512 digest of the
+//! file, so a release can't be served under another version. The 64 bytes are base64 encoded,
+//! e.g.:
+//!
+//! ```sh
+//! (printf %s 1.2.0; openssl dgst -sha512 -binary app) > message
+//! openssl pkeyutl -sign -rawin -inkey key.pem -in message | base64 -w0
+//! ```
+//!
+//! The application is built with the matching public key, its 32 raw bytes base64 encoded,
+//! e.g. `openssl pkey -in key.pem -pubout -outform DER | tail -c 32 | base64`. Downloads that
+//! don't match the signature are discarded, as are releases that aren't newer than the running
+//! version.
+//!
+//! Requests only go over https, redirects included. Releases are downloaded into a directory
+//! only the user can access, and checked against the signature again as they are installed.
+//! Releases over 512 MiB are discarded.
+//!
+//! `Updater::watch` checks and downloads in the background and tells the page with
+//! `webviewupdate` events. Their `detail` has a `status` of `"available"` with the `version` and
+//! `notes` once a newer release is found, `"ready"` once it is downloaded, `"current"` if there
+//! is none or `"error"` with a `message`. Pages allowed with `Updater::allow_install_from` call
+//! `window.__webview_update_check()` to check again, which joins a check still running, and
+//! `window.__webview_update_install()` to restart into the update, e.g. from a "Restart to
+//! update" button.
+
+use std::cmp::Ordering;
+use std::env;
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use sha2::{Digest, Sha512};
+
+use crate::{instance, json, user_dir, Error, Webview, WebviewHandle};
+
+const CHECK_BINDING: &str = "__webview_update_check";
+const INSTALL_BINDING: &str = "__webview_update_install";
+
+/// How long connecting, and each read once connected, may take.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the feed may be.
+const MAX_FEED: u64 = 1024 * 1024;
+
+/// How large a release may be, larger downloads are discarded.
+const MAX_RELEASE: u64 = 512 * 1024 * 1024;
+
+fn failed(reason: impl Display) -> Error {
+    Error::Update(reason.to_string())
+}
+
+/// Checks a feed for releases signed with a key, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct Updater {
+    feed: String,
+    public_key: [u8; 32],
+    current_version: String,
+    install_origins: Vec<String>,
+}
+
+impl Updater {
+    /// `public_key` is the base64 encoded Ed25519 key releases are signed with and
+    /// `current_version` the version running, e.g. `env!("CARGO_PKG_VERSION")`.
+    pub fn new(feed: &str, public_key: &str, current_version: &str) -> Result<Updater, Error> {
+        if !feed.starts_with("https://") {
+            return Err(failed("the feed isn't an https url"));
+        }
+        let mut key = [0; 32];
+        match BASE64.decode(public_key.trim()) {
+            Ok(bytes) if bytes.len() == 32 => key.copy_from_slice(&bytes),
+            _ => return Err(failed("the public key isn't 32 base64 encoded bytes")),
+        }
+        VerifyingKey::from_bytes(&key).map_err(|_| failed("the public key isn't valid"))?;
+        Ok(Updater {
+            feed: feed.to_string(),
+            public_key: key,
+            current_version: current_version.to_string(),
+            install_origins: Vec::new(),
+        })
+    }
+
+    /// Lets pages whose url matches one of `patterns` check for and install updates once
+    /// `watch`ing, with the same patterns as `NavigationPolicy`, e.g. `app://*`. No page can by
+    /// default.
+    pub fn allow_install_from(mut self, patterns: &[&str]) -> Updater {
+        self.install_origins = patterns.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /// Asks the feed for the latest release, `None` if it isn't newer than the running version.
+    ///
+    /// This blocks until the feed answered.
+    pub fn check(&self) -> Result<Option<Release>, Error> {
+        let url = self
+            .feed
+            .replace("{target}", env::consts::OS)
+            .replace("{arch}", env::consts::ARCH)
+            .replace("{current_version}", &self.current_version);
+        let mut feed = String::new();
+        get(&url)?
+            .take(MAX_FEED)
+            .read_to_string(&mut feed)
+            .map_err(failed)?;
+        let members =
+            json::string_members(&feed).ok_or_else(|| failed("the feed isn't a JSON object"))?;
+        let member = |name: &str| {
+            members
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        let version = member("version").ok_or_else(|| failed("the feed has no version"))?;
+        if !newer(&version, &self.current_version) {
+            return Ok(None);
+        }
+        let url = member("url")
+            .filter(|url| url.starts_with("https://"))
+            .ok_or_else(|| failed("the feed has no https url"))?;
+        let mut signature = [0; 64];
+        match member("signature").map(|s| BASE64.decode(s.trim())) {
+            Some(Ok(bytes)) if bytes.len() == 64 => signature.copy_from_slice(&bytes),
+            _ => return Err(failed("the feed has no valid signature")),
+        }
+        Ok(Some(Release {
+            version,
+            notes: member("notes").unwrap_or_default(),
+            url,
+            signature,
+            public_key: self.public_key,
+            current_version: self.current_version.clone(),
+        }))
+    }
+
+    /// Checks for a newer release and downloads it in the background, reporting to the page as
+    /// described in the module documentation.
+    pub fn watch(self, webview: &mut Webview) {
+        let origins: Vec<&str> = self.install_origins.iter().map(|p| p.as_str()).collect();
+        webview.restrict_binding(CHECK_BINDING, &origins);
+        webview.restrict_binding(INSTALL_BINDING, &origins);
+        let watched = Arc::new(Watched {
+            updater: self,
+            ready: Mutex::new(None),
+            checking: AtomicBool::new(false),
+        });
+
+        let (checker, handle) = (watched.clone(), webview.handle());
+        webview.bind(CHECK_BINDING, move |seq, _req| {
+            check_in_background(checker.clone(), handle.clone());
+            let _ = handle.r#return(seq, 0, "null");
+        });
+
+        let (pending, mut handle) = (watched.clone(), webview.handle());
+        webview.bind(INSTALL_BINDING, move |seq, _req| {
+            let update = pending.ready.lock().unwrap().take();
+            let installed = update
+                .ok_or_else(|| failed("no update is ready"))
+                .and_then(Update::install);
+            match installed {
+                Ok(()) => {
+                    let _ = handle.r#return(seq, 0, "null");
+                    let _ = handle.terminate();
+                }
+                Err(e) => {
+                    let _ = handle.r#return(seq, 1, &json::string(&e.to_string()));
+                }
+            }
+        });
+
+        check_in_background(watched, webview.handle());
+    }
+}
+
+/// What `Updater::watch` shares between the page's calls and the checks.
+struct Watched {
+    updater: Updater,
+    /// The update downloaded last, until it is installed.
+    ready: Mutex<Option<Update>>,
+    /// Set while a check runs, the page's calls join it instead of starting another.
+    checking: AtomicBool,
+}
+
+/// Clears `Watched::checking` once the check is over, however it ended.
+struct Checking(Arc<Watched>);
+
+impl Drop for Checking {
+    fn drop(&mut self) {
+        self.0.checking.store(false, atomic::Ordering::SeqCst);
+    }
+}
+
+fn check_in_background(watched: Arc<Watched>, webview: WebviewHandle) {
+    if watched.checking.swap(true, atomic::Ordering::SeqCst) {
+        return;
+    }
+    let checking = Checking(watched);
+    thread::spawn(move || {
+        let watched = &checking.0;
+        let release = match watched.updater.check() {
+            Ok(Some(release)) => release,
+            Ok(None) => return report(&webview, r#"{"status":"current"}"#.to_string()),
+            Err(e) => return report_error(&webview, e),
+        };
+        report(
+            &webview,
+            format!(
+                r#"{{"status":"available","version":{},"notes":{}}}"#,
+                json::string(&release.version),
+                json::string(&release.notes)
+            ),
+        );
+        match release.download() {
+            Ok(update) => {
+                let detail = format!(
+                    r#"{{"status":"ready","version":{}}}"#,
+                    json::string(&update.version)
+                );
+                *watched.ready.lock().unwrap() = Some(update);
+                report(&webview, detail);
+            }
+            Err(e) => report_error(&webview, e),
+        }
+    });
+}
+
+fn report(webview: &WebviewHandle, detail: String) {
+    let script = format!(
+        r#"window.dispatchEvent(new CustomEvent("webviewupdate", {{ detail: {} }}));"#,
+        detail
+    );
+    let _ = webview.clone().dispatch(move |w| w.eval(&script));
+}
+
+fn report_error(webview: &WebviewHandle, error: Error) {
+    let message = match error {
+        Error::Update(reason) => reason,
+        e => e.to_string(),
+    };
+    report(
+        webview,
+        format!(
+            r#"{{"status":"error","message":{}}}"#,
+            json::string(&message)
+        ),
+    );
+}
+
+/// A release newer than the running version, see `Updater::check`.
+#[derive(Debug, Clone)]
+pub struct Release {
+    version: String,
+    notes: String,
+    url: String,
+    signature: [u8; 64],
+    public_key: [u8; 32],
+    current_version: String,
+}
+
+impl Release {
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The release notes, empty if the feed has none.
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    /// Downloads the release into a directory only the user can access and checks its
+    /// signature.
+    ///
+    /// This blocks until the download finished.
+    pub fn download(&self) -> Result<Update, Error> {
+        if !newer(&self.version, &self.current_version) {
+            return Err(failed("the release isn't newer than the running version"));
+        }
+        let name = self
+            .url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .filter(|name| !name.is_empty() && !name.starts_with('.'))
+            .unwrap_or("update");
+        let path = user_dir::temp("webview-updates")
+            .map_err(failed)?
+            .join(name);
+        let _ = fs::remove_file(&path);
+        let body = get(&self.url)?;
+        let digest = create(&path)
+            .and_then(|file| copy_digest(body, file, MAX_RELEASE))
+            .map_err(|e| {
+                let _ = fs::remove_file(&path);
+                failed(format!("downloading the release failed, {}", e))
+            })?;
+        let message = [self.version.as_bytes(), &digest[..]].concat();
+        let verified = VerifyingKey::from_bytes(&self.public_key)
+            .and_then(|key| key.verify_strict(&message, &Signature::from_bytes(&self.signature)));
+        if verified.is_err() {
+            let _ = fs::remove_file(&path);
+            return Err(failed("the download doesn't match the release's signature"));
+        }
+        Ok(Update {
+            path,
+            version: self.version.clone(),
+            digest,
+        })
+    }
+}
+
+/// A downloaded release whose signature matched, see `Release::download`.
+#[derive(Debug)]
+pub struct Update {
+    path: PathBuf,
+    version: String,
+    /// The SHA-512 digest the signature was checked with.
+    digest: [u8; 64],
+}
+
+impl Update {
+    /// The downloaded file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Replaces the running executable with the update and starts it with the same arguments,
+    /// the application should quit right after.
+    ///
+    /// The new instance waits for an `InstanceLock` held by this one to be released, instead of
+    /// forwarding its arguments to it. Fails if the downloaded file changed since its signature
+    /// was checked.
+    pub fn install(self) -> Result<(), Error> {
+        let exe = env::current_exe().map_err(failed)?;
+        let staged = exe.with_extension("update");
+        let _ = fs::remove_file(&staged);
+        // The staged copy is checked, it is what gets installed.
+        let digest = File::open(&self.path)
+            .and_then(|update| copy_digest(update, create(&staged)?, MAX_RELEASE))
+            .map_err(failed)?;
+        if digest != self.digest {
+            let _ = fs::remove_file(&staged);
+            return Err(failed("the update changed since its signature was checked"));
+        }
+        #[cfg(unix)]
+        {
+            let permissions = fs::metadata(&exe).map_err(failed)?.permissions();
+            fs::set_permissions(&staged, permissions).map_err(failed)?;
+        }
+        // A running executable can't be replaced on Windows, but it can be renamed.
+        #[cfg(windows)]
+        {
+            let old = exe.with_extension("old");
+            let _ = fs::remove_file(&old);
+            fs::rename(&exe, &old).map_err(failed)?;
+            if let Err(e) = fs::rename(&staged, &exe) {
+                // Puts the running executable back, there would be none otherwise.
+                let _ = fs::rename(&old, &exe);
+                return Err(failed(e));
+            }
+        }
+        #[cfg(not(windows))]
+        fs::rename(&staged, &exe).map_err(failed)?;
+        let _ = fs::remove_file(&self.path);
+        Command::new(&exe)
+            .args(env::args_os().skip(1))
+            .env(instance::RESTART_VAR, "1")
+            .spawn()
+            .map_err(failed)?;
+        Ok(())
+    }
+}
+
+/// The body of `url`, which has to be https, as do the redirects.
+fn get(url: &str) -> Result<impl Read, Error> {
+    let agent = ureq::AgentBuilder::new()
+        .https_only(true)
+        .timeout_connect(TIMEOUT)
+        .timeout_read(TIMEOUT)
+        .build();
+    let response = agent.get(url).call().map_err(failed)?;
+    Ok(response.into_reader())
+}
+
+/// Creates `path` for the user only, failing if there is a file already.
+fn create(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o700);
+    options.open(path)
+}
+
+/// Copies `from` into `to`, returns the SHA-512 digest of what was copied. Fails once more than
+/// `limit` bytes come.
+fn copy_digest(from: impl Read, mut to: File, limit: u64) -> io::Result<[u8; 64]> {
+    let mut hasher = Sha512::new();
+    let mut buf = [0; 64 * 1024];
+    let mut from = from.take(limit + 1);
+    let mut copied = 0;
+    loop {
+        let n = match from.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        copied += n as u64;
+        if copied > limit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the file is too large",
+            ));
+        }
+        hasher.update(&buf[..n]);
+        to.write_all(&buf[..n])?;
+    }
+    to.sync_all()?;
+    let mut digest = [0; 64];
+    digest.copy_from_slice(&hasher.finalize());
+    Ok(digest)
+}
+
+/// Whether `version` comes after `current`, comparing the dot separated numbers and then the
+/// pre-release after a `-`, which comes before the release itself. Build metadata after a `+` is
+/// ignored.
+fn newer(version: &str, current: &str) -> bool {
+    let split = |v: &str| {
+        let v = v.trim().trim_start_matches('v');
+        let v = v.split('+').next().unwrap_or(v);
+        let (core, pre) = match v.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (v, None),
+        };
+        let numbers: Vec<u64> = core.split('.').map(|n| n.parse().unwrap_or(0)).collect();
+        (numbers, pre)
+    };
+    let (mut a, a_pre) = split(version);
+    let (mut b, b_pre) = split(current);
+    let len = a.len().max(b.len());
+    a.resize(len, 0);
+    b.resize(len, 0);
+    match a.cmp(&b) {
+        Ordering::Equal => match (a_pre, b_pre) {
+            (None, Some(_)) => true,
+            (Some(a), Some(b)) => compare_pre_release(&a, &b) == Ordering::Greater,
+            _ => false,
+        },
+        order => order == Ordering::Greater,
+    }
+}
+
+/// Compares pre-releases as semver does: identifier by identifier, numbers as numbers and
+/// before any text, and a pre-release before the longer ones it starts.
+fn compare_pre_release(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        let order = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process;
+
+    #[test]
+    fn copies_and_digests_what_is_installed() {
+        let path = env::temp_dir().join(format!("webview-updater-test-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let digest = copy_digest(&b"abc"[..], create(&path).unwrap(), 3).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"abc");
+        assert!(create(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        // FIPS 180-4's SHA-512 example.
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+    }
+
+    #[test]
+    fn compares_pre_release_numbers_as_numbers() {
+        assert!(newer("1.0.0-rc.10", "1.0.0-rc.2"));
+        assert!(!newer("1.0.0-rc.2", "1.0.0-rc.10"));
+        assert!(newer("1.0.0-rc.1", "1.0.0-rc"));
+        assert!(newer("1.0.0-beta", "1.0.0-2"));
+        assert!(newer("1.0.0", "1.0.0-rc.10"));
+        assert!(!newer("1.0.0+build.2", "1.0.0"));
+        assert!(newer("1.10.0", "1.9.3"));
+    }
+
+    #[test]
+    fn refuses_to_copy_more_than_the_limit() {
+        let path = env::temp_dir().join(format!("webview-updater-limit-test-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(copy_digest(&b"abcd"[..], create(&path).unwrap(), 3).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
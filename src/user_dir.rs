@@ -0,0 +1,85 @@
+//! Directories only the current user can use, for files other local users mustn't plant or
+//! swap, e.g. the instance lock and downloaded updates.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[cfg(unix)]
+extern "C" {
+    fn geteuid() -> u32;
+}
+
+/// `name` in the temp directory, suffixed with the user's id on Unix, where the temp directory
+/// is shared. It's created so only the user can access it.
+///
+/// Fails if it already exists but isn't a directory of the user's that only they can access,
+/// someone else may have created it to get at what is put in it.
+#[cfg(unix)]
+pub(crate) fn temp(name: &str) -> io::Result<PathBuf> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    let path = env::temp_dir().join(format!("{}-{}", name, unsafe { geteuid() }));
+    match fs::DirBuilder::new().mode(0o700).create(&path) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    let metadata = fs::symlink_metadata(&path)?;
+    if !metadata.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} isn't a directory", path.display()),
+        ));
+    }
+    check_private(&metadata)?;
+    Ok(path)
+}
+
+/// The user's temp directory is their own on Windows.
+#[cfg(not(unix))]
+pub(crate) fn temp(name: &str) -> io::Result<PathBuf> {
+    let path = env::temp_dir().join(name);
+    fs::create_dir_all(&path)?;
+    Ok(path)
+}
+
+/// Fails unless `metadata` is of a file or directory the user owns that only they can access.
+#[cfg(unix)]
+pub(crate) fn check_private(metadata: &fs::Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    if metadata.uid() != unsafe { geteuid() } || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the file is someone else's or others can access it",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn check_private(_metadata: &fs::Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::PermissionsExt;
+    use std::process;
+
+    #[test]
+    fn creates_the_directory_private() {
+        let name = format!("webview-user-dir-test-{}", process::id());
+        let path = temp(&name).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert_eq!(temp(&name).unwrap(), path);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(temp(&name).is_err());
+        fs::remove_dir(&path).unwrap();
+    }
+}
@@ -1,14 +1,73 @@
-use std::ffi::{CStr, CString};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::io;
 use std::mem;
 use std::os::raw::*;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::ptr::null_mut;
-use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock, RwLock, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::{Error};
+use crate::automation::{self, Element};
+use crate::binding::{self, Bindings, Handler};
+use crate::builder::Cache;
+use crate::cancel::{self, Calls};
+use crate::composition::{self, CompositionEvent};
+use crate::csp;
+use crate::css::{self, CssHandle};
+use crate::cstring;
+use crate::deep_link;
+use crate::drag;
+#[cfg(feature = "futures")]
+use crate::events::{self, Event, EventStream, Events};
+use crate::favicon::{self, Favicon};
+use crate::fullscreen::{self, FullscreenElement};
+use crate::geolocation::{self, Coordinates};
+use crate::handlers::Handlers;
+use crate::ipc;
+use crate::metrics;
+use crate::network;
+#[cfg(feature = "oauth")]
+use crate::oauth;
+use crate::panic_hook::{self, Display};
+use crate::platform::{self, HistoryAction};
+use crate::plugin::{self, Plugins};
+use crate::policy;
+use crate::pool::{self, Pool};
+use crate::post;
+use crate::quit::QuitHooks;
+use crate::random;
+use crate::rate_limit::Limits;
+use crate::reply::{self, Replies};
+use crate::schema::{self, Schema};
+use crate::service_worker;
+use crate::session;
+use crate::sidecar::{self, Sidecar, SidecarEvent};
+use crate::splash;
+use crate::storage;
+use crate::timeout;
+use crate::title;
+use crate::window_state::{self, WindowState};
+use crate::{
+    json, Backend, BackgroundThrottling, CancellationToken, Capabilities, CommandError, DragItem,
+    Error, Frame, KeyEvent, Metrics, NavigationPolicy, NetworkConditions, Plugin, ProgressState,
+    QuitGuard, RateLimit, Reply, SessionEvent, StorageReport, Theme, UnsupportedCall,
+    UserAttention, WebContents, Window, WindowEffect,
+};
 
+#[cfg(feature = "raw-window-handle")]
+use raw_window_handle::RawWindowHandle;
+
+#[cfg(feature = "mock")]
+use crate::mock::{self, sys, Mock};
+#[cfg(not(feature = "mock"))]
 use webview_official_sys as sys;
 
-pub enum Window {}
+/// A native window, only ever handled through pointers, see `Webview::get_window`.
+pub enum NativeWindow {}
 
 #[repr(i32)]
 #[derive(Debug)]
@@ -25,222 +84,2582 @@ impl Default for SizeHint {
     }
 }
 
-#[derive(Clone)]
+/// An sRGB color, e.g. `Color::rgb(0x1e, 0x1e, 0x1e)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    /// Opacity, from 0 for transparent to 255 for opaque.
+    pub alpha: u8,
+}
+
+impl Color {
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Color {
+        Color::rgba(red, green, blue, 0xff)
+    }
+
+    pub const fn rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+}
+
+/// A rectangle in the parent's coordinates, with the origin at its top left corner.
+///
+/// Coordinates are logical pixels, they are scaled to the parent's DPI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+struct Inner {
+    webview: sys::webview_t,
+    owns_window: bool,
+    external_loop: bool,
+    /// The `Webview`s sharing it, the webview is destroyed when the last one is dropped.
+    owners: AtomicUsize,
+    /// Whether the native webview still exists, held for reading while another thread uses it.
+    alive: RwLock<bool>,
+    /// Set by `into_raw`, the native webview then belongs to the caller and is left alone.
+    released: AtomicBool,
+    quit_hooks: Mutex<QuitHooks>,
+    panel: Mutex<Option<platform::Panel>>,
+    replies: Arc<Replies>,
+    unsupported: Mutex<Vec<UnsupportedCall>>,
+    session_handlers: Handlers<SessionHandler>,
+    title_handlers: Handlers<TitleHandler>,
+    /// Set once the page reports its title.
+    title_bridge: OnceLock<()>,
+    favicon_handlers: Handlers<FaviconHandler>,
+    /// Set once the page reports its icon.
+    favicon_bridge: OnceLock<()>,
+    theme: Mutex<Theme>,
+    theme_handlers: Handlers<ThemeHandler>,
+    theme_watch: Mutex<Option<platform::ThemeWatch>>,
+    session_watch: Mutex<Option<platform::SessionWatch>>,
+    aspect_ratio: Mutex<Option<platform::AspectRatio>>,
+    sleep_inhibitor: Mutex<Option<platform::SleepInhibitor>>,
+    focus_handlers: Handlers<FocusHandler>,
+    focus_watch: Mutex<Option<platform::FocusWatch>>,
+    file_drop_handlers: Handlers<FileDropHandler>,
+    file_drop_watch: Mutex<Option<platform::FileDropWatch>>,
+    key_handlers: Handlers<KeyHandler>,
+    key_watch: Mutex<Option<platform::KeyWatch>>,
+    load_handlers: Handlers<LoadHandler>,
+    load_watch: Mutex<Option<platform::LoadWatch>>,
+    composition_handlers: Handlers<CompositionHandler>,
+    /// Set once the page reports its compositions.
+    composition_bridge: OnceLock<()>,
+    fullscreen_handlers: Handlers<FullscreenHandler>,
+    /// Set once the page asks before going fullscreen.
+    fullscreen_bridge: OnceLock<()>,
+    /// Set once the page asks the application for its position.
+    geolocation_bridge: OnceLock<()>,
+    /// The custom scheme sign-ins waiting for their redirect, see `oauth_flows`.
+    #[cfg(feature = "oauth")]
+    oauth_flows: OnceLock<oauth::PendingFlows>,
+    deep_link_handlers: Handlers<DeepLinkHandler>,
+    deep_link_watch: Mutex<Option<platform::DeepLinkWatch>>,
+    /// Where the placement is saved and the placement to save there.
+    window_state: Mutex<Option<(PathBuf, WindowState)>>,
+    frame_watch: Mutex<Option<platform::FrameWatch>>,
+    offscreen: Mutex<Option<platform::Offscreen>>,
+    paint_handlers: Handlers<PaintHandler>,
+    /// Keeps the owner alive while it owns the window, the guard goes first.
+    owner: Mutex<Option<(platform::Owner, Webview<'static>)>>,
+    splash: Mutex<Option<Webview<'static>>>,
+    view: platform::View,
+    pending_request: Mutex<Option<platform::Request>>,
+    policy: OnceLock<Arc<NavigationPolicy>>,
+    /// Where the engine asks before navigating, keeps blocked pages from loading.
+    navigation_watch: Mutex<Option<platform::NavigationWatch>>,
+    /// The token binding calls have to carry, see `WebviewBuilder::secure_ipc`.
+    ipc_token: OnceLock<String>,
+    /// The pages that may call any binding, see `WebviewBuilder::secure_ipc`.
+    ipc_trusted: OnceLock<Vec<String>>,
+    /// The pages that may call a binding, see `restrict_binding`.
+    binding_origins: Mutex<HashMap<String, Vec<String>>>,
+    /// What the arguments of a binding are checked against, see `set_binding_schema`.
+    binding_schemas: Mutex<HashMap<String, Arc<Schema>>>,
+    /// How often bindings may be called, see `limit_binding`.
+    binding_limits: Mutex<Limits>,
+    /// The threads of `bind_pooled` handlers, started on the first one.
+    pool: OnceLock<Pool>,
+    /// The `bind_cancellable` calls still running.
+    calls: Mutex<Calls>,
+    /// Set once the page can cancel calls.
+    cancel_bridge: OnceLock<()>,
+    /// The handlers of the bindings, see `unbind`.
+    bindings: Mutex<Bindings>,
+    /// The object bindings are put on, see `WebviewBuilder::binding_namespace`.
+    binding_namespace: OnceLock<String>,
+    /// The style sheets added with `add_css`.
+    css: Mutex<css::Sheets>,
+    /// See `emulate_network`, `None` until the page's requests are wrapped.
+    network: Mutex<Option<NetworkConditions>>,
+    /// How long handlers have to return, see `set_binding_timeout`.
+    timeouts: Arc<timeout::Watch>,
+    /// Set once a thread watches the timeouts.
+    timeout_watch: OnceLock<()>,
+    /// See `add_plugin`.
+    plugins: Mutex<Plugins>,
+    /// See `spawn_sidecar`.
+    sidecars: Mutex<Vec<Sidecar>>,
+    #[cfg(feature = "futures")]
+    events: Arc<Events>,
+}
+
+type SessionHandler = Box<dyn FnMut(&mut Webview<'_>, SessionEvent)>;
+type TitleHandler = Box<dyn FnMut(&mut Webview<'_>, &str)>;
+type FaviconHandler = Box<dyn FnMut(&mut Webview<'_>, &Favicon)>;
+type ThemeHandler = Box<dyn FnMut(&mut Webview<'_>, Theme)>;
+type FocusHandler = Box<dyn FnMut(&mut Webview<'_>, bool)>;
+type FileDropHandler = Box<dyn FnMut(&mut Webview<'_>, &[PathBuf], (i32, i32))>;
+type KeyHandler = Box<dyn FnMut(&mut Webview<'_>, &KeyEvent) -> bool>;
+type LoadHandler = Box<dyn FnMut(&mut Webview<'_>)>;
+type CompositionHandler = Box<dyn FnMut(&mut Webview<'_>, &CompositionEvent)>;
+type FullscreenHandler = Box<dyn FnMut(&mut Webview<'_>, &FullscreenElement) -> bool>;
+type DeepLinkHandler = Box<dyn FnMut(&mut Webview<'_>, &str)>;
+type PaintHandler = Box<dyn FnMut(&mut Webview<'_>, &Frame<'_>)>;
+
+impl Inner {
+    /// Destroys the native webview, on the UI thread once the last `Webview` is gone.
+    ///
+    /// Handles on other threads may keep `Inner` around a while longer, so everything that has
+    /// to go on the UI thread goes now, and they find the webview dead.
+    fn destroy(&self) {
+        *self.alive.write().unwrap() = false;
+        self.timeouts.wake.notify_all();
+        // Tear the panel down while the view it holds is still alive.
+        *self.panel.lock().unwrap() = None;
+        *self.session_watch.lock().unwrap() = None;
+        *self.theme_watch.lock().unwrap() = None;
+        *self.aspect_ratio.lock().unwrap() = None;
+        *self.sleep_inhibitor.lock().unwrap() = None;
+        *self.focus_watch.lock().unwrap() = None;
+        *self.file_drop_watch.lock().unwrap() = None;
+        *self.key_watch.lock().unwrap() = None;
+        *self.load_watch.lock().unwrap() = None;
+        *self.navigation_watch.lock().unwrap() = None;
+        *self.deep_link_watch.lock().unwrap() = None;
+        *self.frame_watch.lock().unwrap() = None;
+        *self.offscreen.lock().unwrap() = None;
+        if let Some((path, state)) = self.window_state.lock().unwrap().take() {
+            let _ = state.save(&path);
+        }
+        *self.owner.lock().unwrap() = None;
+        *self.splash.lock().unwrap() = None;
+        // The handlers may hold anything of the UI thread's.
+        self.session_handlers.clear();
+        self.title_handlers.clear();
+        self.favicon_handlers.clear();
+        self.theme_handlers.clear();
+        self.focus_handlers.clear();
+        self.file_drop_handlers.clear();
+        self.key_handlers.clear();
+        self.load_handlers.clear();
+        self.composition_handlers.clear();
+        self.fullscreen_handlers.clear();
+        self.deep_link_handlers.clear();
+        self.paint_handlers.clear();
+        drop(mem::take(&mut *self.bindings.lock().unwrap()));
+        drop(mem::take(&mut *self.plugins.lock().unwrap()));
+        drop(mem::take(&mut *self.quit_hooks.lock().unwrap()));
+        for sidecar in mem::take(&mut *self.sidecars.lock().unwrap()) {
+            sidecar.kill();
+        }
+        self.replies.cancel();
+        #[cfg(feature = "futures")]
+        self.events.close();
+        if self.released.load(Ordering::SeqCst) {
+            return;
+        }
+        platform::destroy(self.webview, self.owns_window);
+        unsafe { sys::webview_destroy(self.webview) }
+    }
+
+    /// Records that the backend ignored `call`, unless it is already recorded.
+    fn unsupported(&self, call: &'static str) {
+        let mut unsupported = self.unsupported.lock().unwrap();
+        if !unsupported.iter().any(|c| c.call == call) {
+            let backend = platform::backend(self.webview);
+            info!("unsupported call", call = %call, backend = ?backend);
+            unsupported.push(UnsupportedCall { call, backend });
+        }
+    }
+
+    /// Whether the page the view shows may call the binding `name`. The page reports it was
+    /// loaded from `reported`, which is only relied on where the view can't tell.
+    fn allows_call(&self, name: &str, reported: &str) -> bool {
+        let url = self.view.url();
+        let url = url.as_deref().unwrap_or(reported);
+        let matches =
+            |patterns: &Vec<String>| patterns.iter().any(|pattern| policy::matches(pattern, url));
+        let trusted = self.ipc_trusted.get().map(matches).unwrap_or(true);
+        let origins = self.binding_origins.lock().unwrap();
+        trusted && origins.get(name).map(matches).unwrap_or(true)
+    }
+}
+
+/// A handle to a native webview.
+///
+/// Clones share the same webview, which is destroyed along with its window once the last clone
+/// is dropped, on the UI thread. `WebviewHandle` handles don't keep it alive, they fail with
+/// `Error::WebviewNull` from then on.
 pub struct Webview<'a> {
-    inner: Arc<sys::webview_t>,
+    inner: Arc<Inner>,
     url: &'a str,
 }
 
-impl<'a> Drop for Webview<'a> {
+impl Clone for Webview<'_> {
+    fn clone(&self) -> Self {
+        self.inner.owners.fetch_add(1, Ordering::SeqCst);
+        Webview {
+            inner: self.inner.clone(),
+            url: self.url,
+        }
+    }
+}
+
+impl Drop for Webview<'_> {
     fn drop(&mut self) {
-        if Arc::strong_count(&self.inner) == 0 {
-            unsafe {
-                sys::webview_terminate(*self.inner);
-                sys::webview_destroy(*self.inner);
-            }
+        if self.inner.owners.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.inner.destroy();
         }
     }
 }
 
 impl<'a> Webview<'a> {
-    pub fn create(debug: bool, window: Option<&mut Window>) -> Webview {
-        if let Some(w) = window {
-            Webview {
-                inner: Arc::new(unsafe {
-                    sys::webview_create(debug as c_int, w as *mut Window as *mut _)
-                }),
-                url: "",
-            }
+    /// Another owner of `inner`, which must not be destroyed yet.
+    fn adopt(inner: Arc<Inner>) -> Webview<'a> {
+        inner.owners.fetch_add(1, Ordering::SeqCst);
+        Webview { inner, url: "" }
+    }
+
+    /// Another owner of `inner` for the UI thread's callbacks, `None` once it is destroyed.
+    fn revive(inner: Arc<Inner>) -> Option<Webview<'a>> {
+        if *inner.alive.read().unwrap() {
+            Some(Webview::adopt(inner))
         } else {
-            Webview {
-                inner: Arc::new(unsafe { sys::webview_create(debug as c_int, null_mut()) }),
-                url: "",
-            }
+            None
+        }
+    }
+
+    /// Like `revive`, for a handle.
+    fn upgrade(inner: &Weak<Inner>) -> Option<Webview<'a>> {
+        inner.upgrade().and_then(Webview::revive)
+    }
+
+    /// Panics if the native webview can't be created, see `try_create`.
+    pub fn create(debug: bool, window: Option<&mut NativeWindow>) -> Webview {
+        Webview::try_create(debug, window).expect("Creating the webview failed")
+    }
+
+    /// Like `create`, but fails with `Error::CreationFailed` if the native webview can't be
+    /// created.
+    pub fn try_create(
+        debug: bool,
+        window: Option<&mut NativeWindow>,
+    ) -> Result<Webview<'_>, Error> {
+        Webview::new(debug, window, false)
+    }
+
+    /// Creates a webview with `config` on a thread of its own and runs it there until it's
+    /// closed, for programs that go on with their work and control it through the handle.
+    ///
+    /// If `config` panics the handle fails with `Error::WebviewNull`, joining the thread returns
+    /// the panic.
+    ///
+    /// macOS only runs the UI on the main thread, so this isn't available there. Call `run` on
+    /// the main thread instead and move the rest of the work to a thread with a `handle`.
+    #[cfg(any(not(target_os = "macos"), feature = "mock"))]
+    pub fn spawn<F>(config: F) -> (thread::JoinHandle<()>, WebviewHandle)
+    where
+        F: FnOnce() -> Webview<'static> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("webview".to_string())
+            .spawn(move || {
+                let mut webview = config();
+                let _ = sender.send(webview.handle());
+                webview.run();
+            })
+            .expect("Spawning the webview's thread failed");
+        let handle = receiver
+            .recv()
+            .unwrap_or_else(|_| WebviewHandle(Weak::new()));
+        (thread, handle)
+    }
+
+    pub(crate) fn new(
+        debug: bool,
+        window: Option<&mut NativeWindow>,
+        external_loop: bool,
+    ) -> Result<Webview<'_>, Error> {
+        let owns_window = window.is_none();
+        let webview = if let Some(w) = window {
+            unsafe { sys::webview_create(debug as c_int, w as *mut NativeWindow as *mut _) }
+        } else {
+            unsafe { sys::webview_create(debug as c_int, null_mut()) }
+        };
+        if webview.is_null() {
+            return Err(Error::CreationFailed);
         }
+        if external_loop {
+            platform::detach_main_loop(webview);
+        }
+        info!(
+            "webview created",
+            backend = ?platform::backend(webview),
+            external_loop = %external_loop,
+        );
+        Ok(Webview::wrap(webview, owns_window, external_loop))
+    }
+
+    /// Creates a webview backed by the model of the `mock` module, see `WebviewBuilder::mock`.
+    #[cfg(feature = "mock")]
+    pub(crate) fn new_mock(external_loop: bool) -> Webview<'static> {
+        info!(
+            "webview created",
+            backend = ?Backend::Mock,
+            external_loop = %external_loop,
+        );
+        Webview::wrap(mock::create(), true, external_loop)
+    }
+
+    /// Takes over the native `webview`, which must not be null.
+    fn wrap(webview: sys::webview_t, owns_window: bool, external_loop: bool) -> Webview<'a> {
+        let mut w = Webview {
+            inner: Arc::new(Inner {
+                webview,
+                owns_window,
+                external_loop,
+                owners: AtomicUsize::new(1),
+                alive: RwLock::new(true),
+                released: AtomicBool::new(false),
+                quit_hooks: Mutex::new(QuitHooks::default()),
+                panel: Mutex::new(None),
+                replies: Arc::default(),
+                unsupported: Mutex::new(Vec::new()),
+                session_handlers: Handlers::default(),
+                title_handlers: Handlers::default(),
+                title_bridge: OnceLock::new(),
+                favicon_handlers: Handlers::default(),
+                favicon_bridge: OnceLock::new(),
+                theme: Mutex::new(Theme::System),
+                theme_handlers: Handlers::default(),
+                theme_watch: Mutex::new(None),
+                session_watch: Mutex::new(None),
+                aspect_ratio: Mutex::new(None),
+                sleep_inhibitor: Mutex::new(None),
+                focus_handlers: Handlers::default(),
+                focus_watch: Mutex::new(None),
+                file_drop_handlers: Handlers::default(),
+                file_drop_watch: Mutex::new(None),
+                key_handlers: Handlers::default(),
+                key_watch: Mutex::new(None),
+                load_handlers: Handlers::default(),
+                load_watch: Mutex::new(None),
+                composition_handlers: Handlers::default(),
+                composition_bridge: OnceLock::new(),
+                fullscreen_handlers: Handlers::default(),
+                fullscreen_bridge: OnceLock::new(),
+                geolocation_bridge: OnceLock::new(),
+                #[cfg(feature = "oauth")]
+                oauth_flows: OnceLock::new(),
+                deep_link_handlers: Handlers::default(),
+                deep_link_watch: Mutex::new(None),
+                window_state: Mutex::new(None),
+                frame_watch: Mutex::new(None),
+                offscreen: Mutex::new(None),
+                paint_handlers: Handlers::default(),
+                owner: Mutex::new(None),
+                splash: Mutex::new(None),
+                view: platform::View::new(webview),
+                pending_request: Mutex::new(None),
+                policy: OnceLock::new(),
+                navigation_watch: Mutex::new(None),
+                ipc_token: OnceLock::new(),
+                ipc_trusted: OnceLock::new(),
+                binding_origins: Mutex::new(HashMap::new()),
+                binding_schemas: Mutex::new(HashMap::new()),
+                binding_limits: Mutex::new(Limits::default()),
+                pool: OnceLock::new(),
+                calls: Mutex::new(Calls::default()),
+                cancel_bridge: OnceLock::new(),
+                bindings: Mutex::new(Bindings::default()),
+                binding_namespace: OnceLock::new(),
+                css: Mutex::default(),
+                network: Mutex::new(None),
+                timeouts: Arc::default(),
+                timeout_watch: OnceLock::new(),
+                plugins: Mutex::new(Plugins::default()),
+                sidecars: Mutex::new(Vec::new()),
+                #[cfg(feature = "futures")]
+                events: Arc::default(),
+            }),
+            url: "",
+        };
+        w.watch_pages();
+        w
+    }
+
+    /// Takes over a native webview, e.g. one given up by `into_raw` or created with
+    /// `webview_official_sys::webview_create`, and destroys it along with its window once the
+    /// last clone is dropped.
+    ///
+    /// # Safety
+    ///
+    /// `webview` must be a live native webview nothing else destroys, and this must be called
+    /// on its UI thread. Bindings and callbacks set on it before are left as they are.
+    pub unsafe fn from_raw(webview: sys::webview_t) -> Webview<'a> {
+        Webview::wrap(webview, true, false)
+    }
+
+    /// Gives the native webview up to the caller, who has to `webview_destroy` it.
+    ///
+    /// Once every clone is dropped, the bindings and handlers set through this crate stop
+    /// working but the webview and its window are kept. Handles fail with `Error::WebviewNull`
+    /// from then on.
+    pub fn into_raw(self) -> sys::webview_t {
+        self.inner.released.store(true, Ordering::SeqCst);
+        self.inner.webview
+    }
+
+    /// The native webview, for the `webview_official_sys` functions this crate doesn't cover.
+    ///
+    /// It still belongs to this `Webview`, it must not be destroyed and is only used on the UI
+    /// thread. The crate doesn't know about the changes made to it this way.
+    pub fn as_sys(&self) -> sys::webview_t {
+        self.inner.webview
     }
 
     pub fn run(&mut self) {
-        let c_url = CString::new(self.url).expect("No null bytes in parameter url");
-        unsafe { sys::webview_navigate(*self.inner, c_url.as_ptr()) }
-        unsafe { sys::webview_run(*self.inner) }
+        let request = self.inner.pending_request.lock().unwrap().take();
+        match request {
+            Some(request) => self.load(request),
+            None => {
+                let c_url = cstring::url(self.url);
+                unsafe { sys::webview_navigate(self.inner.webview, c_url.as_ptr()) }
+            }
+        }
+        unsafe { sys::webview_run(self.inner.webview) }
+
+        // The window was closed without going through `terminate`, run the hooks now.
+        let hooks = self.inner.quit_hooks.lock().unwrap().take();
+        if let Some(hooks) = hooks {
+            #[cfg(feature = "futures")]
+            self.inner.events.emit(Event::Quit);
+            hooks.run_blocking();
+        }
     }
 
+    /// Stops the main loop, after running the hooks registered with `on_before_quit` and
+    /// `on_before_quit_async`.
+    ///
+    /// The hooks only run once. Calling this again, e.g. while a hook still holds its
+    /// `QuitGuard`, stops the loop right away.
+    ///
+    /// When the event loop is driven by the host (see `WebviewBuilder::external_loop`) only the
+    /// hooks are run, they are the host's signal to stop its loop.
     pub fn terminate(&mut self) {
-        unsafe { sys::webview_terminate(*self.inner) }
+        let hooks = self.inner.quit_hooks.lock().unwrap().take();
+        let hooks = match hooks {
+            Some(hooks) => hooks,
+            None => {
+                if !self.inner.external_loop {
+                    platform::terminate(self.inner.webview);
+                }
+                return;
+            }
+        };
+        #[cfg(feature = "futures")]
+        self.inner.events.emit(Event::Quit);
+        if self.inner.external_loop {
+            hooks.run(|| ());
+            return;
+        }
+        let mut handle = self.handle();
+        hooks.run(move || {
+            let _ = handle.dispatch(|w| platform::terminate(w.inner.webview));
+        });
     }
 
-    pub fn as_mut(&mut self) -> WebviewMut {
-        WebviewMut(Arc::downgrade(&self.inner))
+    /// Registers a hook that runs once before the webview quits.
+    ///
+    /// Hooks run in registration order on the UI thread, either when `terminate` is called or
+    /// after the user closed the window.
+    pub fn on_before_quit<F>(&mut self, f: F)
+    where
+        F: FnOnce() + 'static,
+    {
+        self.inner.quit_hooks.lock().unwrap().push(Box::new(f));
     }
 
-    // TODO Window instance
-    pub fn set_title(&mut self, title: &str) {
-        let c_title = CString::new(title).expect("No null bytes in parameter title");
-        unsafe { sys::webview_set_title(*self.inner, c_title.as_ptr()) }
+    /// Registers a hook that can delay quitting until its cleanup finishes.
+    ///
+    /// The hook receives a `QuitGuard`, the webview quits once every guard has been dropped.
+    /// These hooks run after the ones registered with `on_before_quit`.
+    pub fn on_before_quit_async<F>(&mut self, f: F)
+    where
+        F: FnOnce(QuitGuard) + 'static,
+    {
+        self.inner
+            .quit_hooks
+            .lock()
+            .unwrap()
+            .push_async(Box::new(f));
     }
 
-    pub fn set_size(&mut self, width: i32, height: i32, hints: SizeHint) {
-        unsafe { sys::webview_set_size(*self.inner, width, height, hints as i32) }
+    /// Starts `command` as a helper process of this webview, e.g. a local API server or a
+    /// language server, which is killed once the webview quits or is destroyed.
+    ///
+    /// `handler` runs on the UI thread with each line the process writes to its stdout, and
+    /// when it exits. With the `futures` feature these are also sent to the event streams as
+    /// `Event::Sidecar`. A process that exits unsuccessfully is started again, a second after
+    /// the first crash and up to half a minute after many crashes in a row. Fails if the
+    /// process can't be started the first time.
+    pub fn spawn_sidecar<F>(
+        &mut self,
+        name: &str,
+        command: Command,
+        handler: F,
+    ) -> io::Result<Sidecar>
+    where
+        F: FnMut(&mut Webview, SidecarEvent) + Send + 'static,
+    {
+        let sidecar = sidecar::spawn(self, name, command, handler)?;
+        self.inner.sidecars.lock().unwrap().push(sidecar.clone());
+        Ok(sidecar)
     }
 
-    pub fn get_window(&self) -> *mut Window {
-        unsafe { sys::webview_get_window(*self.inner) as *mut Window }
+    /// Shows a native error dialog with the message and backtrace when the process panics.
+    ///
+    /// This installs a process wide panic hook, chained to the previous one. It is only installed
+    /// once, calling this again points it at this webview. A panic on another thread terminates
+    /// the webview once the dialog is dismissed, so `run` returns instead of leaving a window
+    /// behind that nothing drives anymore.
+    pub fn install_panic_dialog(&mut self) {
+        panic_hook::install(self.handle(), Display::Dialog);
     }
 
-    pub fn navigate(&mut self, url: &'a str) {
-        self.url = url;
+    /// Shows panics from other threads in an overlay on top of the page, instead of leaving the
+    /// page unresponsive.
+    ///
+    /// The overlay includes the backtrace in debug builds. The page also receives a
+    /// `webviewpanic` event with the message and location, and with the `futures` feature an
+    /// `Event::Panic` is sent to the event streams. Like `install_panic_dialog` this installs a
+    /// process wide panic hook, the last call of either decides how panics are shown.
+    pub fn install_panic_overlay(&mut self) {
+        panic_hook::install(self.handle(), Display::Overlay);
     }
 
-    pub fn init(&mut self, js: &str) {
-        let c_js = CString::new(js).expect("No null bytes in parameter js");
-        unsafe { sys::webview_init(*self.inner, c_js.as_ptr()) }
+    #[cfg(feature = "futures")]
+    pub(crate) fn emit(&self, event: Event) {
+        self.inner.events.emit(event);
     }
 
-    pub fn eval(&mut self, js: &str) {
-        let c_js = CString::new(js).expect("No null bytes in parameter js");
-        unsafe { sys::webview_eval(*self.inner, c_js.as_ptr()) }
+    /// Returns a stream of this webview's navigations and lifecycle events.
+    ///
+    /// Each call returns a new stream receiving every event from then on. Navigations are
+    /// reported by an `init` script, so only pages loaded after the first call show up. The
+    /// streams end once the webview is destroyed.
+    #[cfg(feature = "futures")]
+    pub fn events_stream(&mut self) -> EventStream {
+        if self.inner.events.bind_once() {
+            let events = self.inner.events.clone();
+            let handle = self.handle();
+            self.bind(events::BINDING, move |seq, req| {
+                if let Some(event) = Event::from_req(req) {
+                    events.emit(event);
+                }
+                let _ = handle.r#return(seq, 0, "null");
+            });
+            self.init(events::INIT_SCRIPT);
+        }
+        self.inner.events.subscribe()
     }
 
-    pub fn dispatch<F>(&mut self, f: F)
+    /// Calls `f` on the UI thread when the screen is locked or unlocked, or when the session is
+    /// detached from or attached to the console, as with fast user switching or remote desktop.
+    ///
+    /// Before `f` runs the page receives a `webviewsession` event with the new state as its
+    /// `detail`. When the session detaches its audio and video elements are paused, so they
+    /// release the devices another session may need. On Linux and the BSDs session changes are
+    /// read from logind.
+    pub fn on_session_event<F>(&mut self, f: F)
     where
-        F: FnOnce(&mut Webview) + Send + 'static,
+        F: FnMut(&mut Webview<'_>, SessionEvent) + 'static,
     {
-        let closure = Box::into_raw(Box::new(f));
-        extern "C" fn callback<F>(webview: sys::webview_t, arg: *mut c_void)
-        where
-            F: FnOnce(&mut Webview) + Send + 'static,
-        {
-            let mut webview = Webview {
-                inner: Arc::new(webview),
-                url: "",
-            };
-            let closure: Box<F> = unsafe { Box::from_raw(arg as *mut F) };
-            (*closure)(&mut webview);
+        self.inner.session_handlers.push(Box::new(f));
+        let mut watch = self.inner.session_watch.lock().unwrap();
+        if watch.is_none() {
+            let inner = Arc::downgrade(&self.inner);
+            *watch = Some(platform::SessionWatch::new(
+                self.inner.webview,
+                Box::new(move |event| {
+                    if let Some(mut w) = Webview::upgrade(&inner) {
+                        w.session_changed(event);
+                    }
+                }),
+            ));
         }
-        unsafe { sys::webview_dispatch(*self.inner, Some(callback::<F>), closure as *mut _) }
     }
 
-    pub fn bind<F>(&mut self, name: &str, f: F)
+    fn session_changed(&mut self, event: SessionEvent) {
+        self.eval(&session::script(event));
+        let inner = self.inner.clone();
+        inner.session_handlers.emit(|handler| handler(self, event));
+    }
+
+    /// Calls `f` with the page's title whenever `document.title` changes.
+    ///
+    /// The title is reported by an `init` script, so only pages loaded after the first call are
+    /// followed. See `WebviewBuilder::sync_window_title` to keep the window title in sync.
+    pub fn on_title_changed<F>(&mut self, f: F)
     where
-        F: FnMut(&str, &str),
-    {
-        let c_name = CString::new(name).expect("No null bytes in parameter name");
-        let closure = Box::into_raw(Box::new(f));
-        extern "C" fn callback<F>(seq: *const c_char, req: *const c_char, arg: *mut c_void)
-        where
-            F: FnMut(&str, &str),
-        {
-            let seq = unsafe {
-                CStr::from_ptr(seq)
-                    .to_str()
-                    .expect("No null bytes in parameter seq")
-            };
-            let req = unsafe {
-                CStr::from_ptr(req)
-                    .to_str()
-                    .expect("No null bytes in parameter req")
-            };
-            let mut f: Box<F> = unsafe { Box::from_raw(arg as *mut F) };
-            (*f)(seq, req);
-            mem::forget(f);
+        F: FnMut(&mut Webview<'_>, &str) + 'static,
+    {
+        self.inner.title_handlers.push(Box::new(f));
+        if self.inner.title_bridge.set(()).is_err() {
+            return;
         }
-        unsafe {
-            sys::webview_bind(
-                *self.inner,
-                c_name.as_ptr(),
-                Some(callback::<F>),
-                closure as *mut _,
-            )
+        let handle = self.handle();
+        self.bind(title::BINDING, move |seq, req| {
+            if let (Some(mut w), Some(title)) = (
+                Webview::upgrade(&handle.0),
+                json::strings(req).into_iter().next(),
+            ) {
+                w.title_changed(&title);
+            }
+            let _ = handle.r#return(seq, 0, "null");
+        });
+        self.init(title::INIT_SCRIPT);
+    }
+
+    fn title_changed(&mut self, title: &str) {
+        let inner = self.inner.clone();
+        inner.title_handlers.emit(|handler| handler(self, title));
+    }
+
+    /// Calls `f` with the page's icon whenever a page links to a different one.
+    ///
+    /// The icon is fetched by an `init` script, so only pages loaded after the first call are
+    /// followed. Icons on other origins are only seen if they are served with CORS headers. See
+    /// `WebviewBuilder::favicon_as_window_icon` to use them as the window icon.
+    pub fn on_favicon_changed<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Webview<'_>, &Favicon) + 'static,
+    {
+        self.inner.favicon_handlers.push(Box::new(f));
+        if self.inner.favicon_bridge.set(()).is_err() {
+            return;
         }
+        let handle = self.handle();
+        self.bind(favicon::BINDING, move |seq, req| {
+            let mut args = json::strings(req).into_iter();
+            if let (Some(mut w), Some(url), Some(bytes)) = (
+                Webview::upgrade(&handle.0),
+                args.next(),
+                args.next().and_then(|data| favicon::decode_base64(&data)),
+            ) {
+                w.favicon_changed(&Favicon { url, bytes });
+            }
+            let _ = handle.r#return(seq, 0, "null");
+        });
+        self.init(favicon::INIT_SCRIPT);
     }
 
-    pub fn r#return(&self, seq: &str, status: c_int, result: &str) {
-        let c_seq = CString::new(seq).expect("No null bytes in parameter seq");
-        let c_result = CString::new(result).expect("No null bytes in parameter result");
-        unsafe { sys::webview_return(*self.inner, c_seq.as_ptr(), status, c_result.as_ptr()) }
+    fn favicon_changed(&mut self, icon: &Favicon) {
+        let inner = self.inner.clone();
+        inner.favicon_handlers.emit(|handler| handler(self, icon));
     }
-}
 
-#[derive(Clone)]
-pub struct WebviewMut(Weak<sys::webview_t>);
+    /// Makes the page light or dark, as seen by `prefers-color-scheme`, or lets it follow the
+    /// system again with `Theme::System`, which is the default of `WebviewBuilder`.
+    ///
+    /// On Linux and the BSDs this sets GTK's dark theme preference, for every window of the
+    /// process. On Windows the page always follows the system, other themes are recorded in
+    /// `unsupported_calls`.
+    pub fn set_theme(&mut self, theme: Theme) {
+        *self.inner.theme.lock().unwrap() = theme;
+        if !self.inner.view.set_theme(theme) {
+            self.inner.unsupported("Webview::set_theme");
+        }
+        if theme == Theme::System {
+            self.watch_theme();
+        }
+    }
 
-unsafe impl Send for WebviewMut {}
-unsafe impl Sync for WebviewMut {}
+    /// Whether the system prefers a light or a dark appearance.
+    pub fn system_theme(&self) -> Theme {
+        self.inner.view.system_theme()
+    }
 
-impl WebviewMut {
-    pub fn terminate(&mut self) -> Result<(), Error> {
-        let webview = self.0.upgrade().ok_or(Error::WebviewNull)?;
-        unsafe { sys::webview_terminate(*webview) }
-        Ok(())
+    /// Calls `f` with the system's preference, light or dark, whenever it changes.
+    pub fn on_theme_changed<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Webview<'_>, Theme) + 'static,
+    {
+        self.inner.theme_handlers.push(Box::new(f));
+        self.watch_theme();
+    }
+
+    fn watch_theme(&mut self) {
+        let mut watch = self.inner.theme_watch.lock().unwrap();
+        if watch.is_none() {
+            let inner = Arc::downgrade(&self.inner);
+            *watch = Some(platform::ThemeWatch::new(
+                self.inner.webview,
+                Box::new(move |theme| {
+                    if let Some(mut w) = Webview::upgrade(&inner) {
+                        w.theme_changed(theme);
+                    }
+                }),
+            ));
+        }
     }
 
-    pub fn get_window(&self) -> Result<*mut Window, Error> {
-        let webview = self.0.upgrade().ok_or(Error::WebviewNull)?;
-        Ok(unsafe { sys::webview_get_window(*webview) as *mut Window })
+    fn theme_changed(&mut self, theme: Theme) {
+        // Not every engine follows the system by itself.
+        if *self.inner.theme.lock().unwrap() == Theme::System {
+            self.inner.view.set_theme(Theme::System);
+        }
+        let inner = self.inner.clone();
+        inner.theme_handlers.emit(|handler| handler(self, theme));
     }
 
-    pub fn dispatch<F>(&mut self, f: F) -> Result<(), Error>
+    /// Brings the window to the front and puts the keyboard focus in the page.
+    ///
+    /// Window managers may refuse to raise a window the user isn't interacting with, it gets
+    /// marked as wanting attention instead.
+    pub fn focus(&mut self) {
+        self.inner.view.focus();
+    }
+
+    /// Flashes the taskbar entry or bounces the Dock icon, e.g. when a message arrives while the
+    /// window is in the background.
+    ///
+    /// On Linux and the BSDs both levels set the window's urgency hint.
+    pub fn request_user_attention(&mut self, level: UserAttention) {
+        platform::request_user_attention(self.inner.webview, level);
+    }
+
+    /// Shows progress on the taskbar entry or the Dock icon, `fraction` goes from 0 to 1.
+    /// `ProgressState::None` hides it again.
+    ///
+    /// On Linux and the BSDs docks implementing the Unity launcher API show it on the entry of
+    /// the `.desktop` file named after the program.
+    pub fn set_progress(&mut self, state: ProgressState, fraction: f64) {
+        platform::set_progress(self.inner.webview, state, fraction.clamp(0.0, 1.0));
+    }
+
+    /// Shows `count` on the Dock icon or the launcher entry, e.g. of unread messages. `None`
+    /// removes it.
+    ///
+    /// On Linux and the BSDs it shows where `set_progress` does. Windows has no counts on taskbar
+    /// buttons, the call is recorded in `unsupported_calls`.
+    pub fn set_badge_count(&mut self, count: Option<u32>) {
+        if !platform::set_badge_count(self.inner.webview, count) {
+            self.inner.unsupported("Webview::set_badge_count");
+        }
+    }
+
+    /// Keeps the display on and the system awake, e.g. while a video plays or a kiosk shows its
+    /// content, until `allow_sleep` is called or the webview is dropped. `reason` is shown where
+    /// the system lists what keeps it awake.
+    ///
+    /// On Linux and the BSDs this inhibits the screen saver through the desktop's
+    /// `org.freedesktop.ScreenSaver` service. Where that fails the call is recorded in
+    /// `unsupported_calls`.
+    pub fn prevent_sleep(&mut self, reason: &str) {
+        let mut inhibitor = self.inner.sleep_inhibitor.lock().unwrap();
+        // The new reason replaces the old one, which is released after.
+        match platform::SleepInhibitor::new(self.inner.webview, reason) {
+            Some(new) => *inhibitor = Some(new),
+            None => self.inner.unsupported("Webview::prevent_sleep"),
+        }
+    }
+
+    /// Lets the system sleep again after `prevent_sleep`.
+    pub fn allow_sleep(&mut self) {
+        *self.inner.sleep_inhibitor.lock().unwrap() = None;
+    }
+
+    /// Whether the window has the keyboard focus.
+    pub fn is_focused(&self) -> bool {
+        self.inner.view.is_focused()
+    }
+
+    /// Calls `f` with `true` when the window gains the keyboard focus and `false` when it loses
+    /// it, e.g. to pause work while the application is in the background.
+    pub fn on_focus_changed<F>(&mut self, f: F)
     where
-        F: FnOnce(&mut Webview) + Send + 'static,
+        F: FnMut(&mut Webview<'_>, bool) + 'static,
     {
-        let webview = self.0.upgrade().ok_or(Error::WebviewNull)?;
-        let closure = Box::into_raw(Box::new(f));
-        extern "C" fn callback<F>(webview: sys::webview_t, arg: *mut c_void)
-        where
-            F: FnOnce(&mut Webview) + Send + 'static,
-        {
-            let mut webview = Webview {
-                inner: Arc::new(webview),
-                url: "",
-            };
-            let closure: Box<F> = unsafe { Box::from_raw(arg as *mut F) };
-            (*closure)(&mut webview);
+        self.inner.focus_handlers.push(Box::new(f));
+        let mut watch = self.inner.focus_watch.lock().unwrap();
+        if watch.is_none() {
+            let inner = Arc::downgrade(&self.inner);
+            *watch = Some(platform::FocusWatch::new(
+                self.inner.webview,
+                Box::new(move |focused| {
+                    if let Some(mut w) = Webview::upgrade(&inner) {
+                        w.focus_changed(focused);
+                    }
+                }),
+            ));
         }
-        unsafe { sys::webview_dispatch(*webview, Some(callback::<F>), closure as *mut _) }
-        Ok(())
     }
 
-    pub fn bind<F>(&mut self, name: &str, f: F) -> Result<(), Error>
+    fn focus_changed(&mut self, focused: bool) {
+        let inner = self.inner.clone();
+        inner.focus_handlers.emit(|handler| handler(self, focused));
+    }
+
+    /// Calls `f` with the paths of the files dropped on the webview and where they were dropped,
+    /// in CSS pixels from the top left of the view. The page's own `drop` handlers still run,
+    /// but only see the names of the files, see `forward_file_drops`.
+    ///
+    /// On Windows the drops can't be observed, the call is recorded in `unsupported_calls`.
+    pub fn on_file_drop<F>(&mut self, f: F)
     where
-        F: FnMut(&str, &str) + 'static,
+        F: FnMut(&mut Webview<'_>, &[PathBuf], (i32, i32)) + 'static,
+    {
+        let mut watch = self.inner.file_drop_watch.lock().unwrap();
+        if watch.is_none() {
+            let inner = Arc::downgrade(&self.inner);
+            *watch = platform::FileDropWatch::new(
+                &self.inner.view,
+                Box::new(move |paths, position| {
+                    if let Some(mut w) = Webview::upgrade(&inner) {
+                        w.files_dropped(&paths, position);
+                    }
+                }),
+            );
+        }
+        if watch.is_none() {
+            self.inner.unsupported("Webview::on_file_drop");
+            return;
+        }
+        drop(watch);
+        self.inner.file_drop_handlers.push(Box::new(f));
+    }
+
+    /// Tells the page about the files dropped on the webview with a `webviewfiledrop` event,
+    /// whose `detail` has their `paths` and the `x` and `y` they were dropped at.
+    ///
+    /// Every page the webview loads learns where the files are, see
+    /// `WebviewBuilder::navigation_policy` to keep remote pages out.
+    pub fn forward_file_drops(&mut self) {
+        self.on_file_drop(|webview, paths, (x, y)| {
+            let paths: Vec<String> = paths
+                .iter()
+                .map(|path| json::string(&path.to_string_lossy()))
+                .collect();
+            webview.eval(&format!(
+                r#"window.dispatchEvent(new CustomEvent("webviewfiledrop", {{ detail: {{"paths":[{}],"x":{},"y":{}}} }}));"#,
+                paths.join(","),
+                x,
+                y
+            ));
+        });
+    }
+
+    fn files_dropped(&mut self, paths: &[PathBuf], position: (i32, i32)) {
+        let inner = self.inner.clone();
+        inner
+            .file_drop_handlers
+            .emit(|handler| handler(self, paths, position));
+    }
+
+    /// Calls `f` with the keys pressed while the webview has the keyboard focus, before the page
+    /// sees them, e.g. for Ctrl+W or F11 to work even in a text field. `f` returns `true` when
+    /// it handled the key, which then doesn't reach the page or the handlers added after it.
+    ///
+    /// Only presses are reported, including the repeats of a held key. On Windows the keys
+    /// can't be seen before the page, the call is recorded in `unsupported_calls`.
+    pub fn on_key<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Webview<'_>, &KeyEvent) -> bool + 'static,
+    {
+        let mut watch = self.inner.key_watch.lock().unwrap();
+        if watch.is_none() {
+            let inner = Arc::downgrade(&self.inner);
+            *watch = platform::KeyWatch::new(
+                &self.inner.view,
+                Box::new(move |event| match Webview::upgrade(&inner) {
+                    Some(mut w) => w.key_pressed(&event),
+                    None => false,
+                }),
+            );
+        }
+        if watch.is_none() {
+            self.inner.unsupported("Webview::on_key");
+            return;
+        }
+        drop(watch);
+        self.inner.key_handlers.push(Box::new(f));
+    }
+
+    fn key_pressed(&mut self, event: &KeyEvent) -> bool {
+        let inner = self.inner.clone();
+        inner
+            .key_handlers
+            .emit_until(|handler| handler(self, event))
+    }
+
+    /// Calls `f` whenever the view finished loading a page or gave up on it. The engine reports
+    /// it, the page can't.
+    ///
+    /// On Windows the loads can't be observed, the call is recorded in `unsupported_calls`.
+    pub fn on_load_finished<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Webview<'_>) + 'static,
     {
-        let webview = self.0.upgrade().ok_or(Error::WebviewNull)?;
-        let c_name = CString::new(name).expect("No null bytes in parameter name");
-        let closure = Box::into_raw(Box::new(f));
-        extern "C" fn callback<F>(seq: *const c_char, req: *const c_char, arg: *mut c_void)
-        where
-            F: FnMut(&str, &str) + 'static,
-        {
-            let seq = unsafe {
-                CStr::from_ptr(seq)
-                    .to_str()
-                    .expect("No null bytes in parameter seq")
+        let mut watch = self.inner.load_watch.lock().unwrap();
+        if watch.is_none() {
+            let inner = Arc::downgrade(&self.inner);
+            *watch = platform::LoadWatch::new(
+                &self.inner.view,
+                Box::new(move || {
+                    if let Some(mut w) = Webview::upgrade(&inner) {
+                        w.load_finished();
+                    }
+                }),
+            );
+        }
+        if watch.is_none() {
+            self.inner.unsupported("Webview::on_load_finished");
+            return;
+        }
+        drop(watch);
+        self.inner.load_handlers.push(Box::new(f));
+    }
+
+    fn load_finished(&mut self) {
+        let inner = self.inner.clone();
+        inner.load_handlers.emit(|handler| handler(self));
+    }
+
+    /// Calls `f` with the steps of composing text with an input method in the page, e.g. to see
+    /// how a backend's input method behaves or to hold shortcuts back while the user composes.
+    ///
+    /// The page reports them from an `init` script, so compositions in pages loaded before the
+    /// first call are missed. See `gtk::Options::input_method` to turn the input method off.
+    pub fn on_composition<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Webview<'_>, &CompositionEvent) + 'static,
+    {
+        self.inner.composition_handlers.push(Box::new(f));
+        if self.inner.composition_bridge.set(()).is_err() {
+            return;
+        }
+        let inner = Arc::downgrade(&self.inner);
+        self.bind(composition::BINDING, move |seq, req| {
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            if let Some(event) = CompositionEvent::from_req(req) {
+                if let Some(mut w) = Webview::revive(inner.clone()) {
+                    w.composed(&event);
+                }
+            }
+            r#return(&inner, seq, 0, "null");
+        });
+        self.init(composition::INIT_SCRIPT);
+    }
+
+    fn composed(&mut self, event: &CompositionEvent) {
+        let inner = self.inner.clone();
+        inner
+            .composition_handlers
+            .emit(|handler| handler(self, event));
+    }
+
+    /// Whether the window covers the screen, see `set_fullscreen`.
+    pub fn is_fullscreen(&self) -> bool {
+        platform::is_fullscreen(self.inner.webview)
+    }
+
+    /// Makes the window cover its screen without decorations, or puts it back.
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        platform::set_fullscreen(self.inner.webview, fullscreen);
+    }
+
+    /// Calls `f` when the page asks to show an element fullscreen, e.g. a video, `f` returns
+    /// `true` to allow it. Allowed elements fill the window, which is made fullscreen until the
+    /// page exits fullscreen again or the user presses Escape. With several handlers the
+    /// request is allowed if any of them allows it.
+    ///
+    /// The page's Fullscreen API is replaced by an `init` script, so pages loaded before the
+    /// first call go fullscreen the engine's way. Controls the engine draws itself, e.g. the
+    /// fullscreen button of native video controls, may still bypass it.
+    pub fn on_fullscreen_request<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Webview<'_>, &FullscreenElement) -> bool + 'static,
+    {
+        self.inner.fullscreen_handlers.push(Box::new(f));
+        if self.inner.fullscreen_bridge.set(()).is_err() {
+            return;
+        }
+        let inner = Arc::downgrade(&self.inner);
+        // Whether the window went fullscreen for the page, and has to leave it with the page.
+        let mut entered = false;
+        self.bind(fullscreen::BINDING, move |seq, req| {
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            let mut w = match Webview::revive(inner.clone()) {
+                Some(w) => w,
+                None => return,
             };
-            let req = unsafe {
-                CStr::from_ptr(req)
-                    .to_str()
-                    .expect("No null bytes in parameter req")
+            let result = match fullscreen::parse(req) {
+                Some(fullscreen::Request::Enter(element)) => {
+                    let allowed = w.fullscreen_requested(&element);
+                    if allowed && !w.is_fullscreen() {
+                        w.set_fullscreen(true);
+                        entered = true;
+                    }
+                    allowed
+                }
+                Some(fullscreen::Request::Exit) => {
+                    if entered {
+                        w.set_fullscreen(false);
+                        entered = false;
+                    }
+                    true
+                }
+                None => false,
             };
-            let mut f: Box<F> = unsafe { Box::from_raw(arg as *mut F) };
-            (*f)(seq, req);
-            mem::forget(f);
+            r#return(&inner, seq, 0, if result { "true" } else { "false" });
+        });
+        self.init(fullscreen::INIT_SCRIPT);
+    }
+
+    fn fullscreen_requested(&mut self, element: &FullscreenElement) -> bool {
+        let inner = self.inner.clone();
+        let mut allowed = false;
+        inner
+            .fullscreen_handlers
+            .emit(|handler| allowed |= handler(self, element));
+        allowed
+    }
+
+    /// Answers the page's geolocation requests with the position `f` returns, instead of the
+    /// system's location services, which are often missing on desktops. `None` denies the
+    /// request with `PERMISSION_DENIED`.
+    ///
+    /// `f` runs on the worker threads of `bind_pooled`, so it may take its time, e.g. to ask a
+    /// GPS receiver. Watches ask again every five seconds. `navigator.geolocation` is replaced
+    /// by an `init` script, so pages loaded before the first call still use the engine's, and
+    /// calling this again replaces `f`.
+    pub fn set_geolocation_provider<F>(&mut self, f: F)
+    where
+        F: Fn() -> Option<Coordinates> + Send + Sync + 'static,
+    {
+        self.bind_pooled(geolocation::BINDING, move |_req, _token| {
+            Ok(f().map_or("null".to_string(), Coordinates::to_json))
+        });
+        if self.inner.geolocation_bridge.set(()).is_ok() {
+            self.init(geolocation::INIT_SCRIPT);
         }
-        unsafe {
-            sys::webview_bind(
-                *webview,
-                c_name.as_ptr(),
-                Some(callback::<F>),
-                closure as *mut _,
-            )
+    }
+
+    /// Calls `f` with each link to one of the schemes registered with
+    /// `Application::register_url_scheme` the application is opened with.
+    ///
+    /// Links the process was started with are handed to the first webview this is called on,
+    /// from the event loop. Links opened later reach it through the `InstanceLock` the webview
+    /// is given with `forward_to`, or directly on macOS, where the system keeps a single
+    /// instance and hands links over as Apple events.
+    pub fn on_deep_link<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Webview<'_>, &str) + 'static,
+    {
+        self.inner.deep_link_handlers.push(Box::new(f));
+        let mut watch = self.inner.deep_link_watch.lock().unwrap();
+        if watch.is_none() {
+            let inner = Arc::downgrade(&self.inner);
+            *watch = Some(platform::DeepLinkWatch::new(
+                self.inner.webview,
+                Box::new(move |link| {
+                    if let Some(mut w) = Webview::upgrade(&inner) {
+                        w.open_deep_links(&[link]);
+                    }
+                }),
+            ));
+            drop(watch);
+            // Later in the setup the schemes are registered and all handlers are in place.
+            self.dispatch(|w| w.open_deep_links(&deep_link::take_launch_links()));
         }
-        Ok(())
     }
 
-    pub fn r#return(&self, seq: &str, status: c_int, result: &str) -> Result<(), Error> {
-        let webview = self.0.upgrade().ok_or(Error::WebviewNull)?;
-        let c_seq = CString::new(seq).expect("No null bytes in parameter seq");
-        let c_result = CString::new(result).expect("No null bytes in parameter result");
-        unsafe { sys::webview_return(*webview, c_seq.as_ptr(), status, c_result.as_ptr()) }
-        Ok(())
+    /// The custom scheme sign-ins of this webview, which share one deep link handler.
+    #[cfg(feature = "oauth")]
+    pub(crate) fn oauth_flows(&mut self) -> oauth::PendingFlows {
+        if let Some(flows) = self.inner.oauth_flows.get() {
+            return flows.clone();
+        }
+        let flows = oauth::PendingFlows::default();
+        let _ = self.inner.oauth_flows.set(flows.clone());
+        let dispatcher = flows.clone();
+        self.on_deep_link(move |w, link| dispatcher.redirected(w, link));
+        flows
+    }
+
+    /// Opens `url` in the default browser, `false` if that failed.
+    #[cfg(feature = "oauth")]
+    pub(crate) fn open_external(&mut self, url: &str) -> bool {
+        platform::open_external(self.inner.webview, url)
+    }
+
+    /// Hands `links` to the `on_deep_link` handlers.
+    pub(crate) fn open_deep_links(&mut self, links: &[String]) {
+        if links.is_empty() {
+            return;
+        }
+        let inner = self.inner.clone();
+        for link in links {
+            inner.deep_link_handlers.emit(|handler| handler(self, link));
+        }
+    }
+
+    /// Sets the color shown where the page doesn't paint, e.g. while it loads.
+    ///
+    /// Use `WebviewBuilder::background_color` to have it from the first paint on. On macOS the
+    /// window's background shows behind the page instead of the view's. On Windows the color can
+    /// only be set by the builder, this call is recorded in `unsupported_calls`.
+    pub fn set_background_color(&mut self, color: Color) {
+        if !self.inner.view.set_background_color(color) {
+            self.inner.unsupported("Webview::set_background_color");
+        }
+    }
+
+    pub(crate) fn set_owner(&mut self, owner: Webview<'static>, modal: bool) {
+        let guard = platform::Owner::new(self.inner.webview, owner.inner.webview, modal);
+        *self.inner.owner.lock().unwrap() = Some((guard, owner));
+    }
+
+    /// A clone that isn't tied to the url passed to `WebviewBuilder::url`.
+    pub(crate) fn share(&self) -> Webview<'static> {
+        Webview::adopt(self.inner.clone())
+    }
+
+    pub(crate) fn apply_window_effect(&mut self, effect: WindowEffect) -> bool {
+        let applied = platform::set_window_effect(self.inner.webview, effect);
+        if !applied {
+            self.inner.unsupported("WebviewBuilder::window_effect");
+        }
+        applied
+    }
+
+    /// Sets the window's icon from a PNG or ICO image, returns `false` if it can't be decoded.
+    ///
+    /// On Linux and the BSDs any format gdk-pixbuf reads works. On macOS windows have no icon of
+    /// their own, the application's Dock icon is changed instead.
+    pub fn set_window_icon(&mut self, image: &[u8]) -> bool {
+        platform::set_window_icon(self.inner.webview, image)
+    }
+
+    /// Moves the window with the pointer while the primary mouse button is held.
+    ///
+    /// Meant to be called while handling a press of the button, windows without a title bar use
+    /// it to stay movable. `WebviewBuilder::drag_regions` calls it for presses on parts of the
+    /// page. Recorded in `unsupported_calls` when no press is being handled on macOS.
+    pub fn start_window_drag(&mut self) {
+        if !platform::start_window_drag(self.inner.webview) {
+            self.inner.unsupported("Webview::start_window_drag");
+        }
+    }
+
+    /// Drags `item` out of the window with the primary mouse button, e.g. files to the desktop
+    /// or into another application.
+    ///
+    /// Meant to be called while handling a press of the button, typically from a binding the
+    /// page calls on `mousedown` after `preventDefault()`, the drag follows the pointer until
+    /// the button is released. Recorded in `unsupported_calls` when the drag couldn't start,
+    /// e.g. when no press is being handled on macOS.
+    pub fn start_drag(&mut self, item: DragItem) {
+        if !platform::start_drag(&self.inner.view, &item) {
+            self.inner.unsupported("Webview::start_drag");
+        }
+    }
+
+    pub(crate) fn enable_drag_regions(&mut self) {
+        let handle = self.handle();
+        self.bind(drag::BINDING, move |seq, _req| {
+            if let Some(mut w) = Webview::upgrade(&handle.0) {
+                w.start_window_drag();
+            }
+            let _ = handle.r#return(seq, 0, "null");
+        });
+        self.init(drag::INIT_SCRIPT);
+    }
+
+    /// The in-memory model standing in for the native webview, see the `mock` module.
+    ///
+    /// Panics if the webview wasn't built with `WebviewBuilder::mock`.
+    #[cfg(feature = "mock")]
+    pub fn mock(&self) -> Mock<'_> {
+        assert!(
+            mock::is_model(self.inner.webview),
+            "The webview is native, build it with `WebviewBuilder::mock`"
+        );
+        Mock::new(self.inner.webview)
+    }
+
+    /// The engine this webview runs on.
+    pub fn backend(&self) -> Backend {
+        platform::backend(self.inner.webview)
+    }
+
+    /// What the engine supports, see `Capabilities`.
+    pub fn capabilities(&self) -> Capabilities {
+        platform::capabilities(self.inner.webview)
+    }
+
+    /// Keeps the page off the GPU where that is still possible once the view exists, see
+    /// `WebviewBuilder::hardware_acceleration`.
+    pub(crate) fn disable_hardware_acceleration(&mut self) {
+        if !platform::disable_hardware_acceleration(self.inner.webview) {
+            self.inner
+                .unsupported("WebviewBuilder::hardware_acceleration");
+        }
+    }
+
+    /// See `WebviewBuilder::background_throttling`.
+    pub(crate) fn apply_background_throttling(&mut self, throttling: BackgroundThrottling) {
+        if !platform::set_background_throttling(self.inner.webview, throttling) {
+            self.inner
+                .unsupported("WebviewBuilder::background_throttling");
+        }
+    }
+
+    /// See `WebviewBuilder::map_host`.
+    pub(crate) fn map_host(&mut self, host: &str, folder: &Path) {
+        if !platform::map_host(self.inner.webview, host, folder) {
+            self.inner.unsupported("WebviewBuilder::map_host");
+        }
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub(crate) fn apply_gtk_options(&mut self, options: &crate::gtk::Options) {
+        platform::apply_webkit_settings(self.inner.webview, options);
+    }
+
+    pub(crate) fn apply_cache(&mut self, cache: &Cache) {
+        for call in platform::apply_cache(self.inner.webview, cache) {
+            self.inner.unsupported(call);
+        }
+    }
+
+    /// The calls so far that the native engine couldn't honor and ignored, each listed once.
+    ///
+    /// Tests can assert this is empty to make sure an application only relies on what every
+    /// backend supports.
+    pub fn unsupported_calls(&self) -> Vec<UnsupportedCall> {
+        self.inner.unsupported.lock().unwrap().clone()
+    }
+
+    /// A handle other threads can use the webview with, see `WebviewHandle`.
+    pub fn handle(&self) -> WebviewHandle {
+        WebviewHandle(Arc::downgrade(&self.inner))
+    }
+
+    /// The window the webview is shown in, see `Window`.
+    pub fn window(&self) -> Window<'a> {
+        Window::new(self.clone())
+    }
+
+    /// What the webview shows, see `WebContents`.
+    pub fn contents(&self) -> WebContents<'a> {
+        WebContents::new(self.clone())
+    }
+
+    #[deprecated(note = "renamed to `handle`")]
+    pub fn as_mut(&mut self) -> WebviewHandle {
+        self.handle()
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        let c_title = cstring::text(title);
+        unsafe { sys::webview_set_title(self.inner.webview, c_title.as_ptr()) }
+    }
+
+    pub fn set_size(&mut self, width: i32, height: i32, hints: SizeHint) {
+        unsafe { sys::webview_set_size(self.inner.webview, width, height, hints as i32) }
+    }
+
+    /// Keeps the window's content at `width` to `height` while the user resizes it, e.g. 16 by
+    /// 9 for a video. A `width` or `height` of 0 lets it resize freely again.
+    ///
+    /// `set_size` isn't constrained. On Linux and the BSDs the ratio replaces the limits of a
+    /// `SizeHint::MIN` or `SizeHint::MAX` size, and the other way around.
+    pub fn set_aspect_ratio(&mut self, width: u32, height: u32) {
+        let mut aspect_ratio = self.inner.aspect_ratio.lock().unwrap();
+        // Lift the previous ratio first, a window has room for one.
+        *aspect_ratio = None;
+        if width > 0 && height > 0 {
+            *aspect_ratio = Some(platform::AspectRatio::new(
+                self.inner.webview,
+                width,
+                height,
+            ));
+        }
+    }
+
+    /// Restores the window's placement saved at `path` and keeps saving it there.
+    pub(crate) fn persist_window_state(&mut self, path: PathBuf) {
+        let webview = self.inner.webview;
+        let monitors = platform::monitors(webview);
+        let saved = WindowState::load(&path);
+        if let Some(frame) = saved.and_then(|state| state.place(&monitors)) {
+            platform::set_window_frame(webview, frame);
+        }
+        let maximized = saved.is_some_and(|state| state.maximized);
+        if maximized {
+            platform::maximize(webview);
+        }
+        let frame = platform::window_frame(webview);
+        let state = window_state::monitor_of(frame, &monitors).map(|monitor| WindowState {
+            frame,
+            maximized,
+            monitor,
+        });
+        if let Some(state) = state {
+            *self.inner.window_state.lock().unwrap() = Some((path, state));
+        }
+        let inner = Arc::downgrade(&self.inner);
+        *self.inner.frame_watch.lock().unwrap() = Some(platform::FrameWatch::new(
+            webview,
+            Box::new(move || {
+                if let Some(mut w) = Webview::upgrade(&inner) {
+                    w.frame_changed();
+                }
+            }),
+        ));
+        // Saved again when the webview is dropped, in case the process ends before.
+        let inner = Arc::downgrade(&self.inner);
+        self.on_before_quit(move || {
+            if let Some(inner) = inner.upgrade() {
+                if let Some((path, state)) = &*inner.window_state.lock().unwrap() {
+                    let _ = state.save(path);
+                }
+            }
+        });
+    }
+
+    fn frame_changed(&mut self) {
+        let webview = self.inner.webview;
+        if platform::is_minimized(webview) {
+            return;
+        }
+        let mut window_state = self.inner.window_state.lock().unwrap();
+        if let Some((_, state)) = &mut *window_state {
+            // A maximized window keeps the frame it is restored to.
+            state.maximized = platform::is_maximized(webview);
+            if !state.maximized {
+                state.frame = platform::window_frame(webview);
+            }
+            if let Some(monitor) =
+                window_state::monitor_of(state.frame, &platform::monitors(webview))
+            {
+                state.monitor = monitor;
+            }
+        }
+    }
+
+    /// Renders the page for `on_frame` instead of the window, see `WebviewBuilder::offscreen`.
+    pub(crate) fn render_offscreen(&mut self) -> Result<(), Error> {
+        let inner = Arc::downgrade(&self.inner);
+        let offscreen = platform::Offscreen::new(
+            self.inner.webview,
+            Box::new(move |frame| {
+                if let Some(mut w) = Webview::upgrade(&inner) {
+                    w.painted(frame);
+                }
+            }),
+        )
+        .ok_or_else(|| {
+            Error::BackendUnavailable(format!("{:?} can't render offscreen", self.backend()))
+        })?;
+        *self.inner.offscreen.lock().unwrap() = Some(offscreen);
+        Ok(())
+    }
+
+    /// Calls `f` with every frame the page is rendered to, once it renders offscreen, see
+    /// `WebviewBuilder::offscreen`. Webviews shown in a window have no frames.
+    pub fn on_frame<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Webview<'_>, &Frame<'_>) + 'static,
+    {
+        self.inner.paint_handlers.push(Box::new(f));
+    }
+
+    fn painted(&mut self, frame: &Frame<'_>) {
+        let inner = self.inner.clone();
+        inner.paint_handlers.emit(|handler| handler(self, frame));
+    }
+
+    /// Shows or hides the window.
+    pub fn set_visible(&mut self, visible: bool) {
+        platform::set_visible(self.inner.webview, visible);
+    }
+
+    /// Closes the splash window and shows this one, see `WebviewBuilder::splash`. Does nothing
+    /// once the splash window is closed.
+    pub fn close_splash(&mut self) {
+        let splash = self.inner.splash.lock().unwrap().take();
+        if let Some(splash) = splash {
+            self.set_visible(true);
+            drop(splash);
+        }
+    }
+
+    /// Hides the window behind `splash` until the page calls `window.__webview_ready()`.
+    pub(crate) fn show_splash(&mut self, splash: Webview<'static>) {
+        self.set_visible(false);
+        *self.inner.splash.lock().unwrap() = Some(splash);
+        let handle = self.handle();
+        self.bind(splash::BINDING, move |seq, _req| {
+            if let Some(mut w) = Webview::upgrade(&handle.0) {
+                w.close_splash();
+            }
+            let _ = handle.r#return(seq, 0, "null");
+        });
+    }
+
+    pub fn get_window(&self) -> *mut NativeWindow {
+        if !self.capabilities().window_handle {
+            self.inner.unsupported("Webview::get_window");
+        }
+        unsafe { sys::webview_get_window(self.inner.webview) as *mut NativeWindow }
+    }
+
+    /// Moves the webview into `parent`, making it a panel of the host's window.
+    ///
+    /// `parent` is a `GtkContainer` on GTK, an `NSView` on macOS and an `HWND` on Windows. The
+    /// webview's own window is hidden, calling this again moves the panel to another parent.
+    pub fn reparent(&mut self, parent: *mut NativeWindow) {
+        if !self.capabilities().reparent {
+            self.inner.unsupported("Webview::reparent");
+        }
+        let mut panel = self.inner.panel.lock().unwrap();
+        match panel.as_mut() {
+            Some(panel) => panel.reparent(parent as *mut _),
+            None => *panel = Some(platform::Panel::new(self.inner.webview, parent as *mut _)),
+        }
+    }
+
+    /// Places the panel within its parent, has no effect before `reparent`.
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        if let Some(panel) = self.inner.panel.lock().unwrap().as_mut() {
+            panel.set_bounds(bounds);
+        }
+    }
+
+    /// Places the panel with `f` whenever its parent is resized or moves to a display with a
+    /// different scale factor, `f` receives the parent's width and height in logical pixels. Has
+    /// no effect before `reparent`.
+    pub fn set_layout<F>(&mut self, f: F)
+    where
+        F: FnMut(i32, i32) -> Rect + 'static,
+    {
+        if let Some(panel) = self.inner.panel.lock().unwrap().as_mut() {
+            panel.set_layout(Box::new(f));
+        }
+    }
+
+    /// Creates another webview shown as a pane over this one's page, e.g. a sidebar, placed with
+    /// the pane's `set_bounds` or `set_layout`. Panes navigate and bind on their own.
+    ///
+    /// They run in this webview's loop, don't `run` them, and leave the window when their last
+    /// clone is dropped. For a layout made only of panes, leave this webview's page blank.
+    pub fn add_pane(&mut self, debug: bool) -> Result<Webview<'static>, Error> {
+        let mut pane = Webview::new(debug, None, true)?;
+        let parent = platform::pane_parent(self.inner.webview);
+        pane.reparent(parent as *mut NativeWindow);
+        Ok(pane)
+    }
+
+    /// Creates a webview shown as a panel at `bounds` in a window of another toolkit, e.g. an
+    /// HTML panel in an established native application, see `reparent`.
+    ///
+    /// `parent` is an `HWND` on Windows and an `NSView` on macOS. On Linux and the BSDs it is an
+    /// X11 window, which a `GtkPlug` is embedded into. Other handles fail with
+    /// `Error::BackendUnavailable`. The host keeps running its own loop, see
+    /// `WebviewBuilder::external_loop`.
+    #[cfg(feature = "raw-window-handle")]
+    pub fn attach_to(parent: RawWindowHandle, bounds: Rect) -> Result<Webview<'static>, Error> {
+        platform::check_backend()?;
+        let mut webview = Webview::new(false, None, true)?;
+        let parent = platform::foreign_parent(parent)?;
+        webview.reparent(parent as *mut NativeWindow);
+        webview.set_bounds(bounds);
+        Ok(webview)
+    }
+
+    /// Sets the url to load once `run` is called.
+    ///
+    /// When the event loop is driven by the host the url is loaded right away instead.
+    ///
+    /// Urls the navigation policy blocks are not loaded, see `WebviewBuilder::navigation_policy`.
+    pub fn navigate(&mut self, url: &'a str) {
+        if let Some(policy) = self.inner.policy.get() {
+            if !policy.allows(url) {
+                open_blocked(self.inner.webview, policy, url);
+                return;
+            }
+        }
+        debug!("navigating", url = %url);
+        self.url = url;
+        *self.inner.pending_request.lock().unwrap() = None;
+        if self.inner.external_loop {
+            let c_url = cstring::url(url);
+            unsafe { sys::webview_navigate(self.inner.webview, c_url.as_ptr()) }
+        }
+    }
+
+    /// Like `navigate`, but sends `headers` along with the request for the page, e.g. an
+    /// `Authorization` header.
+    ///
+    /// Only the page itself is requested with the headers, not its resources or the pages it
+    /// leads to. On Windows the headers are left out and the call is recorded in
+    /// `unsupported_calls`.
+    pub fn navigate_with_headers(&mut self, url: &str, headers: &[(&str, &str)]) {
+        self.request(platform::Request {
+            url: url.to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: None,
+        });
+    }
+
+    /// Like `navigate`, but posts `body` to `url`, e.g. to hand a form over to a login page.
+    ///
+    /// On Linux, the BSDs and Windows only `application/x-www-form-urlencoded` bodies can be
+    /// posted, by loading a `data:` page that submits them as a form. Other bodies are not sent
+    /// and the call is recorded in `unsupported_calls`. A navigation policy has to allow `data:`
+    /// urls there.
+    pub fn navigate_post(&mut self, url: &str, body: &[u8], content_type: &str) {
+        self.request(platform::Request {
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: Some((body.to_vec(), content_type.to_string())),
+        });
+    }
+
+    fn request(&mut self, request: platform::Request) {
+        if let Some(policy) = self.inner.policy.get() {
+            if !policy.allows(&request.url) {
+                open_blocked(self.inner.webview, policy, &request.url);
+                return;
+            }
+        }
+        debug!("navigating", url = %request.url);
+        if self.inner.external_loop {
+            self.load(request);
+        } else {
+            *self.inner.pending_request.lock().unwrap() = Some(request);
+        }
+    }
+
+    fn load(&self, request: platform::Request) {
+        if self.inner.view.load(&request) {
+            return;
+        }
+        match &request.body {
+            None => self.inner.unsupported("Webview::navigate_with_headers"),
+            Some((body, content_type)) => {
+                let form = std::str::from_utf8(body)
+                    .ok()
+                    .filter(|_| content_type.starts_with(post::FORM));
+                match form {
+                    Some(form) => {
+                        let page = cstring::url(&post::form_page(&request.url, form));
+                        unsafe { sys::webview_navigate(self.inner.webview, page.as_ptr()) }
+                    }
+                    None => self.inner.unsupported("Webview::navigate_post"),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn apply_navigation_policy(&mut self, policy: NavigationPolicy) {
+        let policy = Arc::new(policy);
+        let blocked = policy.clone();
+        let webview = self.inner.webview;
+        let handle = self.handle();
+        self.bind(policy::BINDING, move |seq, req| {
+            if let Some(url) = json::strings(req).into_iter().next() {
+                open_blocked(webview, &blocked, &url);
+            }
+            let _ = handle.r#return(seq, 0, "null");
+        });
+        let decided = policy.clone();
+        *self.inner.navigation_watch.lock().unwrap() = platform::NavigationWatch::new(
+            &self.inner.view,
+            Box::new(move |url| {
+                let allowed = decided.allows(url);
+                if !allowed {
+                    open_blocked(webview, &decided, url);
+                }
+                allowed
+            }),
+        );
+        // Where the engine can't be asked this is all there is.
+        self.init(&policy.script());
+        let _ = self.inner.policy.set(policy);
+    }
+
+    /// See `WebviewBuilder::service_workers`.
+    pub(crate) fn disable_service_workers(&mut self) {
+        self.init(service_worker::DISABLE_SCRIPT);
+    }
+
+    pub(crate) fn apply_content_security_policy(&mut self, policy: &str) {
+        self.init(&csp::script(policy));
+    }
+
+    pub(crate) fn apply_secure_ipc(&mut self, patterns: Vec<String>) {
+        let _ = self.inner.ipc_trusted.set(patterns);
+        self.enable_ipc_token();
+    }
+
+    /// Has binding calls carry the token and the url of the page making them, from the next page
+    /// loaded on. The page already loaded doesn't get the token, its scripts had their chance to
+    /// tamper with what the bridge relies on.
+    fn enable_ipc_token(&mut self) {
+        if self.inner.ipc_token.get().is_some() {
+            return;
+        }
+        let trusted = self.inner.ipc_trusted.get().cloned().unwrap_or_default();
+        let script = ipc::script(self.inner.ipc_token.get_or_init(random::token), &trusted);
+        self.init(&script);
+    }
+
+    pub fn go_back(&mut self) {
+        self.inner.view.go(HistoryAction::Back);
+    }
+
+    pub fn go_forward(&mut self) {
+        self.inner.view.go(HistoryAction::Forward);
+    }
+
+    pub fn reload(&mut self) {
+        self.inner.view.go(HistoryAction::Reload);
+    }
+
+    /// Reloads the page without using cached responses.
+    ///
+    /// On Windows this is a plain reload and is recorded in `unsupported_calls`.
+    pub fn reload_ignoring_cache(&mut self) {
+        if !self.inner.view.go(HistoryAction::ReloadIgnoringCache) {
+            self.inner.unsupported("Webview::reload_ignoring_cache");
+        }
+    }
+
+    /// Stops loading the current page.
+    pub fn stop(&mut self) {
+        self.inner.view.go(HistoryAction::Stop);
+    }
+
+    /// Whether `go_back` has a page to go back to.
+    ///
+    /// On Windows the page reports this after it loads, so it lags behind a navigation until
+    /// then.
+    pub fn can_go_back(&self) -> bool {
+        self.inner.view.can_go_back()
+    }
+
+    /// Whether `go_forward` has a page to go forward to, see `can_go_back`.
+    pub fn can_go_forward(&self) -> bool {
+        self.inner.view.can_go_forward()
+    }
+
+    pub fn init(&mut self, js: &str) {
+        let c_js = cstring::script(js);
+        unsafe { sys::webview_init(self.inner.webview, c_js.as_ptr()) }
+    }
+
+    /// Applies the style sheet `css` to every page from now on, including the current one,
+    /// without racing the page's scripts as with `eval`.
+    ///
+    /// The sheet is put in a `<style>` element with a `data-webview-css` attribute as soon as
+    /// the document has an element for it and the page has the sheets from the application,
+    /// which it asks for once it starts. It doesn't come before or after the page's own styles,
+    /// use selectors at least as specific as the page's to override them.
+    pub fn add_css(&mut self, css: &str) -> CssHandle {
+        let (id, first) = self.inner.css.lock().unwrap().add(css);
+        if first {
+            // Every page asks for the sheets of the moment, the script stays the same.
+            let handle = self.handle();
+            self.bind(css::BINDING, move |seq, _req| {
+                if let Some(w) = Webview::upgrade(&handle.0) {
+                    let sheets = w.inner.css.lock().unwrap().json();
+                    w.r#return(seq, 0, &sheets);
+                }
+            });
+            self.init(css::INIT_SCRIPT);
+            self.eval(css::INIT_SCRIPT);
+        }
+        self.eval(&css::add_script(id, css));
+        CssHandle(id)
+    }
+
+    /// Removes a style sheet added with `add_css`, from the current page and the ones loaded
+    /// after.
+    pub fn remove_css(&mut self, handle: CssHandle) {
+        self.inner.css.lock().unwrap().remove(handle.0);
+        self.eval(&css::remove_script(handle.0));
+    }
+
+    pub fn eval(&mut self, js: &str) {
+        let c_js = cstring::script(js);
+        unsafe { sys::webview_eval(self.inner.webview, c_js.as_ptr()) }
+    }
+
+    /// Evaluates `js` in the page, the reply is the JSON of its result.
+    ///
+    /// Promises are awaited, an exception fails the reply with `Error::Script`.
+    pub fn eval_with_result(&mut self, js: &str) -> Reply {
+        let (id, reply) = self.inner.replies.register();
+        self.eval_reply(&id, js);
+        reply
+    }
+
+    /// Calls the page's function `function` with the JSON array `args` as its arguments, the
+    /// reply is the JSON of its result.
+    pub fn call(&mut self, function: &str, args: &str) -> Reply {
+        self.eval_with_result(&format!("{}(...{})", function, args))
+    }
+
+    /// Describes the first element matching the CSS `selector`, `None` if there is none.
+    pub fn query_selector(&mut self, selector: &str) -> Reply<Option<Element>> {
+        self.eval_with_result(&automation::query_script(selector))
+            .map(automation::parse_element)
+    }
+
+    /// Clicks the first element matching the CSS `selector`, after scrolling it into view.
+    /// Fails with `Error::Script` if there is none.
+    ///
+    /// Like the other automation steps, e.g. `type_text`, this runs a script in the page, for
+    /// end-to-end tests of the application. The events it fires aren't trusted ones.
+    pub fn click(&mut self, selector: &str) -> Reply<()> {
+        self.eval_with_result(&automation::click_script(selector))
+            .map(automation::parse_done)
+    }
+
+    /// Types `text` into the first element matching the CSS `selector`, a form field or an
+    /// editable element, one key at a time. Fails with `Error::Script` if there is none.
+    pub fn type_text(&mut self, selector: &str, text: &str) -> Reply<()> {
+        self.eval_with_result(&automation::type_script(selector, text))
+            .map(automation::parse_done)
+    }
+
+    /// Resolves once an element matches the CSS `selector`, or fails with `Error::Timeout` if
+    /// none does within `timeout`.
+    pub fn wait_for_selector(&mut self, selector: &str, timeout: Duration) -> Reply<()> {
+        self.eval_with_result(&automation::wait_script(selector, timeout))
+            .map(automation::parse_found)
+    }
+
+    /// Reports how much web storage the loaded page's origin uses.
+    ///
+    /// Engines only expose storage to pages, so other origins can't be reported.
+    pub fn storage_usage(&mut self) -> Reply<StorageReport> {
+        self.eval_with_result(storage::USAGE_SCRIPT)
+            .map(storage::parse_report)
+    }
+
+    /// Reports the memory the application and the engine's processes use, with the page's
+    /// JavaScript heap and document size where the engine has them.
+    ///
+    /// Sampled when the page replies, e.g. by a long-running application every few minutes to
+    /// notice leaks and restart before it runs out of memory.
+    pub fn metrics(&mut self) -> Reply<Metrics> {
+        self.eval_with_result(metrics::SCRIPT).map(metrics::parse)
+    }
+
+    /// Clears `localStorage`, `sessionStorage`, IndexedDB and the Cache API's caches of `origin`,
+    /// e.g. `https://example.com`.
+    ///
+    /// `origin` has to be the loaded page's, otherwise the reply fails with `Error::Script`.
+    pub fn evict_origin(&mut self, origin: &str) -> Reply<()> {
+        self.eval_with_result(&storage::evict_script(origin))
+            .map(storage::parse_evicted)
+    }
+
+    /// Unregisters the service workers of the loaded page's origin and deletes its Cache API
+    /// caches, e.g. when an update isn't showing because a worker keeps serving the old files.
+    ///
+    /// The page stays controlled by its worker until it's loaded again, `reload` once the reply
+    /// resolves. See `WebviewBuilder::service_workers` to keep workers off.
+    pub fn clear_service_workers(&mut self) -> Reply<()> {
+        self.eval_with_result(service_worker::CLEAR_SCRIPT)
+            .map(service_worker::parse_cleared)
+    }
+
+    /// Puts the page's requests under `conditions`, e.g. `NetworkConditions::slow_3g()`, to
+    /// test from integration tests how the application copes, on this page and the ones loaded
+    /// after.
+    ///
+    /// None of the engines' own network emulation is within reach, so this is emulated within
+    /// the page: `fetch` and `XMLHttpRequest` are wrapped. Requests are held back by the latency
+    /// and the time their body takes to send, responses by the time their `Content-Length`
+    /// takes to arrive, and offline they fail while `navigator.onLine` is `false` and `online`
+    /// and `offline` events fire. Navigations, images, scripts, WebSockets, workers and
+    /// synchronous requests aren't affected, and the page's own scripts can undo the wrapping,
+    /// e.g. by taking `fetch` from a new frame. It's a test aid, not a way to cut pages off.
+    pub fn emulate_network(&mut self, conditions: NetworkConditions) {
+        let installed = self
+            .inner
+            .network
+            .lock()
+            .unwrap()
+            .replace(conditions)
+            .is_some();
+        if !installed {
+            // Every page asks for the conditions of the moment, the script stays the same.
+            let handle = self.handle();
+            self.bind(network::BINDING, move |seq, _req| {
+                if let Some(w) = Webview::upgrade(&handle.0) {
+                    let conditions = network::json(&w.network_conditions());
+                    w.r#return(seq, 0, &conditions);
+                }
+            });
+            self.init(network::INIT_SCRIPT);
+            self.eval(network::INIT_SCRIPT);
+        }
+        self.eval(&network::set_script(&conditions));
+    }
+
+    /// Takes the page offline or back online, keeping the rest of the `network_conditions`.
+    /// See `emulate_network`.
+    pub fn set_offline(&mut self, offline: bool) {
+        self.emulate_network(NetworkConditions {
+            offline,
+            ..self.network_conditions()
+        });
+    }
+
+    /// The conditions set with `emulate_network`.
+    pub fn network_conditions(&self) -> NetworkConditions {
+        self.inner.network.lock().unwrap().unwrap_or_default()
+    }
+
+    fn eval_reply(&mut self, id: &str, js: &str) {
+        if self.inner.replies.bind_once() {
+            let replies = self.inner.replies.clone();
+            let handle = self.handle();
+            self.bind(reply::BINDING, move |seq, req| {
+                replies.resolve(req);
+                let _ = handle.r#return(seq, 0, "null");
+            });
+        }
+        self.inner.replies.sent(id);
+        self.eval(&reply::script(id, js));
+    }
+
+    pub fn dispatch<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Webview) + Send + 'static,
+    {
+        dispatch(self.inner.clone(), f)
+    }
+
+    pub fn bind<F>(&mut self, name: &str, f: F)
+    where
+        F: FnMut(&str, &str) + 'static,
+    {
+        bind(&self.inner, name, f)
+    }
+
+    /// Like `bind`, but hands `f` a token that is cancelled when the page no longer waits for
+    /// the call, so long work can be given up.
+    ///
+    /// The page cancels a call with `window.__webview_cancel(id)`, taking the id of the call it
+    /// made last from `window.__webview_last_call()`, which rejects its promise with an object
+    /// whose `name` is `"AbortError"`. All calls are cancelled when the page navigates away.
+    /// Results returned for cancelled calls are dropped.
+    pub fn bind_cancellable<F>(&mut self, name: &str, mut f: F)
+    where
+        F: FnMut(&str, &str, CancellationToken) + 'static,
+    {
+        self.enable_cancellation();
+        let inner = Arc::downgrade(&self.inner);
+        self.bind(name, move |seq, req| {
+            if let Some(inner) = inner.upgrade() {
+                let token = inner.calls.lock().unwrap().start(seq);
+                f(seq, req, token);
+            }
+        });
+    }
+
+    /// Cancels the calls of a page once the next one starts, they are never going to be awaited.
+    fn watch_pages(&mut self) {
+        let nonce = random::token();
+        let inner = Arc::downgrade(&self.inner);
+        let expected = nonce.clone();
+        self.bind(cancel::PAGE_BINDING, move |seq, req| {
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            if json::strings(req).first() != Some(&expected) {
+                debug!("page announcement rejected, the nonce doesn't match");
+                return r#return(&inner, seq, 1, "null");
+            }
+            let tokens = inner.calls.lock().unwrap().cancel_all();
+            for token in tokens {
+                token.cancel();
+            }
+            inner.timeouts.lock().clear();
+            inner.binding_limits.lock().unwrap().page_changed();
+            inner.replies.page_changed();
+            r#return(&inner, seq, 0, "null");
+        });
+        self.init(&cancel::page_script(&nonce));
+    }
+
+    fn enable_cancellation(&mut self) {
+        if self.inner.cancel_bridge.set(()).is_err() {
+            return;
+        }
+        let inner = Arc::downgrade(&self.inner);
+        self.bind(cancel::BINDING, move |seq, req| {
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            let target = match json::parse(req) {
+                Some(json::Value::Array(args)) => match args.first() {
+                    Some(json::Value::String(target)) => Some(target.clone()),
+                    Some(json::Value::Number(id)) => Some(id.to_string()),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let tokens = match target.as_deref() {
+                Some("*") => {
+                    inner.timeouts.lock().clear();
+                    inner.calls.lock().unwrap().cancel_all()
+                }
+                Some(seq) => {
+                    let token = inner.calls.lock().unwrap().cancel(seq);
+                    if token.is_some() {
+                        inner.binding_limits.lock().unwrap().returned(seq);
+                        inner.timeouts.lock().returned(seq);
+                        send_return(&inner, seq, 1, cancel::REJECTION);
+                    }
+                    token.into_iter().collect()
+                }
+                None => Vec::new(),
+            };
+            for token in tokens {
+                token.cancel();
+            }
+            r#return(&inner, seq, 0, "null");
+        });
+        self.init(cancel::SCRIPT);
+        self.eval(cancel::SCRIPT);
+    }
+
+    /// Like `bind_cancellable`, but runs `f` on a pool of worker threads, so heavy work doesn't
+    /// hold up the window, and returns its result to the page.
+    ///
+    /// `f` gets the JSON array of arguments. `Ok` resolves the page's promise with the JSON it
+    /// holds and `Err` rejects it, as does a panic in `f`. The pool has
+    /// `WebviewBuilder::worker_threads` threads, shared by all pooled bindings of the webview;
+    /// calls wait their turn when all are busy, `limit_binding` caps how many may wait. Calls
+    /// cancelled while they wait don't run at all.
+    pub fn bind_pooled<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&str, &CancellationToken) -> Result<String, String> + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        let inner = Arc::downgrade(&self.inner);
+        self.bind_cancellable(name, move |seq, req, token| {
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            let (f, seq, req) = (f.clone(), seq.to_string(), req.to_string());
+            let handle = WebviewHandle(Arc::downgrade(&inner));
+            let pool = inner
+                .pool
+                .get_or_init(|| Pool::new(pool::default_threads()));
+            pool.execute(move || {
+                if token.is_cancelled() {
+                    return;
+                }
+                let (status, result) = match pool::call(|req| f(req, &token), &req) {
+                    Ok(result) => (0, result),
+                    Err(result) => (1, result),
+                };
+                let _ = handle.r#return(&seq, status, &result);
+            });
+        });
+    }
+
+    pub(crate) fn set_worker_threads(&mut self, threads: usize) {
+        let _ = self.inner.pool.set(Pool::new(threads));
+    }
+
+    /// Rejects calls to the binding `name` whose handler doesn't `return` within `timeout`,
+    /// so the page's promise doesn't wait forever, overriding
+    /// `WebviewBuilder::binding_timeout`.
+    ///
+    /// The promise fails with an object whose `name` is `"TimeoutError"`, with a `message`.
+    /// The call is reported as a leak, a warning on stderr or a `tracing` event with the
+    /// `tracing` feature, a `bind_cancellable` handler's token is cancelled and a late `return`
+    /// is ignored.
+    pub fn set_binding_timeout(&mut self, name: &str, timeout: Duration) {
+        self.inner.timeouts.lock().set(name, timeout);
+        self.watch_timeouts();
+    }
+
+    pub(crate) fn apply_binding_timeout(&mut self, timeout: Duration) {
+        self.inner.timeouts.lock().set_default(timeout);
+        self.watch_timeouts();
+    }
+
+    fn watch_timeouts(&mut self) {
+        if self.inner.timeout_watch.set(()).is_err() {
+            return;
+        }
+        let handle = self.handle();
+        let watch = self.inner.timeouts.clone();
+        thread::Builder::new()
+            .name("webview-timeouts".to_string())
+            .spawn(move || watch_timeouts(handle, &watch))
+            .expect("Spawning the timeout thread failed");
+    }
+
+    pub(crate) fn apply_binding_namespace(&mut self, namespace: String) {
+        let script = binding::namespace_script(&namespace);
+        if self.inner.binding_namespace.set(namespace).is_ok() {
+            self.init(&script);
+            self.eval(&script);
+        }
+    }
+
+    /// Only lets pages whose url matches one of `patterns` call the binding `name`, with the
+    /// same patterns as `NavigationPolicy`, e.g. `app://*` or `https://example.com/*`. Calls
+    /// from other pages are rejected before the handler runs.
+    ///
+    /// The binding may be bound before or after. The url is the one the view shows, except on
+    /// Windows, where it's the one the top frame was loaded from as reported by the bridge.
+    /// Calls have to carry a random token made for this webview, see
+    /// `WebviewBuilder::secure_ipc`. The bridge is set up for pages loaded
+    /// afterwards, calls from the page already loaded are rejected.
+    pub fn restrict_binding(&mut self, name: &str, patterns: &[&str]) {
+        self.inner.binding_origins.lock().unwrap().insert(
+            name.to_string(),
+            patterns.iter().map(|p| p.to_string()).collect(),
+        );
+        self.enable_ipc_token();
+    }
+
+    /// Checks the arguments of calls to the binding `name` against `schema` before the handler
+    /// runs, so it only sees arguments of the expected shape.
+    ///
+    /// Calls with other arguments are rejected, the page's promise fails with an object whose
+    /// `name` is `"ValidationError"`, with a `message` and the `errors`, each having the JSON
+    /// Pointer `path` to the offending argument, e.g. `/0/email`, and a `message`.
+    pub fn set_binding_schema(&mut self, name: &str, schema: Schema) {
+        self.inner
+            .binding_schemas
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), Arc::new(schema));
+    }
+
+    /// Rejects calls to the binding `name` beyond `limit` before the handler runs, so a page
+    /// calling it in a loop can't keep the application busy.
+    ///
+    /// The page's promise fails with an object whose `name` is `"RateLimitError"`, with a
+    /// `message`.
+    pub fn limit_binding(&mut self, name: &str, limit: RateLimit) {
+        self.inner.binding_limits.lock().unwrap().set(name, limit);
+    }
+
+    /// Removes the binding `name`, dropping its handler, so plugins can take their commands
+    /// away again. Binding `name` once more brings it back.
+    ///
+    /// The C library can't unbind, so this is emulated: the page's function is deleted and
+    /// later calls are rejected. A binding brought back shows up in the pages loaded from then
+    /// on, the loaded page only sees it after a reload.
+    pub fn unbind(&mut self, name: &str) {
+        let removed = self.inner.bindings.lock().unwrap().remove(name);
+        if removed.is_none() {
+            return;
+        }
+        drop(removed);
+        let namespace = self.inner.binding_namespace.get().map(String::as_str);
+        let js = binding::removal_script(binding::namespaced(namespace, name), name);
+        self.init(&js);
+        self.eval(&js);
+    }
+
+    /// Adds `plugin`, registering its init scripts and calling its `setup`, see `Plugin`.
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) {
+        if self.inner.plugins.lock().unwrap().is_empty() {
+            self.enable_plugin_hooks();
+        }
+        let plugin = self.inner.plugins.lock().unwrap().push(plugin);
+        let mut plugin = plugin.lock().unwrap();
+        for script in plugin.init_scripts() {
+            self.init(&script);
+        }
+        plugin.setup(self);
+    }
+
+    /// Calls `f` with the first plugin of type `P`, `None` if there is none.
+    pub fn with_plugin<P, F, R>(&self, f: F) -> Option<R>
+    where
+        P: Plugin,
+        F: FnOnce(&mut P) -> R,
+    {
+        let plugin = self.inner.plugins.lock().unwrap().get::<P>()?;
+        let mut plugin = plugin.lock().unwrap();
+        Some(f(&mut plugin))
+    }
+
+    fn enable_plugin_hooks(&mut self) {
+        let inner = Arc::downgrade(&self.inner);
+        self.bind(plugin::BINDING, move |seq, req| {
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            // The page only says where it was loaded from where the view can't tell.
+            if let Some(url) = inner.view.url().or_else(|| plugin::page_url(req)) {
+                // Hooks may add plugins, so they run outside the lock.
+                let plugins = inner.plugins.lock().unwrap().all();
+                let mut w = match Webview::revive(inner.clone()) {
+                    Some(w) => w,
+                    None => return,
+                };
+                for plugin in plugins {
+                    plugin.lock().unwrap().on_page_load(&mut w, &url);
+                }
+            }
+            r#return(&inner, seq, 0, "null");
+        });
+        self.init(plugin::INIT_SCRIPT);
+        let inner = Arc::downgrade(&self.inner);
+        self.on_before_quit(move || {
+            if let Some(inner) = inner.upgrade() {
+                let plugins = inner.plugins.lock().unwrap().all();
+                for plugin in plugins {
+                    plugin.lock().unwrap().on_quit();
+                }
+            }
+        });
+    }
+
+    /// The names of the current bindings, sorted, including the ones this crate binds itself,
+    /// which start with `__webview_`.
+    pub fn bindings(&self) -> Vec<String> {
+        self.inner.bindings.lock().unwrap().names()
+    }
+
+    pub fn r#return(&self, seq: &str, status: c_int, result: &str) {
+        r#return(&self.inner, seq, status, result)
+    }
+
+    /// Rejects the call `seq` with an `Error` whose `name` is `"CommandError"`, carrying the
+    /// `message`, `code` and `data` of `error`, instead of a status and a result of the
+    /// application's own making.
+    pub fn reject(&self, seq: &str, error: &CommandError) {
+        r#return(&self.inner, seq, 1, &error.script())
+    }
+
+    /// Handles a message the webview posted to the UI thread, returns `false` for any other
+    /// message.
+    ///
+    /// When the event loop is driven by the host (see `WebviewBuilder::external_loop`) every
+    /// thread message it receives has to be passed here, `dispatch` and `return` rely on it.
+    ///
+    /// # Safety
+    ///
+    /// `msg` must point to a valid `MSG`, as filled in by `GetMessageW` or `PeekMessageW`.
+    #[cfg(target_os = "windows")]
+    pub unsafe fn handle_message(&mut self, msg: *const c_void) -> bool {
+        platform::handle_message(self.inner.webview, msg)
+    }
+}
+
+fn bind<F>(inner: &Arc<Inner>, name: &str, f: F)
+where
+    F: FnMut(&str, &str) + 'static,
+{
+    let (replaced, expose) = inner.bindings.lock().unwrap().set(name, Handler::new(f));
+    drop(replaced);
+    if !expose {
+        return;
+    }
+    // The page's function stays bound to the table, so the handler can change later on.
+    let weak = Arc::downgrade(inner);
+    let binding = name.to_string();
+    let dispatch = move |seq: &str, req: &str| {
+        let inner = match weak.upgrade() {
+            Some(inner) => inner,
+            None => return,
+        };
+        // Taken out of the table while it runs, so it may bind and unbind itself.
+        let taken = inner.bindings.lock().unwrap().take(&binding);
+        match taken {
+            Some((id, mut handler)) => {
+                handler.call(seq, req);
+                let stale = inner
+                    .bindings
+                    .lock()
+                    .unwrap()
+                    .restore(&binding, id, handler);
+                drop(stale);
+            }
+            None => {
+                debug!("call rejected, nothing is bound", binding = %binding);
+                let message = format!("Nothing is bound to {}", binding);
+                r#return(&inner, seq, 1, &json::string(&message));
+            }
+        }
+    };
+    bind_raw(
+        inner.webview,
+        name,
+        guard(Arc::downgrade(inner), name, dispatch),
+    );
+    let namespace = inner.binding_namespace.get().map(String::as_str);
+    if let Some(namespace) = binding::namespaced(namespace, name) {
+        // Runs after the C library's script, in the pages to come and the loaded one.
+        let js = cstring::script(&binding::move_script(namespace, name));
+        unsafe {
+            sys::webview_init(inner.webview, js.as_ptr());
+            sys::webview_eval(inner.webview, js.as_ptr());
+        }
+    }
+}
+
+fn bind_raw<F>(webview: sys::webview_t, name: &str, f: F)
+where
+    F: FnMut(&str, &str) + 'static,
+{
+    let c_name = cstring::text(name);
+    let closure = Box::into_raw(Box::new(f));
+    extern "C" fn callback<F>(seq: *const c_char, req: *const c_char, arg: *mut c_void)
+    where
+        F: FnMut(&str, &str) + 'static,
+    {
+        let seq = unsafe { CStr::from_ptr(seq).to_string_lossy() };
+        let req = unsafe { CStr::from_ptr(req).to_string_lossy() };
+        let mut f: Box<F> = unsafe { Box::from_raw(arg as *mut F) };
+        (*f)(&seq, &req);
+        mem::forget(f);
+    }
+    unsafe {
+        sys::webview_bind(
+            webview,
+            c_name.as_ptr(),
+            Some(callback::<F>),
+            closure as *mut _,
+        )
+    }
+}
+
+/// Rejects calls that don't carry the token once `WebviewBuilder::secure_ipc` set one, and
+/// hands the others to `f` without it.
+fn guard<F>(inner: Weak<Inner>, name: &str, mut f: F) -> impl FnMut(&str, &str)
+where
+    F: FnMut(&str, &str) + 'static,
+{
+    // Blocked pages report themselves to the navigation policy and new pages announce
+    // themselves, they are never trusted.
+    let exempt = name == policy::BINDING || name == cancel::PAGE_BINDING;
+    let name = name.to_string();
+    move |seq, req| {
+        let inner = match inner.upgrade() {
+            Some(inner) => inner,
+            None => return,
+        };
+        let _span = span!(DEBUG, "call", binding = %name, seq = %seq);
+        let req = match inner.ipc_token.get() {
+            None => req.to_string(),
+            Some(token) => match ipc::split_call(req, token) {
+                Some((_, req)) if exempt => req,
+                None if exempt => req.to_string(),
+                Some((url, req)) if inner.allows_call(&name, &url) => req,
+                _ => {
+                    info!("call rejected, the page isn't trusted");
+                    let message = "The page isn't allowed to call this binding";
+                    return r#return(&inner, seq, 1, &json::string(message));
+                }
+            },
+        };
+        let admitted = inner.binding_limits.lock().unwrap().admit(&name, seq);
+        if let Err(rejection) = admitted {
+            debug!("call rejected by the binding's limit");
+            return r#return(&inner, seq, 1, &rejection);
+        }
+        let schema = inner.binding_schemas.lock().unwrap().get(&name).cloned();
+        if let Some(schema) = schema {
+            let violations = match json::parse(&req) {
+                Some(arguments) => schema.violations(&arguments),
+                None => vec![(String::new(), "aren't valid JSON".to_string())],
+            };
+            if !violations.is_empty() {
+                debug!(
+                    "call rejected, its arguments don't match the schema",
+                    violations = %violations.len(),
+                );
+                return r#return(&inner, seq, 1, &schema::rejection(&violations));
+            }
+        }
+        if !binding::is_internal(&name) && inner.timeouts.lock().start(&name, seq, Instant::now()) {
+            inner.timeouts.wake.notify_one();
+        }
+        f(seq, &req)
+    }
+}
+
+fn dispatch<F>(inner: Arc<Inner>, f: F)
+where
+    F: FnOnce(&mut Webview) + Send + 'static,
+{
+    let webview = inner.webview;
+    let external_loop = inner.external_loop;
+    let closure = Box::into_raw(Box::new((f, inner)));
+    extern "C" fn callback<F>(_webview: sys::webview_t, arg: *mut c_void)
+    where
+        F: FnOnce(&mut Webview) + Send + 'static,
+    {
+        let closure: Box<(F, Arc<Inner>)> = unsafe { Box::from_raw(arg as *mut (F, Arc<Inner>)) };
+        let (f, inner) = *closure;
+        // Dropped unrun when the webview was destroyed after it was queued.
+        if let Some(mut webview) = Webview::revive(inner) {
+            f(&mut webview);
+        }
+    }
+    if external_loop {
+        platform::dispatch_external(webview, callback::<F>, closure as *mut _);
+    } else {
+        unsafe { sys::webview_dispatch(webview, Some(callback::<F>), closure as *mut _) }
+    }
+}
+
+fn r#return(inner: &Arc<Inner>, seq: &str, status: c_int, result: &str) {
+    debug!("call returned", seq = %seq, status = %status);
+    inner.binding_limits.lock().unwrap().returned(seq);
+    inner.timeouts.lock().returned(seq);
+    if inner.calls.lock().unwrap().finished(seq) {
+        send_return(inner, seq, status, result);
+    }
+}
+
+/// Rejects the calls whose handler took too long, until the webview is gone.
+fn watch_timeouts(handle: WebviewHandle, watch: &timeout::Watch) {
+    let mut timeouts = watch.lock();
+    while handle.is_alive() {
+        let now = Instant::now();
+        let expired = timeouts.expired(now);
+        if expired.is_empty() {
+            let wait = timeouts.wait(now);
+            timeouts = watch.wake.wait_timeout(timeouts, wait).unwrap().0;
+            continue;
+        }
+        drop(timeouts);
+        let _ = handle.with(|inner| {
+            for (seq, name) in expired {
+                time_out(inner, &seq, &name);
+            }
+        });
+        timeouts = watch.lock();
+    }
+}
+
+fn time_out(inner: &Arc<Inner>, seq: &str, name: &str) {
+    warn!(
+        "the binding's handler never returned the call, rejecting it",
+        binding = %name,
+        seq = %seq,
+    );
+    let token = inner.calls.lock().unwrap().abandon(seq);
+    inner.binding_limits.lock().unwrap().returned(seq);
+    send_return(inner, seq, 1, timeout::REJECTION);
+    if let Some(token) = token {
+        token.cancel();
+    }
+}
+
+fn send_return(inner: &Arc<Inner>, seq: &str, status: c_int, result: &str) {
+    if cfg!(target_os = "windows") && inner.external_loop {
+        // `webview_return` resolves the promise from the native dispatch queue, which only the
+        // native loop runs on Windows.
+        let action = if status == 0 { "resolve" } else { "reject" };
+        let js = format!(
+            "window._rpc[{0}].{1}({2}); window._rpc[{0}] = undefined",
+            seq, action, result
+        );
+        dispatch(inner.clone(), move |w| w.eval(&js));
+        return;
+    }
+    let c_seq = cstring::text(seq);
+    let c_result = cstring::script(result);
+    unsafe { sys::webview_return(inner.webview, c_seq.as_ptr(), status, c_result.as_ptr()) }
+}
+
+/// Hands a url the policy blocked to the default browser, if the policy says so.
+fn open_blocked(webview: sys::webview_t, policy: &NavigationPolicy, url: &str) {
+    info!("navigation blocked", url = %url);
+    if policy.opens_externally() && !policy.allows(url) {
+        platform::open_external(webview, url);
+    }
+}
+
+/// A handle to a webview for other threads, which doesn't keep it alive: once the last
+/// `Webview` is dropped its calls fail with `Error::WebviewNull`.
+///
+/// Whatever has to happen on the UI thread is dispatched there, `dispatch` runs any `Webview`
+/// method from another thread.
+#[derive(Clone)]
+pub struct WebviewHandle(Weak<Inner>);
+
+/// The former name of `WebviewHandle`.
+#[deprecated(note = "renamed to `WebviewHandle`")]
+pub type WebviewMut = WebviewHandle;
+
+// Other threads only take the alive lock, the bookkeeping of replies and binding calls behind
+// its mutexes, the window's pointer, and the native webview's dispatch and return, which post
+// to its loop. The rest of `Inner` is only used on the UI thread, where `Inner::destroy` also
+// drops it.
+unsafe impl Send for WebviewHandle {}
+unsafe impl Sync for WebviewHandle {}
+
+impl WebviewHandle {
+    /// Runs `f` with the webview unless it was destroyed, which waits until `f` returned.
+    fn with<R>(&self, f: impl FnOnce(&Arc<Inner>) -> R) -> Result<R, Error> {
+        let inner = self.0.upgrade().ok_or(Error::WebviewNull)?;
+        let alive = inner.alive.read().unwrap();
+        if !*alive {
+            return Err(Error::WebviewNull);
+        }
+        Ok(f(&inner))
+    }
+
+    /// Whether the webview is still there, i.e. not destroyed yet.
+    pub fn is_alive(&self) -> bool {
+        self.with(|_| ()).is_ok()
+    }
+
+    /// Asks the UI thread to terminate the webview, see `Webview::terminate`.
+    pub fn terminate(&mut self) -> Result<(), Error> {
+        self.dispatch(|w| w.terminate())
+    }
+
+    pub fn get_window(&self) -> Result<*mut NativeWindow, Error> {
+        self.with(|inner| {
+            if !platform::capabilities(inner.webview).window_handle {
+                inner.unsupported("WebviewHandle::get_window");
+            }
+            unsafe { sys::webview_get_window(inner.webview) as *mut NativeWindow }
+        })
+    }
+
+    /// The engine the webview runs on, see `Webview::backend`.
+    pub fn backend(&self) -> Result<Backend, Error> {
+        self.with(|inner| platform::backend(inner.webview))
+    }
+
+    pub fn dispatch<F>(&mut self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Webview) + Send + 'static,
+    {
+        self.with(|inner| dispatch(inner.clone(), f))
+    }
+
+    /// Evaluates `js` in the page from any thread, see `Webview::eval_with_result`.
+    pub fn eval_with_result(&mut self, js: &str) -> Result<Reply, Error> {
+        self.with(|inner| {
+            let (id, reply) = inner.replies.register();
+            let js = js.to_string();
+            dispatch(inner.clone(), move |w| w.eval_reply(&id, &js));
+            reply
+        })
+    }
+
+    /// Calls the page's function `function` from any thread, see `Webview::call`.
+    pub fn call(&mut self, function: &str, args: &str) -> Result<Reply, Error> {
+        self.eval_with_result(&format!("{}(...{})", function, args))
+    }
+
+    /// Describes an element from any thread, see `Webview::query_selector`.
+    pub fn query_selector(&mut self, selector: &str) -> Result<Reply<Option<Element>>, Error> {
+        Ok(self
+            .eval_with_result(&automation::query_script(selector))?
+            .map(automation::parse_element))
+    }
+
+    /// Clicks an element from any thread, see `Webview::click`.
+    pub fn click(&mut self, selector: &str) -> Result<Reply<()>, Error> {
+        Ok(self
+            .eval_with_result(&automation::click_script(selector))?
+            .map(automation::parse_done))
+    }
+
+    /// Types into an element from any thread, see `Webview::type_text`.
+    pub fn type_text(&mut self, selector: &str, text: &str) -> Result<Reply<()>, Error> {
+        Ok(self
+            .eval_with_result(&automation::type_script(selector, text))?
+            .map(automation::parse_done))
+    }
+
+    /// Waits for an element from any thread, see `Webview::wait_for_selector`.
+    pub fn wait_for_selector(
+        &mut self,
+        selector: &str,
+        timeout: Duration,
+    ) -> Result<Reply<()>, Error> {
+        Ok(self
+            .eval_with_result(&automation::wait_script(selector, timeout))?
+            .map(automation::parse_found))
+    }
+
+    /// Binds `f` on the UI thread, where it runs, see `Webview::bind`.
+    pub fn bind<F>(&mut self, name: &str, f: F) -> Result<(), Error>
+    where
+        F: FnMut(&str, &str) + Send + 'static,
+    {
+        let name = name.to_string();
+        self.dispatch(move |w| w.bind(&name, f))
+    }
+
+    pub fn r#return(&self, seq: &str, status: c_int, result: &str) -> Result<(), Error> {
+        self.with(|inner| r#return(inner, seq, status, result))
+    }
+
+    pub fn reject(&self, seq: &str, error: &CommandError) -> Result<(), Error> {
+        self.with(|inner| r#return(inner, seq, 1, &error.script()))
     }
 }
@@ -0,0 +1,117 @@
+//! The window half of a webview, see `Window`.
+
+use crate::platform;
+use crate::webview::{NativeWindow, SizeHint};
+use crate::{Error, Rect, Webview};
+
+/// The window a webview is shown in: its title, size, position and visibility.
+///
+/// Got from `Webview::window`, it keeps the webview alive like a clone and leaves the page
+/// alone, see `WebContents` for that.
+#[derive(Clone)]
+pub struct Window<'a> {
+    webview: Webview<'a>,
+}
+
+impl<'a> Window<'a> {
+    pub(crate) fn new(webview: Webview<'a>) -> Window<'a> {
+        Window { webview }
+    }
+
+    /// The webview shown in the window.
+    pub fn webview(&self) -> &Webview<'a> {
+        &self.webview
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.webview.set_title(title);
+    }
+
+    pub fn set_size(&mut self, width: i32, height: i32, hints: SizeHint) {
+        self.webview.set_size(width, height, hints);
+    }
+
+    /// See `Webview::set_aspect_ratio`.
+    pub fn set_aspect_ratio(&mut self, width: u32, height: u32) {
+        self.webview.set_aspect_ratio(width, height);
+    }
+
+    /// The window's frame in screen coordinates.
+    pub fn frame(&self) -> Rect {
+        platform::window_frame(self.webview.as_sys())
+    }
+
+    /// Moves and resizes the window to `frame`, in screen coordinates.
+    pub fn set_frame(&mut self, frame: Rect) {
+        platform::set_window_frame(self.webview.as_sys(), frame);
+    }
+
+    /// The window's top left corner in screen coordinates.
+    pub fn position(&self) -> (i32, i32) {
+        let frame = self.frame();
+        (frame.x, frame.y)
+    }
+
+    /// Moves the window's top left corner to `x`, `y` in screen coordinates, keeping its size.
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        let frame = self.frame();
+        self.set_frame(Rect { x, y, ..frame });
+    }
+
+    /// Shows or hides the window.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.webview.set_visible(visible);
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        platform::is_maximized(self.webview.as_sys())
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        platform::is_minimized(self.webview.as_sys())
+    }
+
+    pub fn maximize(&mut self) {
+        platform::maximize(self.webview.as_sys());
+    }
+
+    /// See `Webview::is_fullscreen`.
+    pub fn is_fullscreen(&self) -> bool {
+        self.webview.is_fullscreen()
+    }
+
+    /// See `Webview::set_fullscreen`.
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.webview.set_fullscreen(fullscreen);
+    }
+
+    /// See `Webview::focus`.
+    pub fn focus(&mut self) {
+        self.webview.focus();
+    }
+
+    /// Whether the window has the keyboard focus.
+    pub fn is_focused(&self) -> bool {
+        self.webview.is_focused()
+    }
+
+    /// See `Webview::set_window_icon`.
+    pub fn set_icon(&mut self, image: &[u8]) -> bool {
+        self.webview.set_window_icon(image)
+    }
+
+    /// See `Webview::start_window_drag`.
+    pub fn start_drag(&mut self) {
+        self.webview.start_window_drag();
+    }
+
+    /// Creates another webview shown as a pane in the window, see `Webview::add_pane`.
+    pub fn add_pane(&mut self, debug: bool) -> Result<Webview<'static>, Error> {
+        self.webview.add_pane(debug)
+    }
+
+    /// The native window, see `Webview::get_window`.
+    pub fn native(&self) -> *mut NativeWindow {
+        self.webview.get_window()
+    }
+}
@@ -0,0 +1,145 @@
+//! Saving and restoring where the window was, see `WebviewBuilder::persist_window_state`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::Rect;
+
+/// The window's placement as saved between runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WindowState {
+    /// The frame in screen coordinates while neither maximized nor minimized.
+    pub(crate) frame: Rect,
+    pub(crate) maximized: bool,
+    /// The work area of the monitor the window was on.
+    pub(crate) monitor: Rect,
+}
+
+const KEYS: [&str; 9] = [
+    "x",
+    "y",
+    "width",
+    "height",
+    "maximized",
+    "monitor_x",
+    "monitor_y",
+    "monitor_width",
+    "monitor_height",
+];
+
+impl WindowState {
+    /// Reads a state written by `save`, `None` if there is none or it is damaged.
+    pub(crate) fn load(path: &Path) -> Option<WindowState> {
+        let content = fs::read_to_string(path).ok()?;
+        let content = content.trim().strip_prefix('{')?.strip_suffix('}')?;
+        let mut values = [0; 9];
+        for field in content.split(',') {
+            let (key, value) = field.split_once(':')?;
+            let index = KEYS
+                .iter()
+                .position(|k| key.trim() == format!("\"{}\"", k))?;
+            values[index] = match value.trim() {
+                "true" => 1,
+                "false" => 0,
+                value => value.parse().ok()?,
+            };
+        }
+        let state = WindowState {
+            frame: Rect {
+                x: values[0],
+                y: values[1],
+                width: values[2],
+                height: values[3],
+            },
+            maximized: values[4] != 0,
+            monitor: Rect {
+                x: values[5],
+                y: values[6],
+                width: values[7],
+                height: values[8],
+            },
+        };
+        if state.frame.width > 0 && state.frame.height > 0 {
+            Some(state)
+        } else {
+            None
+        }
+    }
+
+    /// Writes the state as a JSON object, replacing the previous one in one step.
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let values = [
+            self.frame.x.to_string(),
+            self.frame.y.to_string(),
+            self.frame.width.to_string(),
+            self.frame.height.to_string(),
+            self.maximized.to_string(),
+            self.monitor.x.to_string(),
+            self.monitor.y.to_string(),
+            self.monitor.width.to_string(),
+            self.monitor.height.to_string(),
+        ];
+        let fields: Vec<String> = KEYS
+            .iter()
+            .zip(&values)
+            .map(|(key, value)| format!("\"{}\":{}", key, value))
+            .collect();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, format!("{{{}}}\n", fields.join(",")))?;
+        fs::rename(&temporary, path)
+    }
+
+    /// Where to put the window on `monitors`, the primary one first.
+    ///
+    /// The window goes back onto its monitor if that is still connected. Otherwise it keeps its
+    /// place relative to the monitor the frame overlaps most, or the primary one, and is shrunk
+    /// and moved as needed to lie within it.
+    pub(crate) fn place(&self, monitors: &[Rect]) -> Option<Rect> {
+        let monitor = if monitors.contains(&self.monitor) {
+            self.monitor
+        } else {
+            monitor_of(self.frame, monitors)?
+        };
+        let mut frame = self.frame;
+        if monitor != self.monitor && overlap(monitor, frame) == 0 {
+            frame.x += monitor.x - self.monitor.x;
+            frame.y += monitor.y - self.monitor.y;
+        }
+        frame.width = frame.width.min(monitor.width);
+        frame.height = frame.height.min(monitor.height);
+        frame.x = frame
+            .x
+            .min(monitor.x + monitor.width - frame.width)
+            .max(monitor.x);
+        frame.y = frame
+            .y
+            .min(monitor.y + monitor.height - frame.height)
+            .max(monitor.y);
+        Some(frame)
+    }
+}
+
+/// The monitor `frame` overlaps most, the first one if it overlaps none.
+pub(crate) fn monitor_of(frame: Rect, monitors: &[Rect]) -> Option<Rect> {
+    monitors
+        .iter()
+        .copied()
+        .max_by_key(|monitor| overlap(*monitor, frame))
+        .filter(|monitor| overlap(*monitor, frame) > 0)
+        .or_else(|| monitors.first().copied())
+}
+
+/// The area two rectangles share.
+fn overlap(a: Rect, b: Rect) -> i64 {
+    let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+    let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+    if width > 0 && height > 0 {
+        width as i64 * height as i64
+    } else {
+        0
+    }
+}
@@ -0,0 +1,248 @@
+//! Setup of the WebView2 runtime the Windows backend runs on.
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::iter::once;
+use std::os::raw::c_void;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr::{null, null_mut};
+use std::slice;
+
+use crate::builder::Cache;
+use crate::{BackgroundThrottling, Color, Error, WebviewBuilder};
+
+/// Read by the WebView2 loader in place of the installed runtime's location.
+const BROWSER_EXECUTABLE_FOLDER: &str = "WEBVIEW2_BROWSER_EXECUTABLE_FOLDER";
+const ADDITIONAL_BROWSER_ARGUMENTS: &str = "WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS";
+const USER_DATA_FOLDER: &str = "WEBVIEW2_USER_DATA_FOLDER";
+const DEFAULT_BACKGROUND_COLOR: &str = "WEBVIEW2_DEFAULT_BACKGROUND_COLOR";
+
+#[link(name = "WebView2Loader.dll")]
+extern "system" {
+    fn GetAvailableCoreWebView2BrowserVersionString(
+        browser_executable_folder: *const u16,
+        version_info: *mut *mut u16,
+    ) -> i32;
+}
+
+#[link(name = "ole32")]
+extern "system" {
+    fn CoTaskMemFree(pv: *mut c_void);
+}
+
+fn to_wide(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().chain(once(0)).collect()
+}
+
+/// The version of the runtime new webviews will use, `Error::RuntimeMissing` if there is none.
+///
+/// This is the fixed version runtime set with `use_fixed_runtime`, or else the installed
+/// Evergreen runtime.
+pub fn runtime_version() -> Result<String, Error> {
+    let folder = env::var_os(BROWSER_EXECUTABLE_FOLDER).map(|folder| to_wide(&folder));
+    let mut version = null_mut();
+    let hr = unsafe {
+        GetAvailableCoreWebView2BrowserVersionString(
+            folder.as_ref().map_or(null(), |folder| folder.as_ptr()),
+            &mut version,
+        )
+    };
+    if hr < 0 || version.is_null() {
+        return Err(Error::RuntimeMissing);
+    }
+    let version_string = unsafe {
+        let len = (0..).take_while(|&i| *version.add(i) != 0).count();
+        String::from_utf16_lossy(slice::from_raw_parts(version, len))
+    };
+    unsafe { CoTaskMemFree(version as *mut c_void) };
+    Ok(version_string)
+}
+
+/// Installs the Evergreen runtime with the bootstrapper shipped alongside the application,
+/// returns the installed version.
+///
+/// `bootstrapper` is Microsoft's `MicrosoftEdgeWebview2Setup.exe`, which downloads the runtime.
+/// This blocks until the installation finished.
+pub fn install_runtime(bootstrapper: &Path) -> Result<String, Error> {
+    let status = Command::new(bootstrapper)
+        .args(&["/silent", "/install"])
+        .status()
+        .map_err(|e| Error::RuntimeInstall(e.to_string()))?;
+    if !status.success() {
+        return Err(Error::RuntimeInstall(format!(
+            "the bootstrapper failed with {}",
+            status
+        )));
+    }
+    runtime_version()
+}
+
+/// Makes webviews created from now on use the fixed version runtime in `folder` instead of the
+/// installed one.
+pub fn use_fixed_runtime(folder: &Path) {
+    env::set_var(BROWSER_EXECUTABLE_FOLDER, folder);
+}
+
+/// Options for the WebView2 environment a webview is created in.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    additional_browser_arguments: Option<String>,
+    language: Option<String>,
+    user_data_folder: Option<PathBuf>,
+    background_color: Option<Color>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Command line switches passed to the Edge browser process, e.g. `--disable-gpu`.
+    pub fn additional_browser_arguments(mut self, arguments: &str) -> Self {
+        self.additional_browser_arguments = Some(arguments.to_string());
+        self
+    }
+
+    /// The UI language, e.g. `en-US`, instead of the system's.
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// Where cookies, caches and other browser data are kept, next to the executable by default.
+    pub fn user_data_folder(mut self, folder: &Path) -> Self {
+        self.user_data_folder = Some(folder.to_path_buf());
+        self
+    }
+
+    /// Adds the Chromium switches for the builder's cache settings.
+    pub(crate) fn with_cache(mut self, cache: &Cache) -> Self {
+        let mut arguments: Vec<String> = self
+            .additional_browser_arguments
+            .take()
+            .into_iter()
+            .collect();
+        if let Some(dir) = &cache.dir {
+            arguments.push(format!("--disk-cache-dir=\"{}\"", dir.display()));
+        }
+        if cache.disabled {
+            // Chromium has no switch to turn the cache off, 0 stands for the default size.
+            arguments.push("--disk-cache-size=1".to_string());
+        } else if let Some(size) = cache.max_size {
+            arguments.push(format!("--disk-cache-size={}", size));
+        }
+        if !arguments.is_empty() {
+            self.additional_browser_arguments = Some(arguments.join(" "));
+        }
+        self
+    }
+
+    /// Adds `--disable-gpu` unless `enabled`.
+    pub(crate) fn with_hardware_acceleration(mut self, enabled: bool) -> Self {
+        if !enabled {
+            self.additional_browser_arguments = Some(
+                self.additional_browser_arguments
+                    .take()
+                    .into_iter()
+                    .chain(Some("--disable-gpu".to_string()))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+        self
+    }
+
+    /// Adds the Chromium switches that keep a hidden or covered page at its pace for
+    /// `BackgroundThrottling::Disabled`.
+    pub(crate) fn with_background_throttling(
+        mut self,
+        throttling: Option<BackgroundThrottling>,
+    ) -> Self {
+        if throttling == Some(BackgroundThrottling::Disabled) {
+            self.additional_browser_arguments = Some(
+                self.additional_browser_arguments
+                    .take()
+                    .into_iter()
+                    .chain(Some(
+                        "--disable-background-timer-throttling \
+                         --disable-renderer-backgrounding \
+                         --disable-backgrounding-occluded-windows"
+                            .to_string(),
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+        self
+    }
+
+    /// Sets the color WebView2 paints before the page does, which it only reads on creation.
+    pub(crate) fn with_background_color(mut self, color: Option<Color>) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    /// Hands the options to the WebView2 loader until the returned value is dropped.
+    ///
+    /// The loader reads them from the environment while the webview is created.
+    pub(crate) fn apply(&self) -> Environment {
+        let mut arguments: Vec<String> =
+            self.additional_browser_arguments.iter().cloned().collect();
+        if let Some(language) = &self.language {
+            arguments.push(format!("--lang={}", language));
+        }
+        let mut environment = Environment(Vec::new());
+        if !arguments.is_empty() {
+            environment.set(ADDITIONAL_BROWSER_ARGUMENTS, arguments.join(" ").as_ref());
+        }
+        if let Some(folder) = &self.user_data_folder {
+            environment.set(USER_DATA_FOLDER, folder.as_os_str());
+        }
+        if let Some(color) = self.background_color {
+            // WebView2 only takes opaque or fully transparent colors.
+            let alpha = if color.alpha == 0 { 0 } else { 0xff };
+            let argb = format!(
+                "{:02X}{:02X}{:02X}{:02X}",
+                alpha, color.red, color.green, color.blue
+            );
+            environment.set(DEFAULT_BACKGROUND_COLOR, argb.as_ref());
+        }
+        environment
+    }
+}
+
+/// Environment variables to restore once a webview is created.
+pub(crate) struct Environment(Vec<(&'static str, Option<OsString>)>);
+
+impl Environment {
+    fn set(&mut self, key: &'static str, value: &OsStr) {
+        self.0.push((key, env::var_os(key)));
+        env::set_var(key, value);
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        for (key, value) in self.0.drain(..) {
+            match value {
+                Some(value) => env::set_var(key, value),
+                None => env::remove_var(key),
+            }
+        }
+    }
+}
+
+/// Windows specific options of `WebviewBuilder`.
+pub trait WebviewBuilderExt {
+    /// Creates the webview in a WebView2 environment configured with `options`.
+    fn windows_options(self, options: Options) -> Self;
+}
+
+impl<'a> WebviewBuilderExt for WebviewBuilder<'a> {
+    fn windows_options(mut self, options: Options) -> Self {
+        self.windows_options = Some(options);
+        self
+    }
+}